@@ -0,0 +1,506 @@
+// Animations: property values interpolated over time
+//
+// An animation moves a number or a color property of a node to a new value in `duration` milliseconds,
+// following an easing curve, once or for a number of loops. The animations advance with the frame clock of the
+// engine: `process_events` calls `RmlEngine::update_time`, which advances them by the time elapsed since the
+// last frame on the clock of the engine (see `clock`).
+//
+// They are started three ways:
+// - `RmlEngine::animate` from Rust,
+// - `NumberAnimation` and `ColorAnimation` nodes, which animate the `property` of their `target` (their parent
+//   by default) while their `running` property is true,
+// - a `Behavior on <property> { ColorAnimation { duration: 200 } }` declared in a node: the changes of the
+//   property are animated from its current value instead of being applied at once.
+
+use std::collections::{HashMap, HashSet};
+
+use macroquad::prelude::{warn, Color};
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+
+use crate::repeater::add_missing_property;
+use crate::{AbstractValue, ItemTypeEnum, NodeId, PropertyId, RmlEngine};
+
+/// Progression of an animation over its duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InSine,
+    OutSine,
+    InOutSine,
+    OutBack,
+    OutBounce,
+}
+
+impl Easing {
+    /// Easing named in the DSL: `linear`, `in_quad`, `out_cubic`, `in_out_sine`...
+    pub fn from_name(name: &str) -> Option<Self> {
+        let easing = match name {
+            "linear" => Easing::Linear,
+            "in_quad" => Easing::InQuad,
+            "out_quad" => Easing::OutQuad,
+            "in_out_quad" => Easing::InOutQuad,
+            "in_cubic" => Easing::InCubic,
+            "out_cubic" => Easing::OutCubic,
+            "in_out_cubic" => Easing::InOutCubic,
+            "in_sine" => Easing::InSine,
+            "out_sine" => Easing::OutSine,
+            "in_out_sine" => Easing::InOutSine,
+            "out_back" => Easing::OutBack,
+            "out_bounce" => Easing::OutBounce,
+            _ => return None,
+        };
+        Some(easing)
+    }
+
+    /// Eased progress for the time progress `t`, both from 0 to 1
+    pub fn apply(&self, t: f32) -> f32 {
+        use std::f32::consts::PI;
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::InQuad => t * t,
+            Easing::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::InOutQuad if t < 0.5 => 2.0 * t * t,
+            Easing::InOutQuad => 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0,
+            Easing::InCubic => t * t * t,
+            Easing::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::InOutCubic if t < 0.5 => 4.0 * t * t * t,
+            Easing::InOutCubic => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+            Easing::InSine => 1.0 - (t * PI / 2.0).cos(),
+            Easing::OutSine => (t * PI / 2.0).sin(),
+            Easing::InOutSine => -((t * PI).cos() - 1.0) / 2.0,
+            Easing::OutBack => {
+                let overshoot = 1.70158;
+                1.0 + (overshoot + 1.0) * (t - 1.0).powi(3) + overshoot * (t - 1.0).powi(2)
+            }
+            Easing::OutBounce => {
+                let (n, d) = (7.5625, 2.75);
+                if t < 1.0 / d {
+                    n * t * t
+                } else if t < 2.0 / d {
+                    let t = t - 1.5 / d;
+                    n * t * t + 0.75
+                } else if t < 2.5 / d {
+                    let t = t - 2.25 / d;
+                    n * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / d;
+                    n * t * t + 0.984375
+                }
+            }
+        }
+    }
+}
+
+/// How many times an animation runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Loops {
+    Count(u32),
+    Infinite,
+}
+
+/// Duration (in milliseconds), easing and loops of an animation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimationSpec {
+    pub duration: f32,
+    pub easing: Easing,
+    pub loops: Loops,
+}
+
+impl Default for AnimationSpec {
+    fn default() -> Self {
+        Self { duration: 250.0, easing: Easing::Linear, loops: Loops::Count(1) }
+    }
+}
+
+impl AnimationSpec {
+    pub fn new(duration: f32) -> Self {
+        Self { duration, ..Default::default() }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_loops(mut self, loops: Loops) -> Self {
+        self.loops = loops;
+        self
+    }
+
+    /// Read the `duration`, `easing` and `loops` properties of an animation, the others are ignored.
+    /// `loops` is a number of loops or `infinite`.
+    pub fn from_properties<'a>(properties: impl IntoIterator<Item = (&'a str, &'a AbstractValue)>) -> Result<Self, String> {
+        let mut spec = Self::default();
+        for (name, value) in properties {
+            match (name, value) {
+                ("duration", AbstractValue::Number(duration)) => spec.duration = duration.max(0.0),
+                ("easing", AbstractValue::String(easing)) => {
+                    spec.easing = Easing::from_name(easing).ok_or_else(|| format!("Unknown easing: {}", easing))?;
+                }
+                ("loops", AbstractValue::Number(count)) if *count >= 1.0 => spec.loops = Loops::Count(*count as u32),
+                ("loops", AbstractValue::String(loops)) if loops == "infinite" => spec.loops = Loops::Infinite,
+                ("duration" | "easing" | "loops", value) => return Err(format!("Invalid value for `{}`: {}", name, value.to_string())),
+                _ => {}
+            }
+        }
+        Ok(spec)
+    }
+}
+
+impl ToTokens for Easing {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let variant = quote::format_ident!("{}", format!("{:?}", self));
+        quote! { rml_core::Easing::#variant }.to_tokens(tokens);
+    }
+}
+
+impl ToTokens for AnimationSpec {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { duration, easing, loops } = self;
+        let loops = match loops {
+            Loops::Count(count) => quote! { rml_core::Loops::Count(#count) },
+            Loops::Infinite => quote! { rml_core::Loops::Infinite },
+        };
+        quote! {
+            rml_core::AnimationSpec { duration: #duration, easing: #easing, loops: #loops }
+        }.to_tokens(tokens);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Running {
+    node_id: NodeId,
+    property: String,
+    from: AbstractValue,
+    to: AbstractValue,
+    spec: AnimationSpec,
+    /// milliseconds since the start of the current loop
+    elapsed: f32,
+    /// loops already completed
+    completed: u32,
+    /// the animation node running it
+    source: Option<NodeId>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Animations {
+    running: Vec<Running>,
+    behaviors: HashMap<PropertyId, AnimationSpec>,
+    /// animation nodes whose `running` property is followed
+    nodes: HashSet<NodeId>,
+}
+
+impl Animations {
+    pub(crate) fn behavior(&self, property_id: PropertyId) -> Option<AnimationSpec> {
+        self.behaviors.get(&property_id).copied()
+    }
+}
+
+fn interpolate(from: &AbstractValue, to: &AbstractValue, progress: f32) -> Option<AbstractValue> {
+    match (from, to) {
+        (AbstractValue::Number(from), AbstractValue::Number(to)) => Some(AbstractValue::Number(from + (to - from) * progress)),
+        (AbstractValue::Color(from), AbstractValue::Color(to)) => Some(AbstractValue::Color(Color::new(
+            from.r + (to.r - from.r) * progress,
+            from.g + (to.g - from.g) * progress,
+            from.b + (to.b - from.b) * progress,
+            from.a + (to.a - from.a) * progress,
+        ))),
+        _ => None,
+    }
+}
+
+/// Animate the property `property` of `node_id` from `from` (its current value if `None`) to `to`.
+/// Return false if the values can't be interpolated (only numbers and colors can).
+pub(crate) fn start(engine: &mut RmlEngine, node_id: NodeId, property: &str, from: Option<AbstractValue>, to: AbstractValue, spec: AnimationSpec, source: Option<NodeId>) -> bool {
+    let Some(from) = from.or_else(|| engine.get_property_by_name(node_id, property).map(|property| property.get())) else {
+        return false;
+    };
+    if interpolate(&from, &to, 0.0).is_none() {
+        return false;
+    }
+    stop(engine, node_id, property);
+    engine.write_property_of_node_id(node_id, property, from.clone());
+    engine.animations.running.push(Running { node_id, property: property.to_string(), from, to, spec, elapsed: 0.0, completed: 0, source });
+    true
+}
+
+/// Stop the animation of the property, it keeps its current value
+pub(crate) fn stop(engine: &mut RmlEngine, node_id: NodeId, property: &str) {
+    engine.animations.running.retain(|running| running.node_id != node_id || running.property != property);
+}
+
+/// Value the property is animated to, `None` if it is not animated
+pub(crate) fn target_value(engine: &RmlEngine, node_id: NodeId, property: &str) -> Option<AbstractValue> {
+    engine.animations.running.iter()
+        .find(|running| running.node_id == node_id && running.property == property)
+        .map(|running| running.to.clone())
+}
+
+pub(crate) fn is_running(engine: &RmlEngine, node_id: NodeId, property: &str) -> bool {
+    engine.animations.running.iter().any(|running| running.node_id == node_id && running.property == property)
+}
+
+pub(crate) fn add_behavior(engine: &mut RmlEngine, node_id: NodeId, property: &str, spec: AnimationSpec) {
+    match engine.get_node(node_id).and_then(|node| node.get_property(property)) {
+        Some(property_id) => {
+            engine.animations.behaviors.insert(property_id, spec);
+        }
+        None => warn!("Behavior on an unknown property: {}", property),
+    }
+}
+
+/// Replace the behaviors of the properties of `node_id` by `behaviors`
+pub(crate) fn set_behaviors(engine: &mut RmlEngine, node_id: NodeId, behaviors: &[(String, AnimationSpec)]) {
+    if let Some(node) = engine.get_node(node_id) {
+        let properties: HashSet<PropertyId> = node.properties.values().copied().collect();
+        engine.animations.behaviors.retain(|property_id, _| !properties.contains(property_id));
+    }
+    for (property, spec) in behaviors {
+        add_behavior(engine, node_id, property, *spec);
+    }
+}
+
+/// A property with a behavior is set: animate it towards `value`.
+/// Return false if it can't be animated, the value is then set at once.
+pub(crate) fn animate_change(engine: &mut RmlEngine, node_id: NodeId, property: &str, value: &AbstractValue, spec: AnimationSpec) -> bool {
+    let already_going = engine.animations.running.iter()
+        .any(|running| running.node_id == node_id && running.property == property && running.to == *value);
+    if already_going {
+        return true;
+    }
+    // a new value retargets the animation from where it is
+    stop(engine, node_id, property);
+    let current = engine.get_property_by_name(node_id, property).map(|property| property.get());
+    current.as_ref() != Some(value) && start(engine, node_id, property, current, value.clone(), spec, None)
+}
+
+/// Advance the running animations by `dt` milliseconds
+pub(crate) fn tick(engine: &mut RmlEngine, dt: f32) {
+    let mut writes = Vec::new();
+    let mut finished_sources = Vec::new();
+    engine.animations.running.retain_mut(|running| {
+        running.elapsed += dt;
+        let duration = running.spec.duration.max(f32::EPSILON);
+        while running.elapsed >= duration {
+            running.completed += 1;
+            if running.spec.loops == Loops::Count(running.completed) {
+                writes.push((running.node_id, running.property.clone(), running.to.clone()));
+                finished_sources.extend(running.source);
+                return false;
+            }
+            running.elapsed -= duration;
+        }
+        let progress = running.spec.easing.apply(running.elapsed / duration);
+        if let Some(value) = interpolate(&running.from, &running.to, progress) {
+            writes.push((running.node_id, running.property.clone(), value));
+        }
+        true
+    });
+    for (node_id, property, value) in writes {
+        engine.write_property_of_node_id(node_id, &property, value);
+    }
+    // like in QML, an animation node is not running anymore once it is done
+    for source in finished_sources {
+        engine.set_property_of_node_id(source, "running", AbstractValue::Bool(false));
+    }
+}
+
+pub(crate) fn is_animation_node(node_type: &ItemTypeEnum) -> bool {
+    matches!(node_type, ItemTypeEnum::NumberAnimation | ItemTypeEnum::ColorAnimation)
+}
+
+/// Follow the `running` property of the animation node `node_id`, now attached to the tree
+pub(crate) fn attach_node(engine: &mut RmlEngine, node_id: NodeId) {
+    if !engine.animations.nodes.insert(node_id) {
+        return;
+    }
+    add_missing_property(engine, node_id, "target", AbstractValue::String(String::new()));
+    add_missing_property(engine, node_id, "property", AbstractValue::String(String::new()));
+    add_missing_property(engine, node_id, "from", AbstractValue::Null);
+    add_missing_property(engine, node_id, "to", AbstractValue::Null);
+    add_missing_property(engine, node_id, "running", AbstractValue::Bool(false));
+    let Some(running) = engine.get_node(node_id).and_then(|node| node.get_property("running")) else { return };
+    let callback = engine.add_callback(move |engine| run_node(engine, node_id));
+    engine.bind_property_to_callback(running, callback);
+    // the bindings of the node (`to: { ... }`) are evaluated after it is attached, it starts with the next callbacks
+    engine.callbacks_to_eval.push(callback);
+}
+
+/// Start or stop the animation of the node `node_id` according to its `running` property
+fn run_node(engine: &mut RmlEngine, node_id: NodeId) {
+    let Some(node) = engine.get_node(node_id) else { return };
+    let (name, parent) = (node.id.clone(), node.parent);
+    let names: Vec<String> = node.properties.keys().cloned().collect();
+    let target = match engine.get_property_by_name(node_id, "target").map(|target| target.get()) {
+        Some(AbstractValue::String(target)) if !target.is_empty() => engine.get_node_id(&target),
+        _ => parent,
+    };
+    let property = engine.get_string_property_of_node_id(node_id, "property", String::new());
+    let Some(target) = target else {
+        warn!("The target of the animation '{}' is not found", name);
+        return;
+    };
+    let running = engine.get_bool_property_of_node_id(node_id, "running", false);
+    let started_here = engine.animations.running.iter()
+        .any(|animation| animation.source == Some(node_id) && animation.node_id == target && animation.property == property);
+    if !running {
+        if started_here {
+            stop(engine, target, &property);
+        }
+        return;
+    }
+
+    let values: Vec<(String, AbstractValue)> = names.into_iter()
+        .filter_map(|property| engine.get_property_by_name(node_id, &property).map(|value| value.get()).map(|value| (property, value)))
+        .collect();
+    let spec = match AnimationSpec::from_properties(values.iter().map(|(name, value)| (name.as_str(), value))) {
+        Ok(spec) => spec,
+        Err(error) => {
+            warn!("Animation '{}': {}", name, error);
+            return;
+        }
+    };
+    let from = engine.get_property_by_name(node_id, "from").map(|from| from.get()).filter(|from| *from != AbstractValue::Null);
+    let Some(to) = engine.get_property_by_name(node_id, "to").map(|to| to.get()).filter(|to| *to != AbstractValue::Null) else {
+        warn!("The animation '{}' has no `to` value", name);
+        return;
+    };
+    if !start(engine, target, &property, from, to, spec, Some(node_id)) {
+        warn!("The animation '{}' can't animate `{}`, only numbers and colors are animated", name, property);
+    }
+}
+
+/// Forget the removed nodes and properties
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId], properties: &HashSet<PropertyId>) {
+    let animations = &mut engine.animations;
+    animations.running.retain(|running| {
+        !removed.contains(&running.node_id) && !running.source.is_some_and(|source| removed.contains(&source))
+    });
+    animations.behaviors.retain(|property_id, _| !properties.contains(property_id));
+    animations.nodes.retain(|node_id| !removed.contains(node_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_node, sample_engine};
+    use crate::load_rml;
+    use macroquad::prelude::{BLACK, WHITE};
+
+    fn number(engine: &RmlEngine, node: &str, property: &str) -> f32 {
+        engine.get_number_property_of_node(node, property, -1.0)
+    }
+
+    #[test]
+    fn test_easings() {
+        for easing in [Easing::Linear, Easing::InQuad, Easing::OutQuad, Easing::InOutQuad, Easing::InCubic, Easing::OutCubic,
+            Easing::InOutCubic, Easing::InSine, Easing::OutSine, Easing::InOutSine, Easing::OutBack, Easing::OutBounce] {
+            assert!(easing.apply(0.0).abs() < 1e-5, "{:?} starts at 0", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{:?} ends at 1", easing);
+        }
+        assert_eq!(Easing::InQuad.apply(0.5), 0.25);
+        assert_eq!(Easing::OutQuad.apply(0.5), 0.75);
+        assert_eq!(Easing::from_name("in_out_cubic"), Some(Easing::InOutCubic));
+        assert_eq!(Easing::from_name("bouncy"), None);
+    }
+
+    #[test]
+    fn test_animate_a_number_with_loops() {
+        let mut engine = sample_engine();
+        let panel = engine.get_node_id("panel").unwrap();
+        engine.animate(panel, "x", AbstractValue::Number(100.0), AnimationSpec::new(1000.0).with_loops(Loops::Count(2)));
+        assert_eq!(number(&engine, "panel", "x"), 0.0);
+
+        engine.advance_time(0.25);
+        assert_eq!(number(&engine, "panel", "x"), 25.0);
+        engine.advance_time(1.0);
+        assert_eq!(number(&engine, "panel", "x"), 25.0, "second loop");
+        engine.advance_time(1.0);
+        assert_eq!(number(&engine, "panel", "x"), 100.0);
+        assert!(!engine.is_animating(panel, "x"));
+    }
+
+    #[test]
+    fn test_behavior_animates_changes() {
+        let mut engine = sample_engine();
+        let panel = engine.get_node_id("panel").unwrap();
+        engine.add_behavior(panel, "color", AnimationSpec::new(100.0));
+        engine.set_property_of_node_id(panel, "color", AbstractValue::Color(BLACK));
+        assert!(engine.is_animating(panel, "color"));
+
+        engine.advance_time(0.05);
+        let color = engine.get_color_property_of_node("panel", "color", WHITE);
+        let blue = macroquad::prelude::BLUE;
+        assert!((color.b - blue.b / 2.0).abs() < 1e-5);
+
+        // a new value starts from where the animation is
+        engine.set_property_of_node_id(panel, "color", AbstractValue::Color(WHITE));
+        engine.advance_time(0.1);
+        assert_eq!(engine.get_color_property_of_node("panel", "color", BLACK), WHITE);
+
+        // the layout follows the animated geometry
+        engine.add_behavior(panel, "width", AnimationSpec::new(100.0).with_easing(Easing::InQuad));
+        engine.get_layout_mut().invalidate();
+        engine.set_property_of_node_id(panel, "width", AbstractValue::Number(50.0));
+        engine.advance_time(0.05);
+        assert_eq!(number(&engine, "panel", "width"), 12.5);
+        assert!(engine.get_layout().is_dirty(panel));
+    }
+
+    #[test]
+    fn test_animation_node() {
+        let mut engine = sample_engine();
+        add_node(&mut engine, Some("panel"), "slide", ItemTypeEnum::NumberAnimation, &[
+            ("property", AbstractValue::String("y".into())),
+            ("from", AbstractValue::Number(10.0)),
+            ("to", AbstractValue::Number(20.0)),
+            ("duration", AbstractValue::Number(100.0)),
+            ("running", AbstractValue::Bool(false)),
+        ]);
+        engine.advance_time(0.05);
+        assert_eq!(number(&engine, "panel", "y"), 0.0);
+
+        engine.set_property_of_node("slide", "running", AbstractValue::Bool(true));
+        engine.run_callbacks();
+        assert_eq!(number(&engine, "panel", "y"), 10.0);
+        engine.advance_time(0.05);
+        assert_eq!(number(&engine, "panel", "y"), 15.0);
+        engine.advance_time(0.05);
+        assert_eq!(number(&engine, "panel", "y"), 20.0);
+        assert!(!engine.get_bool_property_of_node("slide", "running", true));
+    }
+
+    #[test]
+    fn test_loaded_behavior_and_animation() {
+        let mut engine = load_rml(r#"
+            Rectangle {
+                id: box
+                width: 10
+                color color: { WHITE }
+                Behavior on color { ColorAnimation { duration: 200 easing: out_quad } }
+                NumberAnimation { property: "width" to: 110 duration: 1000 loops: infinite running: true }
+            }
+        "#).unwrap();
+        let root = engine.get_node_id("box").unwrap();
+        assert_eq!(engine.get_arena().get_childrens_ids(root).len(), 1, "the Behavior is not a node");
+
+        engine.advance_time(0.5);
+        assert_eq!(number(&engine, "box", "width"), 60.0);
+        engine.advance_time(1.0);
+        assert_eq!(number(&engine, "box", "width"), 60.0, "infinite loops");
+
+        engine.set_property_of_node("box", "color", AbstractValue::Color(BLACK));
+        assert!(engine.is_animating(root, "color"));
+        engine.advance_time(0.2);
+        assert_eq!(engine.get_color_property_of_node("box", "color", WHITE), BLACK);
+    }
+}
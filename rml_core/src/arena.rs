@@ -1,6 +1,5 @@
 
 use std::collections::{HashMap};
-use macroquad::color::Color;
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
 
@@ -8,11 +7,43 @@ use indexmap::IndexSet;
 
 use crate::{Property, PropertyId, AbstractValue};
 
-pub type NodeId = usize;
+/// Stable handle on a node of the arena: the index of its slot and the generation of the slot.
+/// The slot of a removed node is reused by a later node with the next generation, so an id kept
+/// after its node was removed never resolves to another node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+impl NodeId {
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl std::fmt::Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+#[derive(Debug)]
+struct Slot {
+    generation: u32,
+    node: Option<ArenaNode>,
+}
 
 #[derive(Debug)]
 pub struct ArenaTree {
-    pub nodes: Vec<ArenaNode>,
+    slots: Vec<Slot>,
+    // indices of the empty slots, reused first
+    free: Vec<u32>,
+    root: Option<NodeId>,
     pub id_to_node_id: HashMap<ArenaNodeId, NodeId>,
 }
 
@@ -23,6 +54,17 @@ pub enum ItemTypeEnum {
     Text,
     MouseArea,
     Texture,
+    Row,
+    Column,
+    Grid,
+    Repeater,
+    ListView,
+    NumberAnimation,
+    ColorAnimation,
+    Timer,
+    TextInput,
+    TextArea,
+    DropArea,
 }
 
 pub type ArenaNodeId = String;
@@ -69,6 +111,17 @@ impl ToTokens for ItemTypeEnum {
             ItemTypeEnum::Text => quote! { ItemTypeEnum::Text },
             ItemTypeEnum::MouseArea => quote! { ItemTypeEnum::MouseArea },
             ItemTypeEnum::Texture => quote! { ItemTypeEnum::Texture },
+            ItemTypeEnum::Row => quote! { ItemTypeEnum::Row },
+            ItemTypeEnum::Column => quote! { ItemTypeEnum::Column },
+            ItemTypeEnum::Grid => quote! { ItemTypeEnum::Grid },
+            ItemTypeEnum::Repeater => quote! { ItemTypeEnum::Repeater },
+            ItemTypeEnum::ListView => quote! { ItemTypeEnum::ListView },
+            ItemTypeEnum::NumberAnimation => quote! { ItemTypeEnum::NumberAnimation },
+            ItemTypeEnum::ColorAnimation => quote! { ItemTypeEnum::ColorAnimation },
+            ItemTypeEnum::Timer => quote! { ItemTypeEnum::Timer },
+            ItemTypeEnum::TextInput => quote! { ItemTypeEnum::TextInput },
+            ItemTypeEnum::TextArea => quote! { ItemTypeEnum::TextArea },
+            ItemTypeEnum::DropArea => quote! { ItemTypeEnum::DropArea },
         };
         tokenized.to_tokens(tokens);
     }
@@ -111,77 +164,144 @@ impl ToTokens for AbstractValue {
     }
 }
 
+impl Default for ArenaTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ArenaTree {
     /// Create a new, empty tree
     pub fn new() -> Self {
         Self {
-            nodes: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            root: None,
             id_to_node_id: HashMap::new(),
         }
     }
 
-    /// Add a node to the tree and return its `NodeId`
+    /// Add a node to the tree and return its `NodeId`. The first node added is the root.
     pub fn add_node(&mut self, node_type: ItemTypeEnum, id: ArenaNodeId, properties: PropertyMap) -> Option<NodeId> {
         if self.id_to_node_id.contains_key(&id) {
             return None;
         }
-        let node_id = self.nodes.len();
-        self.nodes.push(ArenaNode {
+        let node = ArenaNode {
             id: id.clone(),
             node_type,
             properties,
             parent: None,
             children: IndexSet::new(),
-        });
+        };
+        let node_id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.node = Some(node);
+                NodeId { index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, node: Some(node) });
+                NodeId { index: self.slots.len() as u32 - 1, generation: 0 }
+            }
+        };
         self.id_to_node_id.insert(id, node_id);
+        if self.root.is_none() {
+            self.root = Some(node_id);
+        }
         Some(node_id)
     }
 
     /// Add a child to a parent node
     pub fn add_child(&mut self, parent_id: NodeId, child_id: NodeId) {
-        if let Some(parent) = self.nodes.get_mut(parent_id) {
+        if let Some(parent) = self.get_node_mut(parent_id) {
             parent.children.insert(child_id);
         }
-        if let Some(child) = self.nodes.get_mut(child_id) {
+        if let Some(child) = self.get_node_mut(child_id) {
+            child.parent = Some(parent_id);
+        }
+    }
+
+    /// Add a child to a parent node at `index` among its childs, or move it there if it's already a child
+    pub fn insert_child(&mut self, parent_id: NodeId, index: usize, child_id: NodeId) {
+        if let Some(parent) = self.get_node_mut(parent_id) {
+            match parent.children.get_index_of(&child_id) {
+                Some(current) => parent.children.move_index(current, index.min(parent.children.len() - 1)),
+                None => {
+                    parent.children.shift_insert(index.min(parent.children.len()), child_id);
+                }
+            }
+        }
+        if let Some(child) = self.get_node_mut(child_id) {
             child.parent = Some(parent_id);
         }
     }
 
     pub fn get_root_node_id(&self) -> Option<NodeId> {
-        if self.nodes.is_empty() { return None }
-        Some(0)
+        self.root
+    }
+
+    /// Number of nodes in the arena
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All the nodes, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &ArenaNode)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.node.as_ref().map(|node| (NodeId { index: index as u32, generation: slot.generation }, node))
+        })
+    }
+
+    /// A node and its descendants, parents before their children and childs in order:
+    /// the drawing order, the last node is on top
+    pub fn subtree(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![node_id];
+        while let Some(current) = stack.pop() {
+            let Some(node) = self.get_node(current) else { continue };
+            nodes.push(current);
+            stack.extend(node.children.iter().rev().copied());
+        }
+        nodes
     }
 
     /// Get a node by id
     pub fn get_node_by_id(&self, id: &str) -> Option<&ArenaNode> {
-        self.id_to_node_id.get(id).and_then(|&node_id| self.nodes.get(node_id))
+        self.id_to_node_id.get(id).and_then(|&node_id| self.get_node(node_id))
     }
 
     pub fn get_node_mut_by_id(&mut self, id: &str) -> Option<&mut ArenaNode> {
-        self.id_to_node_id.get(id).and_then(|&node_id| self.nodes.get_mut(node_id))
+        let node_id = *self.id_to_node_id.get(id)?;
+        self.get_node_mut(node_id)
     }
 
-    /// Get a node by NodeId
+    /// Get a node by NodeId, None if it was removed
     pub fn get_node(&self, node_id: NodeId) -> Option<&ArenaNode> {
-        self.nodes.get(node_id)
+        self.slots.get(node_id.index())
+            .filter(|slot| slot.generation == node_id.generation)
+            .and_then(|slot| slot.node.as_ref())
     }
 
     pub fn get_node_mut(&mut self, node_id: NodeId) -> Option<&mut ArenaNode> {
-        self.nodes.get_mut(node_id)
+        self.slots.get_mut(node_id.index())
+            .filter(|slot| slot.generation == node_id.generation)
+            .and_then(|slot| slot.node.as_mut())
     }
 
     /// Get all children ids of a node
     pub fn get_childrens_ids(&self, node_id: NodeId) -> Vec<NodeId> {
-        self.nodes
-            .get(node_id)
+        self.get_node(node_id)
             .map(|node| node.children.iter().copied().collect())
             .unwrap_or_default()
     }
 
     /// Get all children str ids of a node
     pub fn get_childrens_ids_str(&self, node_id: NodeId) -> Vec<ArenaNodeId> {
-        self.nodes
-            .get(node_id)
+        self.get_node(node_id)
             .map(|node| {
                 node.children
                     .iter()
@@ -193,8 +313,7 @@ impl ArenaTree {
 
     /// Get all children of a node
     pub fn get_children(&self, node_id: NodeId) -> Vec<&ArenaNode> {
-        self.nodes
-            .get(node_id)
+        self.get_node(node_id)
             .map(|node| {
                 node.children
                     .iter()
@@ -212,29 +331,75 @@ impl ArenaTree {
         }
     }
     
-    /// Remove a node by NodeId
-    pub fn remove_node(&mut self, node_id: NodeId) {
-        let mut parent_node: Option<NodeId> = None;
-        let mut children: IndexSet<usize> = IndexSet::new();
-        let mut id = String::new();
-
-        if let Some(node) = self.nodes.get(node_id) {
-            id = node.id.clone();
-            parent_node = node.parent;
-            children = node.children.clone();
+    /// Remove a node and its descendants, and return them. Their slots are freed for later nodes.
+    pub fn remove_node(&mut self, node_id: NodeId) -> Vec<(NodeId, ArenaNode)> {
+        let Some(parent) = self.get_node(node_id).map(|node| node.parent) else {
+            return Vec::new();
+        };
+        if let Some(parent) = parent.and_then(|parent_id| self.get_node_mut(parent_id)) {
+            parent.children.shift_remove(&node_id);
+        }
+        if self.root == Some(node_id) {
+            self.root = None;
         }
 
-        // remove node from children of parent
-        if let Some(parent_id) = parent_node {
-            if let Some(parent) = self.nodes.get_mut(parent_id) {
-                parent.children.remove(&node_id);
+        let mut removed = Vec::new();
+        for id in self.subtree(node_id) {
+            let slot = &mut self.slots[id.index()];
+            let Some(node) = slot.node.take() else { continue };
+            slot.generation += 1;
+            if self.id_to_node_id.get(&node.id) == Some(&id) {
+                self.id_to_node_id.remove(&node.id);
             }
+            removed.push((id, node));
         }
-        // remove children
-        for &child_id in &children {
-            self.remove_node(child_id);
+        // a subtree built again gets its slots back in the same order
+        self.free.extend(removed.iter().rev().map(|(id, _)| id.index));
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(tree: &mut ArenaTree, id: &str, parent: Option<NodeId>) -> NodeId {
+        let node_id = tree.add_node(ItemTypeEnum::Node, id.to_string(), PropertyMap::new()).unwrap();
+        if let Some(parent) = parent {
+            tree.add_child(parent, node_id);
         }
-        self.id_to_node_id.remove(&id);
-        self.nodes.remove(node_id);
+        node_id
+    }
+
+    #[test]
+    fn test_remove_keeps_other_ids() {
+        let mut tree = ArenaTree::new();
+        let root = add(&mut tree, "root", None);
+        let first = add(&mut tree, "first", Some(root));
+        let inner = add(&mut tree, "inner", Some(first));
+        let last = add(&mut tree, "last", Some(root));
+
+        let removed: Vec<NodeId> = tree.remove_node(first).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(removed, [first, inner]);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.get_node(last).unwrap().id, "last");
+        assert_eq!(tree.id_to_node_id["last"], last);
+        assert!(tree.get_node_by_id("inner").is_none());
+        assert_eq!(tree.get_childrens_ids(root), [last]);
+    }
+
+    #[test]
+    fn test_reused_slot_has_new_generation() {
+        let mut tree = ArenaTree::new();
+        let root = add(&mut tree, "root", None);
+        let item = add(&mut tree, "item", Some(root));
+        tree.remove_node(item);
+
+        let other = add(&mut tree, "other", Some(root));
+        assert_eq!(other.index(), item.index());
+        assert_ne!(other, item);
+        assert!(tree.get_node(item).is_none());
+        assert_eq!(tree.get_node(other).unwrap().id, "other");
+        assert_eq!(tree.subtree(root), [root, other]);
     }
 }
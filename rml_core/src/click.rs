@@ -0,0 +1,213 @@
+// Clicks, double clicks and press and hold
+//
+//     MouseArea {
+//         id: file_area
+//         anchors: fill
+//         press_and_hold_interval: 500
+//         on_click: { if get_click_count!(engine) == 1 { ... } }   // select the file
+//         on_double_click: { ... }                                  // open it
+//         on_press_and_hold: { ... }                                // show its menu
+//     }
+//
+// A click is a press and a release of a button on the same node, the mouse moving less than `CLICK_TOLERANCE`
+// pixels in between: it goes to the nodes the press reached (see `grab`) still under the mouse. A release ending
+// a drag (see `drag`) makes no click. A click on the node of the previous click, with the same button, close to it
+// and within `DOUBLE_CLICK_INTERVAL` counts one more: the `click_count` of the Click event is 2 for the second
+// click, which is followed by a DoubleClick event, and goes on for the next ones.
+//
+// A button held down without moving on its node for `press_and_hold_interval` milliseconds (800 by default, a
+// property of the pressed MouseArea) sends a PressAndHold event. The release that follows makes no click when a
+// node pressed has an `on_press_and_hold` handler.
+
+use macroquad::prelude::MouseButton;
+
+use crate::{propagation, EventType, NodeId, RmlEngine, SystemEvent};
+
+/// Distance the mouse moves between the press and the release of a click at most, in pixels
+const CLICK_TOLERANCE: f32 = 8.0;
+/// Time between two clicks of a double click at most, in seconds
+const DOUBLE_CLICK_INTERVAL: f64 = 0.4;
+/// Time a button is held down before a PressAndHold, unless the MouseArea gives a `press_and_hold_interval`
+const PRESS_AND_HOLD_INTERVAL: f32 = 800.0;
+
+#[derive(Debug)]
+struct Press {
+    /// the nodes the press reached, the topmost first
+    nodes: Vec<NodeId>,
+    button: MouseButton,
+    position: (f32, f32),
+    time: f64,
+    /// a PressAndHold was sent
+    held: bool,
+    /// and a node pressed has a handler of it
+    held_handled: bool,
+}
+
+#[derive(Debug)]
+struct LastClick {
+    node: NodeId,
+    button: MouseButton,
+    position: (f32, f32),
+    time: f64,
+    count: u32,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Clicks {
+    press: Option<Press>,
+    last: Option<LastClick>,
+}
+
+fn is_close(a: (f32, f32), b: (f32, f32)) -> bool {
+    (a.0 - b.0).hypot(a.1 - b.1) <= CLICK_TOLERANCE
+}
+
+/// The press of `button` at (`x`, `y`) reached the nodes `reached`, unless another button is down
+pub(crate) fn press(engine: &mut RmlEngine, button: MouseButton, x: f32, y: f32, reached: Vec<NodeId>) {
+    if engine.clicks.press.is_some() {
+        return;
+    }
+    let time = engine.clock.now();
+    engine.clicks.press = Some(Press { nodes: reached, button, position: (x, y), time, held: false, held_handled: false });
+}
+
+/// `button` was released at (`x`, `y`) over the nodes `hovered`: the pressed ones get a click, unless the mouse
+/// moved, dragged an item (`dragged`) or was held down for a handled PressAndHold
+pub(crate) fn release(engine: &mut RmlEngine, button: MouseButton, x: f32, y: f32, hovered: &[NodeId], dragged: bool) {
+    let Some(press) = engine.clicks.press.take_if(|press| press.button == button) else { return };
+    let targets: Vec<NodeId> = press.nodes.iter().copied().filter(|node_id| hovered.contains(node_id)).collect();
+    if dragged || press.held_handled || targets.is_empty() || !is_close(press.position, (x, y)) {
+        engine.clicks.last = None;
+        return;
+    }
+
+    let time = engine.clock.now();
+    let count = match &engine.clicks.last {
+        Some(last) if last.node == targets[0] && last.button == button && time - last.time <= DOUBLE_CLICK_INTERVAL
+            && is_close(last.position, (x, y)) => last.count + 1,
+        _ => 1,
+    };
+    engine.clicks.last = Some(LastClick { node: targets[0], button, position: (x, y), time, count });
+    propagation::dispatch_to_targets(engine, &targets, |node_id| SystemEvent::Click { node_id, button, x, y, click_count: count });
+    if count == 2 {
+        propagation::dispatch_to_targets(engine, &targets, |node_id| SystemEvent::DoubleClick { node_id, button, x, y });
+    }
+}
+
+/// A button held down on its nodes `hovered` long enough sends a PressAndHold, once per press
+pub(crate) fn tick(engine: &mut RmlEngine, hovered: &[NodeId]) {
+    let Some(press) = engine.clicks.press.as_ref().filter(|press| !press.held) else { return };
+    let (x, y) = engine.get_mouse_position();
+    if !is_close(press.position, (x, y)) {
+        return;
+    }
+    let targets: Vec<NodeId> = press.nodes.iter().copied().filter(|node_id| hovered.contains(node_id)).collect();
+    let Some(&first) = targets.first() else { return };
+    let interval = engine.get_number_property_of_node_id(first, "press_and_hold_interval", PRESS_AND_HOLD_INTERVAL);
+    if engine.clock.now() - press.time < f64::from(interval) / 1000.0 {
+        return;
+    }
+
+    let button = press.button;
+    let handled = press.nodes.iter().any(|&node_id| !engine.event_manager.get_handlers_for_node(node_id, &EventType::PressAndHold).is_empty());
+    if let Some(press) = engine.clicks.press.as_mut() {
+        press.held = true;
+        press.held_handled = handled;
+    }
+    propagation::dispatch_to_targets(engine, &targets, |node_id| SystemEvent::PressAndHold { node_id, button, x, y });
+}
+
+/// The press ended without a release (see `grab::cancel`)
+pub(crate) fn cancel(engine: &mut RmlEngine) {
+    engine.clicks.press = None;
+    engine.clicks.last = None;
+}
+
+/// Drop the removed nodes from the press and the last click
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId]) {
+    if let Some(press) = engine.clicks.press.as_mut() {
+        press.nodes.retain(|node_id| !removed.contains(node_id));
+    }
+    if engine.clicks.last.as_ref().is_some_and(|last| removed.contains(&last.node)) {
+        engine.clicks.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_logging_handler, logged};
+    use crate::{load_rml, TestDriver};
+
+    const SOURCE: &str = r#"
+        Node {
+            id: root
+            string log: ""
+            MouseArea { id: file x: 0 y: 0 width: 100 height: 30 }
+            MouseArea { id: other x: 0 y: 50 width: 100 height: 30 press_and_hold_interval: 500 }
+        }
+    "#;
+
+    fn driver() -> TestDriver {
+        let mut engine = load_rml(SOURCE).unwrap();
+        for node in ["file", "other"] {
+            for event_type in [EventType::Click, EventType::DoubleClick, EventType::PressAndHold] {
+                add_logging_handler(&mut engine, event_type, node, move |engine| match engine.current_event {
+                    Some(SystemEvent::Click { click_count, .. }) => format!("{node}:click{click_count}"),
+                    Some(SystemEvent::DoubleClick { .. }) => format!("{node}:double"),
+                    _ => format!("{node}:hold"),
+                });
+            }
+        }
+        TestDriver::new(engine, 300.0, 300.0)
+    }
+
+    #[test]
+    fn test_clicks_and_double_clicks() {
+        let mut driver = driver();
+        driver.click_at(10.0, 10.0);
+        driver.advance(0.1);
+        driver.click_at(12.0, 10.0);
+        driver.advance(0.1);
+        driver.click_at(12.0, 12.0);
+        assert_eq!(logged(&mut driver), "file:click1 file:click2 file:double file:click3 ");
+
+        // too late for a double click
+        driver.advance(1.0);
+        driver.click_at(12.0, 12.0);
+        assert_eq!(logged(&mut driver), "file:click1 ");
+
+        // released on another area, or too far from the press: no click
+        driver.move_mouse(10.0, 10.0);
+        driver.press(MouseButton::Left);
+        driver.move_mouse(10.0, 60.0);
+        driver.release(MouseButton::Left);
+        driver.move_mouse(10.0, 10.0);
+        driver.press(MouseButton::Left);
+        driver.move_mouse(90.0, 10.0);
+        driver.release(MouseButton::Left);
+        assert_eq!(logged(&mut driver), "");
+    }
+
+    #[test]
+    fn test_press_and_hold() {
+        let mut driver = driver();
+        driver.move_mouse(10.0, 60.0);
+        driver.press(MouseButton::Left);
+        driver.advance(0.4);
+        assert_eq!(logged(&mut driver), "");
+        driver.advance(0.2);
+        driver.advance(1.0);
+        assert_eq!(logged(&mut driver), "other:hold ");
+        // the hold is handled, the release makes no click
+        driver.release(MouseButton::Left);
+        assert_eq!(logged(&mut driver), "");
+
+        // 800 ms by default, a quick press still clicks
+        driver.move_mouse(10.0, 10.0);
+        driver.press(MouseButton::Left);
+        driver.advance(0.5);
+        driver.release(MouseButton::Left);
+        assert_eq!(logged(&mut driver), "file:click1 ");
+    }
+}
@@ -0,0 +1,51 @@
+// Clocks: where the engine reads the time
+//
+// The animations, the flicked ListViews and the timers move on with the time elapsed between two calls of
+// `RmlEngine::update_time`, as told by the clock of the engine. `process_events` calls it once per frame.
+// The default clock is the macroquad one; a `ManualClock` only moves when it is told to, so the headless tests
+// run the timers and the animations deterministically.
+
+use std::sync::{Arc, Mutex};
+
+/// Source of the current time of the engine
+pub trait Clock: Send + Sync {
+    /// Current time in seconds, from an arbitrary origin
+    fn now(&self) -> f64;
+}
+
+/// The macroquad clock, it needs a macroquad window
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameClock;
+
+impl Clock for FrameClock {
+    fn now(&self) -> f64 {
+        macroquad::time::get_time()
+    }
+}
+
+/// A clock moved by hand. Its clones share the same time, keep one to move the clock given to the engine.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Arc<Mutex<f64>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the clock forward by `seconds`
+    pub fn advance(&self, seconds: f64) {
+        *self.now.lock().unwrap() += seconds;
+    }
+
+    pub fn set(&self, seconds: f64) {
+        *self.now.lock().unwrap() = seconds;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        *self.now.lock().unwrap()
+    }
+}
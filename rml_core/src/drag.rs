@@ -0,0 +1,340 @@
+// Drag and drop: MouseAreas dragging items, DropAreas receiving them and the files dropped on the window
+//
+//     Rectangle {
+//         id: card
+//         width: 80 height: 40
+//         MouseArea {
+//             anchors: fill
+//             drag_target: "card"
+//             drag_axis: "x"
+//             drag_minimum_x: 0 drag_maximum_x: 300
+//             drag_key: "card"
+//             number drag_payload: 3
+//         }
+//     }
+//     DropArea {
+//         id: bin
+//         keys: "card files"
+//         on_dropped: { let payload = engine.get_property_of_node_id(...) ... }
+//     }
+//
+// A press on a MouseArea with a `drag_target` starts a drag once the mouse moved `drag_threshold` pixels (8 by
+// default). The target (the id of an item) then follows the mouse on the `drag_axis` ("x", "y" or "xy", the
+// default) inside the `drag_minimum_x`..`drag_maximum_x` and `drag_minimum_y`..`drag_maximum_y` bounds of its
+// `x` and `y`, and the MouseArea has `drag_active: true` until the release. The release does not make a click.
+//
+// The topmost visible DropArea under the mouse (out of the dragged item) whose `keys` hold the `drag_key` of the
+// MouseArea gets the drag: its `payload` is the `drag_payload` of the MouseArea, with its type, and `source` the
+// id of the dragged item. It emits `entered` and `exited` as the drag comes in and goes out, `contains_drag`
+// tells whether the drag is in it, and `drag_x`/`drag_y` give the position of the mouse in it. A release in it
+// emits `dropped`. A DropArea without `keys` takes any drag.
+//
+// The files dropped on the window from the OS go to the DropArea under the mouse taking the key "files", as a
+// `payload` array of paths, with an empty `source`.
+
+use std::path::PathBuf;
+
+use crate::repeater::{add_missing_property, set_or_add_property};
+use crate::{focus, AbstractValue, ItemTypeEnum, NodeId, RmlEngine};
+
+/// Distance the mouse moves before a press becomes a drag, unless the MouseArea gives a `drag_threshold`
+const DRAG_THRESHOLD: f32 = 8.0;
+
+/// Key of the files dropped on the window
+pub const FILES_KEY: &str = "files";
+
+#[derive(Debug)]
+struct Drag {
+    /// the pressed MouseArea and the item it drags
+    area: NodeId,
+    target: Option<NodeId>,
+    press: (f32, f32),
+    /// position of the target when the MouseArea was pressed
+    start: (f32, f32),
+    active: bool,
+    /// the DropArea the drag is in
+    over: Option<NodeId>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Drags {
+    current: Option<Drag>,
+}
+
+pub(crate) fn is_drop_area(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.get_node(node_id).is_some_and(|node| node.node_type == ItemTypeEnum::DropArea)
+}
+
+/// Give its default properties to a DropArea node
+pub(crate) fn attach_drop_area(engine: &mut RmlEngine, node_id: NodeId) {
+    add_missing_property(engine, node_id, "keys", AbstractValue::String(String::new()));
+    add_missing_property(engine, node_id, "contains_drag", AbstractValue::Bool(false));
+    add_missing_property(engine, node_id, "payload", AbstractValue::Null);
+    add_missing_property(engine, node_id, "source", AbstractValue::String(String::new()));
+    add_missing_property(engine, node_id, "drag_x", AbstractValue::Number(0.0));
+    add_missing_property(engine, node_id, "drag_y", AbstractValue::Number(0.0));
+    for signal in ["entered", "exited", "dropped"] {
+        add_missing_property(engine, node_id, signal, AbstractValue::Null);
+    }
+}
+
+/// Give a `drag_active` property to a MouseArea declaring a `drag_target`
+pub(crate) fn attach_mouse_area(engine: &mut RmlEngine, node_id: NodeId) {
+    add_missing_property(engine, node_id, "drag_active", AbstractValue::Bool(false));
+}
+
+/// Mouse press at (`x`, `y`) on the MouseAreas `pressed`, the topmost first: the first one with a `drag_target`
+/// may start a drag. Return true if one may.
+pub(crate) fn press(engine: &mut RmlEngine, x: f32, y: f32, pressed: &[NodeId]) -> bool {
+    let area = pressed.iter().copied().find(|&node_id| {
+        !engine.get_string_property_of_node_id(node_id, "drag_target", String::new()).is_empty()
+    });
+    let Some(area) = area else { return false };
+    let target_id = engine.get_string_property_of_node_id(area, "drag_target", String::new());
+    let target = engine.get_node_id(&target_id);
+    let start = target.map_or((0.0, 0.0), |target| {
+        (engine.get_number_property_of_node_id(target, "x", 0.0), engine.get_number_property_of_node_id(target, "y", 0.0))
+    });
+    engine.drags.current = Some(Drag { area, target, press: (x, y), start, active: false, over: None });
+    true
+}
+
+/// The mouse moved to (`x`, `y`) while the button is down: past the threshold, the target follows it and the
+/// DropAreas under it get the drag
+pub(crate) fn drag(engine: &mut RmlEngine, x: f32, y: f32) {
+    let Some(drag) = engine.drags.current.as_ref() else { return };
+    let (area, target, start) = (drag.area, drag.target, drag.start);
+    let (dx, dy) = (x - drag.press.0, y - drag.press.1);
+    if !drag.active {
+        let threshold = engine.get_number_property_of_node_id(area, "drag_threshold", DRAG_THRESHOLD);
+        if dx.hypot(dy) < threshold {
+            return;
+        }
+        if let Some(drag) = engine.drags.current.as_mut() {
+            drag.active = true;
+        }
+        engine.set_property_of_node_id(area, "drag_active", AbstractValue::Bool(true));
+    }
+
+    if let Some(target) = target {
+        let axis = engine.get_string_property_of_node_id(area, "drag_axis", "xy".to_string());
+        for (name, delta, start) in [("x", dx, start.0), ("y", dy, start.1)] {
+            if !axis.contains(name) {
+                continue;
+            }
+            let minimum = engine.get_number_property_of_node_id(area, &format!("drag_minimum_{name}"), f32::MIN);
+            let maximum = engine.get_number_property_of_node_id(area, &format!("drag_maximum_{name}"), f32::MAX);
+            set_or_add_property(engine, target, name, AbstractValue::Number((start + delta).min(maximum).max(minimum)));
+        }
+    }
+
+    let key = engine.get_string_property_of_node_id(area, "drag_key", String::new());
+    let over = drop_area_at(engine, x, y, &key, target);
+    let previous = engine.drags.current.as_ref().and_then(|drag| drag.over);
+    if over != previous {
+        if let Some(previous) = previous {
+            engine.set_property_of_node_id(previous, "contains_drag", AbstractValue::Bool(false));
+            engine.emit_node_signal(previous, "exited");
+        }
+        if let Some(over) = over {
+            let payload = engine.get_property_by_name(area, "drag_payload").map_or(AbstractValue::Null, |property| property.value.clone());
+            let source = target.and_then(|target| engine.get_node(target)).map(|node| node.id.clone()).unwrap_or_default();
+            engine.set_property_of_node_id(over, "payload", payload);
+            engine.set_property_of_node_id(over, "source", AbstractValue::String(source));
+            engine.set_property_of_node_id(over, "contains_drag", AbstractValue::Bool(true));
+            set_drag_position(engine, over, x, y);
+            engine.emit_node_signal(over, "entered");
+        }
+        if let Some(drag) = engine.drags.current.as_mut() {
+            drag.over = over;
+        }
+    } else if let Some(over) = over {
+        set_drag_position(engine, over, x, y);
+    }
+}
+
+/// The mouse button is released at (`x`, `y`): the DropArea holding the drag gets the drop. Return true if a drag
+/// was active, the release then makes no click.
+pub(crate) fn release(engine: &mut RmlEngine, x: f32, y: f32) -> bool {
+    let Some(drag) = engine.drags.current.take() else { return false };
+    if !drag.active {
+        return false;
+    }
+    engine.set_property_of_node_id(drag.area, "drag_active", AbstractValue::Bool(false));
+    if let Some(over) = drag.over {
+        set_drag_position(engine, over, x, y);
+        engine.set_property_of_node_id(over, "contains_drag", AbstractValue::Bool(false));
+        engine.emit_node_signal(over, "dropped");
+    }
+    true
+}
+
+/// The press ended without a release: the drag stops without a drop
+pub(crate) fn cancel(engine: &mut RmlEngine) {
+    let Some(drag) = engine.drags.current.take() else { return };
+    if !drag.active {
+        return;
+    }
+    engine.set_property_of_node_id(drag.area, "drag_active", AbstractValue::Bool(false));
+    if let Some(over) = drag.over {
+        engine.set_property_of_node_id(over, "contains_drag", AbstractValue::Bool(false));
+        engine.emit_node_signal(over, "exited");
+    }
+}
+
+/// Files dropped on the window, at the mouse position (`x`, `y`)
+pub(crate) fn drop_files(engine: &mut RmlEngine, x: f32, y: f32, paths: &[PathBuf]) {
+    let Some(area) = drop_area_at(engine, x, y, FILES_KEY, None) else { return };
+    let paths = paths.iter().map(|path| AbstractValue::String(path.to_string_lossy().into_owned())).collect();
+    engine.set_property_of_node_id(area, "payload", AbstractValue::Array(paths));
+    engine.set_property_of_node_id(area, "source", AbstractValue::String(String::new()));
+    set_drag_position(engine, area, x, y);
+    engine.emit_node_signal(area, "dropped");
+}
+
+fn set_drag_position(engine: &mut RmlEngine, area: NodeId, x: f32, y: f32) {
+    let rect = engine.layout.get(area).unwrap_or_default();
+    engine.set_property_of_node_id(area, "drag_x", AbstractValue::Number(x - rect.x));
+    engine.set_property_of_node_id(area, "drag_y", AbstractValue::Number(y - rect.y));
+}
+
+/// The topmost visible DropArea at (`x`, `y`) taking the drag key `key`, out of the dragged item `dragged`
+fn drop_area_at(engine: &RmlEngine, x: f32, y: f32, key: &str, dragged: Option<NodeId>) -> Option<NodeId> {
+    let root = engine.get_root_node_id()?;
+    let dragged = dragged.map(|dragged| engine.get_arena().subtree(dragged)).unwrap_or_default();
+    engine.get_arena().subtree(root).into_iter().rev().find(|&node_id| {
+        is_drop_area(engine, node_id)
+            && !dragged.contains(&node_id)
+            && focus::is_shown(engine, node_id)
+            && engine.layout.get(node_id).is_some_and(|rect| rect.contains(x, y))
+            && engine.is_point_clipped_in(node_id, x, y)
+            && takes_key(engine, node_id, key)
+    })
+}
+
+fn takes_key(engine: &RmlEngine, area: NodeId, key: &str) -> bool {
+    let keys = engine.get_string_property_of_node_id(area, "keys", String::new());
+    let mut keys = keys.split([' ', ',']).filter(|key| !key.is_empty()).peekable();
+    keys.peek().is_none() || keys.any(|other| other == key)
+}
+
+/// Drop the drag of removed nodes
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId]) {
+    let Some(drag) = engine.drags.current.as_mut() else { return };
+    if removed.contains(&drag.area) || drag.target.is_some_and(|target| removed.contains(&target)) {
+        engine.drags.current = None;
+    } else if drag.over.is_some_and(|over| removed.contains(&over)) {
+        drag.over = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_rml, TestDriver};
+    use macroquad::prelude::MouseButton;
+    use std::sync::{Arc, Mutex};
+
+    fn payload(driver: &TestDriver, node: &str) -> AbstractValue {
+        driver.engine().get_property_of_node(node, "payload", AbstractValue::Null, |value| Some(value.clone()))
+    }
+
+    const SOURCE: &str = r#"
+        Node {
+            id: root
+            Rectangle {
+                id: card
+                x: 10 y: 10 width: 40 height: 40
+                MouseArea {
+                    id: handle
+                    anchors: fill
+                    drag_target: "card"
+                    drag_axis: "x"
+                    drag_maximum_x: 200
+                    drag_key: "card"
+                    drag_payload: 7
+                }
+            }
+            DropArea { id: bin x: 150 y: 0 width: 100 height: 100 keys: "card files" }
+            DropArea { id: other x: 0 y: 150 width: 100 height: 100 keys: "other" }
+        }
+    "#;
+
+    fn driver() -> TestDriver {
+        TestDriver::new(load_rml(SOURCE).unwrap(), 400.0, 300.0)
+    }
+
+    fn number(driver: &TestDriver, node: &str, property: &str) -> f32 {
+        driver.engine().get_number_property_of_node(node, property, -1.0)
+    }
+
+    #[test]
+    fn test_drag_with_axis_bounds_and_drop() {
+        let mut driver = driver();
+        let signals = Arc::new(Mutex::new(Vec::new()));
+        for signal in ["entered", "exited", "dropped"] {
+            let signals = signals.clone();
+            driver.engine_mut().connect("bin", signal, move |_| signals.lock().unwrap().push(signal));
+        }
+        let click = {
+            let signals = signals.clone();
+            driver.engine_mut().add_callback(move |_| signals.lock().unwrap().push("click"))
+        };
+        driver.engine_mut().add_event_handler(crate::EventType::Click, "handle", click);
+        driver.move_mouse(20.0, 20.0);
+        driver.press(MouseButton::Left);
+        // under the threshold, nothing moves
+        driver.move_mouse(25.0, 22.0);
+        assert_eq!(number(&driver, "card", "x"), 10.0);
+        assert!(!driver.engine().get_bool_property_of_node("handle", "drag_active", true));
+
+        // the card only moves along x
+        driver.move_mouse(100.0, 60.0);
+        assert!(driver.engine().get_bool_property_of_node("handle", "drag_active", false));
+        assert_eq!((number(&driver, "card", "x"), number(&driver, "card", "y")), (90.0, 10.0));
+
+        // in the bin, out of it, in it again, up to the maximum x
+        driver.move_mouse(160.0, 20.0);
+        assert!(driver.engine().get_bool_property_of_node("bin", "contains_drag", false));
+        assert_eq!(payload(&driver, "bin"), AbstractValue::Number(7.0));
+        assert_eq!(driver.engine().get_string_property_of_node("bin", "source", String::new()), "card");
+        driver.move_mouse(140.0, 20.0);
+        driver.move_mouse(240.0, 50.0);
+        assert_eq!(number(&driver, "card", "x"), 200.0);
+        driver.release(MouseButton::Left);
+        assert_eq!(*signals.lock().unwrap(), ["entered", "exited", "entered", "dropped"]);
+        assert_eq!((number(&driver, "bin", "drag_x"), number(&driver, "bin", "drag_y")), (90.0, 50.0));
+        assert!(!driver.engine().get_bool_property_of_node("bin", "contains_drag", true));
+        assert!(!driver.engine().get_bool_property_of_node("handle", "drag_active", true));
+
+        // without a drag, the MouseArea is clicked
+        driver.click_at(210.0, 20.0);
+        assert_eq!(signals.lock().unwrap().last(), Some(&"click"));
+    }
+
+    #[test]
+    fn test_keys_and_file_drops() {
+        let mut driver = driver();
+        let dropped = Arc::new(Mutex::new(0));
+        for area in ["bin", "other"] {
+            let dropped = dropped.clone();
+            driver.engine_mut().connect(area, "dropped", move |_| *dropped.lock().unwrap() += 1);
+        }
+        // the other area does not take the cards
+        driver.move_mouse(20.0, 20.0);
+        driver.press(MouseButton::Left);
+        driver.move_mouse(20.0, 180.0);
+        driver.release(MouseButton::Left);
+        assert!(!driver.engine().get_bool_property_of_node("other", "contains_drag", true));
+        assert_eq!(*dropped.lock().unwrap(), 0);
+
+        // the files go to the bin
+        driver.move_mouse(200.0, 50.0);
+        driver.drop_files(&["/tmp/a.png", "/tmp/b.txt"]);
+        assert_eq!(*dropped.lock().unwrap(), 1);
+        assert_eq!(payload(&driver, "bin"), AbstractValue::Array(vec![
+            AbstractValue::String("/tmp/a.png".to_string()),
+            AbstractValue::String("/tmp/b.txt".to_string()),
+        ]));
+    }
+}
@@ -1,404 +1,310 @@
-use std::arch::x86_64;
-
 use macroquad::prelude::*;
-use crate::{RmlEngine, ItemTypeEnum};
-
-#[derive(Debug, Clone, PartialEq)]
-enum Anchor {
-    Top,
-    Bottom, 
-    Left,
-    Right,
-    HorizontalCenter,
-    VerticalCenter,
-    Center,
-    Fill,
-}
-
-impl Anchor {
-    fn from_str(s: &str) -> Option<Self> {
-        match s {
-            "top" => Some(Anchor::Top),
-            "bottom" => Some(Anchor::Bottom),
-            "left" => Some(Anchor::Left),
-            "right" => Some(Anchor::Right),
-            "horizontal_center" => Some(Anchor::HorizontalCenter),
-            "vertical_center" => Some(Anchor::VerticalCenter),
-            "center" => Some(Anchor::Center),
-            "fill" => Some(Anchor::Fill),
-            _ => None,
-        }
+use crate::{text_input, text_layout, RmlEngine, ItemTypeEnum, NodeId};
+use crate::text_layout::TextLayout;
+use crate::layout::Rect;
+use crate::renderer::{MacroquadRenderer, Renderer, TextStyle};
+
+fn draw_text_with_wrap(renderer: &mut dyn Renderer, text: &str, x: f32, y: f32, max_width: f32, style: &TextStyle) {
+    // the lines end at the newlines, and are wrapped at `max_width` if there is one
+    let max_width = (max_width > 0.0).then_some(max_width);
+    let line_height = text_layout::line_height(style.font_size as f32);
+    let text_layout = TextLayout::new(text, max_width, line_height, |line| renderer.measure_text(line, style).width);
+
+    // the renderer draws text from its baseline, the same for all the lines
+    let baseline_offset = renderer.measure_text(text, style).offset_y;
+    for (i, line) in text_layout.lines.iter().enumerate() {
+        renderer.draw_text(&line.text, x, y + baseline_offset + i as f32 * line_height, style);
     }
 }
 
-#[derive(Debug, Clone)]
-struct Margins {
-    top: f32,
-    bottom: f32,
-    left: f32,
-    right: f32,
-}
-
-impl Margins {
-    fn new(engine: &RmlEngine, node_id: &str) -> Self {
-        let base_margin = engine.get_number_property_of_node(node_id, "margins", 0.0);
-        Self {
-            top: engine.get_number_property_of_node(node_id, "top_margin", base_margin),
-            bottom: engine.get_number_property_of_node(node_id, "bottom_margin", base_margin),
-            left: engine.get_number_property_of_node(node_id, "left_margin", base_margin),
-            right: engine.get_number_property_of_node(node_id, "right_margin", base_margin),
-        }
-    }
-
-    fn apply_anchor_constraints(&mut self, anchors: &[Anchor]) {
-        for anchor in anchors {
-            match anchor {
-                Anchor::HorizontalCenter => {
-                    self.left = 0.0;
-                    self.right = 0.0;
-                }
-                Anchor::VerticalCenter => {
-                    self.top = 0.0;
-                    self.bottom = 0.0;
-                }
-                Anchor::Center if anchors.len() == 1 => {
-                    self.left = 0.0;
-                    self.right = 0.0;
-                    self.top = 0.0;
-                    self.bottom = 0.0;
-                }
-                _ => {} // other anchors keep their margins
-            }
-        }
-
-        // if fill is specified, don't alter margins
-        if anchors.contains(&Anchor::Fill) { return; }
-
-        // special logic: if we don't have the corresponding anchor, no margin
-        if !anchors.contains(&Anchor::Left) { self.left = 0.0; }
-        if !anchors.contains(&Anchor::Right) { self.right = 0.0; }
-        if !anchors.contains(&Anchor::Top) { self.top = 0.0; }
-        if !anchors.contains(&Anchor::Bottom) { self.bottom = 0.0; }
-    }
-}
+pub fn draw_round_rect(x: f32, y: f32, w: f32, h: f32, r: f32, color: Color) {
+    // rectangle central
+    draw_rectangle(x + r, y, w - 2.0*r, h, color);
+    draw_rectangle(x, y + r, w, h - 2.0*r, color);
 
-#[derive(Debug, Clone)]
-struct Geometry {
-    x: f32,
-    y: f32,
-    width: f32,
-    height: f32,
+    // 4 coins arrondis (cercles)
+    draw_circle(x + r,     y + r,     r, color);
+    draw_circle(x + w - r, y + r,     r, color);
+    draw_circle(x + r,     y + h - r, r, color);
+    draw_circle(x + w - r, y + h - r, r, color);
 }
 
-impl Geometry {
-    fn new(engine: &RmlEngine, node_id: &str) -> Self {
-        let mut geometry = Self {
-            x: engine.get_number_property_of_node(node_id, "x", 0.0),
-            y: engine.get_number_property_of_node(node_id, "y", 0.0),
-            width: engine.get_number_property_of_node(node_id, "width", 0.0),
-            height: engine.get_number_property_of_node(node_id, "height", 0.0),
-        };
-
-        // if the node is a text, and don't have a width or height, we need to measure its size
-        if let Some(node) = engine.get_node_by_id(node_id) {
-            if node.node_type == ItemTypeEnum::Text {
-                let text = engine.get_string_property_of_node(node_id, "text", String::new());
-                let font_size = engine.get_number_property_of_node(node_id, "font_size", 20.0);
-                let wanted_font = engine.get_string_property_of_node(node_id, "font", String::new());
-                
-                // mesure the text dimensions
-                let text_dimensions = measure_text(&text, engine.get_font(&wanted_font), font_size as u16, 1.0);
-                if geometry.width == 0.0 {
-                    geometry.width = text_dimensions.width;
-                }
-                if geometry.height == 0.0 {
-                    geometry.height = text_dimensions.height;
-                }
+/// Draw the text of a TextInput or a TextArea (or its placeholder) scrolled to its cursor, with its selection and
+/// its cursor when it has the focus
+fn draw_text_input(engine: &RmlEngine, renderer: &mut dyn Renderer, node_id: NodeId, rect: Rect, clip: Option<Rect>) {
+    let Some(edit) = engine.text_inputs.edits.get(&node_id) else { return };
+    let focus = engine.get_bool_property_of_node_id(node_id, "active_focus", false);
+    let text = text_input::displayed_text(engine, node_id);
+    let placeholder = engine.get_string_property_of_node_id(node_id, "placeholder", String::new());
+    let color = engine.get_color_property_of_node_id(node_id, "color", WHITE);
+    let style = text_input::text_style(engine, node_id, color);
+    let text_layout = &edit.layout;
+    let line_height = text_layout.line_height;
+    let (left, top) = text_input::text_origin(engine, node_id, rect);
+    let line_top = |index: usize| top + index as f32 * line_height;
+
+    let clip_rect = clip.map_or(rect, |clip| clip.intersection(rect));
+    renderer.push_clip(clip_rect);
+    // the lines out of the item are skipped
+    let shown = |index: usize| line_top(index) + line_height > clip_rect.y && line_top(index) < clip_rect.y + clip_rect.height;
+    let (start, end) = text_input::selection(engine, node_id);
+    if focus && start != end {
+        let selection_color = engine.get_color_property_of_node_id(node_id, "selection_color", BLUE);
+        for (index, line) in text_layout.lines.iter().enumerate() {
+            if line.end < start || line.start >= end || !shown(index) {
+                continue;
             }
+            let offset = |position: usize| line.offsets.get(position - line.start).copied().unwrap_or(0.0);
+            let (from, to) = (offset(start.max(line.start)), offset(end.min(line.end)));
+            // a selected newline shows as a bit of selection after its line
+            let newline = end > line.end && text_layout.lines.get(index + 1).is_some_and(|next| next.start > line.end);
+            let width = to - from + if newline { line_height / 4.0 } else { 0.0 };
+            renderer.fill_rect(left + from, line_top(index), width, line_height, selection_color);
         }
-
-        geometry
     }
-
-    fn apply_anchors(&mut self, anchors: &[Anchor], parent_size: (f32, f32)) {
-        let (parent_width, parent_height) = parent_size;
-
-        for anchor in anchors {
-            match anchor {
-                Anchor::Top => self.y = 0.0,
-                Anchor::Bottom => self.y = parent_height - self.height,
-                Anchor::Left => self.x = 0.0,
-                Anchor::Right => self.x = parent_width - self.width,
-                Anchor::HorizontalCenter => self.x = (parent_width - self.width) / 2.0,
-                Anchor::VerticalCenter => self.y = (parent_height - self.height) / 2.0,
-                Anchor::Center => {
-                    self.x = (parent_width - self.width) / 2.0;
-                    self.y = (parent_height - self.height) / 2.0;
-                }
-                Anchor::Fill => {
-                    self.x = 0.0;
-                    self.y = 0.0;
-                    self.width = parent_width;
-                    self.height = parent_height;
-                }
+    if !text.is_empty() {
+        let baseline_offset = renderer.measure_text(&text, &style).offset_y;
+        for (index, line) in text_layout.lines.iter().enumerate() {
+            if shown(index) && !line.text.is_empty() {
+                renderer.draw_text(&line.text, left, line_top(index) + baseline_offset, &style);
             }
         }
-
-        // Gestion des cas spéciaux pour le remplissage
-        if anchors.contains(&Anchor::Top) && anchors.contains(&Anchor::Bottom) {
-            self.y = 0.0;
-            self.height = parent_height;
-        }
-        if anchors.contains(&Anchor::Left) && anchors.contains(&Anchor::Right) {
-            self.x = 0.0;
-            self.width = parent_width;
-        }
-    }
-
-    fn apply_margins(&mut self, margins: &Margins, anchors: &[Anchor], parent_pos: (f32, f32)) {
-        let (parent_x, parent_y) = parent_pos;
-        
-        // fill special case; if fill is specified, also alter the width and height
-        if anchors.contains(&Anchor::Fill) {
-            self.x = parent_x + self.x + margins.left;
-            self.y = parent_y + self.y + margins.top;
-            self.width = self.width - margins.left - margins.right;
-            self.height = self.height - margins.top - margins.bottom;
-        } else {
-            self.x = parent_x + self.x + margins.left - margins.right;
-            self.y = parent_y + self.y + margins.top - margins.bottom;
-        }
+    } else if !placeholder.is_empty() {
+        let placeholder_color = engine.get_color_property_of_node_id(node_id, "placeholder_color", GRAY);
+        let style = text_input::text_style(engine, node_id, placeholder_color);
+        let baseline = top + renderer.measure_text(&placeholder, &style).offset_y;
+        renderer.draw_text(&placeholder, rect.x, baseline, &style);
     }
-}
-
-fn parse_anchors(anchor_string: &str) -> Vec<Anchor> {
-    anchor_string
-        .split("__")
-        .filter_map(|s| Anchor::from_str(s.trim()))
-        .collect()
-}
-
-fn get_parent_size(engine: &RmlEngine, node_id: &str) -> (f32, f32) {
-    match engine.get_parent_by_id(node_id) {
-        Some(parent) => {
-            let width = engine.get_number_property_of_node(&parent.id, "computed_width", 0.0);
-            let height = engine.get_number_property_of_node(&parent.id, "computed_height", 0.0);
-            (width, height)
-        }
-        None => (0.0, 0.0),
+    if focus {
+        let cursor = engine.get_number_property_of_node_id(node_id, "cursor_position", 0.0).max(0.0) as usize;
+        let (x, y) = text_layout.point_of(cursor);
+        renderer.fill_rect(left + x, top + y, 1.0, line_height, color);
     }
+    renderer.pop_clip();
 }
 
-fn compute_root_geometry(window_width: f32, window_height: f32, engine: &RmlEngine, node_id: &str) -> (f32, f32, f32, f32) {
-    let mut geometry = Geometry::new(engine, node_id);
-
-    let anchor_string = engine.get_string_property_of_node(node_id, "anchors", String::new());
-    let anchors = parse_anchors(&anchor_string);
-    geometry.apply_anchors(&anchors, (window_width, window_height));
-
-    let mut margins = Margins::new(engine, node_id);
-    margins.apply_anchor_constraints(&anchors);
-    geometry.apply_margins(&margins, &anchors, (0.0, 0.0));
-
-    (geometry.x, geometry.y, geometry.width, geometry.height)
+pub fn draw_root(engine: &mut RmlEngine) {
+    draw_root_with_renderer(engine, &mut MacroquadRenderer::new());
 }
 
-fn compute_geometry(engine: &RmlEngine, parent_pos: (f32, f32), node_id: &str) -> (f32, f32, f32, f32) {
-    let mut geometry = Geometry::new(engine, node_id);
-    let parent_size = get_parent_size(engine, node_id);
-    
-    let anchor_string = engine.get_string_property_of_node(node_id, "anchors", String::new());
-    let anchors = parse_anchors(&anchor_string);
-    geometry.apply_anchors(&anchors, parent_size);
+pub fn draw_root_with_renderer(engine: &mut RmlEngine, renderer: &mut dyn Renderer) {
+    // lay out the dirty parts of the tree, then draw the root node and its childs
+    engine.update_layout(renderer);
 
-    let mut margins = Margins::new(engine, node_id);
-    margins.apply_anchor_constraints(&anchors);
-    geometry.apply_margins(&margins, &anchors, parent_pos);
-
-    (geometry.x, geometry.y, geometry.width, geometry.height)
-}
+    if let Some(root_id) = engine.get_root_node_id() {
+        let rect = engine.get_layout().get(root_id).unwrap_or_default();
+        let color = engine.get_color_property_of_node_id(root_id, "color", RED);
+        renderer.fill_rect(rect.x, rect.y, rect.width, rect.height, color);
 
-fn draw_text_with_wrap(text: &str, x: f32, y: f32, max_width: f32, text_params: &TextParams) {
-    let font_size = text_params.font_size as f32;
-    
-    if max_width <= 0.0 {
-        // if there is no max width, just draw the text at the specified position
-        // adding the baseline offset to the y position because macroquad's draw_text_ex uses the baseline offset
-        let text_dims = measure_text(&text, text_params.font, font_size as u16, 1.0);
-        let baseline_offset = text_dims.offset_y; // macroquad fournit cet offset
-        let adjusted_y = y + baseline_offset;
-        draw_text_ex(text, x, adjusted_y, text_params.clone());
-        return;
-    }
-
-    let line_height = font_size * 1.2; // space between lines, TODO should be a default value or configurable
-    
-    let words: Vec<&str> = text.split_whitespace().collect();
-    let mut lines = Vec::new();
-    let mut current_line = String::new();
-    
-    for word in words {
-        let test_line = if current_line.is_empty() {
-            word.to_string()
-        } else {
-            format!("{} {}", current_line, word)
-        };
-        
-        let test_width = measure_text(&test_line, text_params.font, text_params.font_size, 1.0).width;
-        
-        if test_width <= max_width {
-            current_line = test_line;
-        } else {
-            if !current_line.is_empty() {
-                lines.push(current_line.clone());
-                current_line = word.to_string();
-            } else {
-                // the word itself is too long, push it as a new line
-                // TODO handle this case better, maybe split the word or truncate it if some parameter is set
-                lines.push(word.to_string());
-            }
-        }
-    }
-    
-    if !current_line.is_empty() {
-        lines.push(current_line);
-    }
-    
-    // draw each line with the adjusted y position
-    for (i, line) in lines.iter().enumerate() {
-        let text_dims = measure_text(&text, text_params.font, font_size as u16, 1.0);
-        let baseline_offset = text_dims.offset_y; // macroquad fournit cet offset
-        let adjusted_y = y + baseline_offset + (i as f32 * line_height);
-        draw_text_ex(line, x, adjusted_y, text_params.clone());
+        draw_node_childs(engine, renderer, root_id, None);
     }
 }
 
-pub fn draw_round_rect(x: f32, y: f32, w: f32, h: f32, r: f32, color: Color) {
-    // rectangle central
-    draw_rectangle(x + r, y, w - 2.0*r, h, color);
-    draw_rectangle(x, y + r, w, h - 2.0*r, color);
-
-    // 4 coins arrondis (cercles)
-    draw_circle(x + r,     y + r,     r, color);
-    draw_circle(x + w - r, y + r,     r, color);
-    draw_circle(x + r,     y + h - r, r, color);
-    draw_circle(x + w - r, y + h - r, r, color);
+/// Draw the childs of `node_id`, at the positions of the layout tree
+pub fn draw_childs(engine: &mut RmlEngine, node_id: &str) {
+    draw_childs_with_renderer(engine, &mut MacroquadRenderer::new(), node_id);
 }
 
-pub fn draw_root(engine: &mut RmlEngine) {
-    // draw the root node,
-    // then draw chils
-    if let Some(root_node) = engine.get_root_node_str_id() {
-        // get  macroquad window size
-        let (window_width, window_height) = miniquad::window::screen_size();
-        let (x, y, width, height) = compute_root_geometry(window_width, window_height, engine, &root_node);
-
-        engine.set_property_of_node(&root_node, "x", crate::AbstractValue::Number(x));
-        engine.set_property_of_node(&root_node, "y", crate::AbstractValue::Number(y));
-        engine.set_property_of_node(&root_node, "width", crate::AbstractValue::Number(width));
-        engine.set_property_of_node(&root_node, "height", crate::AbstractValue::Number(height));
-        engine.set_property_of_node(&root_node, "computed_x", crate::AbstractValue::Number(x));
-        engine.set_property_of_node(&root_node, "computed_y", crate::AbstractValue::Number(y));
-        engine.set_property_of_node(&root_node, "computed_width", crate::AbstractValue::Number(width));
-        engine.set_property_of_node(&root_node, "computed_height", crate::AbstractValue::Number(height));
-
-        let color = engine.get_color_property_of_node(&root_node, "color", RED);
-        draw_rectangle(x, y, width, height, color);
-
-        draw_childs(engine, "root", (0.0, 0.0));
+pub fn draw_childs_with_renderer(engine: &mut RmlEngine, renderer: &mut dyn Renderer, node_id: &str) {
+    engine.update_layout(renderer);
+    if let Some(node_id) = engine.get_node_id(node_id) {
+        draw_node_childs(engine, renderer, node_id, None);
     }
 }
 
-pub fn draw_childs(engine: &mut RmlEngine, node_id: &str, parent_pos: (f32, f32)) {
-    // Get the node by its ID
-    let children_ids = engine.get_children_str_ids_by_id(node_id).unwrap_or_default();
-    if children_ids.is_empty() { return; }
+/// Draw the childs of `node_id`, `clip` is the part of the window they can draw in (the intersection
+/// of the rects of their clipping ancestors), `None` when nothing clips them
+fn draw_node_childs(engine: &RmlEngine, renderer: &mut dyn Renderer, node_id: NodeId, clip: Option<Rect>) {
+    for node_id in engine.get_arena().get_childrens_ids(node_id) {
+        let Some(Rect { x, y, width, height }) = engine.get_layout().get(node_id) else { continue };
 
-    // compute geometry for the node
-    for node_id in &children_ids {
-        let (x, y, width, height) = compute_geometry(engine, parent_pos, node_id);
-        // Store computed geometry in node properties for event system reuse
-        // x, y are absolute coordinates (computed_geometry includes parent_pos)
-        engine.set_property_of_node(node_id, "computed_x", crate::AbstractValue::Number(x));
-        engine.set_property_of_node(node_id, "computed_y", crate::AbstractValue::Number(y));
-        engine.set_property_of_node(node_id, "computed_width", crate::AbstractValue::Number(width));
-        engine.set_property_of_node(node_id, "computed_height", crate::AbstractValue::Number(height));
-        
-        let visible = engine.get_bool_property_of_node(node_id, "visible", true);
+        let visible = engine.get_bool_property_of_node_id(node_id, "visible", true);
         if !visible {
             continue; // skip drawing if the node is not visible
         }
-        
-        let node_type = engine.get_node_type(node_id).unwrap_or(ItemTypeEnum::Node);
+
+        let node_type = engine.get_node(node_id).map(|node| node.node_type.clone()).unwrap_or(ItemTypeEnum::Node);
         // render the node according to its type
         match node_type {
             ItemTypeEnum::Rectangle => {
-                let color = engine.get_color_property_of_node(node_id, "color", WHITE);
-                let radius = engine.get_number_property_of_node(node_id, "radius", 0.0);
+                let color = engine.get_color_property_of_node_id(node_id, "color", WHITE);
+                let radius = engine.get_number_property_of_node_id(node_id, "radius", 0.0);
                 if radius > 0.0 {
-                    draw_round_rect(x, y, width, height, radius, color);
+                    renderer.fill_round_rect(x, y, width, height, radius, color);
                 } else {
-                    draw_rectangle(x, y, width, height, color);
+                    renderer.fill_rect(x, y, width, height, color);
                 }
             }
             ItemTypeEnum::Text => {
-                let text = engine.get_string_property_of_node(node_id, "text", String::new());
-                let color = engine.get_color_property_of_node(node_id, "color", WHITE);
-                let font_size = engine.get_number_property_of_node(node_id, "font_size", 20.0);
-                let wanted_font = engine.get_string_property_of_node(node_id, "font", String::new());
-                let max_width = engine.get_number_property_of_node(node_id, "max_width", 100000.0);
+                let text = engine.get_string_property_of_node_id(node_id, "text", String::new());
+                let color = engine.get_color_property_of_node_id(node_id, "color", WHITE);
+                let font_size = engine.get_number_property_of_node_id(node_id, "font_size", 20.0);
+                let wanted_font = engine.get_string_property_of_node_id(node_id, "font", String::new());
+                let max_width = engine.get_number_property_of_node_id(node_id, "max_width", 100000.0);
 
-                let text_dimensions = measure_text(&text, engine.get_font(&wanted_font), font_size as u16, 1.0);
-                engine.set_property_of_node(node_id, "implicit_width", text_dimensions.width.into());
-                engine.set_property_of_node(node_id, "implicit_height", text_dimensions.height.into());
-                
-                let text_params = TextParams {
+                let style = TextStyle {
                     font: engine.get_font(&wanted_font),
                     font_size: font_size as u16,
-                    font_scale: 1.0,
                     color,
-                    ..Default::default()
                 };
                 let minimal_width = if max_width < width { max_width } else { width };
-                draw_text_with_wrap(&text, x, y, minimal_width, &text_params);
+                draw_text_with_wrap(renderer, &text, x, y, minimal_width, &style);
             }
             ItemTypeEnum::MouseArea => {
                 // MouseArea is invisible by default, but can have a debug color
-                let debug = engine.get_bool_property_of_node(node_id, "debug", false);
+                let debug = engine.get_bool_property_of_node_id(node_id, "debug", false);
                 if debug {
-                    let debug_color = engine.get_color_property_of_node(node_id, "debug_color", 
+                    let debug_color = engine.get_color_property_of_node_id(node_id, "debug_color", 
                         Color::from_rgba(255, 0, 0, 50)); // Semi-transparent red
-                    draw_rectangle(x, y, width, height, debug_color);
+                    renderer.fill_rect(x, y, width, height, debug_color);
                 }
             }
             ItemTypeEnum::Texture => {
-                let texture_name = engine.get_string_property_of_node(node_id, "source", String::new());
-                let keep_aspect_ratio = engine.get_bool_property_of_node(node_id, "keep_aspect_ratio", false);
+                let texture_name = engine.get_string_property_of_node_id(node_id, "source", String::new());
+                let keep_aspect_ratio = engine.get_bool_property_of_node_id(node_id, "keep_aspect_ratio", false);
                 let mut w = width; let mut h = height;
                 let mut x = x; let mut y = y;
                 if keep_aspect_ratio {
                     w = w.min(h);
                     h = w;
                     // center the texture in the allocated area
-                    x = x + (width - w) / 2.0;
-                    y = y + (height - h) / 2.0;
-                }
-                if let Some(texture) = engine.get_texture(&texture_name) {
-                    draw_texture_ex(
-                        texture,
-                        x,
-                        y,
-                        WHITE,
-                        DrawTextureParams {
-                            dest_size: Some(Vec2::new(w, h)),
-                            ..Default::default()
-                        },
-                    );
+                    x += (width - w) / 2.0;
+                    y += (height - h) / 2.0;
                 }
+                renderer.draw_texture(&texture_name, engine.get_texture(&texture_name), x, y, w, h);
             }
+            ItemTypeEnum::TextInput | ItemTypeEnum::TextArea => draw_text_input(engine, renderer, node_id, Rect::new(x, y, width, height), clip),
             _ => {} // Node type and others
         }
 
-        draw_childs(engine, node_id, (x, y));
+        // `clip: true` (and a ListView) keeps the descendants in the rect of the node
+        if engine.clips_childs(node_id) {
+            let rect = Rect::new(x, y, width, height);
+            let rect = clip.map_or(rect, |clip| clip.intersection(rect));
+            renderer.push_clip(rect);
+            draw_node_childs(engine, renderer, node_id, Some(rect));
+            renderer.pop_clip();
+        } else {
+            draw_node_childs(engine, renderer, node_id, clip);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_node, sample_engine};
+    use crate::{AbstractValue, Property, DrawCommand, RecordingRenderer};
+
+    #[test]
+    fn test_draw_root_records_commands() {
+        let mut engine = sample_engine();
+        let mut renderer = RecordingRenderer::new(200.0, 100.0);
+        draw_root_with_renderer(&mut engine, &mut renderer);
+
+        assert_eq!(renderer.commands[0], DrawCommand::Rect { x: 0.0, y: 0.0, width: 200.0, height: 100.0, color: DARKGRAY });
+        assert_eq!(renderer.commands[1], DrawCommand::Rect { x: 10.0, y: 10.0, width: 180.0, height: 80.0, color: BLUE });
+        match &renderer.commands[2] {
+            DrawCommand::Text { text, x, font_size, .. } => {
+                assert_eq!(text, "hello");
+                // 5 chars * 20 * 0.5 = 50 wide, centered in the 180 wide panel
+                assert_eq!(*x, 10.0 + (180.0 - 50.0) / 2.0);
+                assert_eq!(*font_size, 20);
+            }
+            other => panic!("expected a text command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_viewport_size_drives_root_geometry() {
+        let mut engine = sample_engine();
+        let mut renderer = RecordingRenderer::new(200.0, 100.0);
+        draw_root_with_renderer(&mut engine, &mut renderer);
+
+        renderer.clear();
+        renderer.set_viewport_size(400.0, 300.0);
+        draw_root_with_renderer(&mut engine, &mut renderer);
+
+        assert_eq!(engine.get_number_property_of_node("panel", "computed_width", 0.0), 380.0);
+        assert_eq!(engine.get_number_property_of_node("panel", "computed_height", 0.0), 280.0);
+    }
+
+    #[test]
+    fn test_invisible_nodes_are_not_drawn() {
+        let mut engine = sample_engine();
+        let visible = engine.add_property(Property::new(AbstractValue::Bool(false)));
+        let panel = engine.get_node_id("panel").unwrap();
+        engine.add_property_to_node(panel, "visible".to_string(), visible);
+
+        let mut renderer = RecordingRenderer::new(200.0, 100.0);
+        draw_root_with_renderer(&mut engine, &mut renderer);
+        assert_eq!(renderer.commands.len(), 1);
+    }
+
+    #[test]
+    fn test_draw_childs_lays_out_from_the_root() {
+        let mut engine = sample_engine();
+        add_node(&mut engine, Some("root"), "overlay", ItemTypeEnum::Rectangle, &[
+            ("anchors", AbstractValue::String("bottom__right".into())),
+            ("width", AbstractValue::Number(20.0)),
+            ("height", AbstractValue::Number(10.0)),
+        ]);
+        let mut renderer = RecordingRenderer::new(200.0, 100.0);
+        draw_childs_with_renderer(&mut engine, &mut renderer, "root");
+
+        assert_eq!(renderer.commands.last(), Some(&DrawCommand::Rect { x: 180.0, y: 90.0, width: 20.0, height: 10.0, color: WHITE }));
+    }
+
+    /// the panel (10, 10, 180, 80) clips a box moved 150 to the right, which clips a MouseArea overflowing it
+    fn clipped_engine() -> RmlEngine {
+        let mut engine = sample_engine();
+        engine.set_property_of_node("label", "text", AbstractValue::String("a text much too long for the panel".into()));
+        let clip = engine.add_property(Property::new(AbstractValue::Bool(true)));
+        let panel = engine.get_node_id("panel").unwrap();
+        engine.add_property_to_node(panel, "clip".to_string(), clip);
+        add_node(&mut engine, Some("panel"), "box", ItemTypeEnum::Rectangle, &[
+            ("x", AbstractValue::Number(150.0)),
+            ("width", AbstractValue::Number(100.0)),
+            ("height", AbstractValue::Number(50.0)),
+            ("clip", AbstractValue::Bool(true)),
+        ]);
+        add_node(&mut engine, Some("box"), "area", ItemTypeEnum::MouseArea, &[
+            ("y", AbstractValue::Number(40.0)),
+            ("width", AbstractValue::Number(100.0)),
+            ("height", AbstractValue::Number(100.0)),
+        ]);
+        engine
+    }
+
+    #[test]
+    fn test_nested_clips_intersect() {
+        let mut engine = clipped_engine();
+        let mut renderer = RecordingRenderer::new(200.0, 100.0);
+        draw_root_with_renderer(&mut engine, &mut renderer);
+
+        let clips: Vec<&DrawCommand> = renderer.commands.iter()
+            .filter(|command| matches!(command, DrawCommand::PushClip { .. } | DrawCommand::PopClip))
+            .collect();
+        assert_eq!(clips, [
+            &DrawCommand::PushClip { x: 10.0, y: 10.0, width: 180.0, height: 80.0 },
+            &DrawCommand::PushClip { x: 160.0, y: 10.0, width: 30.0, height: 50.0 },
+            &DrawCommand::PopClip,
+            &DrawCommand::PopClip,
+        ]);
+        // the overflowing text is drawn inside the clip of the panel
+        let text = renderer.commands.iter().position(|command| matches!(command, DrawCommand::Text { .. })).unwrap();
+        assert_eq!(renderer.commands[text - 2], DrawCommand::Rect { x: 10.0, y: 10.0, width: 180.0, height: 80.0, color: BLUE });
+        assert!(matches!(renderer.commands[text - 1], DrawCommand::PushClip { .. }));
+    }
+
+    #[test]
+    fn test_clipped_mouse_areas_are_not_hit() {
+        let mut engine = clipped_engine();
+        engine.update_layout(&RecordingRenderer::new(200.0, 100.0));
+        let area = engine.get_node_id("area").unwrap();
+        // the MouseArea spans (160, 50) to (260, 150), only (160, 50) to (190, 60) is visible
+        assert_eq!(engine.get_mouse_area_nodes_at_position(170.0, 55.0), [area]);
+        assert!(engine.get_mouse_area_nodes_at_position(170.0, 70.0).is_empty(), "outside of the box");
+        assert!(engine.get_mouse_area_nodes_at_position(195.0, 55.0).is_empty(), "outside of the panel");
     }
 }
@@ -1,5 +1,6 @@
 use macroquad::prelude::*;
 use std::collections::HashSet;
+use crate::input::{InputSource, InputState, MacroquadInput};
 use crate::{CallbackId, NodeId};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -11,7 +12,13 @@ pub enum SystemEvent {
     MouseLeave { node_id: NodeId },
     MouseDown { node_id: NodeId, button: MouseButton, x: f32, y: f32 },
     MouseUp { node_id: NodeId, button: MouseButton, x: f32, y: f32 },
-    Click { node_id: NodeId, button: MouseButton, x: f32, y: f32 },
+    /// A press and a release on the same node, `click_count` is 2 for the second click of a double click
+    Click { node_id: NodeId, button: MouseButton, x: f32, y: f32, click_count: u32 },
+    DoubleClick { node_id: NodeId, button: MouseButton, x: f32, y: f32 },
+    /// The button stays down on the node for its `press_and_hold_interval`
+    PressAndHold { node_id: NodeId, button: MouseButton, x: f32, y: f32 },
+    /// The press of a node grabbing the mouse ended without a release, the window lost the focus
+    Canceled { node_id: NodeId },
     
     // Window events
     WindowResize { node_id: NodeId, width: f32, height: f32 },
@@ -22,6 +29,11 @@ pub enum SystemEvent {
     KeyDown { node_id: NodeId, key: KeyCode },
     KeyUp { node_id: NodeId, key: KeyCode },
     KeyPressed { node_id: NodeId, key: KeyCode },
+    CharInput { node_id: NodeId, character: char },
+
+    // Focus events
+    FocusIn { node_id: NodeId },
+    FocusOut { node_id: NodeId },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -29,6 +41,9 @@ pub enum EventType {
     KeyDown,
     KeyUp,
     KeyPressed,
+    CharInput,
+    FocusIn,
+    FocusOut,
     MouseDown,
     MouseUp,
     MouseMove,
@@ -36,6 +51,9 @@ pub enum EventType {
     MouseEnter,
     MouseLeave,
     Click,
+    DoubleClick,
+    PressAndHold,
+    Canceled,
     WindowResize,
     WindowFocus,
     WindowLostFocus,
@@ -47,6 +65,9 @@ impl SystemEvent {
             SystemEvent::KeyDown { .. } => EventType::KeyDown,
             SystemEvent::KeyUp { .. } => EventType::KeyUp,
             SystemEvent::KeyPressed { .. } => EventType::KeyPressed,
+            SystemEvent::CharInput { .. } => EventType::CharInput,
+            SystemEvent::FocusIn { .. } => EventType::FocusIn,
+            SystemEvent::FocusOut { .. } => EventType::FocusOut,
             SystemEvent::MouseDown { .. } => EventType::MouseDown,
             SystemEvent::MouseUp { .. } => EventType::MouseUp,
             SystemEvent::MouseMove { .. } => EventType::MouseMove,
@@ -54,11 +75,107 @@ impl SystemEvent {
             SystemEvent::MouseEnter { .. } => EventType::MouseEnter,
             SystemEvent::MouseLeave { .. } => EventType::MouseLeave,
             SystemEvent::Click { .. } => EventType::Click,
+            SystemEvent::DoubleClick { .. } => EventType::DoubleClick,
+            SystemEvent::PressAndHold { .. } => EventType::PressAndHold,
+            SystemEvent::Canceled { .. } => EventType::Canceled,
             SystemEvent::WindowResize { .. } => EventType::WindowResize,
             SystemEvent::WindowFocus { .. } => EventType::WindowFocus,
             SystemEvent::WindowLostFocus { .. } => EventType::WindowLostFocus,
         }
     }
+
+    /// The node the event is for, none for the window events
+    pub fn node_id(&self) -> Option<NodeId> {
+        match self {
+            SystemEvent::MouseMove { node_id, .. }
+            | SystemEvent::MouseWheel { node_id, .. }
+            | SystemEvent::MouseEnter { node_id }
+            | SystemEvent::MouseLeave { node_id }
+            | SystemEvent::MouseDown { node_id, .. }
+            | SystemEvent::MouseUp { node_id, .. }
+            | SystemEvent::Click { node_id, .. }
+            | SystemEvent::DoubleClick { node_id, .. }
+            | SystemEvent::PressAndHold { node_id, .. }
+            | SystemEvent::Canceled { node_id }
+            | SystemEvent::KeyDown { node_id, .. }
+            | SystemEvent::KeyUp { node_id, .. }
+            | SystemEvent::KeyPressed { node_id, .. }
+            | SystemEvent::CharInput { node_id, .. }
+            | SystemEvent::FocusIn { node_id }
+            | SystemEvent::FocusOut { node_id } => Some(*node_id),
+            SystemEvent::WindowResize { .. } | SystemEvent::WindowFocus { .. } | SystemEvent::WindowLostFocus { .. } => None,
+        }
+    }
+
+    /// Whether the event goes through the ancestors of its node, the capture handlers on its way down and the
+    /// other handlers on its way up. The moves, enters and leaves of the mouse and the focus changes only go to
+    /// their node.
+    pub fn bubbles(&self) -> bool {
+        matches!(self,
+            SystemEvent::MouseDown { .. } | SystemEvent::MouseUp { .. } | SystemEvent::Click { .. } | SystemEvent::MouseWheel { .. }
+            | SystemEvent::DoubleClick { .. } | SystemEvent::PressAndHold { .. }
+            | SystemEvent::KeyDown { .. } | SystemEvent::KeyUp { .. } | SystemEvent::KeyPressed { .. } | SystemEvent::CharInput { .. })
+    }
+}
+
+impl EventType {
+    pub fn is_mouse_event(&self) -> bool {
+        matches!(self,
+            EventType::MouseDown | EventType::MouseUp | EventType::MouseMove | EventType::MouseWheel
+            | EventType::MouseEnter | EventType::MouseLeave | EventType::Click | EventType::DoubleClick
+            | EventType::PressAndHold | EventType::Canceled)
+    }
+}
+
+/// Where an event is in its propagation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    /// going down from the root to the parent of the target
+    Capture,
+    /// at the target
+    Target,
+    /// going up from the parent of the target to the root
+    Bubble,
+}
+
+/// An event being delivered, its handlers read it with `RmlEngine::event` and stop it with `RmlEngine::event_mut`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub event: SystemEvent,
+    /// the node the event is for
+    pub target: NodeId,
+    /// the node whose handler runs
+    pub current_target: NodeId,
+    pub phase: EventPhase,
+    accepted: bool,
+    propagation_stopped: bool,
+}
+
+impl Event {
+    pub fn new(event: SystemEvent, target: NodeId) -> Self {
+        Self { event, target, current_target: target, phase: EventPhase::Target, accepted: false, propagation_stopped: false }
+    }
+
+    /// The event is handled: it still goes through the rest of its way, but not to the items below its target,
+    /// and the engine skips what it does by default with it (a TextInput typing a key, Tab moving the focus, the
+    /// wheel scrolling a list...)
+    pub fn accept(&mut self) {
+        self.accepted = true;
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.accepted
+    }
+
+    /// The event goes to no other node: neither the rest of its way nor the items below its target. The other
+    /// handlers of the current node still run.
+    pub fn stop_propagation(&mut self) {
+        self.propagation_stopped = true;
+    }
+
+    pub fn is_propagation_stopped(&self) -> bool {
+        self.propagation_stopped
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +183,8 @@ pub struct EventHandler {
     pub callback_id: CallbackId,
     pub event_type: EventType,
     pub node_id: NodeId,
+    /// run in the capture phase, before the handlers of the descendants
+    pub capture: bool,
 }
 
 #[derive(Debug, Default)]
@@ -77,6 +196,9 @@ pub struct EventManager {
     mouse_position: (f32, f32),
     previous_mouse_position: (f32, f32),
     mouse_buttons_pressed: HashSet<MouseButton>,
+
+    // Keys held down
+    keys_down: HashSet<KeyCode>,
     
     // Nodes currently under mouse cursor
     pub hovered_nodes: Vec<NodeId>,
@@ -86,6 +208,7 @@ pub struct EventManager {
     
     // Window state
     window_size: (f32, f32),
+    window_focused: bool,
 }
 
 impl EventManager {
@@ -95,21 +218,32 @@ impl EventManager {
             mouse_position: (0.0, 0.0),
             previous_mouse_position: (0.0, 0.0),
             mouse_buttons_pressed: HashSet::new(),
+            keys_down: HashSet::new(),
             hovered_nodes: Vec::new(),
             focused_node: None,
             window_size: (800.0, 600.0),
+            window_focused: true,
         }
     }
     
     pub fn add_event_handler(&mut self, event_type: EventType, node_id: NodeId, callback_id: CallbackId) {
-        let handler = EventHandler {
-            callback_id,
-            event_type,
-            node_id,
-        };
-        self.handlers.push(handler);
+        self.handlers.push(EventHandler { callback_id, event_type, node_id, capture: false });
+    }
+
+    /// Add a handler run when `event_type` goes down to a descendant of `node_id`, before its own handlers
+    pub fn add_capture_event_handler(&mut self, event_type: EventType, node_id: NodeId, callback_id: CallbackId) {
+        self.handlers.push(EventHandler { callback_id, event_type, node_id, capture: true });
     }
     
+    /// Drop the handlers and the state of removed nodes, and the handlers running removed callbacks
+    pub fn forget_nodes(&mut self, node_ids: &[NodeId], callback_ids: &HashSet<CallbackId>) {
+        self.handlers.retain(|h| !node_ids.contains(&h.node_id) && !callback_ids.contains(&h.callback_id));
+        self.hovered_nodes.retain(|node_id| !node_ids.contains(node_id));
+        if self.focused_node.is_some_and(|node_id| node_ids.contains(&node_id)) {
+            self.focused_node = None;
+        }
+    }
+
     pub fn set_focused_node(&mut self, node_id: Option<NodeId>) {
         self.focused_node = node_id;
     }
@@ -126,6 +260,10 @@ impl EventManager {
         self.mouse_buttons_pressed.contains(&button)
     }
     
+    pub fn is_key_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+    
     pub fn is_node_hovered(&self, node_id: NodeId) -> bool {
         self.hovered_nodes.contains(&node_id)
     }
@@ -136,6 +274,12 @@ impl EventManager {
             .collect()
     }
 
+    /// Whether `node_id` has a handler of a mouse event, not counting the capture ones: the mouse targets it
+    /// like a MouseArea
+    pub fn has_mouse_handler(&self, node_id: NodeId) -> bool {
+        self.handlers.iter().any(|h| h.node_id == node_id && !h.capture && h.event_type.is_mouse_event())
+    }
+
     pub fn get_handlers_for_event(&self, event_type: &EventType) -> Vec<&EventHandler> {
         self.handlers.iter().filter(|h| h.event_type == *event_type)
         .collect()
@@ -143,11 +287,16 @@ impl EventManager {
     
     // Update internal state based on macroquad input
     pub fn update_from_macroquad(&mut self) -> Vec<SystemEvent> {
+        self.update(&MacroquadInput.poll())
+    }
+
+    /// Update the internal state from the input of a frame and return the events it makes
+    pub fn update(&mut self, input: &InputState) -> Vec<SystemEvent> {
         let mut events = Vec::new();
         
         // Update mouse position
         self.previous_mouse_position = self.mouse_position;
-        self.mouse_position = mouse_position();
+        self.mouse_position = input.mouse_position;
         
         let delta_x = self.mouse_position.0 - self.previous_mouse_position.0;
         let delta_y = self.mouse_position.1 - self.previous_mouse_position.1;
@@ -164,7 +313,7 @@ impl EventManager {
         
         // Check mouse buttons
         for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
-            if is_mouse_button_pressed(button) {
+            if input.buttons_pressed.contains(&button) {
                 self.mouse_buttons_pressed.insert(button);
                 events.push(SystemEvent::MouseDown {
                     node_id: NodeId::default(), // Placeholder, should be set based on hovered nodes
@@ -174,7 +323,7 @@ impl EventManager {
                 });
             }
             
-            if is_mouse_button_released(button) {
+            if input.buttons_released.contains(&button) {
                 self.mouse_buttons_pressed.remove(&button);
                 events.push(SystemEvent::MouseUp {
                     node_id: NodeId::default(), // Placeholder, should be set based on hovered nodes
@@ -182,38 +331,36 @@ impl EventManager {
                     x: self.mouse_position.0,
                     y: self.mouse_position.1,
                 });
-                
-                // Generate click event
-                events.push(SystemEvent::Click {
-                    node_id: NodeId::default(), // Placeholder, should be set based on hovered nodes
-                    button,
-                    x: self.mouse_position.0,
-                    y: self.mouse_position.1,
-                });
+                // the engine makes the clicks, knowing where the press was (see `click`)
             }
         }
         
         // Check mouse wheel
-        let (wheel_x, wheel_y) = mouse_wheel();
+        let (wheel_x, wheel_y) = input.wheel;
         if wheel_x != 0.0 || wheel_y != 0.0 {
             events.push(SystemEvent::MouseWheel { node_id: NodeId::default(), delta_x: wheel_x, delta_y: wheel_y });
         }
         
         // Check keyboard events
-        for key in get_keys_pressed() {
-            events.push(SystemEvent::KeyPressed { node_id: NodeId::default(), key });
+        for key in &input.keys_pressed {
+            events.push(SystemEvent::KeyPressed { node_id: NodeId::default(), key: *key });
         }
         
-        for key in get_keys_down() {
-            events.push(SystemEvent::KeyDown { node_id: NodeId::default(), key });
+        self.keys_down = input.keys_down.iter().copied().collect();
+        for key in &input.keys_down {
+            events.push(SystemEvent::KeyDown { node_id: NodeId::default(), key: *key });
         }
         
-        for key in get_keys_released() {
-            events.push(SystemEvent::KeyUp { node_id: NodeId::default(), key });
+        for key in &input.keys_released {
+            events.push(SystemEvent::KeyUp { node_id: NodeId::default(), key: *key });
+        }
+
+        for character in &input.chars {
+            events.push(SystemEvent::CharInput { node_id: NodeId::default(), character: *character });
         }
         
         // Check window resize
-        let current_size = (screen_width(), screen_height());
+        let current_size = input.screen_size;
         if current_size != self.window_size {
             self.window_size = current_size;
             events.push(SystemEvent::WindowResize {
@@ -222,6 +369,16 @@ impl EventManager {
                 height: current_size.1,
             });
         }
+
+        // Check window focus
+        if input.window_focused != self.window_focused {
+            self.window_focused = input.window_focused;
+            events.push(if input.window_focused {
+                SystemEvent::WindowFocus { node_id: NodeId::default() }
+            } else {
+                SystemEvent::WindowLostFocus { node_id: NodeId::default() }
+            });
+        }
         
         events
     }
@@ -0,0 +1,212 @@
+// Keyboard focus: the focus chain, the focus scopes and the focus on click
+//
+//     Rectangle {
+//         id: dialog
+//         focus_scope: true
+//         TextInput { id: name_field }
+//         MouseArea {
+//             id: ok_button
+//             focusable: true
+//             on_click: { ... }
+//             on_focus_in: { ... }
+//         }
+//     }
+//
+// The key events go to the focused item. Tab gives the focus to the next focusable item of the focus chain,
+// shift+Tab to the previous one. The chain holds the visible items with `focusable: true` (a TextInput or a
+// TextArea is focusable by default) in tree order, sorted by their `focus_order` (0 by default, the items of
+// the same order keep their tree order). It wraps around, and stays inside the innermost item with
+// `focus_scope: true` holding the focused item, so a dialog keeps the focus until the application moves it out.
+//
+// A press on a focusable MouseArea gives it the focus, Space or Enter clicks the focused MouseArea. The focused
+// item has `active_focus: true` and gets a `focus_in` event, the item losing the focus a `focus_out` event.
+
+use macroquad::prelude::{KeyCode, MouseButton};
+
+use crate::repeater::add_missing_property;
+use crate::{text_input, AbstractValue, ItemTypeEnum, NodeId, RmlEngine, SystemEvent};
+
+/// Give the properties of a focusable item to a node declaring `focusable`
+pub(crate) fn attach_node(engine: &mut RmlEngine, node_id: NodeId) {
+    add_missing_property(engine, node_id, "active_focus", AbstractValue::Bool(false));
+    add_missing_property(engine, node_id, "focus_order", AbstractValue::Number(0.0));
+}
+
+pub(crate) fn is_focusable(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.get_bool_property_of_node_id(node_id, "focusable", false) && is_shown(engine, node_id)
+}
+
+/// The node and all its ancestors are visible
+pub(crate) fn is_shown(engine: &RmlEngine, node_id: NodeId) -> bool {
+    let mut current = Some(node_id);
+    while let Some(node_id) = current {
+        if !engine.get_bool_property_of_node_id(node_id, "visible", true) {
+            return false;
+        }
+        current = engine.get_node(node_id).and_then(|node| node.parent);
+    }
+    true
+}
+
+/// The innermost focus scope holding `node_id`, or the root
+fn scope_of(engine: &RmlEngine, node_id: NodeId) -> Option<NodeId> {
+    let mut current = engine.get_node(node_id).and_then(|node| node.parent);
+    while let Some(parent_id) = current {
+        if engine.get_bool_property_of_node_id(parent_id, "focus_scope", false) {
+            return Some(parent_id);
+        }
+        current = engine.get_node(parent_id).and_then(|node| node.parent);
+    }
+    engine.get_root_node_id()
+}
+
+/// The focusable nodes of the scope `scope`, in the order Tab goes through them
+pub(crate) fn focus_chain(engine: &RmlEngine, scope: NodeId) -> Vec<NodeId> {
+    let mut chain: Vec<NodeId> = engine.get_arena().subtree(scope).into_iter()
+        .filter(|&node_id| is_focusable(engine, node_id))
+        .collect();
+    // a stable sort, the tree order stays between equal orders
+    chain.sort_by(|a, b| {
+        let order = |node_id: NodeId| engine.get_number_property_of_node_id(node_id, "focus_order", 0.0);
+        order(*a).total_cmp(&order(*b))
+    });
+    chain
+}
+
+/// Give the focus to the next node of the focus chain of the focused node, or the previous one (`backward`).
+/// Without a focused node, the chain of the root starts at its first or its last node.
+pub(crate) fn move_focus(engine: &mut RmlEngine, backward: bool) {
+    let focused = engine.event_manager.get_focused_node();
+    let Some(scope) = focused.map_or(engine.get_root_node_id(), |node_id| scope_of(engine, node_id)) else { return };
+    let chain = focus_chain(engine, scope);
+    if chain.is_empty() {
+        return;
+    }
+    let index = match focused.and_then(|node_id| chain.iter().position(|&other| other == node_id)) {
+        Some(index) if backward => (index + chain.len() - 1) % chain.len(),
+        Some(index) => (index + 1) % chain.len(),
+        None if backward => chain.len() - 1,
+        None => 0,
+    };
+    engine.focus_node(Some(chain[index]));
+}
+
+/// Tab and shift+Tab move the focus, Space and Enter click the focused MouseArea `focused`
+pub(crate) fn key_pressed(engine: &mut RmlEngine, focused: Option<NodeId>, key: KeyCode) {
+    match key {
+        KeyCode::Tab => move_focus(engine, text_input::is_shift_down(engine)),
+        KeyCode::Space | KeyCode::Enter | KeyCode::KpEnter => {
+            let Some(node_id) = focused else { return };
+            if engine.get_node(node_id).is_none_or(|node| node.node_type != ItemTypeEnum::MouseArea) {
+                return;
+            }
+            let rect = engine.layout.get(node_id).unwrap_or_default();
+            let (x, y) = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+            engine.handle_system_event(&SystemEvent::Click { node_id, button: MouseButton::Left, x, y, click_count: 1 });
+        }
+        _ => {}
+    }
+}
+
+/// Mouse press on the MouseAreas `pressed`, the topmost first: the first focusable one takes the focus
+pub(crate) fn press(engine: &mut RmlEngine, pressed: &[NodeId]) {
+    if let Some(&node_id) = pressed.iter().find(|&&node_id| is_focusable(engine, node_id)) {
+        engine.focus_node(Some(node_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_logging_handler, logged};
+    use crate::{load_rml, EventType, TestDriver};
+
+    const SOURCE: &str = r#"
+        Node {
+            id: root
+            number clicks: 0
+            string log: ""
+            MouseArea { id: first x: 0 y: 0 width: 50 height: 50 focusable: true }
+            TextInput { id: field x: 0 y: 60 width: 100 height: 30 }
+            MouseArea { id: last x: 0 y: 100 width: 50 height: 50 focusable: true focus_order: 1 }
+            MouseArea { id: plain x: 60 y: 0 width: 50 height: 50 }
+            Rectangle {
+                id: dialog
+                focus_scope: true
+                x: 200 y: 0 width: 100 height: 100
+                MouseArea { id: yes width: 40 height: 40 focusable: true }
+                MouseArea { id: no x: 50 width: 40 height: 40 focusable: true }
+                MouseArea { id: hidden x: 50 y: 50 width: 40 height: 40 focusable: true visible: false }
+            }
+        }
+    "#;
+
+    fn driver() -> TestDriver {
+        let mut engine = load_rml(SOURCE).unwrap();
+        for node in ["first", "last"] {
+            for (event_type, change) in [(EventType::FocusIn, "+"), (EventType::FocusOut, "-")] {
+                add_logging_handler(&mut engine, event_type, node, move |_| format!("{change}{node}"));
+            }
+        }
+        let click = engine.add_callback(|engine| {
+            let clicks = engine.get_number_property_of_node("root", "clicks", 0.0);
+            engine.set_property_of_node("root", "clicks", AbstractValue::Number(clicks + 1.0));
+        });
+        engine.add_event_handler(EventType::Click, "last", click);
+        TestDriver::new(engine, 400.0, 300.0)
+    }
+
+    fn focused(driver: &TestDriver) -> Option<String> {
+        let node_id = driver.engine().get_event_manager().get_focused_node()?;
+        driver.engine().get_node(node_id).map(|node| node.id.clone())
+    }
+
+    #[test]
+    fn test_tab_order_and_events() {
+        let mut driver = driver();
+        driver.key(KeyCode::Tab);
+        assert_eq!(focused(&driver).as_deref(), Some("first"));
+        assert!(driver.engine().get_bool_property_of_node("first", "active_focus", false));
+        driver.key(KeyCode::Tab);
+        assert_eq!(focused(&driver).as_deref(), Some("field"));
+        assert!(driver.engine().get_bool_property_of_node("field", "active_focus", false));
+        assert!(!driver.engine().get_bool_property_of_node("first", "active_focus", true));
+        // the dialog comes before `last`, whose order is higher
+        driver.key(KeyCode::Tab);
+        assert_eq!(focused(&driver).as_deref(), Some("yes"));
+        driver.key(KeyCode::Tab);
+        assert_eq!(focused(&driver).as_deref(), Some("no"), "the hidden item is skipped");
+
+        // inside the dialog, the focus stays in it
+        driver.key(KeyCode::Tab);
+        assert_eq!(focused(&driver).as_deref(), Some("yes"));
+        driver.press_key(KeyCode::LeftShift);
+        driver.key(KeyCode::Tab);
+        assert_eq!(focused(&driver).as_deref(), Some("no"));
+
+        // out of it, shift+Tab goes backward and wraps around
+        driver.engine_mut().set_focused_node("first");
+        driver.key(KeyCode::Tab);
+        driver.release_key(KeyCode::LeftShift);
+        assert_eq!(focused(&driver).as_deref(), Some("last"));
+        assert_eq!(logged(&mut driver), "+first -first +first -first +last ");
+    }
+
+    #[test]
+    fn test_focus_on_click_and_keyboard_click() {
+        let mut driver = driver();
+        driver.click_at(10.0, 110.0);
+        assert_eq!(focused(&driver).as_deref(), Some("last"));
+        assert_eq!(driver.engine().get_number_property_of_node("root", "clicks", 0.0), 1.0);
+        driver.key(KeyCode::Space);
+        driver.key(KeyCode::Enter);
+        assert_eq!(driver.engine().get_number_property_of_node("root", "clicks", 0.0), 3.0);
+
+        // a MouseArea that is not focusable keeps the focus where it is
+        driver.click_at(70.0, 10.0);
+        assert_eq!(focused(&driver).as_deref(), Some("last"));
+        driver.click_at(20.0, 70.0);
+        assert_eq!(focused(&driver).as_deref(), Some("field"));
+        assert!(!driver.engine().get_bool_property_of_node("last", "active_focus", true));
+    }
+}
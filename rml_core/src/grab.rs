@@ -0,0 +1,176 @@
+// Pointer grab: the nodes a press reaches keep the mouse until the release
+//
+//     MouseArea {
+//         id: knob_area
+//         anchors: fill
+//         on_mouse_move: { if $.knob_area.pressed { ... } }   // wherever the mouse goes
+//         on_mouse_up: { ... }                                // even out of the area
+//         on_canceled: { ... }                                // the window lost the focus before the release
+//     }
+//
+// A press goes to the MouseAreas and the nodes with a mouse handler under the mouse, the topmost first, down to
+// the one accepting it (see `propagation`). These nodes grab the mouse: they get the moves first and the release
+// of the button wherever the mouse is, and the nodes below the mouse do not get this release. A MouseArea has
+// `pressed: true` while it grabs the mouse, and `contains_mouse: true` while the mouse is over it (and not
+// clipped out), pressed or not. A window losing the focus breaks the grab: the grabbing nodes get a `canceled`
+// event instead of the release, the drag in progress stops without a drop and the press makes no click.
+
+use macroquad::prelude::MouseButton;
+
+use crate::repeater::add_missing_property;
+use crate::{click, drag, list_view, propagation, text_input, AbstractValue, ItemTypeEnum, NodeId, RmlEngine, SystemEvent};
+
+#[derive(Debug, Default)]
+pub(crate) struct Grab {
+    /// the grabbing nodes, the topmost first, and the button they wait the release of
+    nodes: Vec<NodeId>,
+    button: Option<MouseButton>,
+}
+
+/// Give its `pressed` and `contains_mouse` properties to a MouseArea
+pub(crate) fn attach_node(engine: &mut RmlEngine, node_id: NodeId) {
+    if engine.get_node(node_id).is_some_and(|node| node.node_type == ItemTypeEnum::MouseArea) {
+        add_missing_property(engine, node_id, "pressed", AbstractValue::Bool(false));
+        add_missing_property(engine, node_id, "contains_mouse", AbstractValue::Bool(false));
+    }
+}
+
+/// The press of `button` reached the nodes `reached`: they grab the mouse, unless another button holds it
+pub(crate) fn press(engine: &mut RmlEngine, button: MouseButton, reached: Vec<NodeId>) {
+    if engine.grab.button.is_some() {
+        return;
+    }
+    for &node_id in &reached {
+        set_if_changed(engine, node_id, "pressed", true);
+    }
+    engine.grab = Grab { nodes: reached, button: Some(button) };
+}
+
+/// The nodes grabbing the release of `button`, none without a grab of this button
+pub(crate) fn grabbing(engine: &RmlEngine, button: MouseButton) -> Option<Vec<NodeId>> {
+    (engine.grab.button == Some(button)).then(|| engine.grab.nodes.clone())
+}
+
+/// The mouse move targets: the grabbing nodes, then the others
+pub(crate) fn move_targets(engine: &RmlEngine, mouse_area_nodes: &[NodeId]) -> Vec<NodeId> {
+    let mut targets = engine.grab.nodes.clone();
+    targets.extend(mouse_area_nodes.iter().filter(|node_id| !engine.grab.nodes.contains(node_id)));
+    targets
+}
+
+/// `button` was released, the grab of this button ends
+pub(crate) fn release(engine: &mut RmlEngine, button: MouseButton) {
+    if engine.grab.button != Some(button) {
+        return;
+    }
+    for node_id in std::mem::take(&mut engine.grab).nodes {
+        set_if_changed(engine, node_id, "pressed", false);
+    }
+}
+
+/// The window lost the focus: the grab ends with a `canceled` event, the lists, the text inputs and the drags
+/// pressed forget their press
+pub(crate) fn cancel(engine: &mut RmlEngine) {
+    if engine.grab.button.is_none() {
+        return;
+    }
+    let nodes = std::mem::take(&mut engine.grab).nodes;
+    for &node_id in &nodes {
+        set_if_changed(engine, node_id, "pressed", false);
+    }
+    let mut visited = propagation::Visited::new();
+    for node_id in nodes {
+        propagation::dispatch(engine, SystemEvent::Canceled { node_id }, &mut visited);
+    }
+    list_view::release(engine);
+    text_input::release(engine);
+    drag::cancel(engine);
+    click::cancel(engine);
+}
+
+/// The mouse is over the nodes `hovered`: the `contains_mouse` property of the MouseAreas follows it
+pub(crate) fn hover(engine: &mut RmlEngine, mouse_area_nodes: &[NodeId], hovered: &[NodeId]) {
+    for &node_id in mouse_area_nodes {
+        set_if_changed(engine, node_id, "contains_mouse", hovered.contains(&node_id));
+    }
+}
+
+/// Set a bool property only when it changes, the handlers bound to it run once per change
+fn set_if_changed(engine: &mut RmlEngine, node_id: NodeId, name: &str, value: bool) {
+    if engine.get_property_by_name(node_id, name).is_some_and(|property| property.value != AbstractValue::Bool(value)) {
+        engine.set_property_of_node_id(node_id, name, AbstractValue::Bool(value));
+    }
+}
+
+/// Drop the removed nodes from the grab
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId]) {
+    engine.grab.nodes.retain(|node_id| !removed.contains(node_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_logging_handler, logged};
+    use crate::{load_rml, EventType, TestDriver};
+
+    const SOURCE: &str = r#"
+        Node {
+            id: root
+            string log: ""
+            MouseArea { id: button x: 0 y: 0 width: 50 height: 50 }
+            MouseArea { id: other x: 100 y: 0 width: 50 height: 50 }
+        }
+    "#;
+
+    fn driver() -> TestDriver {
+        let mut engine = load_rml(SOURCE).unwrap();
+        for node in ["button", "other"] {
+            for (event_type, name) in [(EventType::MouseUp, "up"), (EventType::MouseMove, "move"), (EventType::Canceled, "canceled")] {
+                add_logging_handler(&mut engine, event_type, node, move |_| format!("{node}:{name}"));
+            }
+        }
+        TestDriver::new(engine, 300.0, 300.0)
+    }
+
+    fn flag(driver: &TestDriver, node: &str, name: &str) -> bool {
+        driver.engine().get_bool_property_of_node(node, name, false)
+    }
+
+    #[test]
+    fn test_release_out_of_the_pressed_area() {
+        let mut driver = driver();
+        driver.move_mouse(10.0, 10.0);
+        assert!(flag(&driver, "button", "contains_mouse"));
+        driver.press(MouseButton::Left);
+        assert!(flag(&driver, "button", "pressed"));
+        logged(&mut driver);
+
+        // out of the button, over the other area: the button gets the moves first, and the release
+        driver.move_mouse(120.0, 10.0);
+        assert_eq!(logged(&mut driver), "button:move other:move ");
+        assert!(!flag(&driver, "button", "contains_mouse"));
+        assert!(flag(&driver, "other", "contains_mouse"));
+        assert!(flag(&driver, "button", "pressed"));
+        driver.release(MouseButton::Left);
+        assert_eq!(logged(&mut driver), "button:up ");
+        assert!(!flag(&driver, "button", "pressed"));
+
+        // without a grab, the release goes to the area under the mouse
+        driver.release(MouseButton::Left);
+        assert_eq!(logged(&mut driver), "other:up ");
+    }
+
+    #[test]
+    fn test_focus_loss_cancels_the_grab() {
+        let mut driver = driver();
+        driver.move_mouse(10.0, 10.0);
+        driver.press(MouseButton::Left);
+        logged(&mut driver);
+        driver.set_window_focused(false);
+        assert_eq!(logged(&mut driver), "button:canceled ");
+        assert!(!flag(&driver, "button", "pressed"));
+        driver.set_window_focused(true);
+        driver.release(MouseButton::Left);
+        assert_eq!(logged(&mut driver), "button:up ");
+    }
+}
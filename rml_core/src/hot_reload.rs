@@ -0,0 +1,581 @@
+// Hot reload of component files
+//
+// Every component instance of the tree is recorded with the .rml file it comes from (by the `rml!` macro
+// or by the runtime loader). Once enabled, `RmlEngine::process_events` polls the modification time of
+// these files, and a changed file is reloaded with the runtime loader and merged in place into each of
+// its instances: nodes are matched by position and type, so they keep their ids, their properties and
+// the callbacks bound to them. The directories of the component files (and the ones imported by a file loaded
+// at runtime) are scanned too: a component file added to one of them reloads the watched files, which can
+// now use it. A property keeps its current value when its name and type are unchanged
+// and the designer didn't change its value in the file (or the instance overrides it). A property with a
+// literal value removed from the file is removed from the node; the ones added from Rust, and the blocks
+// and handlers compiled by the `rml!` macro, stay.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use indexmap::IndexSet;
+use macroquad::prelude::warn;
+use proc_macro2::Span;
+
+use crate::loader::{build_node, ComponentOrigin, NodeDef, PropertyDef, PropertyKind, RmlLoader};
+use crate::{repeater, AbstractValue, ItemTypeEnum, NodeId, Property, PropertyId, RmlEngine};
+
+/// Minimal delay between two checks of the watched files
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A component instance of the tree and the file it comes from
+#[derive(Debug, Clone)]
+struct WatchedComponent {
+    node_id: NodeId,
+    origin: ComponentOrigin,
+    /// Expanded component at the last (re)load, tells which values were changed in the file
+    previous: Option<NodeDef>,
+}
+
+#[derive(Debug)]
+pub struct HotReload {
+    enabled: bool,
+    watched: Vec<WatchedComponent>,
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+    /// Component directories and the .rml files they held at the last check
+    directories: HashMap<PathBuf, HashSet<PathBuf>>,
+    last_poll: Option<Instant>,
+    loader: RmlLoader,
+}
+
+impl Default for HotReload {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            watched: Vec::new(),
+            modified: HashMap::new(),
+            directories: HashMap::new(),
+            last_poll: None,
+            // nodes created by a reload must not collide with the ids generated at build time
+            loader: RmlLoader::new(".").with_id_prefix("reloaded_id_"),
+        }
+    }
+}
+
+impl HotReload {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Component files currently watched
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.watched.iter().map(|watched| watched.origin.path.clone()).collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Stop watching a removed node
+    pub(crate) fn forget(&mut self, node_id: NodeId) {
+        self.watched.retain(|watched| watched.node_id != node_id);
+    }
+
+    fn watch(&mut self, node_id: NodeId, origin: ComponentOrigin, previous: Option<NodeDef>) {
+        self.modified.entry(origin.path.clone()).or_insert_with(|| modified_time(&origin.path));
+        if let Some(dir) = origin.path.parent() {
+            self.watch_directory(dir);
+        }
+        self.watched.retain(|watched| watched.node_id != node_id);
+        self.watched.push(WatchedComponent { node_id, origin, previous });
+    }
+
+    pub(crate) fn watch_directory(&mut self, dir: &Path) {
+        self.directories.entry(dir.to_path_buf()).or_insert_with(|| component_files(dir));
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// The .rml files of the directory `dir`
+fn component_files(dir: &Path) -> HashSet<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else { return HashSet::new() };
+    entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|extension| extension.to_str()) == Some("rml"))
+        .collect()
+}
+
+/// Record that the node `node_id` is an instance of the component file `path`
+pub fn watch_component(engine: &mut RmlEngine, node_id: NodeId, path: &Path, overridden: &[&str], instance_children: usize) {
+    let origin = ComponentOrigin {
+        path: path.to_path_buf(),
+        overridden: overridden.iter().map(|name| name.to_string()).collect(),
+        instance_children,
+    };
+    let previous = if engine.hot_reload.enabled { expand_for(engine, node_id, &origin.path) } else { None };
+    engine.hot_reload.watch(node_id, origin, previous);
+}
+
+/// Same as `watch_component`, for a component built by the runtime loader from `expanded`
+pub(crate) fn watch_expanded_component(engine: &mut RmlEngine, node_id: NodeId, origin: &ComponentOrigin, expanded: NodeDef) {
+    engine.hot_reload.watch(node_id, origin.clone(), Some(expanded));
+}
+
+pub fn enable(engine: &mut RmlEngine) {
+    engine.hot_reload.enabled = true;
+    // the file content at this point is the reference to detect the values changed by the designer
+    let watched = engine.hot_reload.watched.clone();
+    for (index, watched) in watched.iter().enumerate() {
+        if watched.previous.is_none() {
+            engine.hot_reload.watched[index].previous = expand_for(engine, watched.node_id, &watched.origin.path);
+        }
+    }
+}
+
+/// Expand the component file `path` as the instance `node_id`, `None` (with a warning) if it fails
+fn expand_for(engine: &mut RmlEngine, node_id: NodeId, path: &Path) -> Option<NodeDef> {
+    let id = engine.get_node(node_id)?.id.clone();
+    let type_name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+    let instance = NodeDef {
+        type_name,
+        properties: vec![PropertyDef { name: "id".to_string(), kind: PropertyKind::Plain, value: Some(AbstractValue::String(id)), span: Span::call_site() }],
+        children: Vec::new(),
+        behaviors: Vec::new(),
+        states: Vec::new(),
+        transitions: Vec::new(),
+        span: Span::call_site(),
+        component: None,
+    };
+    match engine.hot_reload.loader.expand_component(instance, path.to_path_buf(), &HashMap::new()) {
+        Ok(expanded) => Some(expanded),
+        Err(error) => {
+            warn!("Hot reload of {} failed: {}", path.display(), error);
+            None
+        }
+    }
+}
+
+/// Check the watched files and reload the ones that changed since the last check.
+/// Returns the reloaded files.
+pub fn poll(engine: &mut RmlEngine) -> Vec<PathBuf> {
+    if !engine.hot_reload.enabled {
+        return Vec::new();
+    }
+    let now = Instant::now();
+    if engine.hot_reload.last_poll.is_some_and(|last_poll| now.duration_since(last_poll) < POLL_INTERVAL) {
+        return Vec::new();
+    }
+    engine.hot_reload.last_poll = Some(now);
+
+    let mut changed = Vec::new();
+    for (path, modified) in engine.hot_reload.modified.iter_mut() {
+        let current = modified_time(path);
+        if current != *modified {
+            *modified = current;
+            changed.push(path.clone());
+        }
+    }
+    // a new component file can complete the files that use it, they are all reloaded
+    let mut added = false;
+    for (dir, files) in engine.hot_reload.directories.iter_mut() {
+        let current = component_files(dir);
+        added |= current.iter().any(|path| !files.contains(path));
+        *files = current;
+    }
+    if added {
+        for path in engine.hot_reload.watched_files() {
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+    }
+
+    for path in changed.iter() {
+        reload_file(engine, path);
+    }
+    changed
+}
+
+/// Reload every instance of the component file `path`
+pub fn reload_file(engine: &mut RmlEngine, path: &Path) {
+    let instances: Vec<NodeId> = engine.hot_reload.watched.iter()
+        .filter(|watched| watched.origin.path == path)
+        .map(|watched| watched.node_id)
+        .collect();
+
+    // a reload destroys the nodes removed from the file, and the instances they contained with them
+    for node_id in instances {
+        let Some(watched) = engine.hot_reload.watched.iter().find(|watched| watched.node_id == node_id).cloned() else { continue };
+        if !is_attached(engine, watched.node_id) {
+            continue;
+        }
+        // a file that doesn't load (yet) leaves the tree untouched
+        let Some(expanded) = expand_for(engine, watched.node_id, path) else { continue };
+
+        merge_node(engine, watched.node_id, &expanded, watched.previous.as_ref(), &watched.origin.overridden, watched.origin.instance_children);
+        if let Some(watched) = engine.hot_reload.watched.iter_mut().find(|watched| watched.node_id == node_id) {
+            watched.previous = Some(expanded);
+        }
+    }
+
+    // instances removed by the reload are not watched anymore
+    let watched = std::mem::take(&mut engine.hot_reload.watched);
+    engine.hot_reload.watched = watched.into_iter().filter(|watched| is_attached(engine, watched.node_id)).collect();
+    engine.run_callbacks();
+}
+
+fn is_attached(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.get_node(node_id).is_some_and(|node| engine.get_node_id(&node.id) == Some(node_id))
+}
+
+fn same_type(current: &AbstractValue, value: &AbstractValue) -> bool {
+    std::mem::discriminant(current) == std::mem::discriminant(value) || matches!(current, AbstractValue::Null)
+}
+
+/// Merge the reloaded definition `def` into the existing node `node_id`.
+/// `trailing_children` childs given by the instance are kept after the childs of the component.
+fn merge_node(engine: &mut RmlEngine, node_id: NodeId, def: &NodeDef, previous: Option<&NodeDef>, overridden: &[String], trailing_children: usize) {
+    merge_properties(engine, node_id, def, previous, overridden);
+    crate::animation::set_behaviors(engine, node_id, &def.behaviors);
+    // the values computed by blocks are not in the definition, the states are only replaced when the file changed them
+    let states_changed = previous.is_some_and(|previous| {
+        format!("{:?}{:?}", previous.states, previous.transitions) != format!("{:?}{:?}", def.states, def.transitions)
+    });
+    if states_changed {
+        crate::states::set_states(engine, node_id, def.states.clone(), def.transitions.clone());
+    }
+
+    let node_type = engine.get_node(node_id).map(|node| node.node_type.clone());
+    if matches!(node_type, Some(ItemTypeEnum::Repeater | ItemTypeEnum::ListView)) {
+        // the instances are rebuilt from the reloaded delegate
+        if let Some(delegate) = def.children.first() {
+            let delegate = repeater::Delegate::Definition(delegate.clone());
+            if node_type == Some(ItemTypeEnum::Repeater) {
+                repeater::set_delegate(engine, node_id, delegate);
+            } else {
+                crate::list_view::set_delegate(engine, node_id, delegate);
+            }
+        }
+        return;
+    }
+
+    // the instances of the Repeaters are not in the file, they are put back after their Repeater below
+    let old_children: Vec<NodeId> = engine.get_arena().get_childrens_ids(node_id).into_iter()
+        .filter(|&child| !repeater::is_instance(engine, child))
+        .collect();
+    let own_count = old_children.len().saturating_sub(trailing_children);
+    let (own_children, trailing) = old_children.split_at(own_count);
+
+    // pair the childs of the file with the existing ones: same id first, then same position and type
+    let ids: Vec<Option<String>> = def.children.iter().map(|child_def| child_def.id()).collect();
+    let mut matches: Vec<Option<NodeId>> = ids.iter()
+        .map(|id| own_children.iter().copied().find(|&old_child| id.as_deref() == engine.get_node(old_child).map(|node| node.id.as_str())))
+        .collect();
+    for (index, child_def) in def.children.iter().enumerate() {
+        if matches[index].is_some() {
+            continue;
+        }
+        matches[index] = own_children.get(index).copied().filter(|&old_child| {
+            let node = engine.get_node(old_child);
+            let claimed = matches.contains(&Some(old_child)) || ids.iter().any(|id| id.as_deref() == node.map(|node| node.id.as_str()));
+            !claimed && node.map(|node| node.node_type.clone()) == crate::loader::node_type_from_name(&child_def.type_name)
+        });
+    }
+
+    // childs that are not in the file anymore, removed first so the new ones can reuse their ids
+    for &old_child in own_children {
+        if !matches.contains(&Some(old_child)) {
+            engine.destroy(old_child);
+        }
+    }
+
+    let mut children = Vec::new();
+    let mut new_children = Vec::new();
+    for (index, child_def) in def.children.iter().enumerate() {
+        match matches[index] {
+            Some(old_child) => {
+                let previous_child = previous.and_then(|previous| {
+                    previous.children.iter().find(|previous_child| previous_child.id().is_some() && previous_child.id() == ids[index])
+                        .or_else(|| previous.children.get(index))
+                });
+                // a nested instance has its own childs in the reloaded definition, nothing trails
+                let overridden = child_def.component.as_ref().map(|origin| origin.overridden.clone()).unwrap_or_default();
+                merge_node(engine, old_child, child_def, previous_child, &overridden, 0);
+                children.push(old_child);
+            }
+            None => match build_node(engine, child_def, Some(node_id)) {
+                Ok(new_child) => {
+                    children.push(new_child);
+                    new_children.push(new_child);
+                }
+                Err(error) => warn!("Hot reload can't add a {}: {}", child_def.type_name, error),
+            },
+        }
+    }
+    children.extend_from_slice(trailing);
+
+    // only the new childs are attached, the kept ones already follow their properties
+    for &child in &new_children {
+        engine.add_child(node_id, child);
+    }
+    if let Some(node) = engine.get_node_mut(node_id) {
+        node.children = children.iter().copied().collect::<IndexSet<NodeId>>();
+    }
+    // the instances of the kept Repeaters go back after them, `add_child` placed the ones of the new Repeaters
+    for child in children.into_iter().filter(|child| !new_children.contains(child)) {
+        repeater::update(engine, child);
+    }
+    engine.mark_layout_dirty(node_id);
+}
+
+fn merge_properties(engine: &mut RmlEngine, node_id: NodeId, def: &NodeDef, previous: Option<&NodeDef>, overridden: &[String]) {
+    for property in def.properties.iter().filter(|property| property.name != "id") {
+        // blocks and signals keep what the engine has, their bindings still drive them
+        let Some(value) = &property.value else {
+            if property.name.starts_with("on_") || engine.get_property_by_name(node_id, &property.name).is_some() {
+                continue;
+            }
+            let property_id = engine.add_property(Property::new(property.initial_value()));
+            engine.add_property_to_node(node_id, property.name.clone(), property_id);
+            continue;
+        };
+
+        let Some(current) = engine.get_property_by_name(node_id, &property.name).map(|current| current.get()) else {
+            let property_id = engine.add_property(Property::new(value.clone()));
+            engine.add_property_to_node(node_id, property.name.clone(), property_id);
+            engine.mark_layout_dirty(node_id);
+            continue;
+        };
+
+        let unchanged_in_file = previous
+            .and_then(|previous| previous.properties.iter().find(|previous| previous.name == property.name))
+            .is_some_and(|previous| previous.value.as_ref() == Some(value));
+        if same_type(&current, value) && (unchanged_in_file || overridden.contains(&property.name)) {
+            continue;
+        }
+        engine.set_property_of_node_id(node_id, &property.name, value.clone());
+    }
+
+    // the literal values the file doesn't declare anymore
+    let Some(previous) = previous else { return };
+    let removed: HashSet<PropertyId> = previous.properties.iter()
+        .filter(|property| property.value.is_some() && property.name != "id" && !overridden.contains(&property.name))
+        .filter(|property| !def.properties.iter().any(|other| other.name == property.name))
+        .filter_map(|property| engine.get_node_mut(node_id)?.properties.remove(&property.name))
+        .collect();
+    if removed.is_empty() {
+        return;
+    }
+    for property_id in &removed {
+        engine.properties.remove(property_id);
+        engine.bindings.remove(property_id);
+    }
+    crate::animation::forget(engine, &[], &removed);
+    crate::states::forget(engine, &[], &removed);
+    engine.mark_layout_dirty(node_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RecordingRenderer, RmlLoader};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str, button: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rml_hot_reload_{}_{}", name, std::process::id()));
+            fs::create_dir_all(dir.join("components")).unwrap();
+            let fixture = Self { dir };
+            fixture.write_button(button);
+            fs::write(fixture.dir.join("main.rml"), r#"
+                import "components" as UI
+                Node {
+                    id: root
+                    UI::Button {
+                        id: ok_button
+                        text: "Ok"
+                        Rectangle { id: extra }
+                    }
+                }
+            "#).unwrap();
+            fixture
+        }
+
+        fn write_button(&self, source: &str) {
+            fs::write(self.button_path(), source).unwrap();
+        }
+
+        fn button_path(&self) -> PathBuf {
+            self.dir.join("components").join("Button.rml")
+        }
+
+        fn load(&self) -> RmlEngine {
+            let mut engine = RmlLoader::new(".").load_file(self.dir.join("main.rml")).unwrap();
+            engine.enable_hot_reload();
+            engine
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.dir).ok();
+        }
+    }
+
+    const BUTTON: &str = r#"
+        Rectangle {
+            id: button_template
+            number width: 100
+            number radius: 4
+            number clicks: 0
+            string text: "Button"
+            Text { id: button_label font_size: 16 }
+        }
+    "#;
+
+    #[test]
+    fn test_reload_keeps_unchanged_values() {
+        let fixture = Fixture::new("values", BUTTON);
+        let mut engine = fixture.load();
+        let button = engine.get_node_id("ok_button").unwrap();
+        let label = engine.get_node_id("button_label").unwrap();
+        engine.set_property_of_node("ok_button", "clicks", AbstractValue::Number(3.0));
+
+        let changes = Arc::new(AtomicUsize::new(0));
+        let counter = changes.clone();
+        engine.connect("ok_button", "radius", move |_engine| { counter.fetch_add(1, Ordering::SeqCst); }).unwrap();
+
+        fixture.write_button(&BUTTON
+            .replace("number radius: 4", "number radius: 12")
+            .replace("font_size: 16", "font_size: 18 color: { RED }")
+            .replace("string text: \"Button\"", "string text: \"Button\"\n            bool flat: true"));
+        reload_file(&mut engine, &fixture.button_path());
+
+        // same nodes, runtime state kept, designer changes applied
+        assert_eq!(engine.get_node_id("ok_button"), Some(button));
+        assert_eq!(engine.get_node_id("button_label"), Some(label));
+        assert_eq!(engine.get_number_property_of_node("ok_button", "clicks", 0.0), 3.0);
+        assert_eq!(engine.get_string_property_of_node("ok_button", "text", String::new()), "Ok");
+        assert_eq!(engine.get_number_property_of_node("ok_button", "radius", 0.0), 12.0);
+        assert!(engine.get_bool_property_of_node("ok_button", "flat", false));
+        assert_eq!(engine.get_number_property_of_node("button_label", "font_size", 0.0), 18.0);
+        // callbacks bound to the existing properties still run
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_reload_rebuilds_changed_childs() {
+        let fixture = Fixture::new("childs", BUTTON);
+        let mut engine = fixture.load();
+        let extra = engine.get_node_id("extra").unwrap();
+
+        fixture.write_button(&BUTTON.replace("Text { id: button_label font_size: 16 }", "Rectangle { id: icon } Text { id: button_label }"));
+        reload_file(&mut engine, &fixture.button_path());
+
+        let button = engine.get_node_id("ok_button").unwrap();
+        let children = engine.get_arena().get_childrens_ids(button);
+        assert_eq!(children.len(), 3);
+        assert_eq!(children[0], engine.get_node_id("icon").unwrap());
+        assert_eq!(children[1], engine.get_node_id("button_label").unwrap());
+        // the child given by the instance stays last
+        assert_eq!(children[2], extra);
+        engine.update_layout(&RecordingRenderer::new(100.0, 100.0));
+    }
+
+    #[test]
+    fn test_reload_does_not_attach_kept_childs_again() {
+        let button = BUTTON.replace("Text { id: button_label font_size: 16 }", "Timer { id: blink interval: 500 } TextInput { id: field }");
+        let fixture = Fixture::new("attach", &button);
+        let mut engine = fixture.load();
+        engine.run_callbacks();
+        let callbacks = engine.callbacks.len();
+
+        for interval in ["600", "700"] {
+            fixture.write_button(&button.replace("500", interval));
+            reload_file(&mut engine, &fixture.button_path());
+            assert_eq!(engine.callbacks.len(), callbacks);
+            assert!(engine.callbacks_to_eval.is_empty());
+        }
+        assert_eq!(engine.get_number_property_of_node("blink", "interval", 0.0), 700.0);
+    }
+
+    #[test]
+    fn test_reload_removes_the_properties_removed_from_the_file() {
+        let fixture = Fixture::new("removed", BUTTON);
+        let mut engine = fixture.load();
+        engine.set_property_of_node("ok_button", "clicks", AbstractValue::Number(3.0));
+        let pressed = engine.add_property(Property::new(AbstractValue::Bool(false)));
+        let button = engine.get_node_id("ok_button").unwrap();
+        engine.add_property_to_node(button, "pressed".to_string(), pressed);
+
+        fixture.write_button(&BUTTON.replace("number radius: 4", "").replace("number clicks: 0", "").replace("string text: \"Button\"", ""));
+        reload_file(&mut engine, &fixture.button_path());
+        assert!(engine.get_property_by_name(button, "radius").is_none());
+        assert!(engine.get_property_by_name(button, "clicks").is_none());
+        // the instance sets the text, Rust added `pressed`
+        assert_eq!(engine.get_string_property_of_node("ok_button", "text", String::new()), "Ok");
+        assert!(engine.get_property_by_name(button, "pressed").is_some());
+        assert_eq!(engine.get_number_property_of_node("ok_button", "width", 0.0), 100.0);
+    }
+
+    #[test]
+    fn test_type_change_resets_the_value() {
+        let fixture = Fixture::new("types", BUTTON);
+        let mut engine = fixture.load();
+        engine.set_property_of_node("ok_button", "clicks", AbstractValue::Number(3.0));
+
+        fixture.write_button(&BUTTON.replace("number clicks: 0", "string clicks: \"none\""));
+        reload_file(&mut engine, &fixture.button_path());
+        assert_eq!(engine.get_string_property_of_node("ok_button", "clicks", String::new()), "none");
+    }
+
+    #[test]
+    fn test_invalid_file_keeps_the_tree() {
+        let fixture = Fixture::new("invalid", BUTTON);
+        let mut engine = fixture.load();
+
+        fixture.write_button("Rectangle { id: button_template Slider { } }");
+        reload_file(&mut engine, &fixture.button_path());
+        assert!(engine.get_node_id("button_label").is_some());
+    }
+
+    #[test]
+    fn test_poll_detects_modified_files() {
+        let fixture = Fixture::new("poll", BUTTON);
+        let mut engine = fixture.load();
+        assert_eq!(engine.get_hot_reload().watched_files(), vec![fixture.button_path()]);
+        assert!(poll(&mut engine).is_empty());
+
+        let file = fs::File::options().write(true).open(fixture.button_path()).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        engine.hot_reload.last_poll = None;
+        assert_eq!(poll(&mut engine), vec![fixture.button_path()]);
+    }
+
+    #[test]
+    fn test_poll_detects_new_component_files() {
+        let fixture = Fixture::new("new_files", BUTTON);
+        let mut engine = fixture.load();
+
+        // the Button uses a Badge that doesn't exist yet: the reload fails
+        fixture.write_button(&format!("import \".\"\n{}", BUTTON.replace("Text {", "Badge { id: badge } Text {")));
+        let file = fs::File::options().write(true).open(fixture.button_path()).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        engine.hot_reload.last_poll = None;
+        assert_eq!(poll(&mut engine), vec![fixture.button_path()]);
+        assert!(engine.get_node_id("badge").is_none());
+
+        // adding it to the imported directory reloads the Button
+        fs::write(fixture.dir.join("components").join("Badge.rml"), "Rectangle { id: badge_template number size: 8 }").unwrap();
+        engine.hot_reload.last_poll = None;
+        assert_eq!(poll(&mut engine), vec![fixture.button_path()]);
+        assert_eq!(engine.get_number_property_of_node("badge", "size", 0.0), 8.0);
+        engine.hot_reload.last_poll = None;
+        assert!(poll(&mut engine).is_empty());
+    }
+}
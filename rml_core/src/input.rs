@@ -0,0 +1,208 @@
+// Input sources and clipboards: where the engine reads the mouse, the keyboard, the window size and the clipboard
+//
+// `process_events` polls the input source of the engine once per frame and turns the state it returns into
+// events. The default source reads the macroquad globals; a `ScriptedInput` returns what a test scripted,
+// so the event paths run without a window (see `TestDriver`). Likewise the TextInputs copy and paste through
+// the system clipboard, or a `MemoryClipboard` in the tests.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use macroquad::prelude::{KeyCode, MouseButton};
+
+/// State of the input devices for one frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputState {
+    pub mouse_position: (f32, f32),
+    /// Buttons pressed and released since the last frame
+    pub buttons_pressed: Vec<MouseButton>,
+    pub buttons_released: Vec<MouseButton>,
+    pub wheel: (f32, f32),
+    /// Keys pressed since the last frame, held down, and released since the last frame
+    pub keys_pressed: Vec<KeyCode>,
+    pub keys_down: Vec<KeyCode>,
+    pub keys_released: Vec<KeyCode>,
+    /// Characters typed since the last frame
+    pub chars: Vec<char>,
+    /// Files dropped on the window since the last frame
+    pub dropped_files: Vec<PathBuf>,
+    pub screen_size: (f32, f32),
+    /// Whether the window has the focus of the keyboard and the mouse
+    pub window_focused: bool,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            mouse_position: (0.0, 0.0),
+            buttons_pressed: Vec::new(),
+            buttons_released: Vec::new(),
+            wheel: (0.0, 0.0),
+            keys_pressed: Vec::new(),
+            keys_down: Vec::new(),
+            keys_released: Vec::new(),
+            chars: Vec::new(),
+            dropped_files: Vec::new(),
+            // the initial window size of the event manager
+            screen_size: (800.0, 600.0),
+            window_focused: true,
+        }
+    }
+}
+
+/// Source of the input of the engine, polled once per frame by `process_events`
+pub trait InputSource {
+    fn poll(&mut self) -> InputState;
+}
+
+/// The macroquad input, it needs a macroquad window
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MacroquadInput;
+
+impl InputSource for MacroquadInput {
+    fn poll(&mut self) -> InputState {
+        use macroquad::prelude::*;
+
+        let buttons = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+        let mut chars = Vec::new();
+        while let Some(character) = get_char_pressed() {
+            chars.push(character);
+        }
+        // macroquad gives the last typed character first
+        chars.reverse();
+        InputState {
+            mouse_position: mouse_position(),
+            buttons_pressed: buttons.into_iter().filter(|button| is_mouse_button_pressed(*button)).collect(),
+            buttons_released: buttons.into_iter().filter(|button| is_mouse_button_released(*button)).collect(),
+            wheel: mouse_wheel(),
+            keys_pressed: get_keys_pressed().into_iter().collect(),
+            keys_down: get_keys_down().into_iter().collect(),
+            keys_released: get_keys_released().into_iter().collect(),
+            chars,
+            // the dropped files known by their content only (on the web) are not given
+            dropped_files: get_dropped_files().into_iter().filter_map(|file| file.path).collect(),
+            screen_size: (screen_width(), screen_height()),
+            // macroquad does not tell when the window loses the focus
+            window_focused: true,
+        }
+    }
+}
+
+/// Input written by hand, one frame at a time. Its clones share the same state, keep one to script the input
+/// given to the engine. The presses, releases, wheel moves, characters and dropped files are consumed by the next poll, the
+/// mouse position, the keys held down, the screen size and the focus of the window stay.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedInput {
+    state: Arc<Mutex<InputState>>,
+}
+
+impl ScriptedInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_mouse(&self, x: f32, y: f32) {
+        self.state.lock().unwrap().mouse_position = (x, y);
+    }
+
+    pub fn press(&self, button: MouseButton) {
+        self.state.lock().unwrap().buttons_pressed.push(button);
+    }
+
+    pub fn release(&self, button: MouseButton) {
+        self.state.lock().unwrap().buttons_released.push(button);
+    }
+
+    pub fn wheel(&self, delta_x: f32, delta_y: f32) {
+        let mut state = self.state.lock().unwrap();
+        state.wheel = (state.wheel.0 + delta_x, state.wheel.1 + delta_y);
+    }
+
+    pub fn press_key(&self, key: KeyCode) {
+        let mut state = self.state.lock().unwrap();
+        state.keys_pressed.push(key);
+        if !state.keys_down.contains(&key) {
+            state.keys_down.push(key);
+        }
+    }
+
+    pub fn release_key(&self, key: KeyCode) {
+        let mut state = self.state.lock().unwrap();
+        state.keys_down.retain(|down| *down != key);
+        state.keys_released.push(key);
+    }
+
+    pub fn type_text(&self, text: &str) {
+        self.state.lock().unwrap().chars.extend(text.chars());
+    }
+
+    pub fn resize(&self, width: f32, height: f32) {
+        self.state.lock().unwrap().screen_size = (width, height);
+    }
+
+    pub fn set_window_focused(&self, focused: bool) {
+        self.state.lock().unwrap().window_focused = focused;
+    }
+
+    /// Drop the files `paths` on the window, where the mouse is
+    pub fn drop_files(&self, paths: &[&str]) {
+        self.state.lock().unwrap().dropped_files.extend(paths.iter().map(PathBuf::from));
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn poll(&mut self) -> InputState {
+        let mut state = self.state.lock().unwrap();
+        let frame = state.clone();
+        state.buttons_pressed.clear();
+        state.buttons_released.clear();
+        state.wheel = (0.0, 0.0);
+        state.keys_pressed.clear();
+        state.keys_released.clear();
+        state.chars.clear();
+        state.dropped_files.clear();
+        frame
+    }
+}
+
+/// Clipboard read and written by the TextInputs
+pub trait Clipboard {
+    fn get(&self) -> Option<String>;
+    fn set(&mut self, text: &str);
+}
+
+/// The clipboard of the system, it needs a macroquad window
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClipboard;
+
+impl Clipboard for SystemClipboard {
+    fn get(&self) -> Option<String> {
+        macroquad::miniquad::window::clipboard_get()
+    }
+
+    fn set(&mut self, text: &str) {
+        macroquad::miniquad::window::clipboard_set(text);
+    }
+}
+
+/// A clipboard in memory. Its clones share the same content.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryClipboard {
+    text: Arc<Mutex<Option<String>>>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get(&self) -> Option<String> {
+        self.text.lock().unwrap().clone()
+    }
+
+    fn set(&mut self, text: &str) {
+        *self.text.lock().unwrap() = Some(text.to_string());
+    }
+}
@@ -0,0 +1,304 @@
+// Components instantiated and destroyed at runtime
+//
+// A `Component` builds a subtree in the engine (the `rml_component!` macro generates it from the same
+// syntax as `rml!`). Each instance gets its own scope: its node ids are registered as `id#scope`, and the
+// callbacks created while it is built run in that scope, where a node name resolves to the instance's own
+// node first, then to the enclosing scopes and finally to the global tree. The same component can thus be
+// instantiated many times, and its callbacks keep addressing their own instance with `$.item.text`.
+//
+// Destroying a node removes its subtree from the arena along with the properties, bindings, event handlers
+// and layout entries of its nodes, the callbacks of the instances it contained, the instances of the
+// Repeaters and ListViews it contained and the animations, states, timers and text inputs of its nodes.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::{animation, click, drag, grab, list_view, repeater, states, text_input, timer, AbstractValue, CallbackId, NodeId, RmlEngine};
+
+pub type ScopeId = usize;
+
+type Builder = Arc<dyn Fn(&mut RmlEngine) -> Option<NodeId> + Send + Sync>;
+
+/// A subtree that can be instantiated at runtime, see `RmlEngine::instantiate`
+#[derive(Clone)]
+pub struct Component {
+    build: Builder,
+}
+
+impl std::fmt::Debug for Component {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Component").finish_non_exhaustive()
+    }
+}
+
+impl Component {
+    /// `build` adds the nodes of the subtree to the engine and returns its root
+    pub fn new<F>(build: F) -> Self
+    where
+        F: Fn(&mut RmlEngine) -> Option<NodeId> + Send + Sync + 'static,
+    {
+        Self { build: Arc::new(build) }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Scopes {
+    next: ScopeId,
+    /// scope in which the nodes are created and the names resolved
+    pub(crate) current: Option<ScopeId>,
+    parents: HashMap<ScopeId, Option<ScopeId>>,
+    /// scope of the callbacks created in an instance
+    callbacks: HashMap<CallbackId, ScopeId>,
+    /// root node of each instance
+    roots: HashMap<NodeId, ScopeId>,
+}
+
+impl Scopes {
+    /// Name under which the node `id` of the scope is registered
+    pub(crate) fn scoped_name(id: &str, scope: ScopeId) -> String {
+        format!("{}#{}", id, scope)
+    }
+
+    /// The current scope and its enclosing scopes, innermost first
+    pub(crate) fn chain(&self) -> impl Iterator<Item = ScopeId> + '_ {
+        std::iter::successors(self.current, |scope| self.parents.get(scope).copied().flatten())
+    }
+
+    pub(crate) fn callback_created(&mut self, callback_id: CallbackId) {
+        if let Some(scope) = self.current {
+            self.callbacks.insert(callback_id, scope);
+        }
+    }
+
+    pub(crate) fn of_callback(&self, callback_id: CallbackId) -> Option<ScopeId> {
+        self.callbacks.get(&callback_id).copied()
+    }
+
+    fn open(&mut self) -> ScopeId {
+        let scope = self.next;
+        self.next += 1;
+        self.parents.insert(scope, self.current);
+        scope
+    }
+
+    /// Close the scopes of the instances rooted at `nodes` and the scopes nested in them,
+    /// return the callbacks created in these scopes
+    fn close(&mut self, nodes: &[NodeId]) -> HashSet<CallbackId> {
+        let closed = nodes.iter().filter_map(|node_id| self.roots.remove(node_id)).collect();
+        self.close_scopes(closed)
+    }
+
+    fn close_scopes(&mut self, mut closed: HashSet<ScopeId>) -> HashSet<CallbackId> {
+        if closed.is_empty() {
+            return HashSet::new();
+        }
+        // nested instances whose root was not removed (they were moved elsewhere) still go with their parent scope
+        loop {
+            let nested: Vec<ScopeId> = self.parents.iter()
+                .filter(|(scope, parent)| !closed.contains(scope) && parent.is_some_and(|parent| closed.contains(&parent)))
+                .map(|(scope, _)| *scope)
+                .collect();
+            if nested.is_empty() {
+                break;
+            }
+            closed.extend(nested);
+        }
+        self.parents.retain(|scope, _| !closed.contains(scope));
+        self.roots.retain(|_, scope| !closed.contains(scope));
+
+        let callbacks: HashSet<CallbackId> = self.callbacks.iter()
+            .filter(|(_, scope)| closed.contains(scope))
+            .map(|(callback_id, _)| *callback_id)
+            .collect();
+        self.callbacks.retain(|callback_id, _| !callbacks.contains(callback_id));
+        callbacks
+    }
+}
+
+/// Build an instance of `component` in a new scope, attach it under `parent` and set `properties` on its root
+pub fn instantiate(engine: &mut RmlEngine, component: &Component, parent: NodeId, properties: &[(&str, AbstractValue)]) -> Option<NodeId> {
+    let root = instantiate_with(engine, parent, |engine| (component.build)(engine))?;
+    for (name, value) in properties {
+        engine.set_property_of_node_id(root, name, value.clone());
+    }
+    Some(root)
+}
+
+/// Run `build` in a new scope and attach the node it returns under `parent`
+pub(crate) fn instantiate_with(engine: &mut RmlEngine, parent: NodeId, build: impl FnOnce(&mut RmlEngine) -> Option<NodeId>) -> Option<NodeId> {
+    engine.get_node(parent)?;
+
+    let scope = engine.scopes.open();
+    let enclosing = engine.scopes.current.replace(scope);
+    let root = build(engine);
+    engine.scopes.current = enclosing;
+
+    let Some(root) = root else {
+        // drop what was built before the failure
+        let orphans: Vec<NodeId> = engine.get_arena().iter()
+            .filter(|(_, node)| node.parent.is_none() && node.id.ends_with(&format!("#{}", scope)))
+            .map(|(node_id, _)| node_id)
+            .collect();
+        for node_id in orphans {
+            destroy(engine, node_id);
+        }
+        let callbacks = engine.scopes.close_scopes(HashSet::from([scope]));
+        drop_callbacks(engine, &callbacks);
+        return None;
+    };
+    engine.scopes.roots.insert(root, scope);
+    engine.add_child(parent, root);
+    Some(root)
+}
+
+/// Remove `node_id` and its descendants from the engine, with everything attached to them.
+/// Return false if the node doesn't exist (anymore).
+pub fn destroy(engine: &mut RmlEngine, node_id: NodeId) -> bool {
+    let parent = engine.get_node(node_id).and_then(|node| node.parent);
+    let removed = engine.arena.remove_node(node_id);
+    if removed.is_empty() {
+        return false;
+    }
+    let removed_ids: Vec<NodeId> = removed.iter().map(|(id, _)| *id).collect();
+
+    let mut properties = HashSet::new();
+    for (id, node) in &removed {
+        properties.extend(node.properties.values().copied());
+        engine.layout.forget(*id);
+        engine.hot_reload.forget(*id);
+    }
+    for property_id in &properties {
+        engine.properties.remove(property_id);
+        engine.bindings.remove(property_id);
+    }
+
+    let callbacks = engine.scopes.close(&removed_ids);
+    drop_callbacks(engine, &callbacks);
+    engine.event_manager.forget_nodes(&removed_ids, &callbacks);
+
+    list_view::forget(engine, &removed_ids);
+    animation::forget(engine, &removed_ids, &properties);
+    states::forget(engine, &removed_ids, &properties);
+    timer::forget(engine, &removed_ids);
+    text_input::forget(engine, &removed_ids);
+    drag::forget(engine, &removed_ids);
+    grab::forget(engine, &removed_ids);
+    click::forget(engine, &removed_ids);
+    // the instances of a removed Repeater are its siblings, they go with it
+    for instance in repeater::forget(engine, &removed_ids) {
+        destroy(engine, instance);
+    }
+
+    if let Some(parent) = parent {
+        engine.mark_layout_dirty(parent);
+    }
+    true
+}
+
+fn drop_callbacks(engine: &mut RmlEngine, callbacks: &HashSet<CallbackId>) {
+    if callbacks.is_empty() {
+        return;
+    }
+    engine.callbacks.retain(|callback_id, _| !callbacks.contains(callback_id));
+    for bound in engine.bindings.values_mut() {
+        bound.retain(|callback_id| !callbacks.contains(callback_id));
+    }
+    engine.callbacks_to_eval.retain(|callback_id| !callbacks.contains(callback_id));
+    engine.event_manager.forget_nodes(&[], callbacks);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_node, sample_engine};
+    use crate::{EventType, ItemTypeEnum};
+    use macroquad::input::MouseButton;
+
+    /// item > label, the label text follows the item `title`, a click on the item appends to the root `log`
+    fn item_component() -> Component {
+        Component::new(|engine| {
+            add_node(engine, None, "item", ItemTypeEnum::Rectangle, &[("title", AbstractValue::String("".into()))]);
+            add_node(engine, Some("item"), "label", ItemTypeEnum::Text, &[("text", AbstractValue::String("".into()))]);
+            let follow_title = engine.add_callback(|engine| {
+                let title = engine.get_string_property_of_node("item", "title", String::new());
+                engine.set_property_of_node("label", "text", AbstractValue::String(title));
+            });
+            engine.bind_node_property_to_callback("item", "title", follow_title);
+            let on_click = engine.add_callback(|engine| {
+                let title = engine.get_string_property_of_node("item", "title", String::new());
+                let log = engine.get_string_property_of_node("root", "log", String::new());
+                engine.set_property_of_node("root", "log", AbstractValue::String(log + &title));
+            });
+            engine.add_event_handler(EventType::Click, "item", on_click);
+            engine.get_node_id("item")
+        })
+    }
+
+    fn engine_with_log() -> RmlEngine {
+        let mut engine = sample_engine();
+        let root = engine.get_node_id("root").unwrap();
+        let log = engine.add_property(crate::Property::new(AbstractValue::String("".into())));
+        engine.add_property_to_node(root, "log".to_string(), log);
+        engine
+    }
+
+    #[test]
+    fn test_instances_have_their_own_scope() {
+        let mut engine = engine_with_log();
+        let panel = engine.get_node_id("panel").unwrap();
+        let component = item_component();
+        let first = instantiate(&mut engine, &component, panel, &[("title", "first".into())]).unwrap();
+        let second = instantiate(&mut engine, &component, panel, &[("title", "second".into())]).unwrap();
+        engine.run_callbacks();
+
+        let label_text = |engine: &RmlEngine, item: NodeId| {
+            let label = engine.get_arena().get_childrens_ids(item)[0];
+            engine.get_string_property_of_node_id(label, "text", String::new())
+        };
+        assert_eq!(label_text(&engine, first), "first");
+        assert_eq!(label_text(&engine, second), "second");
+        // the global names are left alone
+        assert!(engine.get_node_id("item").is_none());
+        assert_eq!(engine.get_arena().get_childrens_ids(panel)[1..], [first, second]);
+    }
+
+    #[test]
+    fn test_destroy_cleans_up() {
+        let mut engine = engine_with_log();
+        let panel = engine.get_node_id("panel").unwrap();
+        let component = item_component();
+        let properties_before = engine.properties.len();
+        let callbacks_before = engine.callbacks.len();
+
+        let item = instantiate(&mut engine, &component, panel, &[("title", "a".into())]).unwrap();
+        let label = engine.get_arena().get_childrens_ids(item)[0];
+        assert!(destroy(&mut engine, item));
+        engine.run_callbacks();
+
+        assert!(engine.get_node(item).is_none());
+        assert!(engine.get_node(label).is_none());
+        assert_eq!(engine.properties.len(), properties_before);
+        assert_eq!(engine.callbacks.len(), callbacks_before);
+        assert!(engine.event_manager.handlers.is_empty());
+        assert!(!engine.get_arena().get_childrens_ids(panel).contains(&item));
+        assert!(!destroy(&mut engine, item));
+
+        // a new instance reuses the slots, the old ids stay dead
+        let other = instantiate(&mut engine, &component, panel, &[("title", "b".into())]).unwrap();
+        assert_eq!(other.index(), item.index());
+        assert!(engine.get_node(item).is_none());
+        assert_eq!(engine.get_string_property_of_node_id(other, "title", String::new()), "b");
+    }
+
+    #[test]
+    fn test_instance_callbacks_run_in_their_scope() {
+        let mut engine = engine_with_log();
+        let panel = engine.get_node_id("panel").unwrap();
+        let component = item_component();
+        instantiate(&mut engine, &component, panel, &[("title", "x".into())]).unwrap();
+        let second = instantiate(&mut engine, &component, panel, &[("title", "y".into())]).unwrap();
+
+        engine.handle_system_event(&crate::SystemEvent::Click { node_id: second, button: MouseButton::Left, x: 0.0, y: 0.0, click_count: 1 });
+        assert_eq!(engine.get_string_property_of_node("root", "log", String::new()), "y");
+    }
+}
@@ -0,0 +1,1096 @@
+use std::collections::{HashMap, HashSet};
+
+use macroquad::prelude::{warn, WHITE};
+
+use crate::renderer::{Renderer, TextStyle};
+use crate::text_layout::TextLayout;
+use crate::{list_view, AbstractValue, ItemTypeEnum, NodeId, RmlEngine};
+
+/// Properties that change the geometry of a node (or of its text measurement).
+/// Setting one of them marks the node dirty in the `LayoutTree`.
+const LAYOUT_PROPERTIES: &[&str] = &[
+    "x", "y", "width", "height", "visible",
+    "anchors", "margins", "top_margin", "bottom_margin", "left_margin", "right_margin",
+    "text", "font", "font_size",
+    // anchors to other nodes
+    "anchors_left", "anchors_right", "anchors_horizontal_center",
+    "anchors_top", "anchors_bottom", "anchors_vertical_center",
+    "anchors_fill", "anchors_center_in", "horizontal_center_offset", "vertical_center_offset",
+    // ListView
+    "content_y",
+    // Row, Column and Grid
+    "spacing", "row_spacing", "column_spacing", "columns", "rows", "alignment",
+    "padding", "top_padding", "bottom_padding", "left_padding", "right_padding",
+    // attached properties read by the positioners on their childs
+    "layout_fill_width", "layout_fill_height", "layout_preferred_width", "layout_preferred_height", "layout_alignment",
+];
+
+pub fn is_layout_property(name: &str) -> bool {
+    LAYOUT_PROPERTIES.contains(&name)
+}
+
+/// Row, Column and Grid place their childs themselves, the anchors of their childs are ignored
+pub fn is_positioner(node_type: &ItemTypeEnum) -> bool {
+    matches!(node_type, ItemTypeEnum::Row | ItemTypeEnum::Column | ItemTypeEnum::Grid)
+}
+
+/// Absolute rectangle of a node, in window coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.x + self.width &&
+        y >= self.y && y <= self.y + self.height
+    }
+
+    /// The part of `self` inside `other`, empty (zero sized) if they don't overlap
+    pub fn intersection(&self, other: Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Rect::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+    }
+}
+
+/// Side table holding the computed rect of every laid out node.
+///
+/// Nodes are marked dirty when one of their layout properties changes; the next
+/// `update_layout` only recomputes the subtrees rooted at dirty nodes.
+#[derive(Debug, Default)]
+pub struct LayoutTree {
+    rects: HashMap<NodeId, Rect>,
+    implicit_sizes: HashMap<NodeId, (f32, f32)>,
+    dirty: HashSet<NodeId>,
+    viewport: (f32, f32),
+    // nodes anchored to a node (`anchors.left: target.right`), recomputed with it
+    dependents: HashMap<NodeId, HashSet<NodeId>>,
+}
+
+impl LayoutTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, node_id: NodeId) -> Option<Rect> {
+        self.rects.get(&node_id).copied()
+    }
+
+    pub fn mark_dirty(&mut self, node_id: NodeId) {
+        self.dirty.insert(node_id);
+    }
+
+    pub fn is_dirty(&self, node_id: NodeId) -> bool {
+        self.dirty.contains(&node_id)
+    }
+
+    pub fn has_dirty_nodes(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Forget the rect of a node taken out of the tree
+    pub fn forget(&mut self, node_id: NodeId) {
+        self.rects.remove(&node_id);
+        self.implicit_sizes.remove(&node_id);
+        self.dirty.remove(&node_id);
+        self.dependents.remove(&node_id);
+    }
+
+    /// Forget every computed rect, the next update will lay out the whole tree
+    pub fn invalidate(&mut self) {
+        self.rects.clear();
+        self.implicit_sizes.clear();
+        self.dirty.clear();
+        self.dependents.clear();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Anchor {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    HorizontalCenter,
+    VerticalCenter,
+    Center,
+    Fill,
+}
+
+impl Anchor {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "top" => Some(Anchor::Top),
+            "bottom" => Some(Anchor::Bottom),
+            "left" => Some(Anchor::Left),
+            "right" => Some(Anchor::Right),
+            "horizontal_center" => Some(Anchor::HorizontalCenter),
+            "vertical_center" => Some(Anchor::VerticalCenter),
+            "center" => Some(Anchor::Center),
+            "fill" => Some(Anchor::Fill),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Margins {
+    top: f32,
+    bottom: f32,
+    left: f32,
+    right: f32,
+}
+
+impl Margins {
+    fn new(engine: &RmlEngine, node_id: NodeId) -> Self {
+        let base_margin = engine.get_number_property_of_node_id(node_id, "margins", 0.0);
+        Self {
+            top: engine.get_number_property_of_node_id(node_id, "top_margin", base_margin),
+            bottom: engine.get_number_property_of_node_id(node_id, "bottom_margin", base_margin),
+            left: engine.get_number_property_of_node_id(node_id, "left_margin", base_margin),
+            right: engine.get_number_property_of_node_id(node_id, "right_margin", base_margin),
+        }
+    }
+
+    fn apply_anchor_constraints(&mut self, anchors: &[Anchor]) {
+        for anchor in anchors {
+            match anchor {
+                Anchor::HorizontalCenter => {
+                    self.left = 0.0;
+                    self.right = 0.0;
+                }
+                Anchor::VerticalCenter => {
+                    self.top = 0.0;
+                    self.bottom = 0.0;
+                }
+                Anchor::Center if anchors.len() == 1 => {
+                    self.left = 0.0;
+                    self.right = 0.0;
+                    self.top = 0.0;
+                    self.bottom = 0.0;
+                }
+                _ => {} // other anchors keep their margins
+            }
+        }
+
+        // if fill is specified, don't alter margins
+        if anchors.contains(&Anchor::Fill) { return; }
+
+        // special logic: if we don't have the corresponding anchor, no margin
+        if !anchors.contains(&Anchor::Left) { self.left = 0.0; }
+        if !anchors.contains(&Anchor::Right) { self.right = 0.0; }
+        if !anchors.contains(&Anchor::Top) { self.top = 0.0; }
+        if !anchors.contains(&Anchor::Bottom) { self.bottom = 0.0; }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Geometry {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    implicit_size: Option<(f32, f32)>,
+}
+
+impl Geometry {
+    fn new(engine: &RmlEngine, renderer: &dyn Renderer, node_id: NodeId) -> Self {
+        let mut geometry = Self {
+            x: engine.get_number_property_of_node_id(node_id, "x", 0.0),
+            y: engine.get_number_property_of_node_id(node_id, "y", 0.0),
+            width: engine.get_number_property_of_node_id(node_id, "width", 0.0),
+            height: engine.get_number_property_of_node_id(node_id, "height", 0.0),
+            implicit_size: None,
+        };
+
+        // if the node is a text, and don't have a width or height, we need to measure its size
+        if let Some(node) = engine.get_node(node_id) {
+            if node.node_type == ItemTypeEnum::Text {
+                let text = engine.get_string_property_of_node_id(node_id, "text", String::new());
+                let font_size = engine.get_number_property_of_node_id(node_id, "font_size", 20.0);
+                let wanted_font = engine.get_string_property_of_node_id(node_id, "font", String::new());
+
+                // mesure the text dimensions
+                let style = TextStyle { font: engine.get_font(&wanted_font), font_size: font_size as u16, color: WHITE };
+                let text_dimensions = renderer.measure_text(&text, &style);
+                if geometry.width == 0.0 {
+                    geometry.width = text_dimensions.width;
+                }
+                if geometry.height == 0.0 {
+                    geometry.height = text_dimensions.height;
+                }
+                geometry.implicit_size = Some((text_dimensions.width, text_dimensions.height));
+            }
+            // a TextInput or a TextArea without size takes the size of its text (or its placeholder), unwrapped
+            else if crate::text_input::is_editable(engine, node_id) && (geometry.width == 0.0 || geometry.height == 0.0) {
+                let mut text = crate::text_input::displayed_text(engine, node_id);
+                if text.is_empty() {
+                    text = engine.get_string_property_of_node_id(node_id, "placeholder", String::new());
+                }
+                let style = crate::text_input::text_style(engine, node_id, WHITE);
+                let line_height = crate::text_input::line_height(engine, renderer, node_id);
+                let text_layout = TextLayout::new(&text, None, line_height, |text| renderer.measure_text(text, &style).width);
+                let (width, height) = (text_layout.width(), text_layout.height());
+                if geometry.width == 0.0 {
+                    geometry.width = width;
+                }
+                if geometry.height == 0.0 {
+                    geometry.height = height;
+                }
+                geometry.implicit_size = Some((width, height));
+            }
+            // a positioner without size takes the size of its content
+            else if is_positioner(&node.node_type) && (geometry.width == 0.0 || geometry.height == 0.0) {
+                let (content_width, content_height) = content_size(engine, renderer, node_id, &node.node_type);
+                if geometry.width == 0.0 {
+                    geometry.width = content_width;
+                }
+                if geometry.height == 0.0 {
+                    geometry.height = content_height;
+                }
+            }
+        }
+
+        geometry
+    }
+
+    fn apply_anchors(&mut self, anchors: &[Anchor], parent_size: (f32, f32)) {
+        let (parent_width, parent_height) = parent_size;
+
+        for anchor in anchors {
+            match anchor {
+                Anchor::Top => self.y = 0.0,
+                Anchor::Bottom => self.y = parent_height - self.height,
+                Anchor::Left => self.x = 0.0,
+                Anchor::Right => self.x = parent_width - self.width,
+                Anchor::HorizontalCenter => self.x = (parent_width - self.width) / 2.0,
+                Anchor::VerticalCenter => self.y = (parent_height - self.height) / 2.0,
+                Anchor::Center => {
+                    self.x = (parent_width - self.width) / 2.0;
+                    self.y = (parent_height - self.height) / 2.0;
+                }
+                Anchor::Fill => {
+                    self.x = 0.0;
+                    self.y = 0.0;
+                    self.width = parent_width;
+                    self.height = parent_height;
+                }
+            }
+        }
+
+        // Gestion des cas spéciaux pour le remplissage
+        if anchors.contains(&Anchor::Top) && anchors.contains(&Anchor::Bottom) {
+            self.y = 0.0;
+            self.height = parent_height;
+        }
+        if anchors.contains(&Anchor::Left) && anchors.contains(&Anchor::Right) {
+            self.x = 0.0;
+            self.width = parent_width;
+        }
+    }
+
+    fn apply_margins(&mut self, margins: &Margins, anchors: &[Anchor], parent_pos: (f32, f32)) {
+        let (parent_x, parent_y) = parent_pos;
+
+        // fill special case; if fill is specified, also alter the width and height
+        if anchors.contains(&Anchor::Fill) {
+            self.x = parent_x + self.x + margins.left;
+            self.y = parent_y + self.y + margins.top;
+            self.width = self.width - margins.left - margins.right;
+            self.height = self.height - margins.top - margins.bottom;
+        } else {
+            self.x = parent_x + self.x + margins.left - margins.right;
+            self.y = parent_y + self.y + margins.top - margins.bottom;
+        }
+    }
+
+    fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Padding {
+    top: f32,
+    bottom: f32,
+    left: f32,
+    right: f32,
+}
+
+impl Padding {
+    fn new(engine: &RmlEngine, node_id: NodeId) -> Self {
+        let base_padding = engine.get_number_property_of_node_id(node_id, "padding", 0.0);
+        Self {
+            top: engine.get_number_property_of_node_id(node_id, "top_padding", base_padding),
+            bottom: engine.get_number_property_of_node_id(node_id, "bottom_padding", base_padding),
+            left: engine.get_number_property_of_node_id(node_id, "left_padding", base_padding),
+            right: engine.get_number_property_of_node_id(node_id, "right_padding", base_padding),
+        }
+    }
+
+    fn shrink(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.x + self.left,
+            rect.y + self.top,
+            (rect.width - self.left - self.right).max(0.0),
+            (rect.height - self.top - self.bottom).max(0.0),
+        )
+    }
+}
+
+/// What a positioner needs to know about one of its childs
+#[derive(Debug, Clone)]
+struct LayoutItem {
+    node_id: NodeId,
+    width: f32,
+    height: f32,
+    fill_width: bool,
+    fill_height: bool,
+    alignment: Vec<Anchor>,
+    implicit_size: Option<(f32, f32)>,
+}
+
+impl LayoutItem {
+    fn new(engine: &RmlEngine, renderer: &dyn Renderer, node_id: NodeId, parent_alignment: &[Anchor]) -> Self {
+        let geometry = Geometry::new(engine, renderer, node_id);
+        let preferred_width = engine.get_number_property_of_node_id(node_id, "layout_preferred_width", 0.0);
+        let preferred_height = engine.get_number_property_of_node_id(node_id, "layout_preferred_height", 0.0);
+        let alignment = parse_anchors(&engine.get_string_property_of_node_id(node_id, "layout_alignment", String::new()));
+
+        Self {
+            node_id,
+            width: if preferred_width > 0.0 { preferred_width } else { geometry.width },
+            height: if preferred_height > 0.0 { preferred_height } else { geometry.height },
+            fill_width: engine.get_bool_property_of_node_id(node_id, "layout_fill_width", false),
+            fill_height: engine.get_bool_property_of_node_id(node_id, "layout_fill_height", false),
+            alignment: if alignment.is_empty() { parent_alignment.to_vec() } else { alignment },
+            implicit_size: geometry.implicit_size,
+        }
+    }
+
+    /// Offset of an item of size `size` in a cell of size `available`, for one axis.
+    /// Items stick to the start of the cell unless aligned on the `center` or `end` anchor.
+    fn align(&self, center: &Anchor, end: &Anchor, size: f32, available: f32) -> f32 {
+        if self.alignment.contains(end) {
+            available - size
+        } else if self.alignment.contains(center) || self.alignment.contains(&Anchor::Center) {
+            (available - size) / 2.0
+        } else {
+            0.0
+        }
+    }
+}
+
+fn layout_items(engine: &RmlEngine, renderer: &dyn Renderer, node_id: NodeId) -> Vec<LayoutItem> {
+    let alignment = parse_anchors(&engine.get_string_property_of_node_id(node_id, "alignment", String::new()));
+    engine.get_arena().get_childrens_ids(node_id)
+        .into_iter()
+        .filter(|&child_id| engine.get_bool_property_of_node_id(child_id, "visible", true))
+        // a Repeater takes no space, its instances follow it, and an animation or a timer is not an item
+        .filter(|&child_id| engine.get_node(child_id).is_some_and(|node| {
+            node.node_type != ItemTypeEnum::Repeater && !crate::animation::is_animation_node(&node.node_type)
+                && !crate::timer::is_timer_node(&node.node_type)
+        }))
+        .map(|child_id| LayoutItem::new(engine, renderer, child_id, &alignment))
+        .collect()
+}
+
+/// Number of columns and rows of a Grid holding `count` items.
+/// Without `columns` nor `rows`, a grid has 4 columns.
+fn grid_dimensions(engine: &RmlEngine, node_id: NodeId, count: usize) -> (usize, usize) {
+    let columns = engine.get_number_property_of_node_id(node_id, "columns", 0.0) as usize;
+    let rows = engine.get_number_property_of_node_id(node_id, "rows", 0.0) as usize;
+    let columns = match (columns, rows) {
+        (0, 0) => 4,
+        (0, rows) => count.div_ceil(rows).max(1),
+        (columns, _) => columns,
+    };
+    (columns, count.div_ceil(columns))
+}
+
+/// Width of each column and height of each row of a Grid
+fn grid_tracks(items: &[LayoutItem], columns: usize, rows: usize) -> (Vec<f32>, Vec<f32>) {
+    let mut column_widths = vec![0.0f32; columns];
+    let mut row_heights = vec![0.0f32; rows];
+    for (index, item) in items.iter().enumerate() {
+        let (column, row) = (index % columns, index / columns);
+        column_widths[column] = column_widths[column].max(item.width);
+        row_heights[row] = row_heights[row].max(item.height);
+    }
+    (column_widths, row_heights)
+}
+
+fn spacing_total(spacing: f32, count: usize) -> f32 {
+    spacing * count.saturating_sub(1) as f32
+}
+
+/// Size of the content of a positioner, paddings included
+fn content_size(engine: &RmlEngine, renderer: &dyn Renderer, node_id: NodeId, node_type: &ItemTypeEnum) -> (f32, f32) {
+    let items = layout_items(engine, renderer, node_id);
+    let padding = Padding::new(engine, node_id);
+    let spacing = engine.get_number_property_of_node_id(node_id, "spacing", 0.0);
+
+    let (width, height) = match node_type {
+        ItemTypeEnum::Row => (
+            items.iter().map(|item| item.width).sum::<f32>() + spacing_total(spacing, items.len()),
+            items.iter().map(|item| item.height).fold(0.0, f32::max),
+        ),
+        ItemTypeEnum::Column => (
+            items.iter().map(|item| item.width).fold(0.0, f32::max),
+            items.iter().map(|item| item.height).sum::<f32>() + spacing_total(spacing, items.len()),
+        ),
+        ItemTypeEnum::Grid => {
+            let (columns, rows) = grid_dimensions(engine, node_id, items.len());
+            let (column_widths, row_heights) = grid_tracks(&items, columns, rows);
+            let column_spacing = engine.get_number_property_of_node_id(node_id, "column_spacing", spacing);
+            let row_spacing = engine.get_number_property_of_node_id(node_id, "row_spacing", spacing);
+            (
+                column_widths.iter().sum::<f32>() + spacing_total(column_spacing, columns),
+                row_heights.iter().sum::<f32>() + spacing_total(row_spacing, rows),
+            )
+        }
+        _ => (0.0, 0.0),
+    };
+
+    (width + padding.left + padding.right, height + padding.top + padding.bottom)
+}
+
+/// Share the space left by the fixed size items between the items filling the main axis
+fn fill_share(available: f32, fixed: f32, fill_count: usize) -> f32 {
+    if fill_count == 0 { return 0.0; }
+    ((available - fixed) / fill_count as f32).max(0.0)
+}
+
+/// Place the visible childs of the positioner `node_id` laid out in `rect`
+fn place_items(engine: &RmlEngine, renderer: &dyn Renderer, node_id: NodeId, node_type: &ItemTypeEnum, rect: Rect) -> Vec<(LayoutItem, Rect)> {
+    let items = layout_items(engine, renderer, node_id);
+    let inner = Padding::new(engine, node_id).shrink(rect);
+    let spacing = engine.get_number_property_of_node_id(node_id, "spacing", 0.0);
+    let mut placed = Vec::with_capacity(items.len());
+
+    match node_type {
+        ItemTypeEnum::Row => {
+            let fixed: f32 = items.iter().filter(|item| !item.fill_width).map(|item| item.width).sum();
+            let fill_count = items.iter().filter(|item| item.fill_width).count();
+            let fill_width = fill_share(inner.width, fixed + spacing_total(spacing, items.len()), fill_count);
+
+            let mut x = inner.x;
+            for item in items {
+                let width = if item.fill_width { fill_width } else { item.width };
+                let height = if item.fill_height { inner.height } else { item.height };
+                let y = inner.y + item.align(&Anchor::VerticalCenter, &Anchor::Bottom, height, inner.height);
+                placed.push((item, Rect::new(x, y, width, height)));
+                x += width + spacing;
+            }
+        }
+        ItemTypeEnum::Column => {
+            let fixed: f32 = items.iter().filter(|item| !item.fill_height).map(|item| item.height).sum();
+            let fill_count = items.iter().filter(|item| item.fill_height).count();
+            let fill_height = fill_share(inner.height, fixed + spacing_total(spacing, items.len()), fill_count);
+
+            let mut y = inner.y;
+            for item in items {
+                let height = if item.fill_height { fill_height } else { item.height };
+                let width = if item.fill_width { inner.width } else { item.width };
+                let x = inner.x + item.align(&Anchor::HorizontalCenter, &Anchor::Right, width, inner.width);
+                placed.push((item, Rect::new(x, y, width, height)));
+                y += height + spacing;
+            }
+        }
+        ItemTypeEnum::Grid => {
+            let (columns, rows) = grid_dimensions(engine, node_id, items.len());
+            let (column_widths, row_heights) = grid_tracks(&items, columns, rows);
+            let column_spacing = engine.get_number_property_of_node_id(node_id, "column_spacing", spacing);
+            let row_spacing = engine.get_number_property_of_node_id(node_id, "row_spacing", spacing);
+
+            for (index, item) in items.into_iter().enumerate() {
+                let (column, row) = (index % columns, index / columns);
+                let cell_x = inner.x + column_widths[..column].iter().sum::<f32>() + column as f32 * column_spacing;
+                let cell_y = inner.y + row_heights[..row].iter().sum::<f32>() + row as f32 * row_spacing;
+                let width = if item.fill_width { column_widths[column] } else { item.width };
+                let height = if item.fill_height { row_heights[row] } else { item.height };
+                let x = cell_x + item.align(&Anchor::HorizontalCenter, &Anchor::Right, width, column_widths[column]);
+                let y = cell_y + item.align(&Anchor::VerticalCenter, &Anchor::Bottom, height, row_heights[row]);
+                placed.push((item, Rect::new(x, y, width, height)));
+            }
+        }
+        _ => {}
+    }
+
+    placed
+}
+
+fn parse_anchors(anchor_string: &str) -> Vec<Anchor> {
+    anchor_string
+        .split("__")
+        .filter_map(|s| Anchor::from_str(s.trim()))
+        .collect()
+}
+
+/// Result of laying out a single node
+#[derive(Debug, Clone, Copy)]
+struct ComputedNode {
+    rect: Rect,
+    implicit_size: Option<(f32, f32)>,
+}
+
+/// Properties anchoring an edge of a node to an edge of another node (`anchors.left: header.right`).
+/// Their value is `target.edge`, where target is a node id or `parent`.
+const EDGE_ANCHORS: &[&str] = &[
+    "anchors_left", "anchors_right", "anchors_horizontal_center",
+    "anchors_top", "anchors_bottom", "anchors_vertical_center",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+fn edge_axis(edge: &str) -> Option<Axis> {
+    match edge {
+        "left" | "right" | "horizontal_center" => Some(Axis::Horizontal),
+        "top" | "bottom" | "vertical_center" => Some(Axis::Vertical),
+        _ => None,
+    }
+}
+
+fn edge_position(rect: Rect, edge: &str) -> f32 {
+    match edge {
+        "left" => rect.x,
+        "right" => rect.x + rect.width,
+        "horizontal_center" => rect.x + rect.width / 2.0,
+        "top" => rect.y,
+        "bottom" => rect.y + rect.height,
+        _ => rect.y + rect.height / 2.0, // vertical_center
+    }
+}
+
+/// A single run of the layout over the dirty part of the tree.
+///
+/// Rects are resolved on demand and memoized: a node anchored to another node first resolves
+/// the rect of its target, whatever their order in the tree. `in_progress` holds the nodes being
+/// resolved, finding one of them again means the anchors form a cycle.
+struct LayoutPass<'a> {
+    engine: &'a RmlEngine,
+    renderer: &'a dyn Renderer,
+    root_id: NodeId,
+    viewport: Rect,
+    scheduled: HashSet<NodeId>,
+    computed: HashMap<NodeId, ComputedNode>,
+    order: Vec<NodeId>,
+    in_progress: HashSet<NodeId>,
+    dependencies: Vec<(NodeId, NodeId)>,
+}
+
+impl<'a> LayoutPass<'a> {
+    /// Rect of `node_id` for this pass: recomputed if the node is scheduled, taken from the table otherwise
+    fn rect_of(&mut self, node_id: NodeId) -> Option<Rect> {
+        if let Some(computed) = self.computed.get(&node_id) {
+            return Some(computed.rect);
+        }
+        if !self.scheduled.contains(&node_id) {
+            return self.engine.get_layout().get(node_id);
+        }
+        if !self.in_progress.insert(node_id) {
+            let id = self.engine.get_node(node_id).map(|node| node.id.clone()).unwrap_or_default();
+            warn!("Anchor cycle detected on node '{}', its anchors to other nodes are ignored", id);
+            return None;
+        }
+
+        let parent_id = self.engine.get_node(node_id).and_then(|node| node.parent);
+        match parent_id {
+            _ if node_id == self.root_id => {
+                let geometry = self.compute_geometry(node_id, self.viewport);
+                self.store(node_id, geometry.rect(), geometry.implicit_size);
+            }
+            Some(parent_id) => {
+                if let Some(parent_rect) = self.rect_of(parent_id) {
+                    let parent_type = self.engine.get_node(parent_id).map(|node| node.node_type.clone()).unwrap_or(ItemTypeEnum::Node);
+                    if is_positioner(&parent_type) {
+                        // the positioner places all its childs at once
+                        for (item, item_rect) in place_items(self.engine, self.renderer, parent_id, &parent_type, parent_rect) {
+                            if !self.computed.contains_key(&item.node_id) {
+                                self.store(item.node_id, item_rect, item.implicit_size);
+                            }
+                        }
+                    } else if parent_type == ItemTypeEnum::ListView {
+                        // the delegates are stacked by the ListView and fill its width unless they have their own
+                        let mut geometry = self.compute_geometry(node_id, parent_rect);
+                        if geometry.width == 0.0 {
+                            geometry.width = parent_rect.width;
+                        }
+                        geometry.y = parent_rect.y + list_view::item_offset(self.engine, parent_id, node_id);
+                        self.store(node_id, geometry.rect(), geometry.implicit_size);
+                    } else {
+                        let geometry = self.compute_geometry(node_id, parent_rect);
+                        self.store(node_id, geometry.rect(), geometry.implicit_size);
+                    }
+                }
+            }
+            None => {} // detached node
+        }
+
+        self.in_progress.remove(&node_id);
+        self.computed.get(&node_id).map(|computed| computed.rect)
+    }
+
+    fn store(&mut self, node_id: NodeId, rect: Rect, implicit_size: Option<(f32, f32)>) {
+        self.computed.insert(node_id, ComputedNode { rect, implicit_size });
+        self.order.push(node_id);
+    }
+
+    /// Compute the geometry of `node_id` inside `parent` (absolute rect of the parent, or the viewport for the root)
+    fn compute_geometry(&mut self, node_id: NodeId, parent: Rect) -> Geometry {
+        let mut geometry = Geometry::new(self.engine, self.renderer, node_id);
+
+        let anchor_string = self.engine.get_string_property_of_node_id(node_id, "anchors", String::new());
+        let anchors = parse_anchors(&anchor_string);
+        geometry.apply_anchors(&anchors, (parent.width, parent.height));
+
+        let mut margins = Margins::new(self.engine, node_id);
+        margins.apply_anchor_constraints(&anchors);
+        geometry.apply_margins(&margins, &anchors, (parent.x, parent.y));
+
+        self.apply_node_anchors(node_id, &mut geometry);
+        geometry
+    }
+
+    /// Resolve the node referenced by an anchor value, `parent` being the parent of `node_id`
+    fn anchor_target(&mut self, node_id: NodeId, target: &str) -> Option<Rect> {
+        let target_id = if target == "parent" {
+            self.engine.get_node(node_id).and_then(|node| node.parent)
+        } else {
+            self.engine.get_node_id(target)
+        };
+        let Some(target_id) = target_id else {
+            warn!("Unknown anchor target '{}'", target);
+            return None;
+        };
+        self.dependencies.push((target_id, node_id));
+        self.rect_of(target_id)
+    }
+
+    /// Position of the edge referenced by the anchor property `property` of `node_id`, if set
+    fn anchor_edge(&mut self, node_id: NodeId, property: &str, axis: Axis) -> Option<f32> {
+        let value = self.engine.get_property_by_name(node_id, property)?.value.to_string();
+        let (target, edge) = value.rsplit_once('.')?;
+        if edge_axis(edge) != Some(axis) {
+            warn!("Can't anchor '{}' to '{}', the edges are not on the same axis", property, value);
+            return None;
+        }
+        self.anchor_target(node_id, target).map(|rect| edge_position(rect, edge))
+    }
+
+    /// Apply `anchors.fill`, `anchors.center_in` and the edge anchors (`anchors.left: header.right`...)
+    /// over the geometry computed from the parent. Margins are used as offsets from the anchored edges.
+    fn apply_node_anchors(&mut self, node_id: NodeId, geometry: &mut Geometry) {
+        let has_node_anchors = ["anchors_fill", "anchors_center_in"].iter().chain(EDGE_ANCHORS)
+            .any(|property| self.engine.get_property_by_name(node_id, property).is_some());
+        if !has_node_anchors {
+            return;
+        }
+        let margins = Margins::new(self.engine, node_id);
+        let horizontal_offset = self.engine.get_number_property_of_node_id(node_id, "horizontal_center_offset", 0.0);
+        let vertical_offset = self.engine.get_number_property_of_node_id(node_id, "vertical_center_offset", 0.0);
+
+        let fill = self.engine.get_property_by_name(node_id, "anchors_fill").map(|property| property.value.to_string());
+        if let Some(target) = fill.and_then(|target| self.anchor_target(node_id, &target)) {
+            geometry.x = target.x + margins.left;
+            geometry.y = target.y + margins.top;
+            geometry.width = target.width - margins.left - margins.right;
+            geometry.height = target.height - margins.top - margins.bottom;
+        }
+        let center_in = self.engine.get_property_by_name(node_id, "anchors_center_in").map(|property| property.value.to_string());
+        if let Some(target) = center_in.and_then(|target| self.anchor_target(node_id, &target)) {
+            geometry.x = target.x + (target.width - geometry.width) / 2.0 + horizontal_offset;
+            geometry.y = target.y + (target.height - geometry.height) / 2.0 + vertical_offset;
+        }
+
+        let left = self.anchor_edge(node_id, "anchors_left", Axis::Horizontal).map(|x| x + margins.left);
+        let right = self.anchor_edge(node_id, "anchors_right", Axis::Horizontal).map(|x| x - margins.right);
+        let horizontal_center = self.anchor_edge(node_id, "anchors_horizontal_center", Axis::Horizontal);
+        match (left, right, horizontal_center) {
+            (Some(left), Some(right), _) => {
+                geometry.x = left;
+                geometry.width = right - left;
+            }
+            (Some(left), None, _) => geometry.x = left,
+            (None, Some(right), _) => geometry.x = right - geometry.width,
+            (None, None, Some(center)) => geometry.x = center + horizontal_offset - geometry.width / 2.0,
+            _ => {}
+        }
+
+        let top = self.anchor_edge(node_id, "anchors_top", Axis::Vertical).map(|y| y + margins.top);
+        let bottom = self.anchor_edge(node_id, "anchors_bottom", Axis::Vertical).map(|y| y - margins.bottom);
+        let vertical_center = self.anchor_edge(node_id, "anchors_vertical_center", Axis::Vertical);
+        match (top, bottom, vertical_center) {
+            (Some(top), Some(bottom), _) => {
+                geometry.y = top;
+                geometry.height = bottom - top;
+            }
+            (Some(top), None, _) => geometry.y = top,
+            (None, Some(bottom), _) => geometry.y = bottom - geometry.height,
+            (None, None, Some(center)) => geometry.y = center + vertical_offset - geometry.height / 2.0,
+            _ => {}
+        }
+    }
+}
+
+/// Schedule `node_id` and its descendants, returns the nodes that were not scheduled yet
+fn schedule_subtree(engine: &RmlEngine, node_id: NodeId, scheduled: &mut HashSet<NodeId>, roots: &mut Vec<NodeId>) -> Vec<NodeId> {
+    if !scheduled.insert(node_id) {
+        return Vec::new();
+    }
+    roots.push(node_id);
+    let mut added = vec![node_id];
+    let mut stack = engine.get_arena().get_childrens_ids(node_id);
+    while let Some(child_id) = stack.pop() {
+        if scheduled.insert(child_id) {
+            added.push(child_id);
+            stack.extend(engine.get_arena().get_childrens_ids(child_id));
+        }
+    }
+    added
+}
+
+/// Recompute the rect of every dirty subtree and store it in the engine's `LayoutTree`.
+///
+/// `computed_x/y/width/height` (and `implicit_width/height` for texts) are kept in sync
+/// for the nodes whose rect changed, so bindings on them keep working.
+pub fn update_layout(engine: &mut RmlEngine, renderer: &dyn Renderer) {
+    let Some(root_id) = engine.get_root_node_id() else { return };
+
+    let viewport = renderer.viewport_size();
+    let layout = engine.get_layout_mut();
+    if layout.viewport != viewport || layout.get(root_id).is_none() {
+        layout.viewport = viewport;
+        layout.mark_dirty(root_id);
+    }
+    if !layout.has_dirty_nodes() {
+        return;
+    }
+    let dirty = std::mem::take(&mut layout.dirty);
+
+    // every node below a dirty node is recomputed, as well as the nodes anchored to them
+    let mut scheduled = HashSet::new();
+    let mut roots = Vec::new();
+    let mut pending: Vec<NodeId> = dirty.into_iter().collect();
+    while let Some(node_id) = pending.pop() {
+        // only the nodes just scheduled can bring new dependents
+        for scheduled_id in schedule_subtree(engine, node_id, &mut scheduled, &mut roots) {
+            if let Some(dependents) = engine.get_layout().dependents.get(&scheduled_id) {
+                pending.extend(dependents.iter().filter(|dependent| !scheduled.contains(dependent)));
+            }
+        }
+    }
+
+    let mut pass = LayoutPass {
+        engine,
+        renderer,
+        root_id,
+        viewport: Rect::new(0.0, 0.0, viewport.0, viewport.1),
+        scheduled,
+        computed: HashMap::new(),
+        order: Vec::new(),
+        in_progress: HashSet::new(),
+        dependencies: Vec::new(),
+    };
+    let mut stack = roots;
+    while let Some(node_id) = stack.pop() {
+        pass.rect_of(node_id);
+        stack.extend(engine.get_arena().get_childrens_ids(node_id));
+    }
+    let LayoutPass { computed, order, dependencies, scheduled, .. } = pass;
+
+    let layout = engine.get_layout_mut();
+    for dependents in layout.dependents.values_mut() {
+        dependents.retain(|dependent| !scheduled.contains(dependent));
+    }
+    for (target, dependent) in dependencies {
+        layout.dependents.entry(target).or_default().insert(dependent);
+    }
+
+    for node_id in order {
+        let ComputedNode { rect, implicit_size } = computed[&node_id];
+        let previous = engine.get_layout_mut().rects.insert(node_id, rect);
+        if node_id == root_id {
+            // the root geometry is written back in its own geometry properties
+            engine.set_property_of_node_id(node_id, "x", AbstractValue::Number(rect.x));
+            engine.set_property_of_node_id(node_id, "y", AbstractValue::Number(rect.y));
+            engine.set_property_of_node_id(node_id, "width", AbstractValue::Number(rect.width));
+            engine.set_property_of_node_id(node_id, "height", AbstractValue::Number(rect.height));
+            // writing them back must not schedule another pass
+            engine.get_layout_mut().dirty.remove(&root_id);
+        }
+        if previous != Some(rect) {
+            engine.set_property_of_node_id(node_id, "computed_x", AbstractValue::Number(rect.x));
+            engine.set_property_of_node_id(node_id, "computed_y", AbstractValue::Number(rect.y));
+            engine.set_property_of_node_id(node_id, "computed_width", AbstractValue::Number(rect.width));
+            engine.set_property_of_node_id(node_id, "computed_height", AbstractValue::Number(rect.height));
+        }
+        if let Some((width, height)) = implicit_size {
+            let previous = engine.get_layout_mut().implicit_sizes.insert(node_id, (width, height));
+            if previous != Some((width, height)) {
+                engine.set_property_of_node_id(node_id, "implicit_width", width.into());
+                engine.set_property_of_node_id(node_id, "implicit_height", height.into());
+            }
+        }
+    }
+
+    // a ListView that changed height shows another number of delegates, they are laid out right away
+    if list_view::update_resized(engine) {
+        update_layout(engine, renderer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_node, sample_engine};
+    use crate::RecordingRenderer;
+    use macroquad::prelude::RED;
+
+    #[test]
+    fn test_layout_fills_the_table() {
+        let mut engine = sample_engine();
+        engine.update_layout(&RecordingRenderer::new(200.0, 100.0));
+
+        let panel = engine.get_node_id("panel").unwrap();
+        let label = engine.get_node_id("label").unwrap();
+        assert_eq!(engine.get_layout().get(panel), Some(Rect::new(10.0, 10.0, 180.0, 80.0)));
+        assert_eq!(engine.get_layout().get(label), Some(Rect::new(75.0, 40.0, 50.0, 20.0)));
+        assert!(!engine.get_layout().has_dirty_nodes());
+        // bindings on the computed geometry keep working
+        assert_eq!(engine.get_number_property_of_node("label", "computed_x", 0.0), 75.0);
+    }
+
+    #[test]
+    fn test_only_layout_properties_mark_nodes_dirty() {
+        let mut engine = sample_engine();
+        engine.update_layout(&RecordingRenderer::new(200.0, 100.0));
+
+        engine.set_property_of_node("panel", "color", AbstractValue::Color(RED));
+        assert!(!engine.get_layout().has_dirty_nodes());
+
+        engine.set_property_of_node("panel", "margins", AbstractValue::Number(20.0));
+        let panel = engine.get_node_id("panel").unwrap();
+        assert!(engine.get_layout().is_dirty(panel));
+    }
+
+    #[test]
+    fn test_dirty_subtree_is_recomputed() {
+        let mut engine = sample_engine();
+        let renderer = RecordingRenderer::new(200.0, 100.0);
+        engine.update_layout(&renderer);
+
+        engine.set_property_of_node("label", "text", AbstractValue::String("hi".into()));
+        engine.update_layout(&renderer);
+
+        let label = engine.get_node_id("label").unwrap();
+        assert_eq!(engine.get_layout().get(label), Some(Rect::new(90.0, 40.0, 20.0, 20.0)));
+
+        engine.set_property_of_node("panel", "margins", AbstractValue::Number(0.0));
+        engine.update_layout(&renderer);
+        // the child follows its parent
+        assert_eq!(engine.get_layout().get(label), Some(Rect::new(90.0, 40.0, 20.0, 20.0)));
+        let panel = engine.get_node_id("panel").unwrap();
+        assert_eq!(engine.get_layout().get(panel), Some(Rect::new(0.0, 0.0, 200.0, 100.0)));
+    }
+
+    fn positioner_engine(node_type: ItemTypeEnum, properties: &[(&str, AbstractValue)]) -> RmlEngine {
+        let mut engine = RmlEngine::new();
+        add_node(&mut engine, None, "root", ItemTypeEnum::Node, &[("anchors", AbstractValue::String("fill".into()))]);
+        add_node(&mut engine, Some("root"), "positioner", node_type, properties);
+        engine
+    }
+
+    fn rect_of(engine: &RmlEngine, id: &str) -> Rect {
+        engine.get_layout().get(engine.get_node_id(id).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_row_places_childs_side_by_side() {
+        let mut engine = positioner_engine(ItemTypeEnum::Row, &[
+            ("anchors", AbstractValue::String("fill".into())),
+            ("spacing", AbstractValue::Number(10.0)),
+            ("padding", AbstractValue::Number(5.0)),
+            ("alignment", AbstractValue::String("vertical_center".into())),
+        ]);
+        add_node(&mut engine, Some("positioner"), "a", ItemTypeEnum::Rectangle, &[
+            ("width", AbstractValue::Number(30.0)), ("height", AbstractValue::Number(20.0)),
+            // anchors are ignored inside a positioner
+            ("anchors", AbstractValue::String("right".into())),
+        ]);
+        add_node(&mut engine, Some("positioner"), "b", ItemTypeEnum::Rectangle, &[
+            ("height", AbstractValue::Number(10.0)),
+            ("layout_fill_width", AbstractValue::Bool(true)),
+        ]);
+        add_node(&mut engine, Some("positioner"), "c", ItemTypeEnum::Rectangle, &[
+            ("layout_preferred_width", AbstractValue::Number(40.0)),
+            ("layout_fill_height", AbstractValue::Bool(true)),
+            ("layout_alignment", AbstractValue::String("top".into())),
+        ]);
+        engine.update_layout(&RecordingRenderer::new(200.0, 100.0));
+
+        assert_eq!(rect_of(&engine, "a"), Rect::new(5.0, 40.0, 30.0, 20.0));
+        // 190 inner width - 30 - 40 - 2 * 10 of spacing
+        assert_eq!(rect_of(&engine, "b"), Rect::new(45.0, 45.0, 100.0, 10.0));
+        assert_eq!(rect_of(&engine, "c"), Rect::new(155.0, 5.0, 40.0, 90.0));
+    }
+
+    #[test]
+    fn test_column_takes_the_size_of_its_content() {
+        let mut engine = positioner_engine(ItemTypeEnum::Column, &[
+            ("anchors", AbstractValue::String("center".into())),
+            ("spacing", AbstractValue::Number(4.0)),
+            ("alignment", AbstractValue::String("horizontal_center".into())),
+        ]);
+        add_node(&mut engine, Some("positioner"), "title", ItemTypeEnum::Text, &[
+            ("text", AbstractValue::String("title".into())),
+            ("font_size", AbstractValue::Number(20.0)),
+        ]);
+        add_node(&mut engine, Some("positioner"), "body", ItemTypeEnum::Rectangle, &[
+            ("width", AbstractValue::Number(80.0)), ("height", AbstractValue::Number(30.0)),
+        ]);
+        let renderer = RecordingRenderer::new(200.0, 100.0);
+        engine.update_layout(&renderer);
+
+        assert_eq!(rect_of(&engine, "positioner"), Rect::new(60.0, 23.0, 80.0, 54.0));
+        assert_eq!(rect_of(&engine, "title"), Rect::new(75.0, 23.0, 50.0, 20.0));
+        assert_eq!(rect_of(&engine, "body"), Rect::new(60.0, 47.0, 80.0, 30.0));
+
+        // a longer text grows the column and moves the next items
+        engine.set_property_of_node("title", "text", AbstractValue::String("a longer title".into()));
+        let positioner = engine.get_node_id("positioner").unwrap();
+        assert!(engine.get_layout().is_dirty(positioner));
+        engine.update_layout(&renderer);
+        assert_eq!(rect_of(&engine, "positioner"), Rect::new(30.0, 23.0, 140.0, 54.0));
+        assert_eq!(rect_of(&engine, "title"), Rect::new(30.0, 23.0, 140.0, 20.0));
+        assert_eq!(rect_of(&engine, "body"), Rect::new(60.0, 47.0, 80.0, 30.0));
+    }
+
+    #[test]
+    fn test_grid_aligns_cells_on_rows_and_columns() {
+        let mut engine = positioner_engine(ItemTypeEnum::Grid, &[
+            ("columns", AbstractValue::Number(2.0)),
+            ("spacing", AbstractValue::Number(10.0)),
+        ]);
+        for (id, width, height) in [("a", 20.0, 10.0), ("b", 40.0, 10.0), ("c", 30.0, 25.0)] {
+            add_node(&mut engine, Some("positioner"), id, ItemTypeEnum::Rectangle, &[
+                ("width", AbstractValue::Number(width)), ("height", AbstractValue::Number(height)),
+            ]);
+        }
+        add_node(&mut engine, Some("positioner"), "d", ItemTypeEnum::Rectangle, &[
+            ("height", AbstractValue::Number(5.0)),
+            ("layout_fill_width", AbstractValue::Bool(true)),
+            ("layout_alignment", AbstractValue::String("bottom".into())),
+        ]);
+        engine.update_layout(&RecordingRenderer::new(200.0, 100.0));
+
+        assert_eq!(rect_of(&engine, "positioner"), Rect::new(0.0, 0.0, 80.0, 45.0));
+        assert_eq!(rect_of(&engine, "a"), Rect::new(0.0, 0.0, 20.0, 10.0));
+        assert_eq!(rect_of(&engine, "b"), Rect::new(40.0, 0.0, 40.0, 10.0));
+        assert_eq!(rect_of(&engine, "c"), Rect::new(0.0, 20.0, 30.0, 25.0));
+        assert_eq!(rect_of(&engine, "d"), Rect::new(40.0, 40.0, 40.0, 5.0));
+    }
+
+    #[test]
+    fn test_hidden_childs_take_no_space() {
+        let mut engine = positioner_engine(ItemTypeEnum::Row, &[]);
+        for id in ["a", "b"] {
+            add_node(&mut engine, Some("positioner"), id, ItemTypeEnum::Rectangle, &[
+                ("width", AbstractValue::Number(10.0)), ("height", AbstractValue::Number(10.0)),
+                ("visible", AbstractValue::Bool(true)),
+            ]);
+        }
+        let renderer = RecordingRenderer::new(200.0, 100.0);
+        engine.update_layout(&renderer);
+        assert_eq!(rect_of(&engine, "b").x, 10.0);
+
+        engine.set_property_of_node("a", "visible", AbstractValue::Bool(false));
+        engine.update_layout(&renderer);
+        assert_eq!(rect_of(&engine, "b").x, 0.0);
+        assert_eq!(rect_of(&engine, "positioner").width, 10.0);
+    }
+
+    fn anchored_engine() -> RmlEngine {
+        let mut engine = RmlEngine::new();
+        add_node(&mut engine, None, "root", ItemTypeEnum::Node, &[("anchors", AbstractValue::String("fill".into()))]);
+        // declared before the node it is anchored to
+        add_node(&mut engine, Some("root"), "body", ItemTypeEnum::Rectangle, &[
+            ("anchors_top", AbstractValue::String("header.bottom".into())),
+            ("anchors_bottom", AbstractValue::String("parent.bottom".into())),
+            ("anchors_left", AbstractValue::String("parent.left".into())),
+            ("anchors_right", AbstractValue::String("parent.right".into())),
+            ("top_margin", AbstractValue::Number(5.0)),
+        ]);
+        add_node(&mut engine, Some("root"), "header", ItemTypeEnum::Rectangle, &[
+            ("anchors", AbstractValue::String("top__left__right".into())),
+            ("height", AbstractValue::Number(30.0)),
+        ]);
+        engine
+    }
+
+    #[test]
+    fn test_anchor_to_sibling() {
+        let mut engine = anchored_engine();
+        let renderer = RecordingRenderer::new(200.0, 100.0);
+        engine.update_layout(&renderer);
+        let body = engine.get_node_id("body").unwrap();
+        assert_eq!(engine.get_layout().get(body), Some(Rect::new(0.0, 35.0, 200.0, 65.0)));
+
+        // the anchored node follows its target
+        engine.set_property_of_node("header", "height", AbstractValue::Number(50.0));
+        engine.update_layout(&renderer);
+        assert_eq!(engine.get_layout().get(body), Some(Rect::new(0.0, 55.0, 200.0, 45.0)));
+    }
+
+    #[test]
+    fn test_anchor_to_any_node() {
+        let mut engine = anchored_engine();
+        add_node(&mut engine, Some("body"), "badge", ItemTypeEnum::Rectangle, &[
+            ("width", AbstractValue::Number(20.0)),
+            ("height", AbstractValue::Number(10.0)),
+            ("anchors_right", AbstractValue::String("header.right".into())),
+            ("anchors_vertical_center", AbstractValue::String("header.vertical_center".into())),
+            ("right_margin", AbstractValue::Number(4.0)),
+            ("vertical_center_offset", AbstractValue::Number(2.0)),
+        ]);
+        add_node(&mut engine, Some("header"), "overlay", ItemTypeEnum::Rectangle, &[
+            ("anchors_fill", AbstractValue::String("body".into())),
+            ("margins", AbstractValue::Number(10.0)),
+        ]);
+        engine.update_layout(&RecordingRenderer::new(200.0, 100.0));
+
+        let badge = engine.get_node_id("badge").unwrap();
+        let overlay = engine.get_node_id("overlay").unwrap();
+        assert_eq!(engine.get_layout().get(badge), Some(Rect::new(176.0, 12.0, 20.0, 10.0)));
+        assert_eq!(engine.get_layout().get(overlay), Some(Rect::new(10.0, 45.0, 180.0, 45.0)));
+    }
+
+    #[test]
+    fn test_anchor_cycle_is_ignored() {
+        let mut engine = anchored_engine();
+        engine.set_property_of_node("header", "anchors", AbstractValue::String("left__right".into()));
+        let header = engine.get_node_id("header").unwrap();
+        let bottom = engine.add_property(crate::Property::new(AbstractValue::String("body.top".into())));
+        engine.add_property_to_node(header, "anchors_bottom".to_string(), bottom);
+
+        // terminates, each node keeps a rect
+        engine.update_layout(&RecordingRenderer::new(200.0, 100.0));
+        let body = engine.get_node_id("body").unwrap();
+        assert!(engine.get_layout().get(body).is_some());
+        assert!(engine.get_layout().get(header).is_some());
+    }
+}
@@ -3,14 +3,48 @@ pub mod properties;
 pub mod arena;
 pub mod draw;
 pub mod events;
+pub mod renderer;
+pub mod layout;
+pub mod loader;
+pub mod hot_reload;
+pub mod instances;
+pub mod repeater;
+pub mod list_view;
+pub mod animation;
+pub mod states;
+pub mod clock;
+pub mod timer;
+pub mod input;
+pub mod test_driver;
+pub mod text_input;
+pub mod text_layout;
+pub mod focus;
+pub mod propagation;
+pub mod drag;
+pub mod grab;
+pub mod click;
+#[cfg(test)]
+mod test_utils;
 
 use arena::ArenaNodeId;
 pub use arena::{ArenaNode, ArenaTree, NodeId, PropertyMap, PropertyName, ItemTypeEnum};
-pub use properties::{AbstractValue, Property};
-pub use events::{SystemEvent, EventType, EventManager};
+pub use properties::{AbstractValue, Property, PropertyHandle, PropertyValue};
+pub use events::{Event, EventManager, EventPhase, EventType, SystemEvent};
+pub use renderer::{Renderer, MacroquadRenderer, RecordingRenderer, DrawCommand, TextStyle};
+pub use layout::{LayoutTree, Rect};
+pub use loader::{load_rml, load_rml_file, LoadError, RmlLoader};
+pub use hot_reload::HotReload;
+pub use instances::Component;
+pub use list_view::PositionMode;
+pub use animation::{AnimationSpec, Easing, Loops};
+pub use states::{State, Transition};
+pub use clock::{Clock, FrameClock, ManualClock};
+pub use input::{Clipboard, InputSource, InputState, MacroquadInput, MemoryClipboard, ScriptedInput, SystemClipboard};
+pub use test_driver::TestDriver;
+pub use text_layout::TextLayout;
 
 use std::{collections::{HashMap}, sync::{Arc}};
-use macroquad::{color::Color};
+use macroquad::{color::Color, input::MouseButton};
 
 pub type CallbackId = usize;
 pub type PropertyId = usize;
@@ -131,12 +165,25 @@ macro_rules! get_mouse_event_pos {
         match &$engine.current_event {
             Some(SystemEvent::MouseDown { x, y, .. }) |
             Some(SystemEvent::MouseUp { x, y, .. }) |
-            Some(SystemEvent::Click { x, y, .. }) => (*x, *y),
+            Some(SystemEvent::Click { x, y, .. }) |
+            Some(SystemEvent::DoubleClick { x, y, .. }) |
+            Some(SystemEvent::PressAndHold { x, y, .. }) => (*x, *y),
             _ => $engine.get_mouse_position()
         }
     }};
 }
 
+#[macro_export]
+macro_rules! get_click_count {
+    ($engine:expr) => {{
+        match &$engine.current_event {
+            Some(SystemEvent::Click { click_count, .. }) => *click_count,
+            Some(SystemEvent::DoubleClick { .. }) => 2,
+            _ => 0
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! get_key_event {
     ($engine:expr) => {{
@@ -149,6 +196,16 @@ macro_rules! get_key_event {
     }};
 }
 
+#[macro_export]
+macro_rules! get_char_event {
+    ($engine:expr) => {{
+        match &$engine.current_event {
+            Some(SystemEvent::CharInput { character, .. }) => Some(*character),
+            _ => None
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! emit {
     ($engine:expr, $node:ident, $signal:ident) => {{
@@ -222,10 +279,30 @@ pub struct RmlEngine {
     callbacks: HashMap<CallbackId, Callback>,
     bindings: HashMap<PropertyId, Vec<CallbackId>>,
     callbacks_to_eval: Vec<CallbackId>,
+    next_property_id: PropertyId,
+    next_callback_id: CallbackId,
+    scopes: instances::Scopes,
+    repeaters: repeater::Repeaters,
+    list_views: list_view::ListViews,
+    animations: animation::Animations,
+    states: states::States,
+    timers: timer::Timers,
+    clock: Box<dyn Clock>,
+    last_time: Option<f64>,
+    input: Box<dyn InputSource>,
+    clipboard: Box<dyn Clipboard>,
+    text_inputs: text_input::TextInputs,
+    drags: drag::Drags,
+    grab: grab::Grab,
+    clicks: click::Clicks,
     
     event_manager: EventManager,
+    layout: LayoutTree,
+    hot_reload: HotReload,
     pub current_event: Option<SystemEvent>,
     pub current_event_consumed: bool,
+    /// the event being delivered, with its propagation state
+    event: Option<Event>,
 
     fonts: HashMap<String, macroquad::prelude::Font>,
     textures: HashMap<String, macroquad::prelude::Texture2D>,
@@ -239,9 +316,28 @@ impl RmlEngine {
             callbacks: HashMap::new(),
             bindings: HashMap::new(),
             callbacks_to_eval: Vec::new(),
+            next_property_id: 0,
+            next_callback_id: 0,
+            scopes: instances::Scopes::default(),
+            repeaters: repeater::Repeaters::default(),
+            list_views: list_view::ListViews::default(),
+            animations: animation::Animations::default(),
+            states: states::States::default(),
+            timers: timer::Timers::default(),
+            clock: Box::new(FrameClock),
+            last_time: None,
+            input: Box::new(MacroquadInput),
+            clipboard: Box::new(SystemClipboard),
+            text_inputs: text_input::TextInputs::default(),
+            drags: drag::Drags::default(),
+            grab: grab::Grab::default(),
+            clicks: click::Clicks::default(),
             event_manager: EventManager::new(),
+            layout: LayoutTree::new(),
+            hot_reload: HotReload::default(),
             current_event: None,
             current_event_consumed: false,
+            event: None,
             fonts: HashMap::new(),
             textures: HashMap::new(),
         }
@@ -256,12 +352,41 @@ impl RmlEngine {
     }
 
     // arena tree methods
+    /// Add a node to the arena. While a component is instantiated, `id` is only visible inside the instance.
     pub fn add_node(&mut self, id: String, node_type: ItemTypeEnum, properties: PropertyMap) -> Option<NodeId> {
+        let id = match self.scopes.current {
+            Some(scope) => instances::Scopes::scoped_name(&id, scope),
+            None => id,
+        };
         self.arena.add_node(node_type, id, properties)
     }
 
     pub fn add_child(&mut self, parent_id: NodeId, child_id: NodeId) {
         self.arena.add_child(parent_id, child_id);
+        self.mark_layout_dirty(child_id);
+        // the instances of a Repeater go in its parent
+        if repeater::is_repeater(self, child_id) {
+            repeater::update(self, child_id);
+        }
+        if self.arena.get_node(child_id).is_some_and(|node| animation::is_animation_node(&node.node_type)) {
+            animation::attach_node(self, child_id);
+        }
+        if self.arena.get_node(child_id).is_some_and(|node| timer::is_timer_node(&node.node_type)) {
+            timer::attach_node(self, child_id);
+        }
+        if text_input::is_editable(self, child_id) {
+            text_input::attach_node(self, child_id);
+        }
+        if self.get_property_by_name(child_id, "focusable").is_some() {
+            focus::attach_node(self, child_id);
+        }
+        grab::attach_node(self, child_id);
+        if drag::is_drop_area(self, child_id) {
+            drag::attach_drop_area(self, child_id);
+        }
+        if self.get_property_by_name(child_id, "drag_target").is_some() {
+            drag::attach_mouse_area(self, child_id);
+        }
     }
 
     pub fn get_root_node_id(&self) -> Option<NodeId> {
@@ -279,11 +404,12 @@ impl RmlEngine {
     }
 
     pub fn get_node_by_id(&self, id: &str) -> Option<&ArenaNode> {
-        self.arena.get_node_by_id(id)
+        self.get_node_id(id).and_then(|node_id| self.arena.get_node(node_id))
     }
 
     pub fn get_node_mut_by_id(&mut self, id: &str) -> Option<&mut ArenaNode> {
-        self.arena.get_node_mut_by_id(id)
+        let node_id = self.get_node_id(id)?;
+        self.arena.get_node_mut(node_id)
     }
 
     pub fn get_node(&self, node_id: NodeId) -> Option<&ArenaNode> {
@@ -299,16 +425,11 @@ impl RmlEngine {
     }
 
     pub fn get_children_by_id(&self, node_id_str: &str) -> Option<Vec<&ArenaNode>> {
-        self.arena.get_children_by_id(node_id_str)
+        self.get_node_id(node_id_str).map(|node_id| self.arena.get_children(node_id))
     }
 
     pub fn get_childrens_ids(&self, node_id_str: &str) -> Vec<NodeId> {
-        if let Some(node_id) = self.arena.id_to_node_id.get(node_id_str) {
-            return self.arena.get_childrens_ids(*node_id);
-        }
-        else {
-            Vec::new()
-        }
+        self.get_node_id(node_id_str).map_or_else(Vec::new, |node_id| self.arena.get_childrens_ids(node_id))
     }
 
     pub fn get_children_str_ids(&self, node_id: NodeId) -> Vec<ArenaNodeId> {
@@ -316,19 +437,11 @@ impl RmlEngine {
     }
 
     pub fn get_children_str_ids_by_id(&self, node_id_str: &str) -> Option<Vec<ArenaNodeId>> {
-        if let Some(node_id) = self.arena.id_to_node_id.get(node_id_str) {
-            Some(self.arena.get_childrens_ids_str(*node_id))
-        } else {
-            None
-        }
+        self.get_node_id(node_id_str).map(|node_id| self.arena.get_childrens_ids_str(node_id))
     }
 
     pub fn get_childrens_id(&self, node_id_str: &str) -> Option<Vec<NodeId>> {
-        if let Some(node_id) = self.arena.id_to_node_id.get(node_id_str) {
-            Some(self.arena.get_childrens_ids(*node_id))
-        } else {
-            None
-        }
+        self.get_node_id(node_id_str).map(|node_id| self.arena.get_childrens_ids(node_id))
     }
 
     pub fn get_parent_by_id(&self, node_id_str: &str) -> Option<&ArenaNode> {
@@ -340,18 +453,132 @@ impl RmlEngine {
     }
 
     pub fn get_parent_id(&self, node_id_str: &str) -> Option<NodeId> {
-        if let Some(node_id) = self.arena.id_to_node_id.get(node_id_str) {
-            self.arena.get_node(*node_id).and_then(|node| node.parent)
+        if let Some(node_id) = self.get_node_id(node_id_str) {
+            self.arena.get_node(node_id).and_then(|node| node.parent)
         } else {
             None
         }
     }
     
+    /// Animate the property `property_name` of `node_id` from its current value to `to`.
+    /// Only numbers and colors are animated, return false for other values.
+    pub fn animate(&mut self, node_id: NodeId, property_name: &str, to: AbstractValue, spec: AnimationSpec) -> bool {
+        animation::start(self, node_id, property_name, None, to, spec, None)
+    }
+
+    /// Animate the changes of the property `property_name` of `node_id` instead of applying them at once,
+    /// like a `Behavior on <property>` declaration
+    pub fn add_behavior(&mut self, node_id: NodeId, property_name: &str, spec: AnimationSpec) {
+        animation::add_behavior(self, node_id, property_name, spec);
+    }
+
+    /// True while an animation moves the property `property_name` of `node_id`
+    pub fn is_animating(&self, node_id: NodeId, property_name: &str) -> bool {
+        animation::is_running(self, node_id, property_name)
+    }
+
+    /// Advance the frame clock by `dt` seconds: the animations, the flicked ListViews and the timers move on
+    pub fn advance_time(&mut self, dt: f32) {
+        animation::tick(self, dt * 1000.0);
+        list_view::tick(self, dt);
+        timer::tick(self, dt * 1000.0);
+    }
+
+    /// Read the clock and advance the frame clock by the time elapsed since the last update.
+    /// `process_events` calls it once per frame.
+    pub fn update_time(&mut self) {
+        let now = self.clock.now();
+        let dt = now - self.last_time.unwrap_or(now);
+        self.last_time = Some(now);
+        if dt > 0.0 {
+            self.advance_time(dt as f32);
+        }
+    }
+
+    /// Replace the input polled by `process_events`, the macroquad input by default.
+    /// A `ScriptedInput` feeds simulated input to the engine in headless tests.
+    pub fn set_input_source(&mut self, input: impl InputSource + 'static) {
+        self.input = Box::new(input);
+    }
+
+    /// Replace the clipboard used by the TextInputs, the system one by default
+    pub fn set_clipboard(&mut self, clipboard: impl Clipboard + 'static) {
+        self.clipboard = Box::new(clipboard);
+    }
+
+    /// Replace the clock read by `update_time`, the macroquad clock by default.
+    /// A `ManualClock` makes the animations and the timers deterministic in headless tests.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+        self.last_time = None;
+    }
+
+    /// Add a state to `node_id`, entered when its `state` property is set to the name of the state
+    pub fn add_state(&mut self, node_id: NodeId, state: State) {
+        states::add_state(self, node_id, state);
+    }
+
+    /// Animate the switches between the states of `node_id` matching `transition`
+    pub fn add_transition(&mut self, node_id: NodeId, transition: Transition) {
+        states::add_transition(self, node_id, transition);
+    }
+
+    /// The active state of `node_id`, "" for its base state, `None` if it has no states
+    pub fn current_state(&self, node_id: NodeId) -> Option<&str> {
+        states::current_state(self, node_id)
+    }
+
+    /// Remove a node and its descendants, same as `destroy`
     pub fn remove_node(&mut self, node_id: NodeId) {
-        self.arena.remove_node(node_id);
+        self.destroy(node_id);
     }
+
+    /// Resolve a node name. In a component instance the nodes of the instance (and of the instances
+    /// containing it) shadow the nodes of the tree with the same name.
     pub fn get_node_id(&self, id: &str) -> Option<NodeId> {
-        self.arena.id_to_node_id.get(id).copied()
+        self.scopes.chain()
+            .find_map(|scope| self.arena.id_to_node_id.get(&instances::Scopes::scoped_name(id, scope)))
+            .or_else(|| self.arena.id_to_node_id.get(id))
+            .copied()
+    }
+
+    /// Build an instance of `component` under the node `parent`, with the given properties set on its root.
+    /// Return the root of the instance.
+    pub fn instantiate(&mut self, component: &Component, parent: NodeId, properties: &[(&str, AbstractValue)]) -> Option<NodeId> {
+        instances::instantiate(self, component, parent, properties)
+    }
+
+    /// Give its delegate to the Repeater `node_id`: `delegate` is instantiated for each entry of its `model`,
+    /// with an `index` and a `model_data` property, and the instances follow the changes of the model
+    pub fn set_repeater_delegate(&mut self, node_id: NodeId, delegate: Component) {
+        repeater::set_delegate(self, node_id, repeater::Delegate::Component(delegate));
+    }
+
+    /// Instances built by the Repeater `node_id`, in the order of its model
+    pub fn get_repeater_instances(&self, node_id: NodeId) -> Vec<NodeId> {
+        repeater::instances(self, node_id)
+    }
+
+    /// Give its delegate to the ListView `node_id`: `delegate` is instantiated for the visible entries of its `model`,
+    /// with an `index`, a `model_data` and an `is_current` property
+    pub fn set_list_view_delegate(&mut self, node_id: NodeId, delegate: Component) {
+        list_view::set_delegate(self, node_id, repeater::Delegate::Component(delegate));
+    }
+
+    /// Instances alive in the ListView `node_id`, the ones of its visible entries, in the order of its model
+    pub fn get_list_view_instances(&self, node_id: NodeId) -> Vec<NodeId> {
+        list_view::instances(self, node_id)
+    }
+
+    /// Scroll the ListView `node_id` to show the entry `index` of its model where `mode` asks
+    pub fn position_view_at_index(&mut self, node_id: NodeId, index: usize, mode: PositionMode) {
+        list_view::position_view_at_index(self, node_id, index, mode);
+    }
+
+    /// Remove a node and its descendants with their properties, bindings, event handlers and the callbacks
+    /// of the instances they contain. Return false if the node doesn't exist.
+    pub fn destroy(&mut self, node_id: NodeId) -> bool {
+        instances::destroy(self, node_id)
     }
     pub fn add_property_to_node(&mut self, node_id: NodeId, name: PropertyName, property_id: PropertyId) {
         if let Some(node) = self.arena.get_node_mut(node_id) {
@@ -366,10 +593,8 @@ impl RmlEngine {
         default_value: T,
         convert: impl Fn(&AbstractValue) -> Option<T>,
     ) -> T {
-        self.arena
-            .id_to_node_id
-            .get(node_name)
-            .and_then(|node_id| self.arena.get_node(*node_id))
+        self.get_node_id(node_name)
+            .and_then(|node_id| self.arena.get_node(node_id))
             .and_then(|node| node.get_property(property_name))
             .and_then(|prop_id| self.get_property(prop_id))
             .and_then(|property| convert(&property.value))
@@ -377,10 +602,8 @@ impl RmlEngine {
     }
 
     pub fn get_property_id_of_node(&self, node_name: &str, property_name: &str) -> Option<PropertyId> {
-        self.arena
-            .id_to_node_id
-            .get(node_name)
-            .and_then(|node_id| self.arena.get_node(*node_id))
+        self.get_node_id(node_name)
+            .and_then(|node_id| self.arena.get_node(node_id))
             .and_then(|node| node.get_property(property_name))
     }
 
@@ -400,31 +623,104 @@ impl RmlEngine {
         self.get_property_of_node(node_name, property_name, default_value, |v| v.to_color())
     }
 
+    pub fn get_number_property_of_node_id(&self, node_id: NodeId, property_name: &str, default_value: f32) -> f32 {
+        self.get_property_by_name(node_id, property_name)
+            .and_then(|property| property.value.to_number())
+            .unwrap_or(default_value)
+    }
+
+    pub fn get_string_property_of_node_id(&self, node_id: NodeId, property_name: &str, default_value: String) -> String {
+        self.get_property_by_name(node_id, property_name)
+            .map(|property| property.value.to_string())
+            .unwrap_or(default_value)
+    }
+
+    pub fn get_bool_property_of_node_id(&self, node_id: NodeId, property_name: &str, default_value: bool) -> bool {
+        self.get_property_by_name(node_id, property_name)
+            .and_then(|property| property.value.to_bool())
+            .unwrap_or(default_value)
+    }
+
+    pub fn get_color_property_of_node_id(&self, node_id: NodeId, property_name: &str, default_value: Color) -> Color {
+        self.get_property_by_name(node_id, property_name)
+            .and_then(|property| property.value.to_color())
+            .unwrap_or(default_value)
+    }
+
     pub fn set_property_of_node(&mut self, node_name: &str, property_name: &str, value: AbstractValue) -> bool {
-        if let Some(node_id) = self.arena.id_to_node_id.get(node_name) {
-            if let Some(node) = self.arena.get_node_mut(*node_id) {
-                if let Some(property_id) = node.get_property(property_name) {
-                    if let Some(property) = self.get_property_mut(property_id) {
-                        // test if value changed
-                        if property.get() == value { return false; }
-                        property.set(value);
-
-                        if let Some(callback_ids) = self.bindings.get(&property_id) {
-                            for &cb_id in callback_ids {
-                                self.callbacks_to_eval.push(cb_id);
-                            }
-                        }
+        match self.get_node_id(node_name) {
+            Some(node_id) => self.set_property_of_node_id(node_id, property_name, value),
+            None => false,
+        }
+    }
 
-                        true;
-                    }
-                }
+    /// Set a property of a node, queue the callbacks bound to it and mark the node dirty
+    /// in the layout if the property affects its geometry. Return true if the value changed.
+    pub fn set_property_of_node_id(&mut self, node_id: NodeId, property_name: &str, value: AbstractValue) -> bool {
+        let Some(property_id) = self.arena.get_node(node_id).and_then(|node| node.get_property(property_name)) else {
+            return false;
+        };
+        self.set_node_property(node_id, property_name, property_id, value)
+    }
+
+    /// `set_property_of_node_id` for a property already looked up on its node
+    pub(crate) fn set_node_property(&mut self, node_id: NodeId, property_name: &str, property_id: PropertyId, value: AbstractValue) -> bool {
+        // a property overridden by the active state of a node takes the value once the state is left
+        if states::set_base_value(self, property_id, &value) {
+            return false;
+        }
+        // a property with a Behavior moves to its new value over time
+        if let Some(spec) = self.animations.behavior(property_id) {
+            if animation::animate_change(self, node_id, property_name, &value, spec) {
+                return true;
             }
         }
-        false
+        self.write_node_property(node_id, property_name, property_id, value)
+    }
+
+    /// Set a property without going through its Behavior, the animations write their steps this way
+    pub(crate) fn write_property_of_node_id(&mut self, node_id: NodeId, property_name: &str, value: AbstractValue) -> bool {
+        let Some(property_id) = self.arena.get_node(node_id).and_then(|node| node.get_property(property_name)) else {
+            return false;
+        };
+        self.write_node_property(node_id, property_name, property_id, value)
+    }
+
+    fn write_node_property(&mut self, node_id: NodeId, property_name: &str, property_id: PropertyId, value: AbstractValue) -> bool {
+        let Some(property) = self.get_property_mut(property_id) else { return false };
+
+        // test if value changed
+        if property.get() == value { return false; }
+        property.set(value);
+
+        if let Some(callback_ids) = self.bindings.get(&property_id) {
+            for &cb_id in callback_ids {
+                self.callbacks_to_eval.push(cb_id);
+            }
+        }
+
+        if layout::is_layout_property(property_name) {
+            self.mark_layout_dirty(node_id);
+        }
+        true
+    }
+
+    /// Mark a node dirty in the layout, along with the positioners (Row, Column, Grid)
+    /// containing it since they place their childs according to their size
+    pub fn mark_layout_dirty(&mut self, node_id: NodeId) {
+        self.layout.mark_dirty(node_id);
+        let mut current = self.arena.get_node(node_id).and_then(|node| node.parent);
+        while let Some(parent_id) = current {
+            let Some(parent) = self.arena.get_node(parent_id) else { break };
+            if !layout::is_positioner(&parent.node_type) { break; }
+            self.layout.mark_dirty(parent_id);
+            current = parent.parent;
+        }
     }
 
     pub fn add_property(&mut self, property: Property) -> PropertyId {
-        let id = self.properties.len();
+        let id = self.next_property_id;
+        self.next_property_id += 1;
         self.properties.insert(id, property);
         id
     }
@@ -463,8 +759,10 @@ impl RmlEngine {
     where
         F: Fn(&mut RmlEngine) + Send + Sync + 'static,
     {
-        let id = self.callbacks.len();
+        let id = self.next_callback_id;
+        self.next_callback_id += 1;
         self.callbacks.insert(id, Arc::new(callback));
+        self.scopes.callback_created(id);
         id
     }
 
@@ -476,8 +774,8 @@ impl RmlEngine {
     }
 
     pub fn bind_node_property_to_callback(&mut self, node_name: &str, property_name: &str, callback_id: CallbackId) {
-        if let Some(node_id) = self.arena.id_to_node_id.get(node_name) {
-            if let Some(node) = self.arena.get_node(*node_id) {
+        if let Some(node_id) = self.get_node_id(node_name) {
+            if let Some(node) = self.arena.get_node(node_id) {
                 if let Some(prop_id) = node.get_property(property_name) {
                     self.bind_property_to_callback(prop_id, callback_id);
                 }
@@ -485,6 +783,30 @@ impl RmlEngine {
         }
     }
 
+    /// Run `callback` each time the property or signal `property_name` of the node `node_name` changes.
+    /// Returns `None` if the node or the property doesn't exist.
+    pub fn connect<F>(&mut self, node_name: &str, property_name: &str, callback: F) -> Option<CallbackId>
+    where
+        F: Fn(&mut RmlEngine) + Send + Sync + 'static,
+    {
+        let prop_id = self.get_property_id_of_node(node_name, property_name)?;
+        let callback_id = self.add_callback(callback);
+        self.bind_property_to_callback(prop_id, callback_id);
+        Some(callback_id)
+    }
+
+    /// Emit the signal `signal` of the node `node_name`, same as the `emit!` macro
+    pub fn emit(&mut self, node_name: &str, signal: &str) -> bool {
+        let current_value = self.get_bool_property_of_node(node_name, signal, false);
+        self.set_property_of_node(node_name, signal, AbstractValue::Bool(!current_value))
+    }
+
+    /// `emit` for a node known by its id, used by the built-in items emitting their signals
+    pub(crate) fn emit_node_signal(&mut self, node_id: NodeId, signal: &str) {
+        let current_value = self.get_bool_property_of_node_id(node_id, signal, false);
+        self.set_property_of_node_id(node_id, signal, AbstractValue::Bool(!current_value));
+    }
+
     pub fn run_callbacks(&mut self) {
         let to_eval = std::mem::take(&mut self.callbacks_to_eval);
 
@@ -494,13 +816,21 @@ impl RmlEngine {
             .filter_map(|cb_id| self.callbacks.get(&cb_id).cloned().map(|cb| (cb_id, cb)))
             .collect();
 
-        for (_cb_id, callback) in callbacks_to_run {
-            callback(self);
+        for (cb_id, callback) in callbacks_to_run {
+            self.call(cb_id, &callback);
         }
     }
 
+    /// Run a callback in the scope of the component instance that created it
+    fn call(&mut self, callback_id: CallbackId, callback: &Callback) {
+        let callback_scope = self.scopes.of_callback(callback_id);
+        let scope = std::mem::replace(&mut self.scopes.current, callback_scope);
+        callback(self);
+        self.scopes.current = scope;
+    }
+
     pub fn get_node_type(&self, node_id: &str) -> Option<ItemTypeEnum> {
-        self.arena.get_node_by_id(node_id).map(|node| node.node_type.clone())
+        self.get_node_by_id(node_id).map(|node| node.node_type.clone())
     }
 
     // Event system methods
@@ -510,6 +840,24 @@ impl RmlEngine {
         }
     }
     
+    /// Add a handler run when `event_type` goes down to a descendant of the node `node_id_str`, before the
+    /// handlers of the descendant (see `propagation`)
+    pub fn add_capture_event_handler(&mut self, event_type: EventType, node_id_str: &str, callback_id: CallbackId) {
+        if let Some(node_id) = self.get_node_id(node_id_str) {
+            self.event_manager.add_capture_event_handler(event_type, node_id, callback_id);
+        }
+    }
+
+    /// The event whose handler runs, with its target and its phase
+    pub fn event(&self) -> Option<&Event> {
+        self.event.as_ref()
+    }
+
+    /// The event whose handler runs, to accept it or stop its propagation
+    pub fn event_mut(&mut self) -> Option<&mut Event> {
+        self.event.as_mut()
+    }
+
     pub fn get_event_manager(&self) -> &EventManager {
         &self.event_manager
     }
@@ -520,110 +868,159 @@ impl RmlEngine {
     
     pub fn set_focused_node(&mut self, node_id_str: &str) {
         let node_id = self.get_node_id(node_id_str);
+        self.focus_node(node_id);
+    }
+
+    /// Give the keyboard focus to `node_id`: the `active_focus` property of the nodes having one follows it, the
+    /// node losing the focus gets a `focus_out` event, then the node taking it a `focus_in` event
+    pub(crate) fn focus_node(&mut self, node_id: Option<NodeId>) {
+        let previous = self.event_manager.get_focused_node();
+        if previous == node_id {
+            return;
+        }
         self.event_manager.set_focused_node(node_id);
+        if let Some(previous) = previous {
+            self.set_property_of_node_id(previous, "active_focus", AbstractValue::Bool(false));
+            self.handle_system_event(&SystemEvent::FocusOut { node_id: previous });
+        }
+        if let Some(node_id) = node_id {
+            self.set_property_of_node_id(node_id, "active_focus", AbstractValue::Bool(true));
+            self.handle_system_event(&SystemEvent::FocusIn { node_id });
+        }
     }
     
     pub fn process_events(&mut self) -> Vec<SystemEvent> {
-        // Update from macroquad input
-        let events = self.event_manager.update_from_macroquad();
+        // Reload the component files modified since the last frame (dev mode only)
+        hot_reload::poll(self);
+
+        // Update from the input source, macroquad unless replaced
+        let input = self.input.poll();
+        let events = self.event_manager.update(&input);
         let hovered_nodes = self.get_mouse_area_nodes_under_mouse();
         let mouse_area_nodes = self.get_mouse_area_nodes();
-        let focused_node = self.event_manager.get_focused_node();
+        // the handlers of the frame see where the mouse is
+        grab::hover(self, &mouse_area_nodes, &hovered_nodes);
         let mut current_hovered_nodes = Vec::new();
         self.current_event_consumed = false;
 
         // we will refine events before handling them
-        // Some events can be consumed, some affects hovered only, mousearea only or focused only, and some are globals
+        // The mouse events go to the nodes under the mouse, the key events to the focused node, both through their
+        // ancestors (see `propagation`), the window events to all their handlers
 
         // Handle mouse events
-        // check for enter event
+        // check for enter event, a node accepting it hides the nodes below
         for node in hovered_nodes.clone() {
             current_hovered_nodes.push(node);
-            if !self.event_manager.is_node_hovered(node) {
-                // If the node is not hovered, we trigger MouseEnter
-                self.handle_system_event(&SystemEvent::MouseEnter { node_id: node });
-            }
-            if let Some(consume) = self.get_property_by_name(node, "consume_mouse_enter") {
-                self.current_event_consumed = consume.get().to_bool().unwrap_or(false);
-                if self.current_event_consumed { break; }
-            }
+            let stopped = if self.event_manager.is_node_hovered(node) {
+                propagation::consumes(self, node, &EventType::MouseEnter)
+            } else {
+                let event = propagation::dispatch(self, SystemEvent::MouseEnter { node_id: node }, &mut propagation::Visited::new());
+                event.is_accepted() || event.is_propagation_stopped()
+            };
+            if stopped { break; }
         }
 
         for event in &events {
             match event {
                 // global events
                 SystemEvent::WindowResize { .. } |SystemEvent::WindowFocus { .. } | SystemEvent::WindowLostFocus { .. } => {
+                    // losing the focus, the window may miss the release of the mouse
+                    if matches!(event, SystemEvent::WindowLostFocus { .. }) {
+                        grab::cancel(self);
+                    }
                     self.handle_system_event(event);
                 }
 
-                // key events
+                // key events, to the node focused when they are handled (a Tab or a press may have moved the focus),
+                // the root without one. An accepted key is not handled by the focused TextInput or the focus chain.
                 SystemEvent::KeyUp { node_id: _, key } => {
-                    if let Some(node_id) = focused_node {
-                        self.handle_system_event(&SystemEvent::KeyUp { node_id: node_id, key: *key });
+                    if let Some(node_id) = self.event_manager.get_focused_node().or(self.get_root_node_id()) {
+                        self.handle_system_event(&SystemEvent::KeyUp { node_id, key: *key });
                     }
                 }
                 SystemEvent::KeyDown { node_id: _, key } => {
-                    if let Some(node_id) = focused_node {
-                        self.handle_system_event(&SystemEvent::KeyDown { node_id: node_id, key: *key });
+                    if let Some(node_id) = self.event_manager.get_focused_node().or(self.get_root_node_id()) {
+                        self.handle_system_event(&SystemEvent::KeyDown { node_id, key: *key });
                     }
-                    
                 }
                 SystemEvent::KeyPressed { node_id: _, key } => {
-                    if let Some(node_id) = focused_node {
-                        self.handle_system_event(&SystemEvent::KeyPressed { node_id: node_id, key: *key });
+                    let focused_node = self.event_manager.get_focused_node();
+                    let Some(target) = focused_node.or(self.get_root_node_id()) else { continue };
+                    let event = propagation::dispatch(self, SystemEvent::KeyPressed { node_id: target, key: *key }, &mut propagation::Visited::new());
+                    if !event.is_accepted() {
+                        if let Some(node_id) = focused_node {
+                            text_input::key_pressed(self, node_id, *key);
+                        }
+                        focus::key_pressed(self, focused_node, *key);
                     }
                 }
-
-                // mouse events
-                SystemEvent::Click { node_id: _, x, y, button } => {
-                    for node in &hovered_nodes {
-                        self.handle_system_event(&SystemEvent::Click { node_id: *node, x: *x, y: *y, button: *button });
-                        if let Some(consume) = self.get_property_by_name(*node, "consume_mouse_click") {
-                            self.current_event_consumed = consume.get().to_bool().unwrap_or(false);
-                            if self.current_event_consumed { break; }
-                        }
+                SystemEvent::CharInput { node_id: _, character } => {
+                    let focused_node = self.event_manager.get_focused_node();
+                    let Some(target) = focused_node.or(self.get_root_node_id()) else { continue };
+                    let event = propagation::dispatch(self, SystemEvent::CharInput { node_id: target, character: *character }, &mut propagation::Visited::new());
+                    if let Some(node_id) = focused_node.filter(|_| !event.is_accepted()) {
+                        text_input::char_input(self, node_id, *character);
                     }
                 }
+
+                // mouse events, an accepted press or wheel move is not handled by the lists and the text inputs
                 SystemEvent::MouseDown { node_id: _, x, y, button } => {
-                    for node in &hovered_nodes {
-                        self.handle_system_event(&SystemEvent::MouseDown { node_id: *node, x: *x, y: *y, button: *button });
-                        if let Some(consume) = self.get_property_by_name(*node, "consume_mouse_down") {
-                            self.current_event_consumed = consume.get().to_bool().unwrap_or(false);
-                            if self.current_event_consumed { break; }
+                    let (accepted, reached) = propagation::dispatch_to_targets_reached(self, &hovered_nodes, |node_id| SystemEvent::MouseDown { node_id, x: *x, y: *y, button: *button });
+                    click::press(self, *button, *x, *y, reached.clone());
+                    grab::press(self, *button, reached);
+                    if *button == MouseButton::Left && !accepted {
+                        // a MouseArea dragging an item holds the press, its list does not flick
+                        if !drag::press(self, *x, *y, &hovered_nodes) {
+                            list_view::press(self, *x, *y);
+                        }
+                        if !text_input::press(self, *x, *y) {
+                            focus::press(self, &hovered_nodes);
                         }
                     }
                 }
                 SystemEvent::MouseUp { node_id: _, x, y, button } => {
-                    for node in &hovered_nodes {
-                        self.handle_system_event(&SystemEvent::MouseUp { node_id: *node, x: *x, y: *y, button: *button });
-                        if let Some(consume) = self.get_property_by_name(*node, "consume_mouse_up") {
-                            self.current_event_consumed = consume.get().to_bool().unwrap_or(false);
-                            if self.current_event_consumed { break; }
-                        }
+                    // a release ending a drag makes no click
+                    let mut dropped = false;
+                    if *button == MouseButton::Left {
+                        list_view::release(self);
+                        text_input::release(self);
+                        dropped = drag::release(self, *x, *y);
                     }
+                    // the nodes grabbing the mouse get the release wherever it is
+                    let targets = grab::grabbing(self, *button).unwrap_or_else(|| hovered_nodes.clone());
+                    grab::release(self, *button);
+                    propagation::dispatch_to_targets(self, &targets, |node_id| SystemEvent::MouseUp { node_id, x: *x, y: *y, button: *button });
+                    click::release(self, *button, *x, *y, &hovered_nodes, dropped);
                 }
                 SystemEvent::MouseMove { node_id: _, x, y, delta_x, delta_y } => {
-                    for node in &mouse_area_nodes {
-                        self.handle_system_event(&SystemEvent::MouseMove { node_id: *node, x: *x, y: *y, delta_x: *delta_x, delta_y: *delta_y });
-                        if let Some(consume) = self.get_property_by_name(*node, "consume_mouse_move") {
-                            self.current_event_consumed = consume.get().to_bool().unwrap_or(false);
-                            if self.current_event_consumed { break; }
-                        }
-                    }
+                    list_view::drag(self, *y);
+                    text_input::drag(self, *x, *y);
+                    drag::drag(self, *x, *y);
+                    // all the mouse areas follow the mouse, the ones grabbing it first
+                    let targets = grab::move_targets(self, &mouse_area_nodes);
+                    propagation::dispatch_to_targets(self, &targets, |node_id| SystemEvent::MouseMove { node_id, x: *x, y: *y, delta_x: *delta_x, delta_y: *delta_y });
                 }
                 SystemEvent::MouseWheel { node_id: _, delta_x, delta_y } => {
-                    for node in &mouse_area_nodes {
-                        self.handle_system_event(&SystemEvent::MouseWheel { node_id: *node, delta_x: *delta_x, delta_y: *delta_y });
-                        if let Some(consume) = self.get_property_by_name(*node, "consume_mouse_wheel") {
-                            self.current_event_consumed = consume.get().to_bool().unwrap_or(false);
-                            if self.current_event_consumed { break; }
-                        }
+                    let accepted = propagation::dispatch_to_targets(self, &hovered_nodes, |node_id| SystemEvent::MouseWheel { node_id, delta_x: *delta_x, delta_y: *delta_y });
+                    let (x, y) = self.get_mouse_position();
+                    // a TextArea in a ListView scrolls before the list
+                    if !accepted && !text_input::wheel(self, x, y, *delta_y) {
+                        list_view::wheel(self, x, y, *delta_y);
                     }
                 }
                 _ => {}
             }
         }
 
+        // a button held down long enough
+        click::tick(self, &hovered_nodes);
+
+        // the files dropped on the window go to the DropArea under the mouse
+        if !input.dropped_files.is_empty() {
+            let (x, y) = self.get_mouse_position();
+            drag::drop_files(self, x, y, &input.dropped_files);
+        }
+
         // Check for nodes that are no longer hovered
         let previously_hovered_nodes = self.event_manager.hovered_nodes.clone();
         self.current_event_consumed = false;
@@ -633,14 +1030,16 @@ impl RmlEngine {
                 // If the node was previously hovered but is no longer, trigger MouseLeave
                 self.handle_system_event(&SystemEvent::MouseLeave { node_id });
             }
-            if let Some(consume) = self.get_property_by_name(node_id, "consume_mouse_leave") {
-                self.current_event_consumed = consume.get().to_bool().unwrap_or(false);
-                if self.current_event_consumed { break; }
+            if propagation::consumes(self, node_id, &EventType::MouseLeave) {
+                break;
             }
         }
 
         // Update hovered nodes
         self.event_manager.hovered_nodes = current_hovered_nodes;
+
+        // the animations, the flicked lists and the timers move on
+        self.update_time();
         
         // Run any property change callbacks that might have been triggered by a property set
         self.run_callbacks();
@@ -648,47 +1047,24 @@ impl RmlEngine {
         events
     }
     
+    /// Deliver `event` to its node and the ancestors of its node (see `propagation`), or to all the handlers of a
+    /// window event
     fn handle_system_event(&mut self, event: &SystemEvent) {
-        let event_type = event.event_type();
-        let mut callbacks_with_event = Vec::new();
-        
-        match event {
-            // node related events
-            SystemEvent::MouseWheel { node_id, .. }
-            | SystemEvent::MouseMove { node_id, .. } 
-            | SystemEvent::MouseDown { node_id, .. }
-            | SystemEvent::MouseUp { node_id, .. }
-            | SystemEvent::Click { node_id, .. }
-            | SystemEvent::MouseEnter { node_id }
-            | SystemEvent::MouseLeave { node_id }
-            | SystemEvent::KeyDown { node_id, .. }
-            | SystemEvent::KeyUp { node_id, .. }
-            | SystemEvent::KeyPressed { node_id, .. } => {
-                let handlers = self.event_manager.get_handlers_for_node(*node_id, &event_type);
-                for handler in handlers {
-                    callbacks_with_event.push((handler.callback_id, event.clone()));
-                }
-            },
-            // globals events
-            SystemEvent::WindowResize { .. }
-            | SystemEvent::WindowFocus { .. }
-            | SystemEvent::WindowLostFocus { .. } => {
-                let handlers = self.event_manager.get_handlers_for_event(&event_type);
-                for handler in handlers {
-                    callbacks_with_event.push((handler.callback_id, event.clone()));
-                }
-            }
+        if event.node_id().is_some() {
+            propagation::dispatch(self, event.clone(), &mut propagation::Visited::new());
+            return;
         }
-
-        // Execute callbacks immediately with their specific event (at the difference of other events that are processed in process_events)
-        for (callback_id, event) in callbacks_with_event {
+        let event_type = event.event_type();
+        let callback_ids: Vec<CallbackId> = self.event_manager.get_handlers_for_event(&event_type).iter()
+            .map(|handler| handler.callback_id)
+            .collect();
+        for callback_id in callback_ids {
             if let Some(callback) = self.callbacks.get(&callback_id).cloned() {
                 // Set the specific event for this callback
-                self.current_event = Some(event);
-                callback(self);
+                self.current_event = Some(event.clone());
+                self.call(callback_id, &callback);
                 // Clear immediately after callback execution
                 self.current_event = None;
-                
             }
         }
     }
@@ -700,65 +1076,91 @@ impl RmlEngine {
     
 
     fn get_mouse_area_nodes_at_position(&self, x: f32, y: f32) -> Vec<NodeId> {
-        let mut nodes = Vec::new();
-        
-        // Only check MouseArea nodes for mouse events
-        for node in &self.arena.nodes {
-            if node.node_type == ItemTypeEnum::MouseArea {
-                if let Some(node_id) = self.arena.id_to_node_id.get(&node.id) {
-                    if self.is_point_inside_node(*node_id, x, y) {
-                        nodes.push(*node_id);
-                    }
-                }
-            }
-        }
-
-        nodes.reverse(); // Reverse to ensure topmost nodes are checked first
-        // like the nodes are created in a top-down manner, the last created node is the topmost one
-        // and any node before a node in the list is below it visually 
+        let mut nodes = self.get_mouse_area_nodes();
+        nodes.retain(|&node_id| self.is_point_inside_node(node_id, x, y));
         nodes
     }
 
     fn get_mouse_area_nodes(&self) -> Vec<NodeId> {
-        let mut nodes = Vec::new();
-        
-        // Only check MouseArea nodes for mouse events
-        for node in &self.arena.nodes {
-            if node.node_type == ItemTypeEnum::MouseArea {
-                if let Some(node_id) = self.arena.id_to_node_id.get(&node.id) {
-                    nodes.push(*node_id);
-                }
-            }
-        }
+        let Some(root) = self.arena.get_root_node_id() else { return Vec::new() };
+
+        // the mouse events target the MouseAreas, and the other nodes with a mouse handler
+        let mut nodes: Vec<NodeId> = self.arena.subtree(root).into_iter()
+            .filter(|&node_id| {
+                self.arena.get_node(node_id).is_some_and(|node| node.node_type == ItemTypeEnum::MouseArea)
+                    || self.event_manager.has_mouse_handler(node_id)
+            })
+            .collect();
 
         nodes.reverse(); // Reverse to ensure topmost nodes are checked first
-        // like the nodes are created in a top-down manner, the last created node is the topmost one
-        // and any node before a node in the list is below it visually 
+        // the nodes are listed in drawing order, the last one is the topmost one
+        // and any node before a node in the list is below it visually
         nodes
     }
     
     fn is_point_inside_node(&self, node_id: NodeId, x: f32, y: f32) -> bool {
-        // Use pre-computed absolute geometry from draw phase
-        if let Some(node) = self.arena.get_node(node_id) {
-            let computed_abs_x = self.get_number_property_of_node(&node.id, "computed_x", 0.0);
-            let computed_abs_y = self.get_number_property_of_node(&node.id, "computed_y", 0.0);
-            let computed_width = self.get_number_property_of_node(&node.id, "computed_width", 0.0);
-            let computed_height = self.get_number_property_of_node(&node.id, "computed_height", 0.0);
-            
-            // Now we can directly compare with mouse coordinates (both in window coordinates)
-            x >= computed_abs_x && x <= computed_abs_x + computed_width && 
-            y >= computed_abs_y && y <= computed_abs_y + computed_height
-        } else {
-            false
+        // Use the absolute geometry computed by the layout pass (window coordinates, like the mouse)
+        self.layout.get(node_id).is_some_and(|rect| rect.contains(x, y)) && self.is_point_clipped_in(node_id, x, y)
+    }
+
+    /// Whether a node restricts the drawing and the hit-testing of its descendants to its rect:
+    /// it has `clip: true`, or it is a ListView
+    pub(crate) fn clips_childs(&self, node_id: NodeId) -> bool {
+        list_view::is_list_view(self, node_id) || self.get_bool_property_of_node_id(node_id, "clip", false)
+    }
+
+    /// Whether the point is inside the rect of every ancestor of `node_id` clipping its childs
+    pub(crate) fn is_point_clipped_in(&self, node_id: NodeId, x: f32, y: f32) -> bool {
+        let mut current = self.arena.get_node(node_id).and_then(|node| node.parent);
+        while let Some(parent_id) = current {
+            if self.clips_childs(parent_id) && !self.layout.get(parent_id).is_some_and(|rect| rect.contains(x, y)) {
+                return false;
+            }
+            current = self.arena.get_node(parent_id).and_then(|node| node.parent);
         }
+        true
     }
 
     pub fn get_mouse_position(&self) -> (f32, f32) {
         self.event_manager.get_mouse_position()
     }
 
+    pub fn get_layout(&self) -> &LayoutTree {
+        &self.layout
+    }
+
+    pub fn get_layout_mut(&mut self) -> &mut LayoutTree {
+        &mut self.layout
+    }
+
+    /// Recompute the geometry of the nodes whose layout changed since the last call
+    pub fn update_layout(&mut self, renderer: &dyn Renderer) {
+        layout::update_layout(self, renderer);
+        text_input::measure(self, renderer);
+    }
+
+    /// Dev mode: reload the imported component files (`import "components"`) when they change on disk.
+    /// The files are checked in `process_events`.
+    pub fn enable_hot_reload(&mut self) {
+        hot_reload::enable(self);
+    }
+
+    pub fn get_hot_reload(&self) -> &HotReload {
+        &self.hot_reload
+    }
+
+    /// Record that the node `node_name` is an instance of the component file `path`, for the hot reload.
+    /// `overridden` are the properties set by the instance, `instance_children` the number of childs it adds.
+    pub fn watch_component(&mut self, node_name: &str, path: &str, overridden: &[&str], instance_children: usize) {
+        if let Some(node_id) = self.get_node_id(node_name) {
+            hot_reload::watch_component(self, node_id, std::path::Path::new(path), overridden, instance_children);
+        }
+    }
+
     pub fn add_font(&mut self, name: String, font: macroquad::text::Font) {
         self.fonts.insert(name, font);
+        // texts may now be measured with another font
+        self.layout.invalidate();
     }
 
     pub fn get_font(&self, name: &str) -> Option<&macroquad::text::Font> {
@@ -781,7 +1183,15 @@ pub mod prelude {
 
     pub use super::{
         RmlEngine,
+        Component,
+        PositionMode,
+        AnimationSpec,
+        Easing,
+        Loops,
+        State,
+        Transition,
         Property,
+        PropertyHandle,
         AbstractValue,
         get_value,
         get_bool,
@@ -800,7 +1210,11 @@ pub mod prelude {
         ItemTypeEnum,
         decompose_color_string,
         get_key_event,
+        get_click_count,
         SystemEvent,
-        NodeId
+        NodeId,
+        load_rml,
+        load_rml_file,
+        LoadError
     };
 }
\ No newline at end of file
@@ -0,0 +1,556 @@
+// ListView: a scrollable list that only instantiates its visible delegates
+//
+// Like for a Repeater, the child of a `ListView` is its delegate, instantiated with an `index` and a `model_data`
+// property for the entries of the `model` (an array, or a number of entries). The instances are childs of the
+// ListView, stacked from its top `spacing` apart and shifted up by `content_y`. Only the entries in the visible
+// part of the list have an instance, they are built and destroyed as the list scrolls, so a model of ten thousand
+// entries costs the same as the rows on screen. All the delegates have the height of the first instance, the list
+// measures its `content_height` with it.
+//
+// The content is clipped to the ListView. It scrolls with the mouse wheel and with a kinetic drag: the content
+// follows the pointer while it is pressed and keeps its velocity once released, slowed down by
+// `flick_deceleration` (pixels per second²).
+
+use std::collections::HashMap;
+
+use macroquad::prelude::warn;
+
+use crate::repeater::{add_missing_property, set_or_add_property, Delegate};
+use crate::{AbstractValue, ItemTypeEnum, NodeId, RmlEngine};
+
+/// Pixels scrolled for one step of the mouse wheel
+const WHEEL_STEP: f32 = 40.0;
+/// Distance the pointer moves before a press on the list becomes a drag
+const DRAG_THRESHOLD: f32 = 8.0;
+/// Under this speed (pixels per second) a flick stops
+const MIN_VELOCITY: f32 = 10.0;
+
+/// Where `position_view_at_index` puts the item in the view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionMode {
+    /// the item is at the top of the view
+    Beginning,
+    /// the item is at the center of the view
+    Center,
+    /// the item is at the bottom of the view
+    End,
+    /// the view scrolls as little as possible to show the whole item
+    Contain,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    pointer_y: f32,
+    content_y: f32,
+    /// the pointer went past `DRAG_THRESHOLD`, the content follows it
+    active: bool,
+    /// `content_y` at the previous tick, to measure the velocity
+    last_content_y: f32,
+}
+
+#[derive(Debug)]
+pub(crate) struct ListViewState {
+    delegate: Delegate,
+    /// instances by model index, with the entry they were built for
+    items: HashMap<usize, (NodeId, AbstractValue)>,
+    /// height of the delegates, measured on the first instance
+    item_height: Option<f32>,
+    /// height of the view the instances were built for
+    viewport_height: f32,
+    /// entries in the model at the last update
+    count: usize,
+    drag: Option<Drag>,
+    /// scrolling speed in pixels per second, kept after a drag is released
+    velocity: f32,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ListViews {
+    states: HashMap<NodeId, ListViewState>,
+}
+
+fn model_count(model: &AbstractValue) -> usize {
+    match model {
+        AbstractValue::Array(entries) => entries.len(),
+        AbstractValue::Number(count) => count.max(0.0) as usize,
+        _ => 0,
+    }
+}
+
+fn model_entry(model: &AbstractValue, index: usize) -> AbstractValue {
+    match model {
+        AbstractValue::Array(entries) => entries[index].clone(),
+        _ => AbstractValue::Number(index as f32),
+    }
+}
+
+/// Give a delegate to the ListView `list` and build the instances of its visible entries
+pub(crate) fn set_delegate(engine: &mut RmlEngine, list: NodeId, delegate: Delegate) {
+    if engine.get_node(list).is_none() {
+        return;
+    }
+    // a new delegate replaces all the instances of the previous one
+    if let Some(previous) = engine.list_views.states.remove(&list) {
+        for (node_id, _) in previous.items.into_values() {
+            engine.destroy(node_id);
+        }
+    } else {
+        add_missing_property(engine, list, "model", AbstractValue::Null);
+        add_missing_property(engine, list, "content_y", AbstractValue::Number(0.0));
+        add_missing_property(engine, list, "content_height", AbstractValue::Number(0.0));
+        add_missing_property(engine, list, "current_index", AbstractValue::Number(0.0));
+        add_missing_property(engine, list, "spacing", AbstractValue::Number(0.0));
+        add_missing_property(engine, list, "flick_deceleration", AbstractValue::Number(1500.0));
+        add_missing_property(engine, list, "interactive", AbstractValue::Bool(true));
+
+        let callback = engine.add_callback(move |engine| update(engine, list));
+        for name in ["model", "content_y", "spacing", "height"] {
+            if let Some(property_id) = engine.get_node(list).and_then(|node| node.get_property(name)) {
+                engine.bind_property_to_callback(property_id, callback);
+            }
+        }
+        // the view follows the current item
+        let follow_current = engine.add_callback(move |engine| {
+            let current = engine.get_number_property_of_node_id(list, "current_index", -1.0);
+            if current >= 0.0 {
+                position_view_at_index(engine, list, current as usize, PositionMode::Contain);
+            }
+            update(engine, list);
+        });
+        if let Some(property_id) = engine.get_node(list).and_then(|node| node.get_property("current_index")) {
+            engine.bind_property_to_callback(property_id, follow_current);
+        }
+    }
+
+    engine.list_views.states.insert(list, ListViewState {
+        delegate,
+        items: HashMap::new(),
+        item_height: None,
+        viewport_height: 0.0,
+        count: 0,
+        drag: None,
+        velocity: 0.0,
+    });
+    update(engine, list);
+}
+
+/// Instances of the ListView `list`, in the model order
+pub(crate) fn instances(engine: &RmlEngine, list: NodeId) -> Vec<NodeId> {
+    let Some(state) = engine.list_views.states.get(&list) else { return Vec::new() };
+    let mut items: Vec<(usize, NodeId)> = state.items.iter().map(|(&index, (node_id, _))| (index, *node_id)).collect();
+    items.sort_unstable();
+    items.into_iter().map(|(_, node_id)| node_id).collect()
+}
+
+/// Forget the removed ListViews and instances
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId]) {
+    engine.list_views.states.retain(|node_id, _| !removed.contains(node_id));
+    for state in engine.list_views.states.values_mut() {
+        state.items.retain(|_, (item, _)| !removed.contains(item));
+    }
+}
+
+/// Height of the view: the rect of the last layout, or the `height` property before the first one
+fn viewport_height(engine: &RmlEngine, list: NodeId) -> f32 {
+    engine.get_layout().get(list)
+        .map(|rect| rect.height)
+        .unwrap_or_else(|| engine.get_number_property_of_node_id(list, "height", 0.0))
+}
+
+/// Distance between the tops of two consecutive delegates
+fn pitch(engine: &RmlEngine, list: NodeId) -> Option<f32> {
+    let item_height = engine.list_views.states.get(&list)?.item_height?;
+    Some((item_height + engine.get_number_property_of_node_id(list, "spacing", 0.0)).max(1.0))
+}
+
+/// Vertical offset of the instance `node_id` from the top of its ListView `list`
+pub(crate) fn item_offset(engine: &RmlEngine, list: NodeId, node_id: NodeId) -> f32 {
+    let index = engine.get_number_property_of_node_id(node_id, "index", 0.0);
+    let content_y = engine.get_number_property_of_node_id(list, "content_y", 0.0);
+    index * pitch(engine, list).unwrap_or(0.0) - content_y
+}
+
+/// Largest `content_y`, where the last item is at the bottom of the view
+fn max_content_y(engine: &RmlEngine, list: NodeId) -> f32 {
+    let content_height = engine.get_number_property_of_node_id(list, "content_height", 0.0);
+    (content_height - viewport_height(engine, list)).max(0.0)
+}
+
+/// Scroll `list` to `content_y`, kept in the content. Return false if it can't scroll further that way.
+fn scroll_to(engine: &mut RmlEngine, list: NodeId, content_y: f32) -> bool {
+    let clamped = content_y.clamp(0.0, max_content_y(engine, list));
+    engine.set_property_of_node_id(list, "content_y", AbstractValue::Number(clamped));
+    clamped == content_y
+}
+
+/// Build the instances of the visible entries of `list` and destroy the others
+pub(crate) fn update(engine: &mut RmlEngine, list: NodeId) {
+    let Some(state) = engine.list_views.states.get_mut(&list) else { return };
+    let delegate = state.delegate.clone();
+    let mut old = std::mem::take(&mut state.items);
+    let count = engine.get_property_by_name(list, "model").map(|model| model_count(&model.value)).unwrap_or(0);
+
+    // the first instance gives the height of all the delegates
+    let measured = engine.list_views.states.get(&list).and_then(|state| state.item_height).is_some();
+    if !measured && count > 0 {
+        let entry = engine.get_property_by_name(list, "model").map(|model| model_entry(&model.value, 0)).unwrap_or(AbstractValue::Null);
+        if let Some(node_id) = build_instance(engine, list, &delegate, 0, entry.clone()) {
+            let height = engine.get_number_property_of_node_id(node_id, "height", 0.0);
+            if height <= 0.0 {
+                warn!("The delegate of a ListView needs a height");
+            }
+            old.insert(0, (node_id, entry));
+            if let Some(state) = engine.list_views.states.get_mut(&list) {
+                state.item_height = Some(height);
+            }
+        }
+    }
+    let spacing = engine.get_number_property_of_node_id(list, "spacing", 0.0);
+    let pitch = pitch(engine, list).unwrap_or(1.0);
+    let content_height = if count > 0 { count as f32 * pitch - spacing } else { 0.0 };
+    engine.set_property_of_node_id(list, "content_height", AbstractValue::Number(content_height));
+
+    let viewport = viewport_height(engine, list);
+    let content_y = engine.get_number_property_of_node_id(list, "content_y", 0.0);
+    let content_y = content_y.clamp(0.0, max_content_y(engine, list));
+    engine.set_property_of_node_id(list, "content_y", AbstractValue::Number(content_y));
+    // like in QML the first item becomes current when the model is filled
+    let previous_count = engine.list_views.states.get(&list).map(|state| state.count).unwrap_or(0);
+    let current = match engine.get_number_property_of_node_id(list, "current_index", -1.0) {
+        _ if count == 0 => -1.0,
+        current if current < 0.0 && previous_count == 0 => 0.0,
+        current => current.min(count as f32 - 1.0),
+    };
+    engine.set_property_of_node_id(list, "current_index", AbstractValue::Number(current));
+
+    let first = ((content_y / pitch).floor() as usize).min(count);
+    let last = (((content_y + viewport) / pitch).ceil() as usize).min(count);
+    let visible: Vec<(usize, AbstractValue)> = match engine.get_property_by_name(list, "model") {
+        Some(model) => (first..last).map(|index| (index, model_entry(&model.value, index))).collect(),
+        None => Vec::new(),
+    };
+
+    // keep the instances of the entries still visible: at the same index first, then anywhere (the model moved)
+    let mut kept: Vec<Option<NodeId>> = vec![None; visible.len()];
+    for (position, (index, entry)) in visible.iter().enumerate() {
+        if old.get(index).is_some_and(|(_, data)| data == entry) {
+            kept[position] = old.remove(index).map(|(node_id, _)| node_id);
+        }
+    }
+    for (position, (_, entry)) in visible.iter().enumerate() {
+        if kept[position].is_some() {
+            continue;
+        }
+        if let Some(&index) = old.iter().find(|(_, (_, data))| data == entry).map(|(index, _)| index) {
+            kept[position] = old.remove(&index).map(|(node_id, _)| node_id);
+        }
+    }
+    for (node_id, _) in old.into_values() {
+        engine.destroy(node_id);
+    }
+
+    let mut items = HashMap::new();
+    for (position, (index, entry)) in visible.into_iter().enumerate() {
+        let built = match kept[position] {
+            Some(node_id) => Some(node_id),
+            None => build_instance(engine, list, &delegate, index, entry.clone()),
+        };
+        let Some(node_id) = built else { continue };
+        set_or_add_property(engine, node_id, "index", AbstractValue::Number(index as f32));
+        set_or_add_property(engine, node_id, "is_current", AbstractValue::Bool(index as f32 == current));
+        engine.get_arena_mut().insert_child(list, position, node_id);
+        items.insert(index, (node_id, entry));
+    }
+    engine.mark_layout_dirty(list);
+
+    if let Some(state) = engine.list_views.states.get_mut(&list) {
+        state.items = items;
+        state.viewport_height = viewport;
+        state.count = count;
+    }
+    // the bindings of the new instances follow their `model_data` before they are drawn
+    engine.run_callbacks();
+}
+
+fn build_instance(engine: &mut RmlEngine, list: NodeId, delegate: &Delegate, index: usize, entry: AbstractValue) -> Option<NodeId> {
+    let node_id = delegate.instantiate(engine, list)?;
+    set_or_add_property(engine, node_id, "index", AbstractValue::Number(index as f32));
+    set_or_add_property(engine, node_id, "model_data", entry);
+    Some(node_id)
+}
+
+/// Update the ListViews whose height changed in the last layout pass. Return true if one was updated.
+pub(crate) fn update_resized(engine: &mut RmlEngine) -> bool {
+    let resized: Vec<NodeId> = engine.list_views.states.iter()
+        .filter(|(&list, state)| engine.get_layout().get(list).is_some_and(|rect| rect.height != state.viewport_height))
+        .map(|(&list, _)| list)
+        .collect();
+    for &list in &resized {
+        update(engine, list);
+    }
+    !resized.is_empty()
+}
+
+/// Scroll `list` so that the item `index` is placed in the view as asked by `mode`
+pub(crate) fn position_view_at_index(engine: &mut RmlEngine, list: NodeId, index: usize, mode: PositionMode) {
+    if pitch(engine, list).is_none() {
+        // nothing was measured yet
+        update(engine, list);
+    }
+    let Some(pitch) = pitch(engine, list) else { return };
+    let Some(state) = engine.list_views.states.get_mut(&list) else { return };
+    state.velocity = 0.0;
+    let item_height = state.item_height.unwrap_or(0.0);
+    let viewport = viewport_height(engine, list);
+    let content_y = engine.get_number_property_of_node_id(list, "content_y", 0.0);
+    let item_y = index as f32 * pitch;
+    let target = match mode {
+        PositionMode::Beginning => item_y,
+        PositionMode::Center => item_y + (item_height - viewport) / 2.0,
+        PositionMode::End => item_y + item_height - viewport,
+        PositionMode::Contain if item_y < content_y => item_y,
+        PositionMode::Contain if item_y + item_height > content_y + viewport => item_y + item_height - viewport,
+        PositionMode::Contain => content_y,
+    };
+    scroll_to(engine, list, target);
+}
+
+/// The topmost interactive ListView under the point
+fn list_view_at(engine: &RmlEngine, x: f32, y: f32) -> Option<NodeId> {
+    let root = engine.get_root_node_id()?;
+    engine.get_arena().subtree(root).into_iter().rev().find(|&node_id| {
+        engine.list_views.states.contains_key(&node_id)
+            && engine.get_bool_property_of_node_id(node_id, "interactive", true)
+            && engine.get_bool_property_of_node_id(node_id, "visible", true)
+            && engine.get_layout().get(node_id).is_some_and(|rect| rect.contains(x, y))
+            && engine.is_point_clipped_in(node_id, x, y)
+    })
+}
+
+/// Scroll the ListView under the pointer by `delta_y` wheel steps
+pub(crate) fn wheel(engine: &mut RmlEngine, x: f32, y: f32, delta_y: f32) {
+    let Some(list) = list_view_at(engine, x, y) else { return };
+    if let Some(state) = engine.list_views.states.get_mut(&list) {
+        state.velocity = 0.0;
+    }
+    let content_y = engine.get_number_property_of_node_id(list, "content_y", 0.0);
+    scroll_to(engine, list, content_y - delta_y * WHEEL_STEP);
+}
+
+/// The pointer is pressed at `(x, y)`: the ListView under it may be dragged
+pub(crate) fn press(engine: &mut RmlEngine, x: f32, y: f32) {
+    let Some(list) = list_view_at(engine, x, y) else { return };
+    let content_y = engine.get_number_property_of_node_id(list, "content_y", 0.0);
+    if let Some(state) = engine.list_views.states.get_mut(&list) {
+        state.velocity = 0.0;
+        state.drag = Some(Drag { pointer_y: y, content_y, active: false, last_content_y: content_y });
+    }
+}
+
+/// The pointer moved to `y`, the dragged ListView follows it
+pub(crate) fn drag(engine: &mut RmlEngine, y: f32) {
+    let dragged: Vec<(NodeId, Drag)> = engine.list_views.states.iter_mut()
+        .filter_map(|(&list, state)| {
+            let drag = state.drag.as_mut()?;
+            drag.active |= (y - drag.pointer_y).abs() > DRAG_THRESHOLD;
+            drag.active.then_some((list, *drag))
+        })
+        .collect();
+    for (list, drag) in dragged {
+        scroll_to(engine, list, drag.content_y - (y - drag.pointer_y));
+    }
+}
+
+/// The pointer is released, a dragged ListView keeps its velocity
+pub(crate) fn release(engine: &mut RmlEngine) {
+    for state in engine.list_views.states.values_mut() {
+        if state.drag.take().is_some_and(|drag| !drag.active) {
+            state.velocity = 0.0;
+        }
+    }
+}
+
+/// Advance the flicks by `dt` seconds and measure the velocity of the drags
+pub(crate) fn tick(engine: &mut RmlEngine, dt: f32) {
+    if dt <= 0.0 {
+        return;
+    }
+    let lists: Vec<NodeId> = engine.list_views.states.keys().copied().collect();
+    for list in lists {
+        let content_y = engine.get_number_property_of_node_id(list, "content_y", 0.0);
+        let deceleration = engine.get_number_property_of_node_id(list, "flick_deceleration", 1500.0);
+        let Some(state) = engine.list_views.states.get_mut(&list) else { continue };
+        if let Some(drag) = state.drag.as_mut() {
+            // smoothed, so the last moves before the release count the most
+            let velocity = (content_y - drag.last_content_y) / dt;
+            state.velocity = (state.velocity + velocity) / 2.0;
+            drag.last_content_y = content_y;
+            continue;
+        }
+        if state.velocity.abs() < MIN_VELOCITY {
+            state.velocity = 0.0;
+            continue;
+        }
+        let velocity = state.velocity;
+        let slowed = velocity - velocity.signum() * deceleration * dt;
+        state.velocity = if slowed.signum() == velocity.signum() { slowed } else { 0.0 };
+        if !scroll_to(engine, list, content_y + velocity * dt) {
+            // the flick hit an end of the list
+            if let Some(state) = engine.list_views.states.get_mut(&list) {
+                state.velocity = 0.0;
+            }
+        }
+    }
+}
+
+pub(crate) fn is_list_view(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.get_node(node_id).is_some_and(|node| node.node_type == ItemTypeEnum::ListView)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instances::Component;
+    use crate::test_utils::{add_node, sample_engine};
+    use crate::{load_rml, DrawCommand, RecordingRenderer};
+
+    /// a 100 high ListView in the panel, its delegate is a 20 high row
+    fn list_engine(count: usize) -> (RmlEngine, NodeId) {
+        let mut engine = sample_engine();
+        let entries = (0..count).map(|index| AbstractValue::String(format!("entry {}", index))).collect();
+        add_node(&mut engine, Some("panel"), "list", ItemTypeEnum::ListView, &[
+            ("anchors", AbstractValue::String("fill".into())),
+            ("model", AbstractValue::Array(entries)),
+        ]);
+        let list = engine.get_node_id("list").unwrap();
+        engine.set_list_view_delegate(list, Component::new(|engine| {
+            add_node(engine, None, "row", ItemTypeEnum::Rectangle, &[("height", AbstractValue::Number(20.0))]);
+            engine.get_node_id("row")
+        }));
+        // the panel is 100 high in a 120 high window
+        engine.update_layout(&RecordingRenderer::new(200.0, 120.0));
+        (engine, list)
+    }
+
+    fn indexes(engine: &RmlEngine, list: NodeId) -> Vec<usize> {
+        engine.get_list_view_instances(list).iter()
+            .map(|&node_id| engine.get_number_property_of_node_id(node_id, "index", -1.0) as usize)
+            .collect()
+    }
+
+    #[test]
+    fn test_only_visible_delegates_are_instantiated() {
+        let (mut engine, list) = list_engine(10_000);
+        assert_eq!(indexes(&engine, list), (0..5).collect::<Vec<_>>());
+        assert_eq!(engine.get_number_property_of_node_id(list, "content_height", 0.0), 200_000.0);
+
+        engine.set_property_of_node("list", "content_y", AbstractValue::Number(1010.0));
+        engine.run_callbacks();
+        assert_eq!(indexes(&engine, list), (50..56).collect::<Vec<_>>());
+        let first = engine.get_list_view_instances(list)[0];
+        assert_eq!(engine.get_string_property_of_node_id(first, "model_data", String::new()), "entry 50");
+
+        engine.update_layout(&RecordingRenderer::new(200.0, 120.0));
+        // the list is at y 10, the item 50 starts 10 pixels above the top of the view
+        assert_eq!(engine.get_layout().get(first).unwrap(), crate::Rect::new(10.0, 0.0, 180.0, 20.0));
+    }
+
+    #[test]
+    fn test_content_y_stays_in_the_content() {
+        let (mut engine, list) = list_engine(10);
+        engine.set_property_of_node("list", "content_y", AbstractValue::Number(500.0));
+        engine.run_callbacks();
+        assert_eq!(engine.get_number_property_of_node_id(list, "content_y", 0.0), 100.0);
+        assert_eq!(indexes(&engine, list), (5..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_position_view_at_index() {
+        let (mut engine, list) = list_engine(100);
+        engine.position_view_at_index(list, 50, PositionMode::Beginning);
+        assert_eq!(engine.get_number_property_of_node_id(list, "content_y", 0.0), 1000.0);
+        engine.position_view_at_index(list, 50, PositionMode::End);
+        assert_eq!(engine.get_number_property_of_node_id(list, "content_y", 0.0), 920.0);
+        engine.position_view_at_index(list, 48, PositionMode::Contain);
+        assert_eq!(engine.get_number_property_of_node_id(list, "content_y", 0.0), 920.0);
+
+        // the view follows the current item
+        engine.set_property_of_node("list", "current_index", AbstractValue::Number(3.0));
+        engine.run_callbacks();
+        assert_eq!(engine.get_number_property_of_node_id(list, "content_y", 0.0), 60.0);
+        let current: Vec<bool> = engine.get_list_view_instances(list).iter()
+            .map(|&node_id| engine.get_bool_property_of_node_id(node_id, "is_current", false))
+            .collect();
+        assert_eq!(current, [true, false, false, false, false]);
+
+        // there is no current item in an empty list, the first one becomes current when it is filled again
+        engine.set_property_of_node("list", "model", AbstractValue::Array(Vec::new()));
+        engine.run_callbacks();
+        assert_eq!(engine.get_number_property_of_node_id(list, "current_index", 0.0), -1.0);
+        engine.set_property_of_node("list", "model", AbstractValue::Number(3.0));
+        engine.run_callbacks();
+        assert_eq!(engine.get_number_property_of_node_id(list, "current_index", -1.0), 0.0);
+    }
+
+    #[test]
+    fn test_wheel_and_kinetic_drag() {
+        let (mut engine, list) = list_engine(100);
+        let content_y = |engine: &RmlEngine| engine.get_number_property_of_node_id(list, "content_y", 0.0);
+
+        wheel(&mut engine, 50.0, 50.0, -2.0);
+        assert_eq!(content_y(&engine), 80.0);
+        // outside of the list
+        wheel(&mut engine, 5.0, 5.0, -2.0);
+        assert_eq!(content_y(&engine), 80.0);
+
+        press(&mut engine, 50.0, 80.0);
+        drag(&mut engine, 75.0);
+        assert_eq!(content_y(&engine), 80.0, "under the drag threshold");
+        for step in 1..=3 {
+            drag(&mut engine, 80.0 - 20.0 * step as f32);
+            tick(&mut engine, 0.02);
+        }
+        assert_eq!(content_y(&engine), 140.0);
+        release(&mut engine);
+
+        // the content keeps going after the release, then stops
+        tick(&mut engine, 0.02);
+        let flicked = content_y(&engine);
+        assert!(flicked > 140.0);
+        for _ in 0..100 {
+            tick(&mut engine, 0.02);
+        }
+        let stopped = content_y(&engine);
+        tick(&mut engine, 0.02);
+        assert_eq!(content_y(&engine), stopped);
+        assert!(stopped > flicked);
+    }
+
+    #[test]
+    fn test_loaded_list_view_is_clipped() {
+        let mut engine = load_rml(r#"
+            Node {
+                id: root
+                anchors: fill
+                ListView {
+                    id: list
+                    width: 100
+                    height: 50
+                    spacing: 10
+                    model: 4
+                    Text { height: 20 text: "row" }
+                }
+            }
+        "#).unwrap();
+        let list = engine.get_node_id("list").unwrap();
+        // 0 and 30 are visible, 60 is not
+        assert_eq!(indexes(&engine, list), [0, 1]);
+        assert_eq!(engine.get_number_property_of_node_id(list, "content_height", 0.0), 110.0);
+
+        let mut renderer = RecordingRenderer::new(200.0, 200.0);
+        crate::draw::draw_root_with_renderer(&mut engine, &mut renderer);
+        assert_eq!(renderer.commands[1], DrawCommand::PushClip { x: 0.0, y: 0.0, width: 100.0, height: 50.0 });
+        assert_eq!(renderer.commands.last(), Some(&DrawCommand::PopClip));
+        assert_eq!(renderer.commands.iter().filter(|command| matches!(command, DrawCommand::Text { .. })).count(), 2);
+    }
+}
@@ -0,0 +1,1043 @@
+// Runtime loader for .rml files
+//
+// The `rml!` macro reads the .rml sources at compile time; this module reads the same syntax at runtime
+// and builds an `RmlEngine` from it, so a layout can be tweaked without rebuilding the application.
+// Rust code can't be evaluated at runtime: literal property values, colors and the built-in node types
+// are supported, blocks (bindings, handlers) and functions are skipped with a warning.
+// Rust callbacks are attached afterwards with `RmlEngine::connect` and `RmlEngine::add_event_handler`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use macroquad::prelude::*;
+use proc_macro2::{Delimiter, Literal, Span, TokenStream, TokenTree};
+
+use crate::{AbstractValue, AnimationSpec, ItemTypeEnum, NodeId, Property, RmlEngine, State, Transition};
+
+/// Error returned when a .rml source can't be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadError {
+    pub message: String,
+    /// File the error comes from, `None` for a source string
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl LoadError {
+    fn new(message: impl Into<String>, span: Span) -> Self {
+        let start = span.start();
+        Self { message: message.into(), path: None, line: start.line, column: start.column + 1 }
+    }
+
+    fn in_file(mut self, path: &Path) -> Self {
+        if self.path.is_none() {
+            self.path = Some(path.to_path_buf());
+        }
+        self
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}:{}:{}: {}", path.display(), self.line, self.column, self.message),
+            None => write!(f, "{}:{}: {}", self.line, self.column, self.message),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Type given in a property declaration (`number width: 10`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DeclaredType {
+    Number,
+    Bool,
+    String,
+    Color,
+}
+
+impl DeclaredType {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "number" => Some(DeclaredType::Number),
+            "bool" => Some(DeclaredType::Bool),
+            "string" => Some(DeclaredType::String),
+            "color" => Some(DeclaredType::Color),
+            _ => None,
+        }
+    }
+
+    fn default_value(&self) -> AbstractValue {
+        match self {
+            DeclaredType::Number => AbstractValue::Number(0.0),
+            DeclaredType::Bool => AbstractValue::Bool(false),
+            DeclaredType::String => AbstractValue::String(String::new()),
+            DeclaredType::Color => AbstractValue::Color(BLANK),
+        }
+    }
+
+    /// Convert a parsed value to this type, `None` if it doesn't fit
+    fn coerce(&self, value: AbstractValue) -> Option<AbstractValue> {
+        match (self, value) {
+            (DeclaredType::Number, value @ AbstractValue::Number(_)) => Some(value),
+            (DeclaredType::Bool, value @ AbstractValue::Bool(_)) => Some(value),
+            (DeclaredType::String, value @ AbstractValue::String(_)) => Some(value),
+            (DeclaredType::Color, value @ AbstractValue::Color(_)) => Some(value),
+            (DeclaredType::Color, AbstractValue::String(name)) => named_color(&name).map(AbstractValue::Color),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PropertyKind {
+    /// `width: 10`
+    Plain,
+    /// `number width: 10`
+    Declared(DeclaredType),
+    /// `signal clicked`
+    Signal,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PropertyDef {
+    pub name: String,
+    pub kind: PropertyKind,
+    /// `None` when the value is a block the loader can't evaluate
+    pub value: Option<AbstractValue>,
+    pub span: Span,
+}
+
+impl PropertyDef {
+    /// Value the property gets in the engine
+    pub fn initial_value(&self) -> AbstractValue {
+        match (&self.kind, &self.value) {
+            (PropertyKind::Signal, _) => AbstractValue::Null,
+            (_, Some(value)) => value.clone(),
+            (PropertyKind::Declared(declared), None) => declared.default_value(),
+            (PropertyKind::Plain, None) => AbstractValue::Null,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct NodeDef {
+    pub type_name: String,
+    pub properties: Vec<PropertyDef>,
+    pub children: Vec<NodeDef>,
+    /// `Behavior on <property> { ... }` declarations: the animated property and its animation
+    pub behaviors: Vec<(String, AnimationSpec)>,
+    /// `states { ... }` and `transitions { ... }` sections
+    pub states: Vec<State>,
+    pub transitions: Vec<Transition>,
+    pub span: Span,
+    /// Set on the root of an expanded component instance
+    pub component: Option<ComponentOrigin>,
+}
+
+/// Where an expanded component comes from, kept to reload it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ComponentOrigin {
+    pub path: PathBuf,
+    /// Properties given by the instance (`UI::Button { text: "Ok" }`)
+    pub overridden: Vec<String>,
+    /// Childs given by the instance, appended after the childs of the component
+    pub instance_children: usize,
+}
+
+impl NodeDef {
+    pub fn id(&self) -> Option<String> {
+        self.properties.iter()
+            .find(|property| property.name == "id")
+            .and_then(|property| property.value.as_ref())
+            .map(|value| value.to_string())
+    }
+
+    fn set_property(&mut self, property: PropertyDef) {
+        match self.properties.iter_mut().find(|existing| existing.name == property.name) {
+            Some(existing) => {
+                existing.value = property.value;
+                existing.span = property.span;
+                if let (PropertyKind::Declared(_), PropertyKind::Declared(_)) = (&existing.kind, &property.kind) {
+                    warn!("Property type defined in component cannot be overridden: {}", property.name);
+                } else if existing.kind == PropertyKind::Plain {
+                    existing.kind = property.kind;
+                }
+            }
+            None => self.properties.push(property),
+        }
+    }
+
+    /// Replace references to the node `from` (`from`, `from.edge`) by references to `to` in this subtree
+    fn rename_references(&mut self, from: &str, to: &str) {
+        let prefix = format!("{}.", from);
+        for property in self.properties.iter_mut() {
+            if let Some(AbstractValue::String(value)) = &mut property.value {
+                if value == from {
+                    *value = to.to_string();
+                } else if let Some(edge) = value.strip_prefix(&prefix) {
+                    *value = format!("{}.{}", to, edge);
+                }
+            }
+        }
+        for child in self.children.iter_mut() {
+            child.rename_references(from, to);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Import {
+    pub path: String,
+    pub alias: Option<String>,
+}
+
+/// A parsed .rml source: its imports and its root node
+#[derive(Debug, Clone)]
+pub(crate) struct Document {
+    pub imports: Vec<Import>,
+    pub root: NodeDef,
+}
+
+/// Map a macroquad color constant name to its color
+pub fn named_color(name: &str) -> Option<Color> {
+    let color = match name {
+        "LIGHTGRAY" => LIGHTGRAY,
+        "GRAY" => GRAY,
+        "DARKGRAY" => DARKGRAY,
+        "YELLOW" => YELLOW,
+        "GOLD" => GOLD,
+        "ORANGE" => ORANGE,
+        "PINK" => PINK,
+        "RED" => RED,
+        "MAROON" => MAROON,
+        "GREEN" => GREEN,
+        "LIME" => LIME,
+        "DARKGREEN" => DARKGREEN,
+        "SKYBLUE" => SKYBLUE,
+        "BLUE" => BLUE,
+        "DARKBLUE" => DARKBLUE,
+        "PURPLE" => PURPLE,
+        "VIOLET" => VIOLET,
+        "DARKPURPLE" => DARKPURPLE,
+        "BEIGE" => BEIGE,
+        "BROWN" => BROWN,
+        "DARKBROWN" => DARKBROWN,
+        "WHITE" => WHITE,
+        "BLACK" => BLACK,
+        "BLANK" => BLANK,
+        "MAGENTA" => MAGENTA,
+        _ => return None,
+    };
+    Some(color)
+}
+
+pub(crate) fn node_type_from_name(name: &str) -> Option<ItemTypeEnum> {
+    match name {
+        "Node" => Some(ItemTypeEnum::Node),
+        "Rectangle" => Some(ItemTypeEnum::Rectangle),
+        "Text" => Some(ItemTypeEnum::Text),
+        "MouseArea" => Some(ItemTypeEnum::MouseArea),
+        "Texture" => Some(ItemTypeEnum::Texture),
+        "Row" => Some(ItemTypeEnum::Row),
+        "Column" => Some(ItemTypeEnum::Column),
+        "Grid" => Some(ItemTypeEnum::Grid),
+        "Repeater" => Some(ItemTypeEnum::Repeater),
+        "ListView" => Some(ItemTypeEnum::ListView),
+        "NumberAnimation" => Some(ItemTypeEnum::NumberAnimation),
+        "ColorAnimation" => Some(ItemTypeEnum::ColorAnimation),
+        "Timer" => Some(ItemTypeEnum::Timer),
+        "TextInput" => Some(ItemTypeEnum::TextInput),
+        "TextArea" => Some(ItemTypeEnum::TextArea),
+        "DropArea" => Some(ItemTypeEnum::DropArea),
+        _ => None,
+    }
+}
+
+/// Cursor over a flat list of token trees (the content of a file or of a `{ }` group)
+struct Cursor {
+    tokens: Vec<TokenTree>,
+    pos: usize,
+    end_span: Span,
+}
+
+impl Cursor {
+    fn new(stream: TokenStream, end_span: Span) -> Self {
+        Self { tokens: stream.into_iter().collect(), pos: 0, end_span }
+    }
+
+    fn peek(&self, offset: usize) -> Option<&TokenTree> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn next(&mut self) -> Option<TokenTree> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn span(&self) -> Span {
+        self.peek(0).map(|token| token.span()).unwrap_or(self.end_span)
+    }
+
+    fn peek_ident(&self, offset: usize) -> Option<String> {
+        match self.peek(offset) {
+            Some(TokenTree::Ident(ident)) => Some(ident.to_string()),
+            _ => None,
+        }
+    }
+
+    fn peek_punct(&self, offset: usize, ch: char) -> bool {
+        matches!(self.peek(offset), Some(TokenTree::Punct(punct)) if punct.as_char() == ch)
+    }
+
+    fn peek_path_separator(&self, offset: usize) -> bool {
+        self.peek_punct(offset, ':') && self.peek_punct(offset + 1, ':')
+    }
+
+    fn peek_brace(&self, offset: usize) -> bool {
+        matches!(self.peek(offset), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace)
+    }
+
+    fn expect_ident(&mut self, what: &str) -> Result<String, LoadError> {
+        match self.next() {
+            Some(TokenTree::Ident(ident)) => Ok(ident.to_string()),
+            Some(other) => Err(LoadError::new(format!("Expected {}, found `{}`", what, other), other.span())),
+            None => Err(LoadError::new(format!("Expected {}", what), self.end_span)),
+        }
+    }
+
+    fn expect_punct(&mut self, ch: char) -> Result<(), LoadError> {
+        match self.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ch => Ok(()),
+            Some(other) => Err(LoadError::new(format!("Expected `{}`, found `{}`", ch, other), other.span())),
+            None => Err(LoadError::new(format!("Expected `{}`", ch), self.end_span)),
+        }
+    }
+
+    fn skip_comma(&mut self) {
+        if self.peek_punct(0, ',') {
+            self.pos += 1;
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<TokenStream, LoadError> {
+    TokenStream::from_str(source).map_err(|error| LoadError::new(format!("Invalid tokens: {}", error), error.span()))
+}
+
+/// Parse a .rml source into a `Document`
+pub(crate) fn parse_document(source: &str) -> Result<Document, LoadError> {
+    let mut cursor = Cursor::new(tokenize(source)?, Span::call_site());
+    let mut imports = Vec::new();
+
+    // imports come first: import "path" [as Alias]
+    while cursor.peek_ident(0).as_deref() == Some("import") {
+        cursor.next();
+        let path = match cursor.next() {
+            Some(TokenTree::Literal(literal)) => parse_string_literal(&literal)
+                .ok_or_else(|| LoadError::new("Expected the import path as a string", literal.span()))?,
+            _ => return Err(LoadError::new("Expected the import path as a string", cursor.span())),
+        };
+        let alias = if cursor.peek_ident(0).as_deref() == Some("as") {
+            cursor.next();
+            Some(cursor.expect_ident("an import alias")?)
+        } else {
+            None
+        };
+        imports.push(Import { path, alias });
+    }
+
+    let root = parse_node(&mut cursor)?;
+    if !cursor.is_empty() {
+        return Err(LoadError::new("Only one root node is allowed", cursor.span()));
+    }
+    Ok(Document { imports, root })
+}
+
+/// Parse `Type { ... }` or `Alias::Type { ... }`
+fn parse_node(cursor: &mut Cursor) -> Result<NodeDef, LoadError> {
+    let span = cursor.span();
+    let mut type_name = cursor.expect_ident("a node type")?;
+    if cursor.peek_path_separator(0) {
+        cursor.pos += 2;
+        type_name = format!("{}::{}", type_name, cursor.expect_ident("a component name")?);
+    }
+
+    let body = match cursor.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group,
+        Some(other) => return Err(LoadError::new(format!("Expected `{{` after `{}`", type_name), other.span())),
+        None => return Err(LoadError::new(format!("Expected `{{` after `{}`", type_name), cursor.end_span)),
+    };
+
+    let mut node = NodeDef { type_name, properties: Vec::new(), children: Vec::new(), behaviors: Vec::new(), states: Vec::new(), transitions: Vec::new(), span, component: None };
+    let mut content = Cursor::new(body.stream(), body.span_close());
+
+    while !content.is_empty() {
+        let keyword = content.peek_ident(0);
+        match keyword.as_deref() {
+            Some("fn") => skip_function(&mut content, &node.type_name),
+            Some("signal") if content.peek_ident(1).is_some() => {
+                let span = content.span();
+                content.next();
+                let name = content.expect_ident("a signal name")?;
+                node.properties.push(PropertyDef { name, kind: PropertyKind::Signal, value: None, span });
+            }
+            Some(keyword) if DeclaredType::from_keyword(keyword).is_some() && content.peek_ident(1).is_some() => {
+                let declared = DeclaredType::from_keyword(keyword).unwrap_or(DeclaredType::Number);
+                content.next();
+                let property = parse_property(&mut content, PropertyKind::Declared(declared))?;
+                node.properties.push(property);
+            }
+            Some("Behavior") if content.peek_ident(1).as_deref() == Some("on") => node.behaviors.push(parse_behavior(&mut content)?),
+            Some("states") if content.peek_brace(1) => node.states.extend(parse_states(&mut content)?),
+            Some("transitions") if content.peek_brace(1) => node.transitions.extend(parse_transitions(&mut content)?),
+            Some(_) if is_property_start(&content) => {
+                let property = parse_property(&mut content, PropertyKind::Plain)?;
+                node.properties.push(property);
+            }
+            Some(_) => node.children.push(parse_node(&mut content)?),
+            None => return Err(LoadError::new("Unexpected token", content.span())),
+        }
+        content.skip_comma();
+    }
+
+    Ok(node)
+}
+
+/// Parse `Behavior on color { ColorAnimation { duration: 200 } }`
+fn parse_behavior(cursor: &mut Cursor) -> Result<(String, AnimationSpec), LoadError> {
+    cursor.pos += 2;
+    let property = cursor.expect_ident("the property of the Behavior")?;
+    let body = match cursor.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group,
+        Some(other) => return Err(LoadError::new(format!("Expected `{{` after `Behavior on {}`", property), other.span())),
+        None => return Err(LoadError::new(format!("Expected `{{` after `Behavior on {}`", property), cursor.end_span)),
+    };
+    let mut content = Cursor::new(body.stream(), body.span_close());
+    let animation = parse_node(&mut content)?;
+    if !content.is_empty() {
+        return Err(LoadError::new("A Behavior holds a single animation", content.span()));
+    }
+    Ok((property, animation_spec(&animation, "Behavior")?))
+}
+
+/// Duration, easing and loops of the animation held by a Behavior or a Transition
+fn animation_spec(animation: &NodeDef, holder: &str) -> Result<AnimationSpec, LoadError> {
+    if !matches!(animation.type_name.as_str(), "NumberAnimation" | "ColorAnimation") || !animation.children.is_empty() {
+        return Err(LoadError::new(format!("A {} holds a NumberAnimation or a ColorAnimation", holder), animation.span));
+    }
+    let properties = animation.properties.iter()
+        .filter_map(|property| property.value.as_ref().map(|value| (property.name.as_str(), value)));
+    AnimationSpec::from_properties(properties).map_err(|error| LoadError::new(error, animation.span))
+}
+
+/// Content of a `{ }` section: `states { ... }`, `transitions { ... }`
+fn section(cursor: &mut Cursor) -> Result<Cursor, LoadError> {
+    let name = cursor.expect_ident("a section")?;
+    match cursor.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => Ok(Cursor::new(group.stream(), group.span_close())),
+        Some(other) => Err(LoadError::new(format!("Expected `{{` after `{}`", name), other.span())),
+        None => Err(LoadError::new(format!("Expected `{{` after `{}`", name), cursor.end_span)),
+    }
+}
+
+/// Parse `states { name { property: value PropertyChanges { target: other property: value } } }`.
+/// Blocks can't be evaluated at runtime, the properties they set are skipped.
+fn parse_states(cursor: &mut Cursor) -> Result<Vec<State>, LoadError> {
+    let mut content = section(cursor)?;
+    let mut states = Vec::new();
+    while !content.is_empty() {
+        let name = content.peek_ident(0).unwrap_or_default();
+        let mut body = section(&mut content)?;
+        let mut state = State::new(name);
+        while !body.is_empty() {
+            if body.peek_ident(0).as_deref() == Some("PropertyChanges") && body.peek_brace(1) {
+                let changes = parse_node(&mut body)?;
+                let target = changes.properties.iter()
+                    .find(|property| property.name == "target")
+                    .and_then(|property| property.value.as_ref())
+                    .map(|target| target.to_string())
+                    .ok_or_else(|| LoadError::new("PropertyChanges needs a `target`", changes.span))?;
+                for property in changes.properties.iter().filter(|property| property.name != "target") {
+                    if let Some(value) = &property.value {
+                        state = state.with_value(Some(&target), &property.name, value.clone());
+                    }
+                }
+            } else {
+                let property = parse_property(&mut body, PropertyKind::Plain)?;
+                if let Some(value) = property.value {
+                    state = state.with_value(None, &property.name, value);
+                }
+            }
+            body.skip_comma();
+        }
+        states.push(state);
+        content.skip_comma();
+    }
+    Ok(states)
+}
+
+/// Parse `transitions { Transition { from: "a" to: "b" NumberAnimation { ... } } }`
+fn parse_transitions(cursor: &mut Cursor) -> Result<Vec<Transition>, LoadError> {
+    let mut content = section(cursor)?;
+    let mut transitions = Vec::new();
+    while !content.is_empty() {
+        let transition = parse_node(&mut content)?;
+        if transition.type_name != "Transition" {
+            return Err(LoadError::new("Expected a Transition", transition.span));
+        }
+        let [animation] = transition.children.as_slice() else {
+            return Err(LoadError::new("A Transition holds a single animation", transition.span));
+        };
+        let mut result = Transition::new(animation_spec(animation, "Transition")?);
+        for property in &transition.properties {
+            match (property.name.as_str(), &property.value) {
+                ("from", Some(AbstractValue::String(from))) => result = result.from_state(from),
+                ("to", Some(AbstractValue::String(to))) => result = result.to_state(to),
+                _ => return Err(LoadError::new(format!("Unexpected property `{}` in a Transition", property.name), property.span)),
+            }
+        }
+        transitions.push(result);
+        content.skip_comma();
+    }
+    Ok(transitions)
+}
+
+/// `key:` or `key.field:`, as opposed to a child node `Type {`
+fn is_property_start(cursor: &Cursor) -> bool {
+    let mut offset = 1;
+    while cursor.peek_punct(offset, '.') && cursor.peek_ident(offset + 1).is_some() {
+        offset += 2;
+    }
+    cursor.peek_punct(offset, ':') && !cursor.peek_path_separator(offset)
+}
+
+fn skip_function(cursor: &mut Cursor, node_type: &str) {
+    let span = cursor.span();
+    // fn name(args) [-> type] { body }
+    while !cursor.is_empty() {
+        let is_body = cursor.peek_brace(0);
+        cursor.next();
+        if is_body {
+            break;
+        }
+    }
+    warn!("{}:{}: functions are not supported by the runtime loader, skipped in {}", span.start().line, span.start().column + 1, node_type);
+}
+
+fn parse_property(cursor: &mut Cursor, kind: PropertyKind) -> Result<PropertyDef, LoadError> {
+    let span = cursor.span();
+    // composed keys are flattened like in the macro: anchors.left -> anchors_left
+    let mut name = cursor.expect_ident("a property name")?;
+    while cursor.peek_punct(0, '.') {
+        cursor.next();
+        name = format!("{}_{}", name, cursor.expect_ident("a property name")?);
+    }
+    cursor.expect_punct(':')?;
+
+    let value_span = cursor.span();
+    let value = parse_value(cursor)?;
+
+    let value = match (&kind, value) {
+        (PropertyKind::Declared(declared), Some(value)) => {
+            let coerced = declared.coerce(value.clone());
+            if coerced.is_none() {
+                return Err(LoadError::new(format!("Expected a {:?} value for `{}`, found {:?}", declared, name, value), value_span));
+            }
+            coerced
+        }
+        (_, value) => value,
+    };
+
+    if value.is_none() {
+        if name.starts_with("on_") {
+            warn!("{}:{}: handler `{}` is skipped by the runtime loader, attach it from Rust", value_span.start().line, value_span.start().column + 1, name);
+        } else {
+            warn!("{}:{}: expression of `{}` can't be evaluated by the runtime loader, skipped", value_span.start().line, value_span.start().column + 1, name);
+        }
+    }
+
+    Ok(PropertyDef { name, kind, value, span })
+}
+
+/// Parse a property value. Returns `None` for blocks that are not a plain literal or color.
+fn parse_value(cursor: &mut Cursor) -> Result<Option<AbstractValue>, LoadError> {
+    let span = cursor.span();
+    match cursor.next() {
+        Some(TokenTree::Literal(literal)) => literal_value(&literal)
+            .map(Some)
+            .ok_or_else(|| LoadError::new(format!("Unsupported literal `{}`", literal), literal.span())),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '-' => match cursor.next() {
+            Some(TokenTree::Literal(literal)) => match literal_value(&literal) {
+                Some(AbstractValue::Number(number)) => Ok(Some(AbstractValue::Number(-number))),
+                _ => Err(LoadError::new("Expected a number after `-`", literal.span())),
+            },
+            _ => Err(LoadError::new("Expected a number after `-`", span)),
+        },
+        Some(TokenTree::Ident(ident)) => {
+            let ident = ident.to_string();
+            if ident == "true" || ident == "false" {
+                return Ok(Some(AbstractValue::Bool(ident == "true")));
+            }
+            if cursor.peek_punct(0, '|') {
+                // composed values: top | left -> top__left
+                let mut composed = ident;
+                while cursor.peek_punct(0, '|') {
+                    cursor.next();
+                    composed = format!("{}__{}", composed, cursor.expect_ident("an identifier after `|`")?);
+                }
+                return Ok(Some(AbstractValue::String(composed)));
+            }
+            if cursor.peek_punct(0, '.') && cursor.peek_ident(1).is_some() {
+                // reference to an edge of another node: header.bottom
+                cursor.next();
+                let edge = cursor.expect_ident("an identifier after `.`")?;
+                return Ok(Some(AbstractValue::String(format!("{}.{}", ident, edge))));
+            }
+            Ok(Some(AbstractValue::String(ident)))
+        }
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => Ok(block_value(group.stream())),
+        Some(other) => Err(LoadError::new(format!("Expected literal, identifier or block, found `{}`", other), other.span())),
+        None => Err(LoadError::new("Expected literal, identifier or block", span)),
+    }
+}
+
+/// Value of a block that doesn't need to run Rust code: `{ 10 }`, `{ RED }`, `{ Color::new(1.0, 0.5, 0.0, 1.0) }`
+fn block_value(stream: TokenStream) -> Option<AbstractValue> {
+    let tokens: Vec<TokenTree> = stream.into_iter().collect();
+    match tokens.as_slice() {
+        [TokenTree::Literal(literal)] => literal_value(literal),
+        [TokenTree::Ident(ident)] => named_color(&ident.to_string()).map(AbstractValue::Color),
+        [TokenTree::Ident(ty), TokenTree::Punct(_), TokenTree::Punct(_), TokenTree::Ident(constructor), TokenTree::Group(args)]
+            if ty == "Color" && args.delimiter() == Delimiter::Parenthesis =>
+        {
+            let numbers = args.stream().into_iter()
+                .filter(|token| !matches!(token, TokenTree::Punct(punct) if punct.as_char() == ','))
+                .map(|token| match token {
+                    TokenTree::Literal(literal) => literal_value(&literal).and_then(|value| value.to_number()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<f32>>>()?;
+            let [r, g, b, a] = numbers.as_slice() else { return None };
+            match constructor.to_string().as_str() {
+                "new" => Some(AbstractValue::Color(Color::new(*r, *g, *b, *a))),
+                "from_rgba" => Some(AbstractValue::Color(Color::from_rgba(*r as u8, *g as u8, *b as u8, *a as u8))),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn literal_value(literal: &Literal) -> Option<AbstractValue> {
+    if let Some(string) = parse_string_literal(literal) {
+        return Some(AbstractValue::String(string));
+    }
+    let text = literal.to_string().replace('_', "");
+    // strip numeric suffixes: 10u32, 1.5f32
+    let number = ["f32", "f64", "usize", "u8", "u16", "u32", "u64", "isize", "i8", "i16", "i32", "i64"].iter()
+        .find_map(|suffix| text.strip_suffix(suffix))
+        .unwrap_or(&text);
+    number.parse::<f32>().ok().map(AbstractValue::Number)
+}
+
+fn parse_string_literal(literal: &Literal) -> Option<String> {
+    let text = literal.to_string();
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut result = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    Some(result)
+}
+
+/// Builds engines from .rml sources.
+///
+/// `import "dir" as Alias` statements are resolved relative to the directory of the loaded file
+/// (or to `base_dir` for a source string), like the `rml!` macro does relative to the crate.
+#[derive(Debug, Clone)]
+pub struct RmlLoader {
+    base_dir: PathBuf,
+    id_prefix: String,
+    id_counter: u32,
+    /// component files being expanded, a component instantiating itself is an error
+    expanding: Vec<PathBuf>,
+}
+
+impl RmlLoader {
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self { base_dir: base_dir.into(), id_prefix: "generated_id_".to_string(), id_counter: 0, expanding: Vec::new() }
+    }
+
+    /// Prefix of the ids given to the nodes without id, `generated_id_` by default
+    pub fn with_id_prefix(mut self, prefix: &str) -> Self {
+        self.id_prefix = prefix.to_string();
+        self
+    }
+
+    /// Build an engine from a .rml source string
+    pub fn load_str(&mut self, source: &str) -> Result<RmlEngine, LoadError> {
+        let document = parse_document(source)?;
+        let base_dir = self.base_dir.clone();
+        let root = self.expand(document.root, &self.components_of(&document.imports, &base_dir))?;
+
+        let mut engine = RmlEngine::new();
+        build_node(&mut engine, &root, None)?;
+        // a component file added to an imported directory is picked up by the hot reload
+        for import in &document.imports {
+            engine.hot_reload.watch_directory(&base_dir.join(&import.path));
+        }
+        engine.run_callbacks();
+        Ok(engine)
+    }
+
+    /// Build an engine from a .rml file
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<RmlEngine, LoadError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|error| LoadError::new(format!("Can't read file: {}", error), Span::call_site()).in_file(path))?;
+        if let Some(dir) = path.parent() {
+            self.base_dir = dir.to_path_buf();
+        }
+        self.load_str(&source).map_err(|error| error.in_file(path))
+    }
+
+    /// Components available through `imports`: component name (with its alias) -> file
+    pub(crate) fn components_of(&self, imports: &[Import], base_dir: &Path) -> HashMap<String, PathBuf> {
+        let mut components = HashMap::new();
+        for import in imports {
+            let dir = base_dir.join(&import.path);
+            let Ok(entries) = fs::read_dir(&dir) else {
+                warn!("Component directory '{}' not found", dir.display());
+                continue;
+            };
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.extension().and_then(|extension| extension.to_str()) != Some("rml") {
+                    continue;
+                }
+                let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else { continue };
+                let name = match &import.alias {
+                    Some(alias) => format!("{}::{}", alias, stem),
+                    None => stem.to_string(),
+                };
+                components.insert(name, path);
+            }
+        }
+        components
+    }
+
+    fn generated_id(&mut self) -> String {
+        let id = format!("{}{}", self.id_prefix, self.id_counter);
+        self.id_counter += 1;
+        id
+    }
+
+    /// Replace the component instances of the tree by the content of their file,
+    /// and give an id to every node
+    pub(crate) fn expand(&mut self, mut node: NodeDef, components: &HashMap<String, PathBuf>) -> Result<NodeDef, LoadError> {
+        let Some(path) = components.get(&node.type_name) else {
+            if node_type_from_name(&node.type_name).is_none() {
+                return Err(LoadError::new(format!("Unknown node type: {}", node.type_name), node.span));
+            }
+            if node.id().is_none() {
+                let id = self.generated_id();
+                node.properties.insert(0, PropertyDef { name: "id".to_string(), kind: PropertyKind::Plain, value: Some(AbstractValue::String(id)), span: node.span });
+            }
+            node.children = node.children.into_iter()
+                .map(|child| self.expand(child, components))
+                .collect::<Result<_, _>>()?;
+            return Ok(node);
+        };
+        self.expand_component(node, path.clone(), components)
+    }
+
+    pub(crate) fn expand_component(&mut self, instance: NodeDef, path: PathBuf, components: &HashMap<String, PathBuf>) -> Result<NodeDef, LoadError> {
+        // the same file is reached through different paths (`components/./Card.rml`)
+        let file = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if self.expanding.contains(&file) {
+            return Err(LoadError::new(format!("Recursive component: {}", path.display()), instance.span));
+        }
+        let source = fs::read_to_string(&path)
+            .map_err(|error| LoadError::new(format!("Can't read component: {}", error), instance.span))?;
+        let document = parse_document(&source).map_err(|error| error.in_file(&path))?;
+        let mut root = document.root;
+
+        // the instance id replaces the id of the component root, everywhere it's referenced
+        let instance_id = match instance.id() {
+            Some(id) => id,
+            None => self.generated_id(),
+        };
+        if let Some(original_id) = root.id() {
+            root.rename_references(&original_id, &instance_id);
+        }
+        root.set_property(PropertyDef { name: "id".to_string(), kind: PropertyKind::Plain, value: Some(AbstractValue::String(instance_id)), span: instance.span });
+
+        // instance properties override the ones of the component, instance childs are appended
+        let overridden = instance.properties.iter().map(|property| property.name.clone()).collect();
+        let instance_children = instance.children.len();
+        for property in instance.properties {
+            root.set_property(property);
+        }
+        root.behaviors.extend(instance.behaviors);
+        root.states.extend(instance.states);
+        root.transitions.extend(instance.transitions);
+        let children = instance.children.into_iter()
+            .map(|child| self.expand(child, components))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let component_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let component_components = self.components_of(&document.imports, &component_dir);
+        self.expanding.push(file);
+        let root = self.expand(root, &component_components);
+        self.expanding.pop();
+        let mut root = root.map_err(|error| error.in_file(&path))?;
+        root.children.extend(children);
+        root.component = Some(ComponentOrigin { path, overridden, instance_children });
+        Ok(root)
+    }
+}
+
+/// Build an engine from a .rml source string, imports are resolved from the current directory
+pub fn load_rml(source: &str) -> Result<RmlEngine, LoadError> {
+    RmlLoader::new(".").load_str(source)
+}
+
+/// Build an engine from a .rml file, imports are resolved from the directory of the file
+pub fn load_rml_file(path: impl AsRef<Path>) -> Result<RmlEngine, LoadError> {
+    RmlLoader::new(".").load_file(path)
+}
+
+/// Add the nodes of an expanded tree to the engine, under `parent`
+pub(crate) fn build_node(engine: &mut RmlEngine, node: &NodeDef, parent: Option<NodeId>) -> Result<NodeId, LoadError> {
+    let node_type = node_type_from_name(&node.type_name)
+        .ok_or_else(|| LoadError::new(format!("Unknown node type: {}", node.type_name), node.span))?;
+    let id = node.id().unwrap_or_default();
+    let has_delegate = matches!(node_type, ItemTypeEnum::Repeater | ItemTypeEnum::ListView);
+    if has_delegate && node.children.len() != 1 {
+        return Err(LoadError::new(format!("A {} must have a single child, its delegate", node.type_name), node.span));
+    }
+    let node_id = engine.add_node(id.clone(), node_type.clone(), HashMap::new())
+        .ok_or_else(|| LoadError::new(format!("Duplicated node id: {}", id), node.span))?;
+
+    // geometry properties exist on every node, like in the macro
+    for name in ["x", "y", "width", "height", "computed_x", "computed_y", "computed_width", "computed_height"] {
+        let property_id = engine.add_property(Property::new(AbstractValue::Number(0.0)));
+        engine.add_property_to_node(node_id, name.to_string(), property_id);
+    }
+    for property in node.properties.iter().filter(|property| property.name != "id") {
+        if property.value.is_none() && property.name.starts_with("on_") {
+            continue;
+        }
+        let property_id = engine.add_property(Property::new(property.initial_value()));
+        engine.add_property_to_node(node_id, property.name.clone(), property_id);
+    }
+
+    if has_delegate {
+        // the child of a Repeater or a ListView is its delegate
+        if let Some(delegate) = node.children.first() {
+            let delegate = crate::repeater::Delegate::Definition(delegate.clone());
+            if node_type == ItemTypeEnum::Repeater {
+                crate::repeater::set_delegate(engine, node_id, delegate);
+            } else {
+                crate::list_view::set_delegate(engine, node_id, delegate);
+            }
+        }
+    } else {
+        for child in node.children.iter() {
+            let child_id = build_node(engine, child, Some(node_id))?;
+            engine.add_child(node_id, child_id);
+        }
+    }
+    // added last, the initial values are not animated
+    for (property, spec) in &node.behaviors {
+        engine.add_behavior(node_id, property, *spec);
+    }
+    for state in &node.states {
+        engine.add_state(node_id, state.clone());
+    }
+    for transition in &node.transitions {
+        engine.add_transition(node_id, transition.clone());
+    }
+    if let Some(origin) = &node.component {
+        crate::hot_reload::watch_expanded_component(engine, node_id, origin, node.clone());
+    }
+    if let Some(parent) = parent {
+        engine.mark_layout_dirty(parent);
+    }
+    Ok(node_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const SOURCE: &str = r#"
+        Node {
+            id: root
+            anchors: fill
+            color color: { DARKGRAY }
+
+            Rectangle {
+                id: panel
+                anchors: top | left
+                margins: 10
+                width: 120
+                height: -1.5
+                radius: 4
+                color: { Color::new(0.0, 0.0, 1.0, 1.0) }
+                bool selected: true
+                signal clicked
+
+                Text {
+                    anchors.left: panel.left
+                    text: "a \"quoted\" label"
+                    font_size: 20
+                }
+            }
+        }
+    "#;
+
+    #[test]
+    fn test_load_literal_properties() {
+        let engine = load_rml(SOURCE).unwrap();
+
+        assert_eq!(engine.get_node_type("panel"), Some(ItemTypeEnum::Rectangle));
+        assert_eq!(engine.get_number_property_of_node("panel", "width", 0.0), 120.0);
+        assert_eq!(engine.get_number_property_of_node("panel", "height", 0.0), -1.5);
+        assert_eq!(engine.get_string_property_of_node("panel", "anchors", String::new()), "top__left");
+        assert_eq!(engine.get_color_property_of_node("root", "color", RED), DARKGRAY);
+        assert_eq!(engine.get_color_property_of_node("panel", "color", RED), Color::new(0.0, 0.0, 1.0, 1.0));
+        assert!(engine.get_bool_property_of_node("panel", "selected", false));
+
+        let text = engine.get_childrens_ids("panel")[0];
+        assert_eq!(engine.get_string_property_of_node_id(text, "text", String::new()), "a \"quoted\" label");
+        assert_eq!(engine.get_string_property_of_node_id(text, "anchors_left", String::new()), "panel.left");
+        assert_eq!(engine.get_node(text).unwrap().id, "generated_id_0");
+    }
+
+    #[test]
+    fn test_connect_rust_callback_to_signal() {
+        let mut engine = load_rml(SOURCE).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        engine.connect("panel", "clicked", move |_engine| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }).unwrap();
+
+        engine.emit("panel", "clicked");
+        engine.run_callbacks();
+        engine.emit("panel", "clicked");
+        engine.run_callbacks();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(engine.connect("panel", "missing", |_engine| {}).is_none());
+    }
+
+    #[test]
+    fn test_errors_point_to_the_source() {
+        let error = load_rml("Node {\n    id: root\n    Slider { }\n}").err().unwrap();
+        assert_eq!(error.message, "Unknown node type: Slider");
+        assert_eq!((error.line, error.column), (3, 5));
+
+        let error = load_rml("Node {\n    number width: \"wide\"\n}").err().unwrap();
+        assert_eq!(error.line, 2);
+
+        let error = load_rml("Node { id: a Node { id: a } }").err().unwrap();
+        assert_eq!(error.message, "Duplicated node id: a");
+    }
+
+    #[test]
+    fn test_blocks_and_functions_are_skipped() {
+        let engine = load_rml(r#"
+            Node {
+                id: root
+                number counter: { $.root.width * 2.0 }
+                on_click: { println!("clicked"); }
+                fn increment(step: f32) -> f32 { step + 1.0 }
+                Rectangle { id: child }
+            }
+        "#).unwrap();
+
+        assert_eq!(engine.get_number_property_of_node("root", "counter", -1.0), 0.0);
+        assert!(engine.get_property_id_of_node("root", "on_click").is_none());
+        assert!(engine.get_node_id("child").is_some());
+    }
+
+    #[test]
+    fn test_load_imported_components() {
+        let dir = std::env::temp_dir().join(format!("rml_loader_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("components")).unwrap();
+        fs::write(dir.join("components").join("Badge.rml"), r#"
+            Rectangle {
+                id: badge_template
+                number width: 40
+                string label: "new"
+                Text { anchors.left: badge_template.left }
+            }
+        "#).unwrap();
+        fs::write(dir.join("main.rml"), r#"
+            import "components" as Ui
+            Node {
+                id: root
+                Ui::Badge { id: first label: "one" }
+                Ui::Badge { width: 60 }
+            }
+        "#).unwrap();
+
+        let engine = load_rml_file(dir.join("main.rml")).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(engine.get_string_property_of_node("first", "label", String::new()), "one");
+        assert_eq!(engine.get_number_property_of_node("first", "width", 0.0), 40.0);
+        let text = engine.get_childrens_ids("first")[0];
+        assert_eq!(engine.get_string_property_of_node_id(text, "anchors_left", String::new()), "first.left");
+        let second = engine.get_childrens_ids("root")[1];
+        assert_eq!(engine.get_number_property_of_node_id(second, "width", 0.0), 60.0);
+    }
+
+    #[test]
+    fn test_recursive_components_are_errors() {
+        let dir = std::env::temp_dir().join(format!("rml_loader_recursive_{}", std::process::id()));
+        fs::create_dir_all(dir.join("components")).unwrap();
+        // Panel holds a Card holding a Panel
+        fs::write(dir.join("components").join("Panel.rml"), "import \".\"\nRectangle {\n    Card { }\n}").unwrap();
+        fs::write(dir.join("components").join("Card.rml"), "import \".\"\nRectangle {\n    Text { }\n    Panel { }\n}").unwrap();
+        fs::write(dir.join("components").join("Loop.rml"), "import \".\"\nRectangle {\n    Loop { }\n}").unwrap();
+        fs::write(dir.join("main.rml"), "import \"components\"\nNode {\n    Panel { }\n}").unwrap();
+        fs::write(dir.join("loop.rml"), "import \"components\"\nNode {\n    Loop { }\n}").unwrap();
+
+        let error = load_rml_file(dir.join("main.rml")).err().unwrap();
+        let looped = load_rml_file(dir.join("loop.rml")).err().unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(error.message.starts_with("Recursive component: "));
+        assert!(error.message.ends_with("Panel.rml"));
+        assert!(error.path.as_ref().is_some_and(|path| path.ends_with("Card.rml")));
+        assert_eq!((error.line, error.column), (4, 5));
+        assert!(looped.message.starts_with("Recursive component: "));
+        assert!(looped.path.as_ref().is_some_and(|path| path.ends_with("Loop.rml")));
+    }
+}
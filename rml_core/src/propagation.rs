@@ -0,0 +1,247 @@
+// Propagation of the events through the node hierarchy
+//
+//     Rectangle {
+//         id: editor
+//         on_key_pressed: {
+//             if get_key_event!(engine) == Some(KeyCode::F5) {
+//                 // a shortcut of the container, the focused TextInput does not type it
+//                 if let Some(event) = engine.event_mut() { event.accept(); }
+//             }
+//         }
+//         on_click_capture: { ... }   // sees the clicks of the descendants before them
+//         MouseArea { anchors: fill on_click: { ... } }
+//     }
+//
+// An event for a node, its target, goes down from the root to the parent of the target running the capture
+// handlers (`on_<event>_capture`) of the nodes on its way, then runs the handlers of the target, then goes back
+// up to the root running the other handlers (`on_<event>`) of the ancestors. The key events target the focused
+// node (the root without one). The mouse events target the MouseAreas and the nodes with a mouse handler under
+// the mouse, the topmost first: an event neither accepted nor stopped goes on to the next one below, and the
+// handlers of a node run once per event, even when the node is on the way of several targets.
+//
+// The handlers read the event with `RmlEngine::event` (or `current_event`), and stop it with `accept()` or
+// `stop_propagation()` on `RmlEngine::event_mut` (see `Event`). A `consume_<event>: true` property
+// (`consume_mouse_click`, `consume_mouse_down`...) accepts the events of its node. The moves, enters and leaves
+// of the mouse and the focus changes only go to their target.
+
+use std::collections::HashSet;
+
+use crate::{Event, EventPhase, EventType, NodeId, RmlEngine, SystemEvent};
+
+/// Nodes whose capture (`true`) or other handlers already ran for an event
+pub(crate) type Visited = HashSet<(NodeId, bool)>;
+
+/// Deliver `event` to its node and the ancestors of its node, return it once delivered. The handlers in `visited`
+/// are skipped.
+pub(crate) fn dispatch(engine: &mut RmlEngine, event: SystemEvent, visited: &mut Visited) -> Event {
+    let target = event.node_id().unwrap_or_default();
+    let event_type = event.event_type();
+    let bubbles = event.bubbles();
+    let mut state = Event::new(event, target);
+
+    // the ancestors of the target, from the root
+    let mut ancestors = Vec::new();
+    let mut current = engine.get_node(target).and_then(|node| node.parent);
+    while let Some(node_id) = current {
+        ancestors.push(node_id);
+        current = engine.get_node(node_id).and_then(|node| node.parent);
+    }
+    ancestors.reverse();
+
+    if bubbles {
+        for &node_id in &ancestors {
+            if state.is_propagation_stopped() {
+                break;
+            }
+            run_handlers(engine, &mut state, node_id, EventPhase::Capture, true, visited);
+        }
+    }
+    if !state.is_propagation_stopped() {
+        run_handlers(engine, &mut state, target, EventPhase::Target, true, visited);
+        run_handlers(engine, &mut state, target, EventPhase::Target, false, visited);
+        if consumes(engine, target, &event_type) {
+            state.accept();
+        }
+    }
+    if bubbles {
+        for &node_id in ancestors.iter().rev() {
+            if state.is_propagation_stopped() {
+                break;
+            }
+            run_handlers(engine, &mut state, node_id, EventPhase::Bubble, false, visited);
+        }
+    }
+    engine.current_event_consumed = state.is_accepted();
+    state
+}
+
+/// Dispatch the event `make` builds for each node of `targets`, the topmost first, until one accepts or stops it.
+/// Return true if it was accepted.
+pub(crate) fn dispatch_to_targets(engine: &mut RmlEngine, targets: &[NodeId], make: impl Fn(NodeId) -> SystemEvent) -> bool {
+    dispatch_to_targets_reached(engine, targets, make).0
+}
+
+/// `dispatch_to_targets`, also returning the targets the event reached
+pub(crate) fn dispatch_to_targets_reached(engine: &mut RmlEngine, targets: &[NodeId], make: impl Fn(NodeId) -> SystemEvent) -> (bool, Vec<NodeId>) {
+    let mut visited = Visited::new();
+    for (index, &target) in targets.iter().enumerate() {
+        let event = dispatch(engine, make(target), &mut visited);
+        if event.is_accepted() || event.is_propagation_stopped() {
+            return (event.is_accepted(), targets[..=index].to_vec());
+        }
+    }
+    (false, targets.to_vec())
+}
+
+/// Whether the `consume_<event>` property of `node_id` accepts its events of type `event_type`
+pub(crate) fn consumes(engine: &RmlEngine, node_id: NodeId, event_type: &EventType) -> bool {
+    let name = match event_type {
+        EventType::MouseEnter => "consume_mouse_enter",
+        EventType::MouseLeave => "consume_mouse_leave",
+        EventType::MouseDown => "consume_mouse_down",
+        EventType::MouseUp => "consume_mouse_up",
+        EventType::MouseMove => "consume_mouse_move",
+        EventType::MouseWheel => "consume_mouse_wheel",
+        EventType::Click => "consume_mouse_click",
+        _ => return false,
+    };
+    engine.get_bool_property_of_node_id(node_id, name, false)
+}
+
+/// Run the capture handlers of `node_id`, or its other handlers, for the event `state`
+fn run_handlers(engine: &mut RmlEngine, state: &mut Event, node_id: NodeId, phase: EventPhase, capture: bool, visited: &mut Visited) {
+    if !visited.insert((node_id, capture)) {
+        return;
+    }
+    let event_type = state.event.event_type();
+    let callback_ids: Vec<_> = engine.event_manager.handlers.iter()
+        .filter(|handler| handler.node_id == node_id && handler.event_type == event_type && handler.capture == capture)
+        .map(|handler| handler.callback_id)
+        .collect();
+    for callback_id in callback_ids {
+        let Some(callback) = engine.callbacks.get(&callback_id).cloned() else { continue };
+        state.current_target = node_id;
+        state.phase = phase;
+        // a handler may deliver other events, the ones being delivered are set back after it
+        let outer_event = engine.event.replace(state.clone());
+        let outer_current_event = engine.current_event.replace(state.event.clone());
+        engine.call(callback_id, &callback);
+        if let Some(event) = engine.event.take() {
+            *state = event;
+        }
+        engine.event = outer_event;
+        engine.current_event = outer_current_event;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{log, logged};
+    use crate::{load_rml, AbstractValue, TestDriver};
+    use macroquad::prelude::KeyCode;
+
+    // `button` has a Text on top of it, `area` covers `button`
+    const SOURCE: &str = r#"
+        Node {
+            id: root
+            string log: ""
+            Rectangle {
+                id: panel
+                x: 0 y: 0 width: 200 height: 200
+                Rectangle {
+                    id: button
+                    x: 10 y: 10 width: 100 height: 40
+                    Text { text: "ok" }
+                }
+                MouseArea { id: area x: 0 y: 0 width: 50 height: 50 }
+                TextInput { id: field x: 10 y: 100 width: 100 height: 30 }
+            }
+        }
+    "#;
+
+    /// A handler logging `entry`, then stopping the event as `stop` says
+    fn add_handler(engine: &mut RmlEngine, event_type: EventType, node: &str, capture: bool, entry: &'static str) {
+        let callback = engine.add_callback(move |engine| {
+            let stop = engine.get_string_property_of_node("root", "stop", String::new());
+            let phase = engine.event().map(|event| format!("{:?}", event.phase)).unwrap_or_default();
+            log(engine, &format!("{entry}:{phase}"));
+            if let Some(event) = engine.event_mut() {
+                if stop == format!("accept {entry}") {
+                    event.accept();
+                }
+                if stop == format!("stop {entry}") {
+                    event.stop_propagation();
+                }
+            }
+        });
+        if capture {
+            engine.add_capture_event_handler(event_type, node, callback);
+        } else {
+            engine.add_event_handler(event_type, node, callback);
+        }
+    }
+
+    fn driver() -> TestDriver {
+        let mut engine = load_rml(SOURCE).unwrap();
+        let stop = engine.add_property(crate::Property::new(AbstractValue::String(String::new())));
+        let root = engine.get_node_id("root").unwrap();
+        engine.add_property_to_node(root, "stop".to_string(), stop);
+        add_handler(&mut engine, EventType::Click, "root", true, "root");
+        add_handler(&mut engine, EventType::Click, "panel", false, "panel");
+        add_handler(&mut engine, EventType::Click, "button", false, "button");
+        add_handler(&mut engine, EventType::Click, "area", false, "area");
+        add_handler(&mut engine, EventType::KeyPressed, "panel", false, "key");
+        TestDriver::new(engine, 300.0, 300.0)
+    }
+
+    fn stop(driver: &mut TestDriver, how: &str) {
+        driver.engine_mut().set_property_of_node("root", "stop", AbstractValue::String(how.to_string()));
+    }
+
+    #[test]
+    fn test_capture_target_bubble() {
+        let mut driver = driver();
+        // a Rectangle with a handler is a target, under the Text drawn on it
+        driver.click_at(80.0, 30.0);
+        assert_eq!(logged(&mut driver), "root:Capture button:Target panel:Bubble ");
+
+        // the area is on top of the button: both get the click, the panel once
+        driver.click_at(20.0, 20.0);
+        assert_eq!(logged(&mut driver), "root:Capture area:Target panel:Bubble button:Target ");
+
+        // accepted by the area, the click still bubbles but does not go to the button below
+        stop(&mut driver, "accept area");
+        driver.click_at(20.0, 20.0);
+        assert_eq!(logged(&mut driver), "root:Capture area:Target panel:Bubble ");
+
+        // stopped on its way down, the click goes nowhere else
+        stop(&mut driver, "stop root");
+        driver.click_at(20.0, 20.0);
+        assert_eq!(logged(&mut driver), "root:Capture ");
+    }
+
+    #[test]
+    fn test_keys_bubble_from_the_focused_node() {
+        let mut driver = driver();
+        let field = driver.engine().get_node_id("field").unwrap();
+        driver.click(field);
+        logged(&mut driver);
+        driver.type_text("a");
+        driver.key(KeyCode::Backspace);
+        assert_eq!(logged(&mut driver), "key:Bubble ");
+        assert_eq!(driver.engine().get_string_property_of_node("field", "text", "?".to_string()), "");
+
+        // a shortcut accepted by the container is not typed in the TextInput
+        driver.type_text("ab");
+        stop(&mut driver, "accept key");
+        driver.key(KeyCode::Backspace);
+        assert_eq!(driver.engine().get_string_property_of_node("field", "text", String::new()), "ab");
+
+        // without a focused node, the keys go to the root
+        driver.engine_mut().get_event_manager_mut().set_focused_node(None);
+        logged(&mut driver);
+        driver.key(KeyCode::Backspace);
+        assert_eq!(logged(&mut driver), "");
+    }
+}
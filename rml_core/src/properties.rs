@@ -1,5 +1,8 @@
+use std::marker::PhantomData;
+
 use macroquad::color::Color;
-use crate::decompose_color_string;
+use macroquad::logging::warn;
+use crate::{decompose_color_string, NodeId, PropertyId, RmlEngine};
 
 pub struct Property {
     pub value: AbstractValue,
@@ -129,4 +132,152 @@ impl AbstractValue {
             _ => None,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Rust types a property can hold, read and written by the typed `PropertyHandle`
+pub trait PropertyValue: Sized + Default {
+    fn from_value(value: &AbstractValue) -> Option<Self>;
+    fn into_value(self) -> AbstractValue;
+}
+
+impl PropertyValue for f32 {
+    fn from_value(value: &AbstractValue) -> Option<Self> {
+        value.to_number()
+    }
+
+    fn into_value(self) -> AbstractValue {
+        AbstractValue::Number(self)
+    }
+}
+
+impl PropertyValue for bool {
+    fn from_value(value: &AbstractValue) -> Option<Self> {
+        value.to_bool()
+    }
+
+    fn into_value(self) -> AbstractValue {
+        AbstractValue::Bool(self)
+    }
+}
+
+impl PropertyValue for String {
+    fn from_value(value: &AbstractValue) -> Option<Self> {
+        match value {
+            AbstractValue::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    fn into_value(self) -> AbstractValue {
+        AbstractValue::String(self)
+    }
+}
+
+impl PropertyValue for Color {
+    fn from_value(value: &AbstractValue) -> Option<Self> {
+        value.to_color()
+    }
+
+    fn into_value(self) -> AbstractValue {
+        AbstractValue::Color(self)
+    }
+}
+
+/// Typed access to a property of a node, backed by its `PropertyId`.
+/// The `rml_ui!` macro emits one for each property whose type is known at compile time.
+pub struct PropertyHandle<T> {
+    node_id: NodeId,
+    name: &'static str,
+    property_id: PropertyId,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for PropertyHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PropertyHandle<T> {}
+
+impl<T> std::fmt::Debug for PropertyHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PropertyHandle")
+            .field("node_id", &self.node_id)
+            .field("name", &self.name)
+            .field("property_id", &self.property_id)
+            .finish()
+    }
+}
+
+impl<T: PropertyValue> PropertyHandle<T> {
+    /// Handle on the property `name` of the node `node_name`, None if the node or the property doesn't exist
+    /// or if the property doesn't hold a `T`
+    pub fn new(engine: &RmlEngine, node_name: &str, name: &'static str) -> Option<Self> {
+        let node_id = engine.get_node_id(node_name)?;
+        let property_id = engine.get_node(node_id)?.get_property(name)?;
+        T::from_value(&engine.get_property(property_id)?.value)?;
+        Some(Self { node_id, name, property_id, marker: PhantomData })
+    }
+
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+
+    pub fn property_id(&self) -> PropertyId {
+        self.property_id
+    }
+
+    /// The value of the property; the default of `T` with a warning if the stored value
+    /// was replaced by one of another type through the untyped API
+    pub fn get(&self, engine: &RmlEngine) -> T {
+        let value = engine.get_property(self.property_id).map(|property| &property.value);
+        match value.and_then(T::from_value) {
+            Some(value) => value,
+            None => {
+                warn!("Property '{}' doesn't hold a {}: {:?}", self.name, std::any::type_name::<T>(), value);
+                T::default()
+            }
+        }
+    }
+
+    /// Set the value like `RmlEngine::set_property_of_node_id`, return true if it changed
+    pub fn set(&self, engine: &mut RmlEngine, value: T) -> bool {
+        engine.set_node_property(self.node_id, self.name, self.property_id, value.into_value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::sample_engine;
+    use macroquad::prelude::BLUE;
+
+    #[test]
+    fn test_property_handle() {
+        let mut engine = sample_engine();
+        let text = PropertyHandle::<String>::new(&engine, "label", "text").unwrap();
+        let color = PropertyHandle::<Color>::new(&engine, "panel", "color").unwrap();
+        assert_eq!(text.get(&engine), "hello");
+        assert_eq!(color.get(&engine), BLUE);
+
+        assert!(text.set(&mut engine, "bye".to_string()));
+        assert!(!text.set(&mut engine, "bye".to_string()));
+        assert_eq!(engine.get_string_property_of_node("label", "text", String::new()), "bye");
+
+        assert!(PropertyHandle::<f32>::new(&engine, "label", "txet").is_none());
+        assert!(PropertyHandle::<f32>::new(&engine, "lable", "text").is_none());
+        assert!(PropertyHandle::<f32>::new(&engine, "label", "text").is_none(), "the text is not a number");
+    }
+
+    #[test]
+    fn test_property_handle_marks_layout_dirty() {
+        let mut engine = sample_engine();
+        let width = PropertyHandle::<f32>::new(&engine, "panel", "width").unwrap();
+        let panel = width.node_id();
+        engine.get_layout_mut().invalidate();
+        width.set(&mut engine, 42.0);
+        assert!(engine.get_layout().is_dirty(panel));
+        assert_eq!(width.get(&engine), 42.0);
+    }
+}
@@ -0,0 +1,205 @@
+use macroquad::prelude::*;
+
+use crate::layout::Rect;
+
+/// Text parameters handed to a renderer when drawing or measuring text.
+#[derive(Debug, Clone, Copy)]
+pub struct TextStyle<'a> {
+    pub font: Option<&'a Font>,
+    pub font_size: u16,
+    pub color: Color,
+}
+
+/// Drawing backend used by the draw pass.
+///
+/// The engine never talks to macroquad directly when drawing; it goes through this trait
+/// so the same draw pass can run on screen (`MacroquadRenderer`) or in memory
+/// (`RecordingRenderer`), which is what tests use on machines without a display.
+pub trait Renderer {
+    /// Size of the drawable area, used as the reference frame of the root node
+    fn viewport_size(&self) -> (f32, f32);
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color);
+
+    fn fill_round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radius: f32, color: Color);
+
+    /// Draw `text` with its baseline at `y`
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, style: &TextStyle);
+
+    /// Draw a texture registered in the engine under `source`.
+    /// `texture` is `None` when no texture was registered with that name.
+    fn draw_texture(&mut self, source: &str, texture: Option<&Texture2D>, x: f32, y: f32, width: f32, height: f32);
+
+    fn measure_text(&self, text: &str, style: &TextStyle) -> TextDimensions;
+
+    /// Restrict the drawing to `rect` until the matching `pop_clip`.
+    /// The draw pass already intersects nested clips, `rect` is the whole clip.
+    fn push_clip(&mut self, rect: Rect);
+
+    /// Go back to the clip before the last `push_clip`
+    fn pop_clip(&mut self);
+}
+
+/// Renderer drawing in the macroquad window
+#[derive(Debug, Default)]
+pub struct MacroquadRenderer {
+    // nested clips, the last one applies
+    clips: Vec<Rect>,
+}
+
+impl MacroquadRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn apply_clip(&self) {
+        let scale = miniquad::window::dpi_scale();
+        let clip = self.clips.last().map(|rect| {
+            (
+                (rect.x * scale) as i32,
+                (rect.y * scale) as i32,
+                (rect.width * scale).max(0.0) as i32,
+                (rect.height * scale).max(0.0) as i32,
+            )
+        });
+        unsafe { get_internal_gl() }.quad_gl.scissor(clip);
+    }
+}
+
+impl Renderer for MacroquadRenderer {
+    fn viewport_size(&self) -> (f32, f32) {
+        miniquad::window::screen_size()
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        draw_rectangle(x, y, width, height, color);
+    }
+
+    fn fill_round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radius: f32, color: Color) {
+        crate::draw::draw_round_rect(x, y, width, height, radius, color);
+    }
+
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, style: &TextStyle) {
+        let params = TextParams {
+            font: style.font,
+            font_size: style.font_size,
+            font_scale: 1.0,
+            color: style.color,
+            ..Default::default()
+        };
+        draw_text_ex(text, x, y, params);
+    }
+
+    fn draw_texture(&mut self, _source: &str, texture: Option<&Texture2D>, x: f32, y: f32, width: f32, height: f32) {
+        if let Some(texture) = texture {
+            draw_texture_ex(
+                texture,
+                x,
+                y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(Vec2::new(width, height)),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn measure_text(&self, text: &str, style: &TextStyle) -> TextDimensions {
+        measure_text(text, style.font, style.font_size, 1.0)
+    }
+
+    fn push_clip(&mut self, rect: Rect) {
+        self.clips.push(rect);
+        self.apply_clip();
+    }
+
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+        self.apply_clip();
+    }
+}
+
+/// A single draw call captured by the `RecordingRenderer`
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand {
+    Rect { x: f32, y: f32, width: f32, height: f32, color: Color },
+    RoundRect { x: f32, y: f32, width: f32, height: f32, radius: f32, color: Color },
+    Text { text: String, x: f32, y: f32, font_size: u16, color: Color },
+    Texture { source: String, x: f32, y: f32, width: f32, height: f32 },
+    PushClip { x: f32, y: f32, width: f32, height: f32 },
+    PopClip,
+}
+
+/// In-memory renderer that records every draw call instead of drawing it.
+///
+/// Text is measured with a fixed-advance approximation (each char is half the font size wide,
+/// lines are one font size high) so layouts are deterministic without any font loaded.
+#[derive(Debug, Clone)]
+pub struct RecordingRenderer {
+    viewport: (f32, f32),
+    pub commands: Vec<DrawCommand>,
+}
+
+impl RecordingRenderer {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            viewport: (width, height),
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn set_viewport_size(&mut self, width: f32, height: f32) {
+        self.viewport = (width, height);
+    }
+
+    /// Drop the commands recorded so far, typically between two frames
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    fn viewport_size(&self) -> (f32, f32) {
+        self.viewport
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        self.commands.push(DrawCommand::Rect { x, y, width, height, color });
+    }
+
+    fn fill_round_rect(&mut self, x: f32, y: f32, width: f32, height: f32, radius: f32, color: Color) {
+        self.commands.push(DrawCommand::RoundRect { x, y, width, height, radius, color });
+    }
+
+    fn draw_text(&mut self, text: &str, x: f32, y: f32, style: &TextStyle) {
+        self.commands.push(DrawCommand::Text {
+            text: text.to_string(),
+            x,
+            y,
+            font_size: style.font_size,
+            color: style.color,
+        });
+    }
+
+    fn draw_texture(&mut self, source: &str, _texture: Option<&Texture2D>, x: f32, y: f32, width: f32, height: f32) {
+        self.commands.push(DrawCommand::Texture { source: source.to_string(), x, y, width, height });
+    }
+
+    fn measure_text(&self, text: &str, style: &TextStyle) -> TextDimensions {
+        let font_size = style.font_size as f32;
+        TextDimensions {
+            width: text.chars().count() as f32 * font_size * 0.5,
+            height: font_size,
+            offset_y: font_size * 0.8,
+        }
+    }
+
+    fn push_clip(&mut self, rect: Rect) {
+        self.commands.push(DrawCommand::PushClip { x: rect.x, y: rect.y, width: rect.width, height: rect.height });
+    }
+
+    fn pop_clip(&mut self) {
+        self.commands.push(DrawCommand::PopClip);
+    }
+}
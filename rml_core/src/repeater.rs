@@ -0,0 +1,298 @@
+// Repeater: a delegate instantiated for each entry of a model
+//
+// The child of a `Repeater` is not added to the tree, it is the delegate: it is instantiated once per entry
+// of the `model` property (an array, or a number of entries) with an `index` and a `model_data` property on
+// its root. Like in QML the instances are inserted in the parent of the Repeater, right after it, so a Row
+// or a Column lays them out with its other childs. When the model changes, the instances whose entry is
+// still in the model are kept (and moved if needed), the others are destroyed and the new entries built.
+
+use std::collections::HashMap;
+
+use macroquad::prelude::warn;
+
+use crate::instances::{self, Component};
+use crate::loader::{build_node, NodeDef};
+use crate::{AbstractValue, CallbackId, NodeId, Property, RmlEngine};
+
+/// What a Repeater instantiates
+#[derive(Debug, Clone)]
+pub(crate) enum Delegate {
+    /// built by the `rml!` macro
+    Component(Component),
+    /// read by the runtime loader
+    Definition(NodeDef),
+}
+
+impl Delegate {
+    /// Build an instance of the delegate under `parent`, in its own scope
+    pub(crate) fn instantiate(&self, engine: &mut RmlEngine, parent: NodeId) -> Option<NodeId> {
+        match self {
+            Delegate::Component(component) => instances::instantiate(engine, component, parent, &[]),
+            Delegate::Definition(definition) => instances::instantiate_with(engine, parent, |engine| {
+                build_node(engine, definition, None).map_err(|error| warn!("Delegate: {}", error)).ok()
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RepeaterState {
+    delegate: Delegate,
+    /// callback bound to the model
+    callback: CallbackId,
+    /// instances in the model order, with the entry they were built for
+    items: Vec<(NodeId, AbstractValue)>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Repeaters {
+    states: HashMap<NodeId, RepeaterState>,
+}
+
+/// Entries of a model: the items of an array, or `0..n` for a number
+pub fn model_entries(model: &AbstractValue) -> Vec<AbstractValue> {
+    match model {
+        AbstractValue::Array(entries) => entries.clone(),
+        AbstractValue::Number(count) => (0..count.max(0.0) as usize).map(|index| AbstractValue::Number(index as f32)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Give a delegate to the Repeater `repeater` and build its instances, they follow the model from now on
+pub(crate) fn set_delegate(engine: &mut RmlEngine, repeater: NodeId, delegate: Delegate) {
+    if engine.get_node(repeater).is_none() {
+        return;
+    }
+    let model = match engine.get_node(repeater).and_then(|node| node.get_property("model")) {
+        Some(property_id) => property_id,
+        None => {
+            let property_id = engine.add_property(Property::new(AbstractValue::Null));
+            engine.add_property_to_node(repeater, "model".to_string(), property_id);
+            property_id
+        }
+    };
+
+    // a new delegate replaces all the instances of the previous one
+    let callback = match engine.repeaters.states.remove(&repeater) {
+        Some(previous) => {
+            for (node_id, _) in previous.items {
+                engine.destroy(node_id);
+            }
+            previous.callback
+        }
+        None => {
+            let callback = engine.add_callback(move |engine| update(engine, repeater));
+            engine.bind_property_to_callback(model, callback);
+            callback
+        }
+    };
+    engine.repeaters.states.insert(repeater, RepeaterState { delegate, callback, items: Vec::new() });
+    update(engine, repeater);
+}
+
+/// Instances of the Repeater `repeater`, in the model order
+pub(crate) fn instances(engine: &RmlEngine, repeater: NodeId) -> Vec<NodeId> {
+    engine.repeaters.states.get(&repeater)
+        .map(|state| state.items.iter().map(|(node_id, _)| *node_id).collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn is_repeater(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.repeaters.states.contains_key(&node_id)
+}
+
+/// Whether `node_id` was built by a Repeater
+pub(crate) fn is_instance(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.repeaters.states.values().any(|state| state.items.iter().any(|(item, _)| *item == node_id))
+}
+
+/// Forget the removed nodes, return the instances of the removed Repeaters
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId]) -> Vec<NodeId> {
+    let mut orphans = Vec::new();
+    for node_id in removed {
+        if let Some(state) = engine.repeaters.states.remove(node_id) {
+            orphans.extend(state.items.into_iter().map(|(item, _)| item));
+        }
+    }
+    for state in engine.repeaters.states.values_mut() {
+        state.items.retain(|(item, _)| !removed.contains(item));
+    }
+    orphans
+}
+
+/// Update the instances of `repeater` after a change of its model
+pub(crate) fn update(engine: &mut RmlEngine, repeater: NodeId) {
+    let Some(state) = engine.repeaters.states.get(&repeater) else { return };
+    // the instances are built once the Repeater is in the tree
+    let Some(parent) = engine.get_node(repeater).and_then(|node| node.parent) else { return };
+    let delegate = state.delegate.clone();
+    let old = state.items.clone();
+    let model = engine.get_property_by_name(repeater, "model").map(|property| property.get()).unwrap_or(AbstractValue::Null);
+    let entries = model_entries(&model);
+
+    // keep the instances of the entries still in the model: at the same position first, then anywhere
+    let mut kept: Vec<Option<NodeId>> = vec![None; entries.len()];
+    let mut used = vec![false; old.len()];
+    for (index, entry) in entries.iter().enumerate() {
+        if old.get(index).is_some_and(|(_, data)| data == entry) {
+            kept[index] = Some(old[index].0);
+            used[index] = true;
+        }
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        if kept[index].is_some() {
+            continue;
+        }
+        if let Some(position) = (0..old.len()).find(|&position| !used[position] && old[position].1 == *entry) {
+            kept[index] = Some(old[position].0);
+            used[position] = true;
+        }
+    }
+    for (position, (node_id, _)) in old.iter().enumerate() {
+        if !used[position] {
+            engine.destroy(*node_id);
+        }
+    }
+
+    let mut items = Vec::new();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let built = match kept[index] {
+            Some(node_id) => Some(node_id),
+            None => delegate.instantiate(engine, parent),
+        };
+        let Some(node_id) = built else { continue };
+        set_or_add_property(engine, node_id, "index", AbstractValue::Number(index as f32));
+        set_or_add_property(engine, node_id, "model_data", entry.clone());
+        items.push((node_id, entry));
+    }
+
+    // the instances follow the Repeater in its parent, in the model order
+    let position = engine.get_arena().get_childrens_ids(parent).iter().position(|&child| child == repeater).unwrap_or(0);
+    for (offset, (node_id, _)) in items.iter().enumerate() {
+        engine.get_arena_mut().insert_child(parent, position + 1 + offset, *node_id);
+    }
+    engine.mark_layout_dirty(parent);
+
+    if let Some(state) = engine.repeaters.states.get_mut(&repeater) {
+        state.items = items;
+    }
+}
+
+pub(crate) fn set_or_add_property(engine: &mut RmlEngine, node_id: NodeId, name: &str, value: AbstractValue) {
+    if engine.get_property_by_name(node_id, name).is_some() {
+        engine.set_property_of_node_id(node_id, name, value);
+    } else {
+        let property_id = engine.add_property(Property::new(value));
+        engine.add_property_to_node(node_id, name.to_string(), property_id);
+    }
+}
+
+pub(crate) fn add_missing_property(engine: &mut RmlEngine, node_id: NodeId, name: &str, value: AbstractValue) {
+    if engine.get_property_by_name(node_id, name).is_none() {
+        let property_id = engine.add_property(Property::new(value));
+        engine.add_property_to_node(node_id, name.to_string(), property_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_node, sample_engine};
+    use crate::{load_rml, ItemTypeEnum, RecordingRenderer};
+
+    /// root > panel > [label, list (Repeater)], the delegate is a row showing `model_data`
+    fn repeater_engine(model: AbstractValue) -> (RmlEngine, NodeId) {
+        let mut engine = sample_engine();
+        add_node(&mut engine, Some("panel"), "list", ItemTypeEnum::Repeater, &[("model", model)]);
+        let list = engine.get_node_id("list").unwrap();
+        engine.set_repeater_delegate(list, Component::new(|engine| {
+            add_node(engine, None, "row", ItemTypeEnum::Rectangle, &[]);
+            engine.get_node_id("row")
+        }));
+        (engine, list)
+    }
+
+    fn model_data(engine: &RmlEngine, instances: &[NodeId]) -> Vec<String> {
+        instances.iter()
+            .map(|&node_id| engine.get_string_property_of_node_id(node_id, "model_data", String::new()))
+            .collect()
+    }
+
+    #[test]
+    fn test_number_model_keeps_existing_instances() {
+        let (mut engine, list) = repeater_engine(AbstractValue::Number(3.0));
+        let first = engine.get_repeater_instances(list);
+        assert_eq!(first.len(), 3);
+        assert_eq!(engine.get_number_property_of_node_id(first[2], "index", -1.0), 2.0);
+        // the instances follow the Repeater in its parent
+        let panel = engine.get_node_id("panel").unwrap();
+        assert_eq!(engine.get_arena().get_childrens_ids(panel)[2..], first[..]);
+
+        engine.set_property_of_node("list", "model", AbstractValue::Number(5.0));
+        engine.run_callbacks();
+        let grown = engine.get_repeater_instances(list);
+        assert_eq!(grown.len(), 5);
+        assert_eq!(grown[..3], first[..]);
+
+        engine.set_property_of_node("list", "model", AbstractValue::Number(1.0));
+        engine.run_callbacks();
+        assert_eq!(engine.get_repeater_instances(list), [first[0]]);
+        assert!(engine.get_node(first[1]).is_none());
+    }
+
+    #[test]
+    fn test_array_model_reorders_instances() {
+        let entries = |names: &[&str]| AbstractValue::Array(names.iter().map(|name| AbstractValue::from(*name)).collect());
+        let (mut engine, list) = repeater_engine(entries(&["a", "b", "c"]));
+        let before = engine.get_repeater_instances(list);
+        assert_eq!(model_data(&engine, &before), ["a", "b", "c"]);
+
+        engine.set_property_of_node("list", "model", entries(&["c", "d", "a"]));
+        engine.run_callbacks();
+        let after = engine.get_repeater_instances(list);
+        assert_eq!(model_data(&engine, &after), ["c", "d", "a"]);
+        assert_eq!(after[0], before[2]);
+        assert_eq!(after[2], before[0]);
+        assert!(engine.get_node(before[1]).is_none());
+        assert_eq!(engine.get_number_property_of_node_id(after[0], "index", -1.0), 0.0);
+
+        let panel = engine.get_node_id("panel").unwrap();
+        assert_eq!(engine.get_arena().get_childrens_ids(panel)[2..], after[..]);
+    }
+
+    #[test]
+    fn test_destroying_the_repeater_destroys_its_instances() {
+        let (mut engine, list) = repeater_engine(AbstractValue::Number(2.0));
+        let instances = engine.get_repeater_instances(list);
+        engine.destroy(list);
+        assert!(instances.iter().all(|&node_id| engine.get_node(node_id).is_none()));
+        assert_eq!(engine.get_childrens_ids("panel").len(), 1);
+    }
+
+    #[test]
+    fn test_loaded_repeater_in_a_column() {
+        let mut engine = load_rml(r#"
+            Node {
+                id: root
+                anchors: fill
+                Column {
+                    id: column
+                    spacing: 5
+                    Repeater {
+                        id: rows
+                        model: 3
+                        Rectangle { id: row width: 50 height: 20 }
+                    }
+                }
+            }
+        "#).unwrap();
+        let rows = engine.get_node_id("rows").unwrap();
+        let instances = engine.get_repeater_instances(rows);
+        assert_eq!(instances.len(), 3);
+        // the Repeater takes no space in the Column
+        engine.update_layout(&RecordingRenderer::new(200.0, 200.0));
+        let ys: Vec<f32> = instances.iter().map(|&node_id| engine.get_layout().get(node_id).unwrap().y).collect();
+        assert_eq!(ys, [0.0, 25.0, 50.0]);
+        assert_eq!(engine.get_layout().get(engine.get_node_id("column").unwrap()).unwrap().height, 70.0);
+    }
+}
@@ -0,0 +1,416 @@
+// States: named sets of property values a node switches between
+//
+// A node declares its states in a `states` section, each state overrides properties of the node or, with
+// `PropertyChanges { target: other ... }`, of another node. Its `state` property names the active state,
+// the empty string being the base state:
+//
+//     string state: ""
+//     states {
+//         hovered { color: { lighter_color($.button.base_color, 0.1) } }
+//         pushed { color: { darker_color($.button.base_color, 0.1) } PropertyChanges { target: label text: "..." } }
+//     }
+//     transitions {
+//         Transition { to: "pushed" ColorAnimation { duration: 100 } }
+//         Transition { ColorAnimation { duration: 200 } }
+//     }
+//
+// When a state is left, the properties it overrode get back their base value. A property overridden by the
+// active state keeps its base value aside: a binding or a handler setting it meanwhile changes the base value,
+// seen once the state is left. The values computed by a block are evaluated again when the properties they
+// read change. The first transition matching the switch (`from` and `to` default to "*", any state) animates
+// the numbers and colors that change, the other properties are set at once.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use macroquad::prelude::warn;
+
+use crate::animation::{self, AnimationSpec};
+use crate::repeater::add_missing_property;
+use crate::{AbstractValue, NodeId, PropertyId, RmlEngine};
+
+type Binding = Arc<dyn Fn(&mut RmlEngine) -> AbstractValue + Send + Sync>;
+
+/// Value given to a property by a state
+#[derive(Clone)]
+pub enum StateValue {
+    Value(AbstractValue),
+    /// computed when the state is entered, and again when one of the `(node, property)` it depends on changes
+    Binding { dependencies: Vec<(String, String)>, compute: Binding },
+}
+
+impl fmt::Debug for StateValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateValue::Value(value) => f.debug_tuple("Value").field(value).finish(),
+            StateValue::Binding { dependencies, .. } => f.debug_struct("Binding").field("dependencies", dependencies).finish(),
+        }
+    }
+}
+
+/// A property overridden by a state, on the node declaring the state when `target` is `None`
+#[derive(Debug, Clone)]
+pub struct PropertyChange {
+    pub target: Option<String>,
+    pub property: String,
+    pub value: StateValue,
+}
+
+#[derive(Debug, Clone)]
+pub struct State {
+    pub name: String,
+    pub changes: Vec<PropertyChange>,
+}
+
+impl State {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), changes: Vec::new() }
+    }
+
+    /// Set `property` of `target` (the node of the state if `None`) to `value` in this state
+    pub fn with_value(mut self, target: Option<&str>, property: &str, value: AbstractValue) -> Self {
+        self.changes.push(PropertyChange { target: target.map(str::to_string), property: property.to_string(), value: StateValue::Value(value) });
+        self
+    }
+
+    /// Set `property` of `target` to the value computed by `compute` in this state,
+    /// computed again when one of the `dependencies` (node, property) changes
+    pub fn with_binding<F>(mut self, target: Option<&str>, property: &str, dependencies: &[(&str, &str)], compute: F) -> Self
+    where
+        F: Fn(&mut RmlEngine) -> AbstractValue + Send + Sync + 'static,
+    {
+        let dependencies = dependencies.iter().map(|(node, property)| (node.to_string(), property.to_string())).collect();
+        let value = StateValue::Binding { dependencies, compute: Arc::new(compute) };
+        self.changes.push(PropertyChange { target: target.map(str::to_string), property: property.to_string(), value });
+        self
+    }
+}
+
+/// Animation of the switches from the state `from` to the state `to`, "*" matches any state
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub from: String,
+    pub to: String,
+    pub spec: AnimationSpec,
+}
+
+impl Transition {
+    pub fn new(spec: AnimationSpec) -> Self {
+        Self { from: "*".to_string(), to: "*".to_string(), spec }
+    }
+
+    pub fn from_state(mut self, from: &str) -> Self {
+        self.from = from.to_string();
+        self
+    }
+
+    pub fn to_state(mut self, to: &str) -> Self {
+        self.to = to.to_string();
+        self
+    }
+
+    fn matches(&self, from: &str, to: &str) -> bool {
+        (self.from == "*" || self.from == from) && (self.to == "*" || self.to == to)
+    }
+}
+
+#[derive(Debug, Default)]
+struct StateMachine {
+    states: Vec<State>,
+    transitions: Vec<Transition>,
+    /// the active state, "" for the base state
+    current: String,
+    /// base values of the properties overridden by the active state
+    saved: HashMap<PropertyId, (NodeId, String, AbstractValue)>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct States {
+    machines: HashMap<NodeId, StateMachine>,
+    /// the properties overridden by an active state, and the node whose state it is
+    overridden: HashMap<PropertyId, NodeId>,
+}
+
+pub(crate) fn add_state(engine: &mut RmlEngine, node_id: NodeId, state: State) {
+    if engine.get_node(node_id).is_none() {
+        return;
+    }
+    let dependencies: Vec<(String, String)> = state.changes.iter()
+        .filter_map(|change| match &change.value {
+            StateValue::Binding { dependencies, .. } => Some(dependencies.clone()),
+            StateValue::Value(_) => None,
+        })
+        .flatten()
+        .collect();
+    machine(engine, node_id).states.push(state);
+
+    if !dependencies.is_empty() {
+        let callback = engine.add_callback(move |engine| refresh(engine, node_id));
+        for (node, property) in dependencies {
+            engine.bind_node_property_to_callback(&node, &property, callback);
+        }
+    }
+}
+
+pub(crate) fn add_transition(engine: &mut RmlEngine, node_id: NodeId, transition: Transition) {
+    if engine.get_node(node_id).is_some() {
+        machine(engine, node_id).transitions.push(transition);
+    }
+}
+
+/// Replace the states and transitions of `node_id`, the active state is applied again
+pub(crate) fn set_states(engine: &mut RmlEngine, node_id: NodeId, states: Vec<State>, transitions: Vec<Transition>) {
+    if states.is_empty() && transitions.is_empty() && !engine.states.machines.contains_key(&node_id) {
+        return;
+    }
+    let current = machine(engine, node_id).current.clone();
+    switch(engine, node_id, "", false);
+    let machine = machine(engine, node_id);
+    machine.states.clear();
+    machine.transitions = transitions;
+    for state in states {
+        add_state(engine, node_id, state);
+    }
+    switch(engine, node_id, &current, false);
+}
+
+/// The state machine of `node_id`, created with its `state` property on the first state or transition
+fn machine(engine: &mut RmlEngine, node_id: NodeId) -> &mut StateMachine {
+    if !engine.states.machines.contains_key(&node_id) {
+        add_missing_property(engine, node_id, "state", AbstractValue::String(String::new()));
+        if let Some(property_id) = engine.get_node(node_id).and_then(|node| node.get_property("state")) {
+            let callback = engine.add_callback(move |engine| {
+                let state = engine.get_string_property_of_node_id(node_id, "state", String::new());
+                switch(engine, node_id, &state, true);
+            });
+            engine.bind_property_to_callback(property_id, callback);
+            // a `state` already set applies with the next callbacks, once the node is complete
+            engine.callbacks_to_eval.push(callback);
+        }
+        engine.states.machines.insert(node_id, StateMachine::default());
+    }
+    engine.states.machines.get_mut(&node_id).expect("state machine just inserted")
+}
+
+pub(crate) fn current_state(engine: &RmlEngine, node_id: NodeId) -> Option<&str> {
+    engine.states.machines.get(&node_id).map(|machine| machine.current.as_str())
+}
+
+/// A property overridden by a state is set from outside: keep the value for when the state is left.
+/// Return false if the property is not overridden.
+pub(crate) fn set_base_value(engine: &mut RmlEngine, property_id: PropertyId, value: &AbstractValue) -> bool {
+    let Some(owner) = engine.states.overridden.get(&property_id) else { return false };
+    let Some((_, _, base)) = engine.states.machines.get_mut(owner).and_then(|machine| machine.saved.get_mut(&property_id)) else {
+        return false;
+    };
+    *base = value.clone();
+    true
+}
+
+/// Values given by the state `name` of `node_id`, with the node and the property they go to
+fn evaluate(engine: &mut RmlEngine, node_id: NodeId, name: &str) -> Vec<(NodeId, PropertyId, String, AbstractValue)> {
+    let Some(state) = engine.states.machines.get(&node_id).and_then(|machine| machine.states.iter().find(|state| state.name == name)) else {
+        if !name.is_empty() {
+            warn!("Unknown state '{}'", name);
+        }
+        return Vec::new();
+    };
+    let changes = state.changes.clone();
+    let mut values = Vec::new();
+    for change in changes {
+        let target = match &change.target {
+            Some(target) => engine.get_node_id(target),
+            None => Some(node_id),
+        };
+        let Some(target) = target else {
+            warn!("State '{}': the target '{}' is not found", name, change.target.unwrap_or_default());
+            continue;
+        };
+        let Some(property_id) = engine.get_node(target).and_then(|node| node.get_property(&change.property)) else {
+            warn!("State '{}': unknown property '{}'", name, change.property);
+            continue;
+        };
+        let value = match change.value {
+            StateValue::Value(value) => value,
+            StateValue::Binding { compute, .. } => compute(engine),
+        };
+        values.push((target, property_id, change.property, value));
+    }
+    values
+}
+
+/// Switch `node_id` to the state `name`, animated by the matching transition if `animate`
+fn switch(engine: &mut RmlEngine, node_id: NodeId, name: &str, animate: bool) {
+    let Some(machine) = engine.states.machines.get(&node_id) else { return };
+    if machine.current == name {
+        return;
+    }
+    let spec = machine.transitions.iter()
+        .find(|transition| transition.matches(&machine.current, name))
+        .map(|transition| transition.spec)
+        .filter(|_| animate);
+    let values = evaluate(engine, node_id, name);
+
+    // the properties of the previous state that the new one doesn't override get back their base value
+    let Some(machine) = engine.states.machines.get_mut(&node_id) else { return };
+    machine.current = name.to_string();
+    let left: Vec<PropertyId> = machine.saved.keys()
+        .filter(|property_id| !values.iter().any(|(_, overridden, _, _)| overridden == *property_id))
+        .copied()
+        .collect();
+    let restored: Vec<(NodeId, String, AbstractValue)> = left.iter().filter_map(|property_id| machine.saved.remove(property_id)).collect();
+    for property_id in left {
+        engine.states.overridden.remove(&property_id);
+    }
+    for (target, property, base) in restored {
+        write(engine, target, &property, base, spec);
+    }
+
+    for (target, property_id, property, value) in values {
+        if !engine.states.overridden.contains_key(&property_id) {
+            // an animation going on (from a previous transition) tells the value the property is heading to
+            let base = animation::target_value(engine, target, &property)
+                .or_else(|| engine.get_property(property_id).map(|property| property.get()))
+                .unwrap_or(AbstractValue::Null);
+            if let Some(machine) = engine.states.machines.get_mut(&node_id) {
+                machine.saved.insert(property_id, (target, property.clone(), base));
+            }
+            engine.states.overridden.insert(property_id, node_id);
+        }
+        write(engine, target, &property, value, spec);
+    }
+}
+
+/// The values of the active state are computed again, one of the properties they read changed
+fn refresh(engine: &mut RmlEngine, node_id: NodeId) {
+    let Some(current) = current_state(engine, node_id).map(str::to_string) else { return };
+    for (target, _, property, value) in evaluate(engine, node_id, &current) {
+        write(engine, target, &property, value, None);
+    }
+}
+
+/// Set a property for a state, animated with `spec` or else its Behavior
+fn write(engine: &mut RmlEngine, node_id: NodeId, property: &str, value: AbstractValue, spec: Option<AnimationSpec>) {
+    let spec = spec.or_else(|| {
+        let property_id = engine.get_node(node_id)?.get_property(property)?;
+        engine.animations.behavior(property_id)
+    });
+    if spec.is_some_and(|spec| animation::animate_change(engine, node_id, property, &value, spec)) {
+        return;
+    }
+    animation::stop(engine, node_id, property);
+    engine.write_property_of_node_id(node_id, property, value);
+}
+
+/// Forget the removed nodes and properties
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId], properties: &std::collections::HashSet<PropertyId>) {
+    let states = &mut engine.states;
+    for node_id in removed {
+        states.machines.remove(node_id);
+    }
+    states.overridden.retain(|property_id, owner| !properties.contains(property_id) && !removed.contains(owner));
+    for machine in states.machines.values_mut() {
+        machine.saved.retain(|property_id, _| !properties.contains(property_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::sample_engine;
+    use crate::load_rml;
+    use macroquad::prelude::{BLUE, RED, WHITE};
+
+    fn set_state(engine: &mut RmlEngine, node: &str, state: &str) {
+        engine.set_property_of_node(node, "state", AbstractValue::String(state.to_string()));
+        engine.run_callbacks();
+    }
+
+    #[test]
+    fn test_states_override_and_restore() {
+        let mut engine = sample_engine();
+        let panel = engine.get_node_id("panel").unwrap();
+        engine.add_state(panel, State::new("alert")
+            .with_value(None, "color", AbstractValue::Color(RED))
+            .with_value(Some("label"), "text", AbstractValue::String("alert!".into())));
+        engine.add_state(panel, State::new("wide").with_value(None, "width", AbstractValue::Number(300.0)));
+        engine.run_callbacks();
+
+        set_state(&mut engine, "panel", "alert");
+        assert_eq!(engine.get_color_property_of_node("panel", "color", WHITE), RED);
+        assert_eq!(engine.get_string_property_of_node("label", "text", String::new()), "alert!");
+
+        // a value set while the state is active is the one restored
+        engine.set_property_of_node("label", "text", AbstractValue::String("bye".into()));
+        assert_eq!(engine.get_string_property_of_node("label", "text", String::new()), "alert!");
+
+        set_state(&mut engine, "panel", "wide");
+        assert_eq!(engine.get_color_property_of_node("panel", "color", WHITE), BLUE);
+        assert_eq!(engine.get_string_property_of_node("label", "text", String::new()), "bye");
+        assert_eq!(engine.get_number_property_of_node("panel", "width", 0.0), 300.0);
+
+        set_state(&mut engine, "panel", "");
+        assert_eq!(engine.get_number_property_of_node("panel", "width", -1.0), 0.0);
+        assert_eq!(engine.current_state(panel), Some(""));
+    }
+
+    #[test]
+    fn test_transitions_and_bindings() {
+        let mut engine = sample_engine();
+        let panel = engine.get_node_id("panel").unwrap();
+        engine.add_state(panel, State::new("moved").with_binding(None, "x", &[("root", "width")], |engine| {
+            AbstractValue::Number(engine.get_number_property_of_node("root", "width", 0.0) / 2.0)
+        }));
+        engine.add_transition(panel, Transition::new(AnimationSpec::new(100.0)).to_state("moved"));
+        engine.set_property_of_node("root", "width", AbstractValue::Number(200.0));
+        engine.run_callbacks();
+
+        set_state(&mut engine, "panel", "moved");
+        assert!(engine.is_animating(panel, "x"));
+        engine.advance_time(0.05);
+        assert_eq!(engine.get_number_property_of_node("panel", "x", -1.0), 50.0);
+        engine.advance_time(0.05);
+        assert_eq!(engine.get_number_property_of_node("panel", "x", -1.0), 100.0);
+
+        // the binding follows the properties it reads
+        engine.set_property_of_node("root", "width", AbstractValue::Number(100.0));
+        engine.run_callbacks();
+        assert_eq!(engine.get_number_property_of_node("panel", "x", -1.0), 50.0);
+
+        // no transition matches the way back
+        set_state(&mut engine, "panel", "");
+        assert!(!engine.is_animating(panel, "x"));
+        assert_eq!(engine.get_number_property_of_node("panel", "x", -1.0), 0.0);
+    }
+
+    #[test]
+    fn test_loaded_states() {
+        let mut engine = load_rml(r#"
+            Rectangle {
+                id: box
+                width: 10
+                visible: true
+                string state: "big"
+                Text { id: caption text: "small" }
+                states {
+                    big { width: 100 PropertyChanges { target: caption text: "big" } }
+                    hidden { visible: false }
+                }
+                transitions {
+                    Transition { from: "big" NumberAnimation { duration: 100 } }
+                }
+            }
+        "#).unwrap();
+        engine.run_callbacks();
+        assert_eq!(engine.get_number_property_of_node("box", "width", 0.0), 100.0);
+        assert_eq!(engine.get_string_property_of_node("caption", "text", String::new()), "big");
+
+        set_state(&mut engine, "box", "hidden");
+        assert_eq!(engine.get_string_property_of_node("caption", "text", String::new()), "small");
+        assert_eq!(engine.get_number_property_of_node("box", "width", 0.0), 100.0, "animated back");
+        engine.advance_time(0.1);
+        assert_eq!(engine.get_number_property_of_node("box", "width", 0.0), 10.0);
+        assert!(!engine.get_bool_property_of_node("box", "visible", true));
+    }
+}
@@ -0,0 +1,246 @@
+// Test driver: simulated frames of an application, without a window
+//
+//     let mut driver = TestDriver::new(engine, 400.0, 300.0);
+//     driver.click(driver.engine().get_node_id("ok_button").unwrap());
+//     assert_eq!(driver.engine().get_number_property_of_node("counter", "value", 0.0), 1.0);
+//
+// The driver gives the engine a `ScriptedInput`, a `ManualClock`, a `MemoryClipboard` and a `RecordingRenderer`. Every call scripts
+// the input of a frame and runs it like the main loop of an application: `process_events`, the callbacks it
+// queued, then the draw pass, which lays out the tree for the hit tests of the next frame.
+
+use macroquad::prelude::{KeyCode, MouseButton};
+
+use crate::draw::draw_root_with_renderer;
+use crate::{ManualClock, MemoryClipboard, NodeId, RecordingRenderer, RmlEngine, ScriptedInput, SystemEvent};
+
+/// Callbacks queued by callbacks are run in the same frame, up to this depth
+const MAX_CALLBACK_PASSES: usize = 100;
+
+pub struct TestDriver {
+    engine: RmlEngine,
+    input: ScriptedInput,
+    clock: ManualClock,
+    clipboard: MemoryClipboard,
+    renderer: RecordingRenderer,
+}
+
+impl TestDriver {
+    /// Drive `engine` in a window of `width` x `height`, after a first frame
+    pub fn new(mut engine: RmlEngine, width: f32, height: f32) -> Self {
+        let input = ScriptedInput::new();
+        input.resize(width, height);
+        let clock = ManualClock::new();
+        engine.set_input_source(input.clone());
+        engine.set_clock(clock.clone());
+        let clipboard = MemoryClipboard::new();
+        engine.set_clipboard(clipboard.clone());
+        let mut driver = Self { engine, input, clock, clipboard, renderer: RecordingRenderer::new(width, height) };
+        driver.draw();
+        driver.frame();
+        driver
+    }
+
+    pub fn engine(&self) -> &RmlEngine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut RmlEngine {
+        &mut self.engine
+    }
+
+    /// The draw commands of the last frame
+    pub fn renderer(&self) -> &RecordingRenderer {
+        &self.renderer
+    }
+
+    /// The clipboard of the engine
+    pub fn clipboard(&self) -> &MemoryClipboard {
+        &self.clipboard
+    }
+
+    pub fn into_engine(self) -> RmlEngine {
+        self.engine
+    }
+
+    /// Run a frame with the input scripted so far, return the events it made
+    pub fn frame(&mut self) -> Vec<SystemEvent> {
+        let events = self.engine.process_events();
+        for _ in 0..MAX_CALLBACK_PASSES {
+            if self.engine.callbacks_to_eval.is_empty() {
+                break;
+            }
+            self.engine.run_callbacks();
+        }
+        self.draw();
+        events
+    }
+
+    fn draw(&mut self) {
+        self.renderer.clear();
+        draw_root_with_renderer(&mut self.engine, &mut self.renderer);
+    }
+
+    /// Move the clock `seconds` forward in one frame
+    pub fn advance(&mut self, seconds: f64) {
+        self.clock.advance(seconds);
+        self.frame();
+    }
+
+    pub fn move_mouse(&mut self, x: f32, y: f32) {
+        self.input.move_mouse(x, y);
+        self.frame();
+    }
+
+    /// Press `button` where the mouse is
+    pub fn press(&mut self, button: MouseButton) {
+        self.input.press(button);
+        self.frame();
+    }
+
+    pub fn release(&mut self, button: MouseButton) {
+        self.input.release(button);
+        self.frame();
+    }
+
+    /// Move the mouse to (`x`, `y`) then press and release the left button, in three frames
+    pub fn click_at(&mut self, x: f32, y: f32) {
+        self.move_mouse(x, y);
+        self.press(MouseButton::Left);
+        self.release(MouseButton::Left);
+    }
+
+    /// Click the center of the node `node_id`, return false if it has no geometry
+    pub fn click(&mut self, node_id: NodeId) -> bool {
+        let Some(rect) = self.engine.get_layout().get(node_id) else { return false };
+        self.click_at(rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+        true
+    }
+
+    pub fn wheel(&mut self, delta_x: f32, delta_y: f32) {
+        self.input.wheel(delta_x, delta_y);
+        self.frame();
+    }
+
+    /// Give the focus to the window or take it away, in one frame
+    pub fn set_window_focused(&mut self, focused: bool) {
+        self.input.set_window_focused(focused);
+        self.frame();
+    }
+
+    /// Drop the files `paths` where the mouse is, as the OS drops them on the window
+    pub fn drop_files(&mut self, paths: &[&str]) {
+        self.input.drop_files(paths);
+        self.frame();
+    }
+
+    /// Press `key` and keep it down
+    pub fn press_key(&mut self, key: KeyCode) {
+        self.input.press_key(key);
+        self.frame();
+    }
+
+    pub fn release_key(&mut self, key: KeyCode) {
+        self.input.release_key(key);
+        self.frame();
+    }
+
+    /// Press and release `key`, in two frames
+    pub fn key(&mut self, key: KeyCode) {
+        self.press_key(key);
+        self.release_key(key);
+    }
+
+    /// Type the characters of `text`, one per frame
+    pub fn type_text(&mut self, text: &str) {
+        for character in text.chars() {
+            self.input.type_text(&character.to_string());
+            self.frame();
+        }
+    }
+
+    /// Resize the window
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.input.resize(width, height);
+        self.renderer.set_viewport_size(width, height);
+        self.frame();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_rml, AbstractValue, EventType};
+
+    const SOURCE: &str = r#"
+        Node {
+            id: root
+            anchors: fill
+            number clicks: 0
+            string typed: ""
+            Rectangle {
+                id: button
+                x: 20 y: 20 width: 100 height: 40
+                MouseArea { id: area anchors: fill bool hovered: false }
+            }
+        }
+    "#;
+
+    /// The handlers the rml! macro would generate, the loader skips them
+    fn driver() -> TestDriver {
+        let mut engine = load_rml(SOURCE).unwrap();
+        let click = engine.add_callback(|engine| {
+            let clicks = engine.get_number_property_of_node("root", "clicks", 0.0);
+            engine.set_property_of_node("root", "clicks", AbstractValue::Number(clicks + 1.0));
+        });
+        engine.add_event_handler(EventType::Click, "area", click);
+        for (event_type, hovered) in [(EventType::MouseEnter, true), (EventType::MouseLeave, false)] {
+            let callback = engine.add_callback(move |engine| {
+                engine.set_property_of_node("area", "hovered", AbstractValue::Bool(hovered));
+            });
+            engine.add_event_handler(event_type, "area", callback);
+        }
+        let typed = engine.add_callback(|engine| {
+            if let Some(SystemEvent::CharInput { character, .. }) = engine.current_event {
+                let mut typed = engine.get_string_property_of_node("root", "typed", String::new());
+                typed.push(character);
+                engine.set_property_of_node("root", "typed", AbstractValue::String(typed));
+            }
+        });
+        engine.add_event_handler(EventType::CharInput, "root", typed);
+        TestDriver::new(engine, 400.0, 300.0)
+    }
+
+    #[test]
+    fn test_click_and_hover() {
+        let mut driver = driver();
+        let area = driver.engine().get_node_id("area").unwrap();
+        assert!(driver.click(area));
+        assert_eq!(driver.engine().get_number_property_of_node("root", "clicks", 0.0), 1.0);
+        assert!(driver.engine().get_bool_property_of_node("area", "hovered", false));
+
+        // a click outside of the area is not counted
+        driver.move_mouse(300.0, 200.0);
+        assert!(!driver.engine().get_bool_property_of_node("area", "hovered", true));
+        driver.click_at(10.0, 10.0);
+        assert_eq!(driver.engine().get_number_property_of_node("root", "clicks", 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_keyboard_and_resize() {
+        let mut driver = driver();
+        driver.engine_mut().set_focused_node("root");
+        driver.type_text("hi!");
+        assert_eq!(driver.engine().get_string_property_of_node("root", "typed", String::new()), "hi!");
+
+        driver.input.press_key(KeyCode::A);
+        let events = driver.frame();
+        assert!(events.contains(&SystemEvent::KeyPressed { node_id: NodeId::default(), key: KeyCode::A }));
+        assert!(driver.frame().contains(&SystemEvent::KeyDown { node_id: NodeId::default(), key: KeyCode::A }), "held down");
+        driver.release_key(KeyCode::A);
+        assert!(driver.frame().is_empty());
+
+        driver.resize(600.0, 500.0);
+        let root = driver.engine().get_node_id("root").unwrap();
+        assert_eq!(driver.engine().get_layout().get(root).map(|rect| rect.width), Some(600.0));
+    }
+}
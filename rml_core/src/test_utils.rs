@@ -0,0 +1,62 @@
+use macroquad::prelude::*;
+
+use crate::{AbstractValue, EventType, ItemTypeEnum, Property, PropertyMap, RmlEngine, TestDriver};
+
+/// Add a node with the geometry properties the `rml!` macro creates for every node,
+/// plus the given `properties`, and attach it to `parent`
+pub fn add_node(engine: &mut RmlEngine, parent: Option<&str>, id: &str, node_type: ItemTypeEnum, properties: &[(&str, AbstractValue)]) {
+    let node = engine.add_node(id.to_string(), node_type, PropertyMap::new()).unwrap();
+    for name in ["x", "y", "width", "height", "computed_x", "computed_y", "computed_width", "computed_height"] {
+        let prop_id = engine.add_property(Property::new(AbstractValue::Number(0.0)));
+        engine.add_property_to_node(node, name.to_string(), prop_id);
+    }
+    for (name, value) in properties {
+        let prop_id = engine.add_property(Property::new(value.clone()));
+        engine.add_property_to_node(node, name.to_string(), prop_id);
+    }
+    if let Some(parent) = parent.and_then(|p| engine.get_node_id(p)) {
+        engine.add_child(parent, node);
+    }
+}
+
+/// root (fill) > panel (fill, margins 10) > label (centered text)
+pub fn sample_engine() -> RmlEngine {
+    let mut engine = RmlEngine::new();
+    add_node(&mut engine, None, "root", ItemTypeEnum::Node, &[
+        ("anchors", AbstractValue::String("fill".into())),
+        ("color", AbstractValue::Color(DARKGRAY)),
+    ]);
+    add_node(&mut engine, Some("root"), "panel", ItemTypeEnum::Rectangle, &[
+        ("anchors", AbstractValue::String("fill".into())),
+        ("margins", AbstractValue::Number(10.0)),
+        ("color", AbstractValue::Color(BLUE)),
+    ]);
+    add_node(&mut engine, Some("panel"), "label", ItemTypeEnum::Text, &[
+        ("anchors", AbstractValue::String("center".into())),
+        ("text", AbstractValue::String("hello".into())),
+        ("font_size", AbstractValue::Number(20.0)),
+    ]);
+    engine
+}
+
+/// Append `entry` to the `log` string property of the `root` node of the event tests
+pub fn log(engine: &mut RmlEngine, entry: &str) {
+    let log = engine.get_string_property_of_node("root", "log", String::new());
+    engine.set_property_of_node("root", "log", AbstractValue::String(format!("{log}{entry} ")));
+}
+
+/// The entries logged since the last call
+pub fn logged(driver: &mut TestDriver) -> String {
+    let log = driver.engine().get_string_property_of_node("root", "log", String::new());
+    driver.engine_mut().set_property_of_node("root", "log", AbstractValue::String(String::new()));
+    log
+}
+
+/// A handler of `event_type` on the node `node` logging what `entry` makes of the event
+pub fn add_logging_handler(engine: &mut RmlEngine, event_type: EventType, node: &str, entry: impl Fn(&RmlEngine) -> String + Send + Sync + 'static) {
+    let callback = engine.add_callback(move |engine| {
+        let entry = entry(engine);
+        log(engine, &entry);
+    });
+    engine.add_event_handler(event_type, node, callback);
+}
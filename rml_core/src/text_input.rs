@@ -0,0 +1,661 @@
+// TextInput and TextArea: an editable line of text, and an editable text of several lines
+//
+//     TextInput {
+//         id: name_field
+//         width: 200 height: 30
+//         placeholder: "Your name"
+//         max_length: 40
+//         on_accepted: { ... }
+//     }
+//
+//     TextArea {
+//         id: notes
+//         width: 300 height: 200
+//         wrap: true
+//     }
+//
+// An editable item gets the keyboard focus when it is pressed (or with Tab, see `focus`), then the
+// typed characters are inserted at the cursor. Left/Right/Home/End move the cursor, with shift they extend the
+// selection, as does a drag with the mouse. Backspace/Delete erase, ctrl+A selects all, ctrl+C/X/V copy, cut
+// and paste through the clipboard of the engine. In a TextInput, Enter emits the `accepted` signal.
+//
+// In a TextArea, Enter inserts a newline, Up/Down move the cursor from line to line (keeping its x), Home/End
+// go to the start and the end of the line, ctrl+Home/End to the start and the end of the text. With `wrap` (the
+// default) the lines are wrapped at the width of the item, otherwise they scroll horizontally. The text
+// scrolls vertically to keep the cursor visible, and with the mouse wheel.
+//
+// Its state is in properties: `text`, `cursor_position` and the selection `selection_start`..`selection_end`,
+// counted in characters. `max_length` limits the length of the text (0 for no limit), `read_only` forbids
+// editing. In a TextInput, `echo_mode: "password"` shows `password_character` in place of each character and
+// forbids copying.
+//
+// The text is laid out in lines with the renderer when the layout is updated (see `text_layout`), the mouse
+// uses its lines to place the cursor and the draw pass to draw the text, the selection and the cursor.
+
+use std::collections::HashMap;
+
+use macroquad::prelude::{Color, KeyCode, GRAY, WHITE};
+
+use crate::layout::Rect;
+use crate::renderer::{Renderer, TextStyle};
+use crate::repeater::add_missing_property;
+use crate::text_layout::{self, TextLayout};
+use crate::{AbstractValue, ItemTypeEnum, NodeId, RmlEngine};
+
+/// Lines scrolled for one step of the mouse wheel
+const WHEEL_LINES: f32 = 3.0;
+
+/// Layout and scrolling of an editable item
+#[derive(Debug, Default)]
+pub(crate) struct Edit {
+    /// displayed text, font, font size and wrap width the layout was made for
+    measured: Option<(String, String, f32, Option<f32>)>,
+    pub(crate) layout: TextLayout,
+    /// width scrolled out on the left and height scrolled out on the top, to keep the cursor visible
+    pub(crate) scroll: (f32, f32),
+    /// cursor position and size of the item the scroll was last adjusted to, the wheel scrolls freely until
+    /// one of them changes
+    scrolled_to: Option<(usize, f32, f32)>,
+    /// x kept by the cursor while it moves up and down
+    goal_x: Option<f32>,
+}
+
+impl Edit {
+    fn clamp_scroll(&mut self, rect: Rect, multiline: bool) {
+        // the cursor is one pixel wide
+        self.scroll.0 = self.scroll.0.min(self.layout.width() - rect.width + 1.0).max(0.0);
+        self.scroll.1 = if multiline { self.scroll.1.min(self.layout.height() - rect.height).max(0.0) } else { 0.0 };
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct TextInputs {
+    pub(crate) edits: HashMap<NodeId, Edit>,
+    /// the item whose selection follows the mouse, from a press to the release of the button
+    dragging: Option<NodeId>,
+}
+
+/// A TextInput or a TextArea
+pub(crate) fn is_editable(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.get_node(node_id).is_some_and(|node| matches!(node.node_type, ItemTypeEnum::TextInput | ItemTypeEnum::TextArea))
+}
+
+pub(crate) fn is_multiline(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.get_node(node_id).is_some_and(|node| node.node_type == ItemTypeEnum::TextArea)
+}
+
+/// Give its default properties to a TextInput or a TextArea node
+pub(crate) fn attach_node(engine: &mut RmlEngine, node_id: NodeId) {
+    // attached once, its edit state exists until it is removed
+    if engine.text_inputs.edits.contains_key(&node_id) {
+        return;
+    }
+    add_missing_property(engine, node_id, "text", AbstractValue::String(String::new()));
+    add_missing_property(engine, node_id, "placeholder", AbstractValue::String(String::new()));
+    add_missing_property(engine, node_id, "color", AbstractValue::Color(WHITE));
+    add_missing_property(engine, node_id, "placeholder_color", AbstractValue::Color(GRAY));
+    add_missing_property(engine, node_id, "selection_color", AbstractValue::Color(Color::new(0.2, 0.4, 0.9, 0.5)));
+    add_missing_property(engine, node_id, "font_size", AbstractValue::Number(20.0));
+    add_missing_property(engine, node_id, "max_length", AbstractValue::Number(0.0));
+    add_missing_property(engine, node_id, "read_only", AbstractValue::Bool(false));
+    add_missing_property(engine, node_id, "cursor_position", AbstractValue::Number(0.0));
+    add_missing_property(engine, node_id, "selection_start", AbstractValue::Number(0.0));
+    add_missing_property(engine, node_id, "selection_end", AbstractValue::Number(0.0));
+    add_missing_property(engine, node_id, "focusable", AbstractValue::Bool(true));
+    if is_multiline(engine, node_id) {
+        add_missing_property(engine, node_id, "wrap", AbstractValue::Bool(true));
+    } else {
+        add_missing_property(engine, node_id, "echo_mode", AbstractValue::String("normal".to_string()));
+        add_missing_property(engine, node_id, "password_character", AbstractValue::String("•".to_string()));
+        add_missing_property(engine, node_id, "accepted", AbstractValue::Null);
+    }
+    engine.text_inputs.edits.insert(node_id, Edit::default());
+
+    // a text set from outside keeps the cursor and the selection inside it
+    let Some(text) = engine.get_node(node_id).and_then(|node| node.get_property("text")) else { return };
+    let callback = engine.add_callback(move |engine| {
+        let (cursor, anchor) = cursor_and_anchor(engine, node_id);
+        set_cursor(engine, node_id, cursor, anchor);
+    });
+    engine.bind_property_to_callback(text, callback);
+}
+
+fn text(engine: &RmlEngine, node_id: NodeId) -> Vec<char> {
+    engine.get_string_property_of_node_id(node_id, "text", String::new()).chars().collect()
+}
+
+/// The text as it is drawn: a password shows a character in place of each character
+pub(crate) fn displayed_text(engine: &RmlEngine, node_id: NodeId) -> String {
+    let text = engine.get_string_property_of_node_id(node_id, "text", String::new());
+    if !is_password(engine, node_id) {
+        return text;
+    }
+    let echo = engine.get_string_property_of_node_id(node_id, "password_character", String::new());
+    echo.repeat(text.chars().count())
+}
+
+fn is_password(engine: &RmlEngine, node_id: NodeId) -> bool {
+    engine.get_string_property_of_node_id(node_id, "echo_mode", String::new()) == "password"
+}
+
+/// Position of the cursor and the other end of the selection, inside the text
+fn cursor_and_anchor(engine: &RmlEngine, node_id: NodeId) -> (usize, usize) {
+    let length = text(engine, node_id).len();
+    let index = |name: &str| (engine.get_number_property_of_node_id(node_id, name, 0.0).max(0.0) as usize).min(length);
+    let (cursor, start, end) = (index("cursor_position"), index("selection_start"), index("selection_end"));
+    let anchor = if start == end || (cursor != start && cursor != end) {
+        cursor
+    } else if cursor == start {
+        end
+    } else {
+        start
+    };
+    (cursor, anchor)
+}
+
+/// Selected characters, `start..end`
+pub(crate) fn selection(engine: &RmlEngine, node_id: NodeId) -> (usize, usize) {
+    let (cursor, anchor) = cursor_and_anchor(engine, node_id);
+    (cursor.min(anchor), cursor.max(anchor))
+}
+
+fn set_cursor(engine: &mut RmlEngine, node_id: NodeId, cursor: usize, anchor: usize) {
+    engine.set_property_of_node_id(node_id, "cursor_position", AbstractValue::Number(cursor as f32));
+    engine.set_property_of_node_id(node_id, "selection_start", AbstractValue::Number(cursor.min(anchor) as f32));
+    engine.set_property_of_node_id(node_id, "selection_end", AbstractValue::Number(cursor.max(anchor) as f32));
+    if let Some(edit) = engine.text_inputs.edits.get_mut(&node_id) {
+        edit.goal_x = None;
+    }
+}
+
+/// Replace the selection by `inserted`, cut to the `max_length` of the node. Only a TextArea keeps the newlines.
+fn replace_selection(engine: &mut RmlEngine, node_id: NodeId, inserted: &str) {
+    if engine.get_bool_property_of_node_id(node_id, "read_only", false) {
+        return;
+    }
+    let mut text = text(engine, node_id);
+    let (start, end) = selection(engine, node_id);
+    let max_length = engine.get_number_property_of_node_id(node_id, "max_length", 0.0).max(0.0) as usize;
+    let room = if max_length == 0 { usize::MAX } else { max_length.saturating_sub(text.len() - (end - start)) };
+    let multiline = is_multiline(engine, node_id);
+    let inserted: Vec<char> = inserted.chars()
+        .filter(|character| !character.is_control() || (multiline && *character == '\n'))
+        .take(room)
+        .collect();
+    if start == end && inserted.is_empty() {
+        return;
+    }
+    let cursor = start + inserted.len();
+    text.splice(start..end, inserted);
+    engine.set_property_of_node_id(node_id, "text", AbstractValue::String(text.into_iter().collect()));
+    set_cursor(engine, node_id, cursor, cursor);
+}
+
+/// Type `character` in the focused node `node_id`
+pub(crate) fn char_input(engine: &mut RmlEngine, node_id: NodeId, character: char) {
+    // ctrl+V also types a 'v' on some platforms
+    if !is_editable(engine, node_id) || is_control_down(engine) {
+        return;
+    }
+    replace_selection(engine, node_id, &character.to_string());
+}
+
+pub(crate) fn is_shift_down(engine: &RmlEngine) -> bool {
+    let events = &engine.event_manager;
+    events.is_key_down(KeyCode::LeftShift) || events.is_key_down(KeyCode::RightShift)
+}
+
+fn is_control_down(engine: &RmlEngine) -> bool {
+    let events = &engine.event_manager;
+    [KeyCode::LeftControl, KeyCode::RightControl, KeyCode::LeftSuper, KeyCode::RightSuper]
+        .into_iter()
+        .any(|key| events.is_key_down(key))
+}
+
+/// Start and end of the line holding `cursor`, the whole text for a TextInput
+fn line_bounds(engine: &RmlEngine, node_id: NodeId, cursor: usize, length: usize) -> (usize, usize) {
+    let edit = engine.text_inputs.edits.get(&node_id);
+    match edit.filter(|_| is_multiline(engine, node_id)) {
+        Some(edit) => {
+            let line = edit.layout.line_of(cursor);
+            let start = edit.layout.lines.get(line).map_or(0, |line| line.start);
+            (start.min(length), edit.layout.line_end(line).min(length))
+        }
+        None => (0, length),
+    }
+}
+
+/// Position one line above (`up`) or below `cursor` at the x the cursor keeps, and that x. Past the first and
+/// the last line, the cursor goes to the start and the end of the text.
+fn vertical_move(engine: &RmlEngine, node_id: NodeId, cursor: usize, length: usize, up: bool) -> (usize, Option<f32>) {
+    let Some(edit) = engine.text_inputs.edits.get(&node_id) else { return (cursor, None) };
+    let layout = &edit.layout;
+    let goal_x = edit.goal_x.unwrap_or_else(|| layout.point_of(cursor).0);
+    let line = layout.line_of(cursor);
+    let target = if up { line.checked_sub(1) } else { Some(line + 1).filter(|line| *line < layout.lines.len()) };
+    let position = match target {
+        Some(line) => layout.position_in_line(line, goal_x).min(length),
+        None if up => 0,
+        None => length,
+    };
+    (position, Some(goal_x))
+}
+
+/// Edit the focused node `node_id` with `key`
+pub(crate) fn key_pressed(engine: &mut RmlEngine, node_id: NodeId, key: KeyCode) {
+    if !is_editable(engine, node_id) {
+        return;
+    }
+    let length = text(engine, node_id).len();
+    let (cursor, anchor) = cursor_and_anchor(engine, node_id);
+    let (start, end) = (cursor.min(anchor), cursor.max(anchor));
+    let shift = is_shift_down(engine);
+    // where a key moves the cursor, the selection collapses unless shift is down
+    let move_to = |engine: &mut RmlEngine, position: usize| {
+        set_cursor(engine, node_id, position, if shift { anchor } else { position });
+    };
+
+    let control = is_control_down(engine);
+    let multiline = is_multiline(engine, node_id);
+    match key {
+        KeyCode::A if control => set_cursor(engine, node_id, length, 0),
+        // a password is not copied
+        KeyCode::C | KeyCode::X if control && start != end && !is_password(engine, node_id) => {
+            let selected: String = text(engine, node_id)[start..end].iter().collect();
+            engine.clipboard.set(&selected);
+            if key == KeyCode::X {
+                replace_selection(engine, node_id, "");
+            }
+        }
+        KeyCode::V if control => {
+            if let Some(pasted) = engine.clipboard.get() {
+                let pasted = if multiline {
+                    pasted.replace("\r\n", "\n").replace('\r', "\n")
+                } else {
+                    pasted.replace(['\r', '\n'], " ")
+                };
+                replace_selection(engine, node_id, &pasted);
+            }
+        }
+        KeyCode::Left if start != end && !shift => move_to(engine, start),
+        KeyCode::Right if start != end && !shift => move_to(engine, end),
+        KeyCode::Left => move_to(engine, cursor.saturating_sub(1)),
+        KeyCode::Right => move_to(engine, (cursor + 1).min(length)),
+        KeyCode::Up | KeyCode::Down if multiline => {
+            let (position, goal_x) = vertical_move(engine, node_id, cursor, length, key == KeyCode::Up);
+            move_to(engine, position);
+            if let Some(edit) = engine.text_inputs.edits.get_mut(&node_id) {
+                edit.goal_x = goal_x;
+            }
+        }
+        KeyCode::Home if control => move_to(engine, 0),
+        KeyCode::End if control => move_to(engine, length),
+        KeyCode::Home => move_to(engine, line_bounds(engine, node_id, cursor, length).0),
+        KeyCode::End => move_to(engine, line_bounds(engine, node_id, cursor, length).1),
+        KeyCode::Backspace | KeyCode::Delete if start != end => replace_selection(engine, node_id, ""),
+        KeyCode::Backspace if cursor > 0 => {
+            set_cursor(engine, node_id, cursor, cursor - 1);
+            replace_selection(engine, node_id, "");
+        }
+        KeyCode::Delete if cursor < length => {
+            set_cursor(engine, node_id, cursor, cursor + 1);
+            replace_selection(engine, node_id, "");
+        }
+        KeyCode::Enter | KeyCode::KpEnter if multiline => replace_selection(engine, node_id, "\n"),
+        KeyCode::Enter | KeyCode::KpEnter => engine.emit_node_signal(node_id, "accepted"),
+        _ => {}
+    }
+}
+
+/// Window point of the top left of the text of the editable item `node_id` in `rect`, once scrolled. The text of
+/// a TextInput is centered vertically.
+pub(crate) fn text_origin(engine: &RmlEngine, node_id: NodeId, rect: Rect) -> (f32, f32) {
+    let Some(edit) = engine.text_inputs.edits.get(&node_id) else { return (rect.x, rect.y) };
+    let top = if is_multiline(engine, node_id) { rect.y } else { rect.y + (rect.height - edit.layout.line_height) / 2.0 };
+    (rect.x - edit.scroll.0, top - edit.scroll.1)
+}
+
+/// Position in the text nearest to the window point (`x`, `y`) in the editable item `node_id`
+fn index_at(engine: &RmlEngine, node_id: NodeId, x: f32, y: f32) -> usize {
+    let Some(rect) = engine.layout.get(node_id) else { return 0 };
+    let Some(edit) = engine.text_inputs.edits.get(&node_id) else { return 0 };
+    let (left, top) = text_origin(engine, node_id, rect);
+    edit.layout.position_at(x - left, y - top).min(text(engine, node_id).len())
+}
+
+/// The topmost visible editable item at (`x`, `y`), the last drawn
+fn editable_at(engine: &RmlEngine, x: f32, y: f32) -> Option<NodeId> {
+    let root = engine.get_root_node_id()?;
+    engine.get_arena().subtree(root).into_iter().rev().find(|&node_id| {
+        is_editable(engine, node_id)
+            && engine.get_bool_property_of_node_id(node_id, "visible", true)
+            && engine.layout.get(node_id).is_some_and(|rect| rect.contains(x, y))
+            && engine.is_point_clipped_in(node_id, x, y)
+    })
+}
+
+/// Mouse press at (`x`, `y`): the editable item under the mouse takes the focus and the cursor, shift extends its
+/// selection. Return true if an item was pressed.
+pub(crate) fn press(engine: &mut RmlEngine, x: f32, y: f32) -> bool {
+    let Some(node_id) = editable_at(engine, x, y) else { return false };
+    engine.focus_node(Some(node_id));
+    let index = index_at(engine, node_id, x, y);
+    let (_, anchor) = cursor_and_anchor(engine, node_id);
+    set_cursor(engine, node_id, index, if is_shift_down(engine) { anchor } else { index });
+    engine.text_inputs.dragging = Some(node_id);
+    true
+}
+
+/// The mouse moved to (`x`, `y`) while the button is down, the selection of the pressed item follows it
+pub(crate) fn drag(engine: &mut RmlEngine, x: f32, y: f32) {
+    let Some(node_id) = engine.text_inputs.dragging else { return };
+    let (_, anchor) = cursor_and_anchor(engine, node_id);
+    let index = index_at(engine, node_id, x, y);
+    set_cursor(engine, node_id, index, anchor);
+}
+
+pub(crate) fn release(engine: &mut RmlEngine) {
+    engine.text_inputs.dragging = None;
+}
+
+/// Scroll the TextArea under the pointer by `delta_y` wheel steps, return true if there is one
+pub(crate) fn wheel(engine: &mut RmlEngine, x: f32, y: f32, delta_y: f32) -> bool {
+    let Some(node_id) = editable_at(engine, x, y).filter(|node_id| is_multiline(engine, *node_id)) else { return false };
+    let rect = engine.layout.get(node_id).unwrap_or_default();
+    let Some(edit) = engine.text_inputs.edits.get_mut(&node_id) else { return false };
+    edit.scroll.1 -= delta_y * WHEEL_LINES * edit.layout.line_height;
+    edit.clamp_scroll(rect, true);
+    true
+}
+
+pub(crate) fn text_style<'a>(engine: &'a RmlEngine, node_id: NodeId, color: Color) -> TextStyle<'a> {
+    let font = engine.get_string_property_of_node_id(node_id, "font", String::new());
+    let font_size = engine.get_number_property_of_node_id(node_id, "font_size", 20.0);
+    TextStyle { font: engine.get_font(&font), font_size: font_size as u16, color }
+}
+
+/// Height of a line of the editable item `node_id`: a TextInput takes the height of its font, the lines of a
+/// TextArea are spaced like the ones of a Text
+pub(crate) fn line_height(engine: &RmlEngine, renderer: &dyn Renderer, node_id: NodeId) -> f32 {
+    if is_multiline(engine, node_id) {
+        let font_size = engine.get_number_property_of_node_id(node_id, "font_size", 20.0);
+        text_layout::line_height(font_size)
+    } else {
+        renderer.measure_text("Mg", &text_style(engine, node_id, WHITE)).height
+    }
+}
+
+/// Lay out again the editable items whose text, font or wrap width changed, and scroll them to keep their cursor
+/// visible when it moved. Run after each layout update.
+pub(crate) fn measure(engine: &mut RmlEngine, renderer: &dyn Renderer) {
+    let nodes: Vec<NodeId> = engine.text_inputs.edits.keys().copied().collect();
+    for node_id in nodes {
+        let multiline = is_multiline(engine, node_id);
+        let rect = engine.layout.get(node_id).unwrap_or_default();
+        let displayed = displayed_text(engine, node_id);
+        let font = engine.get_string_property_of_node_id(node_id, "font", String::new());
+        let font_size = engine.get_number_property_of_node_id(node_id, "font_size", 20.0);
+        let wrap = multiline && engine.get_bool_property_of_node_id(node_id, "wrap", true);
+        let key = Some((displayed.clone(), font, font_size, wrap.then_some(rect.width)));
+        let layout = if engine.text_inputs.edits.get(&node_id).is_some_and(|edit| edit.measured == key) {
+            None
+        } else {
+            let style = text_style(engine, node_id, WHITE);
+            let line_height = line_height(engine, renderer, node_id);
+            let measure = |text: &str| renderer.measure_text(text, &style).width;
+            Some(TextLayout::new(&displayed, wrap.then_some(rect.width), line_height, measure))
+        };
+
+        let (cursor, _) = cursor_and_anchor(engine, node_id);
+        let Some(edit) = engine.text_inputs.edits.get_mut(&node_id) else { continue };
+        let laid_out = layout.is_some();
+        if let Some(layout) = layout {
+            edit.layout = layout;
+            edit.measured = key;
+        }
+        let target = Some((cursor, rect.width, rect.height));
+        if laid_out || edit.scrolled_to != target {
+            let (x, top) = edit.layout.point_of(cursor);
+            // the cursor is one pixel wide
+            if x - edit.scroll.0 > rect.width - 1.0 {
+                edit.scroll.0 = x - rect.width + 1.0;
+            }
+            if x < edit.scroll.0 {
+                edit.scroll.0 = x;
+            }
+            let bottom = top + edit.layout.line_height;
+            if bottom - edit.scroll.1 > rect.height {
+                edit.scroll.1 = bottom - rect.height;
+            }
+            if top < edit.scroll.1 {
+                edit.scroll.1 = top;
+            }
+            edit.scrolled_to = target;
+        }
+        edit.clamp_scroll(rect, multiline);
+    }
+}
+
+/// Drop the state of removed nodes
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId]) {
+    engine.text_inputs.edits.retain(|node_id, _| !removed.contains(node_id));
+    if engine.text_inputs.dragging.is_some_and(|node_id| removed.contains(&node_id)) {
+        engine.text_inputs.dragging = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_rml, Clipboard, TestDriver};
+    use macroquad::prelude::MouseButton;
+
+    // the recording renderer measures 10 pixels per character at font size 20
+    const SOURCE: &str = r#"
+        Node {
+            id: root
+            TextInput { id: field x: 10 y: 10 width: 100 height: 30 }
+            TextInput { id: pin x: 10 y: 50 width: 100 height: 30 max_length: 4 echo_mode: "password" }
+        }
+    "#;
+
+    fn driver() -> TestDriver {
+        TestDriver::new(load_rml(SOURCE).unwrap(), 300.0, 200.0)
+    }
+
+    fn text_of(driver: &TestDriver, node: &str) -> String {
+        driver.engine().get_string_property_of_node(node, "text", String::new())
+    }
+
+    fn number(driver: &TestDriver, node: &str, property: &str) -> f32 {
+        driver.engine().get_number_property_of_node(node, property, -1.0)
+    }
+
+    #[test]
+    fn test_typing_and_editing() {
+        let mut driver = driver();
+        let field = driver.engine().get_node_id("field").unwrap();
+        assert!(driver.click(field));
+        assert!(driver.engine().get_bool_property_of_node("field", "active_focus", false));
+        driver.type_text("héllo");
+        assert_eq!(text_of(&driver, "field"), "héllo");
+        assert_eq!(number(&driver, "field", "cursor_position"), 5.0);
+
+        driver.key(KeyCode::Left);
+        driver.key(KeyCode::Left);
+        driver.key(KeyCode::Backspace);
+        driver.type_text("L");
+        assert_eq!(text_of(&driver, "field"), "héLlo");
+        driver.key(KeyCode::Home);
+        driver.key(KeyCode::Delete);
+        assert_eq!(text_of(&driver, "field"), "éLlo");
+
+        // shift+End selects to the end, typing replaces the selection
+        driver.press_key(KeyCode::LeftShift);
+        driver.key(KeyCode::Right);
+        driver.key(KeyCode::End);
+        driver.release_key(KeyCode::LeftShift);
+        assert_eq!((number(&driver, "field", "selection_start"), number(&driver, "field", "selection_end")), (0.0, 4.0));
+        driver.type_text("ok");
+        assert_eq!(text_of(&driver, "field"), "ok");
+        assert_eq!(number(&driver, "field", "selection_start"), number(&driver, "field", "selection_end"));
+
+        // a text set from outside keeps the cursor inside it
+        driver.engine_mut().set_property_of_node("field", "text", AbstractValue::String("x".to_string()));
+        driver.frame();
+        assert_eq!(number(&driver, "field", "cursor_position"), 1.0);
+    }
+
+    #[test]
+    fn test_mouse_selection_and_clipboard() {
+        let mut driver = driver();
+        let clipboard = driver.clipboard().clone();
+        driver.engine_mut().set_property_of_node("field", "text", AbstractValue::String("abcdef".to_string()));
+        driver.frame();
+
+        // drag from the boundary after 'a' to the one after 'd'
+        driver.move_mouse(21.0, 20.0);
+        driver.press(MouseButton::Left);
+        driver.move_mouse(49.0, 20.0);
+        driver.release(MouseButton::Left);
+        assert_eq!((number(&driver, "field", "selection_start"), number(&driver, "field", "selection_end")), (1.0, 4.0));
+
+        driver.press_key(KeyCode::LeftControl);
+        driver.key(KeyCode::X);
+        assert_eq!(clipboard.get().as_deref(), Some("bcd"));
+        assert_eq!(text_of(&driver, "field"), "aef");
+        driver.key(KeyCode::End);
+        driver.key(KeyCode::V);
+        driver.key(KeyCode::A);
+        driver.release_key(KeyCode::LeftControl);
+        assert_eq!(text_of(&driver, "field"), "aefbcd");
+        assert_eq!((number(&driver, "field", "selection_start"), number(&driver, "field", "selection_end")), (0.0, 6.0));
+    }
+
+    #[test]
+    fn test_password_max_length_and_accepted() {
+        let mut driver = driver();
+        let accepted = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = accepted.clone();
+        driver.engine_mut().connect("pin", "accepted", move |_| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        });
+        let pin = driver.engine().get_node_id("pin").unwrap();
+        driver.click(pin);
+        assert!(!driver.engine().get_bool_property_of_node("field", "active_focus", true));
+        driver.type_text("123456");
+        assert_eq!(text_of(&driver, "pin"), "1234");
+        assert_eq!(displayed_text(driver.engine(), pin), "••••");
+        driver.key(KeyCode::Enter);
+        assert_eq!(accepted.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // the text is drawn with the password characters, the cursor after them
+        assert!(driver.renderer().commands.iter().any(|command| matches!(command,
+            crate::DrawCommand::Text { text, .. } if text == "••••")));
+    }
+
+    // lines of 10 characters at most, two of them visible
+    const AREA_SOURCE: &str = r#"
+        Node {
+            id: root
+            TextArea { id: area x: 10 y: 10 width: 100 height: 48 }
+            TextArea { id: plain x: 150 y: 10 width: 200 height: 100 wrap: false }
+        }
+    "#;
+
+    fn area_driver() -> TestDriver {
+        TestDriver::new(load_rml(AREA_SOURCE).unwrap(), 400.0, 200.0)
+    }
+
+    fn scroll_y(driver: &TestDriver, node: &str) -> f32 {
+        let node_id = driver.engine().get_node_id(node).unwrap();
+        driver.engine().text_inputs.edits[&node_id].scroll.1
+    }
+
+    #[test]
+    fn test_text_area_lines_and_scrolling() {
+        let mut driver = area_driver();
+        let area = driver.engine().get_node_id("area").unwrap();
+        driver.click(area);
+        // "one two " | "three four" | "x"
+        driver.type_text("one two three four");
+        driver.key(KeyCode::Enter);
+        driver.type_text("x");
+        assert_eq!(text_of(&driver, "area"), "one two three four\nx");
+        let lines: Vec<String> = driver.engine().text_inputs.edits[&area].layout.lines.iter().map(|line| line.text.clone()).collect();
+        assert_eq!(lines, ["one two ", "three four", "x"]);
+        assert_eq!(scroll_y(&driver, "area"), 24.0, "scrolled to the cursor on the third line");
+
+        // the cursor keeps its x from line to line, and stays before the end of a wrapped line
+        driver.key(KeyCode::Up);
+        assert_eq!(number(&driver, "area", "cursor_position"), 9.0);
+        driver.key(KeyCode::End);
+        assert_eq!(number(&driver, "area", "cursor_position"), 18.0);
+        driver.key(KeyCode::Up);
+        assert_eq!(number(&driver, "area", "cursor_position"), 7.0);
+        driver.key(KeyCode::Down);
+        assert_eq!(number(&driver, "area", "cursor_position"), 18.0);
+        driver.key(KeyCode::Home);
+        assert_eq!(number(&driver, "area", "cursor_position"), 8.0);
+        driver.press_key(KeyCode::LeftControl);
+        driver.key(KeyCode::Home);
+        driver.release_key(KeyCode::LeftControl);
+        assert_eq!(number(&driver, "area", "cursor_position"), 0.0);
+        assert_eq!(scroll_y(&driver, "area"), 0.0);
+
+        // the wheel scrolls until the cursor moves
+        driver.move_mouse(50.0, 30.0);
+        driver.wheel(0.0, -1.0);
+        driver.frame();
+        assert_eq!(scroll_y(&driver, "area"), 24.0);
+        driver.click_at(35.0, 40.0);
+        assert_eq!(number(&driver, "area", "cursor_position"), 20.0, "after the 'x' of the third line");
+        assert_eq!(scroll_y(&driver, "area"), 24.0);
+    }
+
+    #[test]
+    fn test_text_area_selection_and_paste() {
+        let mut driver = area_driver();
+        let clipboard = driver.clipboard().clone();
+        let plain = driver.engine().get_node_id("plain").unwrap();
+        driver.engine_mut().set_property_of_node("plain", "text", AbstractValue::String("ab\ncd\nef".to_string()));
+        driver.click(plain);
+
+        // from the 'b' down two lines
+        driver.press_key(KeyCode::LeftControl);
+        driver.key(KeyCode::Home);
+        driver.release_key(KeyCode::LeftControl);
+        driver.key(KeyCode::Right);
+        driver.press_key(KeyCode::LeftShift);
+        driver.key(KeyCode::Down);
+        driver.key(KeyCode::Down);
+        driver.release_key(KeyCode::LeftShift);
+        assert_eq!(selection(driver.engine(), plain), (1, 7));
+
+        // a rect per line, the selected newlines show after their line
+        let selection_color = Color::new(0.2, 0.4, 0.9, 0.5);
+        let rects: Vec<(f32, f32, f32)> = driver.renderer().commands.iter().filter_map(|command| match command {
+            crate::DrawCommand::Rect { x, y, width, color, .. } if *color == selection_color => Some((*x, *y, *width)),
+            _ => None,
+        }).collect();
+        assert_eq!(rects, [(160.0, 10.0, 16.0), (150.0, 34.0, 26.0), (150.0, 58.0, 10.0)]);
+        let texts: Vec<(String, f32)> = driver.renderer().commands.iter().filter_map(|command| match command {
+            crate::DrawCommand::Text { text, y, .. } => Some((text.clone(), *y)),
+            _ => None,
+        }).collect();
+        assert_eq!(texts, [("ab".to_string(), 26.0), ("cd".to_string(), 50.0), ("ef".to_string(), 74.0)]);
+
+        // the newlines are copied and pasted
+        driver.press_key(KeyCode::LeftControl);
+        driver.key(KeyCode::C);
+        assert_eq!(clipboard.get().as_deref(), Some("b\ncd\ne"));
+        driver.key(KeyCode::End);
+        driver.release_key(KeyCode::LeftControl);
+        let mut clipboard = clipboard;
+        clipboard.set("1\r\n2");
+        driver.press_key(KeyCode::LeftControl);
+        driver.key(KeyCode::V);
+        driver.release_key(KeyCode::LeftControl);
+        assert_eq!(text_of(&driver, "plain"), "ab\ncd\nef1\n2");
+    }
+}
@@ -0,0 +1,182 @@
+// Line layout of a text: where it breaks and where each character is
+//
+// A text is cut into lines at its newlines, then, when a width is given, each paragraph is wrapped: a line ends
+// after the last whitespace that fits (the whitespace hangs at the end of the line), and a word wider than the
+// whole width is cut between two characters. The positions in the text are counted in characters; a line holds
+// the characters `start..end`, without the newline ending it.
+//
+// The Text item draws its lines, the TextInput and the TextArea also use them to place the cursor, to map the
+// mouse to a position and to move the cursor from a line to the next.
+
+/// Space between two baselines, relative to the font size
+pub const LINE_SPACING: f32 = 1.2;
+
+pub fn line_height(font_size: f32) -> f32 {
+    font_size * LINE_SPACING
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    /// the characters `start..end` of the text
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    /// x of the boundary before each character and after the last one, from the left of the line
+    pub offsets: Vec<f32>,
+}
+
+impl Line {
+    pub fn width(&self) -> f32 {
+        self.offsets.last().copied().unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextLayout {
+    pub lines: Vec<Line>,
+    pub line_height: f32,
+}
+
+impl TextLayout {
+    /// Lay out `text` in lines of `line_height`, wrapped at `max_width` if given.
+    /// `measure` returns the width of a string.
+    pub fn new(text: &str, max_width: Option<f32>, line_height: f32, measure: impl Fn(&str) -> f32) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let width = |start: usize, end: usize| measure(&chars[start..end].iter().collect::<String>());
+        let mut lines = Vec::new();
+        let mut paragraph_start = 0;
+        loop {
+            let paragraph_end = chars[paragraph_start..].iter().position(|c| *c == '\n')
+                .map_or(chars.len(), |newline| paragraph_start + newline);
+            let mut start = paragraph_start;
+            loop {
+                let end = match max_width {
+                    Some(max_width) => wrap(&chars, start, paragraph_end, max_width, &width),
+                    None => paragraph_end,
+                };
+                let offsets = (start..=end).map(|boundary| width(start, boundary)).collect();
+                lines.push(Line { start, end, text: chars[start..end].iter().collect(), offsets });
+                start = end;
+                if start >= paragraph_end {
+                    break;
+                }
+            }
+            if paragraph_end == chars.len() {
+                break;
+            }
+            paragraph_start = paragraph_end + 1;
+        }
+        Self { lines, line_height }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.lines.iter().map(Line::width).fold(0.0, f32::max)
+    }
+
+    pub fn height(&self) -> f32 {
+        self.lines.len() as f32 * self.line_height
+    }
+
+    /// Index of the line holding the position `position`. A position between two wrapped lines is at the start
+    /// of the second one.
+    pub fn line_of(&self, position: usize) -> usize {
+        self.lines.iter().rposition(|line| line.start <= position).unwrap_or(0)
+    }
+
+    /// Point of the position `position`: its x and the top of its line
+    pub fn point_of(&self, position: usize) -> (f32, f32) {
+        let index = self.line_of(position);
+        let Some(line) = self.lines.get(index) else { return (0.0, 0.0) };
+        let column = position.saturating_sub(line.start).min(line.offsets.len().saturating_sub(1));
+        (line.offsets.get(column).copied().unwrap_or(0.0), index as f32 * self.line_height)
+    }
+
+    /// Position nearest to the point (`x`, `y`), relative to the top left of the text
+    pub fn position_at(&self, x: f32, y: f32) -> usize {
+        let index = ((y / self.line_height).max(0.0) as usize).min(self.lines.len().saturating_sub(1));
+        self.position_in_line(index, x)
+    }
+
+    /// Position nearest to `x` in the line `index`
+    pub fn position_in_line(&self, index: usize, x: f32) -> usize {
+        let Some(line) = self.lines.get(index) else { return 0 };
+        let column = line.offsets.iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| (*a - x).abs().total_cmp(&(*b - x).abs()))
+            .map_or(0, |(column, _)| column);
+        (line.start + column).min(self.line_end(index))
+    }
+
+    /// Last position of the line `index`: the end of a wrapped line is the start of the next one, it stays
+    /// before it
+    pub fn line_end(&self, index: usize) -> usize {
+        let Some(line) = self.lines.get(index) else { return 0 };
+        let wrapped = self.lines.get(index + 1).is_some_and(|next| next.start == line.end);
+        if wrapped { line.end.saturating_sub(1).max(line.start) } else { line.end }
+    }
+}
+
+/// End of the line starting at `start` in the paragraph ending at `paragraph_end`
+fn wrap(chars: &[char], start: usize, paragraph_end: usize, max_width: f32, width: &impl Fn(usize, usize) -> f32) -> usize {
+    let mut end = start;
+    let mut last_break = None;
+    while end < paragraph_end {
+        // the whitespace hangs at the end of the line, it never makes it too wide
+        if !chars[end].is_whitespace() && width(start, end + 1) > max_width {
+            break;
+        }
+        end += 1;
+        if chars[end - 1].is_whitespace() && (end == paragraph_end || !chars[end].is_whitespace()) {
+            last_break = Some(end);
+        }
+    }
+    if end == paragraph_end {
+        return end;
+    }
+    // a word wider than the line is cut, with at least one character per line
+    last_break.unwrap_or(end.max(start + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 pixels per character
+    fn lay_out(text: &str, max_width: Option<f32>) -> TextLayout {
+        TextLayout::new(text, max_width, 20.0, |text| text.chars().count() as f32 * 10.0)
+    }
+
+    fn texts(layout: &TextLayout) -> Vec<&str> {
+        layout.lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_wrapping() {
+        assert_eq!(texts(&lay_out("the quick brown fox", Some(100.0))), ["the quick ", "brown fox"]);
+        assert_eq!(texts(&lay_out("a\n\nb c", Some(100.0))), ["a", "", "b c"]);
+        assert_eq!(texts(&lay_out("abcdefghijklmnop xy", Some(50.0))), ["abcde", "fghij", "klmno", "p xy"]);
+        assert_eq!(texts(&lay_out("no wrap at all", None)), ["no wrap at all"]);
+        assert_eq!(texts(&lay_out("", Some(50.0))), [""]);
+        assert_eq!(lay_out("ab\ncdef", None).width(), 40.0);
+    }
+
+    #[test]
+    fn test_positions() {
+        // "the quick " | "brown fox"
+        let layout = lay_out("the quick brown fox", Some(100.0));
+        assert_eq!(layout.line_of(9), 0);
+        assert_eq!(layout.line_of(10), 1);
+        assert_eq!(layout.point_of(12), (20.0, 20.0));
+        assert_eq!(layout.position_at(22.0, 25.0), 12);
+        assert_eq!(layout.position_at(500.0, 5.0), 9, "before the start of the next line");
+        assert_eq!(layout.position_at(500.0, 500.0), 19);
+        assert_eq!(layout.line_end(0), 9);
+
+        // the newline ends its line
+        let layout = lay_out("ab\ncd", None);
+        assert_eq!(layout.point_of(2), (20.0, 0.0));
+        assert_eq!(layout.point_of(3), (0.0, 20.0));
+        assert_eq!(layout.position_in_line(0, 100.0), 2);
+        assert_eq!(layout.line_end(0), 2);
+    }
+}
@@ -0,0 +1,206 @@
+// Timers: code run on a schedule
+//
+//     Timer {
+//         id: autosave
+//         interval: 2000
+//         running: true
+//         repeat: true
+//         on_triggered: { ... }
+//     }
+//
+// A Timer emits its `triggered` signal every `interval` milliseconds while `running` is true, once if `repeat` is
+// false (then `running` goes back to false), and also when it starts if `triggered_on_start` is true. Setting
+// `running` or `interval` restarts the count. The timers advance with the clock of the engine, like the
+// animations: a `ManualClock` runs them deterministically in the tests.
+//
+// A timer fires at most once per `RmlEngine::advance_time`, an interval shorter than a frame fires every frame.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::repeater::add_missing_property;
+use crate::{AbstractValue, ItemTypeEnum, NodeId, RmlEngine};
+
+/// The running timers and the milliseconds elapsed since they started or last fired
+#[derive(Default)]
+pub(crate) struct Timers {
+    elapsed: HashMap<NodeId, f32>,
+    /// timer nodes whose `running` and `interval` properties are followed
+    nodes: HashSet<NodeId>,
+}
+
+pub(crate) fn is_timer_node(node_type: &ItemTypeEnum) -> bool {
+    *node_type == ItemTypeEnum::Timer
+}
+
+/// Give its default properties to a Timer node and follow its `running` and `interval` properties
+pub(crate) fn attach_node(engine: &mut RmlEngine, node_id: NodeId) {
+    if !engine.timers.nodes.insert(node_id) {
+        return;
+    }
+    add_missing_property(engine, node_id, "interval", AbstractValue::Number(1000.0));
+    add_missing_property(engine, node_id, "running", AbstractValue::Bool(false));
+    add_missing_property(engine, node_id, "repeat", AbstractValue::Bool(false));
+    add_missing_property(engine, node_id, "triggered_on_start", AbstractValue::Bool(false));
+    add_missing_property(engine, node_id, "triggered", AbstractValue::Null);
+    let Some(node) = engine.get_node(node_id) else { return };
+    let (Some(running), Some(interval)) = (node.get_property("running"), node.get_property("interval")) else { return };
+
+    let callback = engine.add_callback(move |engine| run_node(engine, node_id));
+    engine.bind_property_to_callback(running, callback);
+    let callback_interval = engine.add_callback(move |engine| {
+        if let Some(elapsed) = engine.timers.elapsed.get_mut(&node_id) {
+            *elapsed = 0.0;
+        }
+    });
+    engine.bind_property_to_callback(interval, callback_interval);
+    // a timer declared `running: true` starts once its bindings are evaluated
+    engine.callbacks_to_eval.push(callback);
+}
+
+/// Start or stop the timer `node_id` according to its `running` property
+fn run_node(engine: &mut RmlEngine, node_id: NodeId) {
+    if !engine.get_bool_property_of_node_id(node_id, "running", false) {
+        engine.timers.elapsed.remove(&node_id);
+        return;
+    }
+    if engine.timers.elapsed.insert(node_id, 0.0).is_none() && engine.get_bool_property_of_node_id(node_id, "triggered_on_start", false) {
+        engine.emit_node_signal(node_id, "triggered");
+    }
+}
+
+/// Move the running timers `dt` milliseconds forward and fire the ones whose interval is over
+pub(crate) fn tick(engine: &mut RmlEngine, dt: f32) {
+    if engine.timers.elapsed.is_empty() {
+        return;
+    }
+    let mut fired = Vec::new();
+    let mut nodes: Vec<NodeId> = engine.timers.elapsed.keys().copied().collect();
+    // the timers due in the same frame fire in a stable order
+    nodes.sort();
+    for node_id in nodes {
+        let interval = engine.get_number_property_of_node_id(node_id, "interval", 1000.0).max(0.0);
+        let Some(elapsed) = engine.timers.elapsed.get_mut(&node_id) else { continue };
+        *elapsed += dt;
+        if *elapsed >= interval {
+            *elapsed = if interval > 0.0 { *elapsed % interval } else { 0.0 };
+            fired.push(node_id);
+        }
+    }
+    for node_id in fired {
+        if !engine.get_bool_property_of_node_id(node_id, "repeat", false) {
+            engine.timers.elapsed.remove(&node_id);
+            engine.set_property_of_node_id(node_id, "running", AbstractValue::Bool(false));
+        }
+        engine.emit_node_signal(node_id, "triggered");
+    }
+}
+
+/// Drop the timers of removed nodes
+pub(crate) fn forget(engine: &mut RmlEngine, removed: &[NodeId]) {
+    engine.timers.elapsed.retain(|node_id, _| !removed.contains(node_id));
+    engine.timers.nodes.retain(|node_id| !removed.contains(node_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{add_node, sample_engine};
+    use crate::{load_rml, ManualClock};
+
+    /// A Timer in the sample engine, with a Rust callback counting its triggers in the `count` property of root
+    fn engine_with_timer(properties: &[(&str, AbstractValue)]) -> (RmlEngine, ManualClock) {
+        let mut engine = sample_engine();
+        add_node(&mut engine, Some("root"), "timer", ItemTypeEnum::Timer, properties);
+        let count = engine.add_property(crate::Property::new(AbstractValue::Number(0.0)));
+        let root = engine.get_node_id("root").unwrap();
+        engine.add_property_to_node(root, "count".to_string(), count);
+        engine.connect("timer", "triggered", |engine| {
+            let count = engine.get_number_property_of_node("root", "count", 0.0);
+            engine.set_property_of_node("root", "count", AbstractValue::Number(count + 1.0));
+        });
+        let clock = ManualClock::new();
+        engine.set_clock(clock.clone());
+        engine.update_time();
+        engine.run_callbacks();
+        (engine, clock)
+    }
+
+    fn step(engine: &mut RmlEngine, clock: &ManualClock, seconds: f64) -> f32 {
+        clock.advance(seconds);
+        engine.update_time();
+        engine.run_callbacks();
+        engine.run_callbacks();
+        engine.get_number_property_of_node("root", "count", 0.0)
+    }
+
+    #[test]
+    fn test_single_shot_timer() {
+        let (mut engine, clock) = engine_with_timer(&[
+            ("interval", AbstractValue::Number(500.0)),
+            ("running", AbstractValue::Bool(true)),
+        ]);
+        assert_eq!(step(&mut engine, &clock, 0.375), 0.0);
+        assert_eq!(step(&mut engine, &clock, 0.125), 1.0);
+        assert!(!engine.get_bool_property_of_node("timer", "running", true));
+        assert_eq!(step(&mut engine, &clock, 2.0), 1.0);
+
+        // restarted from Rust
+        engine.set_property_of_node("timer", "running", AbstractValue::Bool(true));
+        engine.run_callbacks();
+        assert_eq!(step(&mut engine, &clock, 0.5), 2.0);
+    }
+
+    #[test]
+    fn test_repeating_timer() {
+        let (mut engine, clock) = engine_with_timer(&[
+            ("interval", AbstractValue::Number(250.0)),
+            ("running", AbstractValue::Bool(true)),
+            ("repeat", AbstractValue::Bool(true)),
+            ("triggered_on_start", AbstractValue::Bool(true)),
+        ]);
+        assert_eq!(step(&mut engine, &clock, 0.0), 1.0, "triggered on start");
+        assert_eq!(step(&mut engine, &clock, 0.375), 2.0);
+        assert_eq!(step(&mut engine, &clock, 0.125), 3.0, "the extra 125ms count for the next interval");
+        assert_eq!(step(&mut engine, &clock, 0.125), 3.0);
+
+        // a new interval restarts the count
+        engine.set_property_of_node("timer", "interval", AbstractValue::Number(500.0));
+        engine.run_callbacks();
+        assert_eq!(step(&mut engine, &clock, 0.25), 3.0);
+        assert_eq!(step(&mut engine, &clock, 0.25), 4.0);
+
+        engine.set_property_of_node("timer", "running", AbstractValue::Bool(false));
+        engine.run_callbacks();
+        assert_eq!(step(&mut engine, &clock, 1.0), 4.0);
+    }
+
+    #[test]
+    fn test_timer_from_rml() {
+        let mut engine = load_rml(r#"
+            Node {
+                id: root
+                Timer { id: toast interval: 3000 running: true }
+                Rectangle { id: panel width: 10 height: 10 }
+            }
+        "#).unwrap();
+        let clock = ManualClock::new();
+        engine.set_clock(clock.clone());
+        engine.update_time();
+        engine.run_callbacks();
+        engine.connect("toast", "triggered", |engine| {
+            engine.set_property_of_node("panel", "width", AbstractValue::Number(0.0));
+        });
+        clock.advance(3.0);
+        engine.update_time();
+        engine.run_callbacks();
+        assert_eq!(engine.get_number_property_of_node("panel", "width", -1.0), 0.0);
+
+        // a destroyed timer is dropped
+        let toast = engine.get_node_id("toast").unwrap();
+        engine.set_property_of_node("toast", "running", AbstractValue::Bool(true));
+        engine.run_callbacks();
+        assert!(engine.timers.elapsed.contains_key(&toast));
+        engine.destroy(toast);
+        assert!(engine.timers.elapsed.is_empty());
+    }
+}
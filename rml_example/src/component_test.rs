@@ -105,6 +105,9 @@ async fn main() {
 
     set_default_filter_mode(FilterMode::Linear);
 
+    // dev mode: edits of components/*.rml are applied without rebuilding
+    engine.enable_hot_reload();
+
     loop {
         engine.process_events();
         clear_background(DARKGRAY);
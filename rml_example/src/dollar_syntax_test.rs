@@ -142,7 +142,7 @@ async fn main() {
     loop {
         engine.process_events();
         clear_background(LIGHTGRAY);
-        rml_core::draw::draw_childs(&mut engine, "root", (0., 0.));
+        rml_core::draw::draw_childs(&mut engine, "root");
         next_frame().await
     }
 }
\ No newline at end of file
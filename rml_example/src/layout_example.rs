@@ -0,0 +1,106 @@
+// Example demonstrating the Row, Column and Grid positioners, and anchors to other nodes
+
+use rml_core::prelude::*;
+use rml_macros::rml;
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "RML Layout Example".to_owned(),
+        window_width: 600,
+        window_height: 400,
+        window_resizable: true,
+        fullscreen: false,
+        platform: miniquad::conf::Platform {
+            linux_backend: miniquad::conf::LinuxBackend::WaylandOnly,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
+async fn main() {
+    let mut engine = rml!(
+        Node {
+            id: root
+            anchors: fill
+            color color: { DARKGRAY }
+
+            Column {
+                id: form
+                anchors: fill
+                margins: 20
+                spacing: 10
+
+                // A form row: the label keeps its text size, the field takes the remaining width
+                Row {
+                    spacing: 10
+                    alignment: vertical_center
+                    layout.fill_width: true
+
+                    Text {
+                        text: "Name"
+                        font_size: 20
+                    }
+                    Rectangle {
+                        height: 30
+                        layout.fill_width: true
+                        color color: { WHITE }
+                    }
+                }
+
+                Row {
+                    spacing: 10
+                    alignment: vertical_center
+                    layout.fill_width: true
+
+                    Text {
+                        text: "A much longer label"
+                        font_size: 20
+                    }
+                    Rectangle {
+                        height: 30
+                        layout.fill_width: true
+                        color color: { WHITE }
+                    }
+                }
+
+                // A grid of swatches, the last one fills the rest of the window
+                Grid {
+                    columns: 3
+                    spacing: 5
+                    padding: 5
+
+                    Rectangle { width: 40 height: 40 color color: { RED } }
+                    Rectangle { width: 40 height: 40 color color: { GREEN } }
+                    Rectangle { width: 40 height: 40 color color: { BLUE } }
+                    Rectangle { width: 40 height: 20 layout.alignment: vertical_center color color: { YELLOW } }
+                    Rectangle { width: 40 height: 40 color color: { ORANGE } }
+                }
+
+                Rectangle {
+                    id: swatches_end
+                    layout.fill_width: true
+                    layout.fill_height: true
+                    color color: { GRAY }
+                }
+            }
+
+            // Anchored to a node that is not its parent
+            Text {
+                text: "Fills the remaining space"
+                font_size: 16
+                anchors.top: swatches_end.top
+                anchors.horizontal_center: swatches_end.horizontal_center
+                top_margin: 10
+            }
+        }
+    );
+
+    loop {
+        engine.process_events();
+        clear_background(BLACK);
+        rml_core::draw::draw_root(&mut engine);
+        next_frame().await
+    }
+}
@@ -0,0 +1,76 @@
+// Example demonstrating a ListView: ten thousand log lines, only the visible ones are instantiated
+
+use rml_core::prelude::*;
+use rml_macros::rml;
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "RML Log Viewer Example".to_owned(),
+        window_width: 600,
+        window_height: 400,
+        window_resizable: true,
+        fullscreen: false,
+        platform: miniquad::conf::Platform {
+            linux_backend: miniquad::conf::LinuxBackend::WaylandOnly,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
+async fn main() {
+    let mut engine = rml!(
+        Node {
+            id: root
+            anchors: fill
+            color color: { DARKGRAY }
+
+            ListView {
+                id: logs
+                anchors: fill
+                margins: 20
+                spacing: 2
+
+                Rectangle {
+                    id: line
+                    height: 24
+                    color color: { if $.line.is_current { Color::new(0.3, 0.5, 0.8, 1.0) } else { Color::new(0.2, 0.2, 0.25, 1.0) } }
+
+                    Text {
+                        anchors: left | vertical_center
+                        left_margin: 8
+                        text: { $.line.model_data }
+                        color color: { WHITE }
+                        number font_size: 16
+                    }
+
+                    MouseArea {
+                        anchors: fill
+                        on_click: {
+                            let index = $.line.index;
+                            $.logs.current_index = index;
+                        }
+                    }
+                }
+            }
+        }
+    );
+
+    let lines = (0..10_000)
+        .map(|index| AbstractValue::String(format!("[{:05}] request handled in {} ms", index, index % 97)))
+        .collect();
+    engine.set_property_of_node("logs", "model", AbstractValue::Array(lines));
+
+    // jump to the end of the log, like a tail
+    if let Some(logs) = engine.get_node_id("logs") {
+        engine.position_view_at_index(logs, 9_999, PositionMode::End);
+    }
+
+    loop {
+        engine.process_events();
+        clear_background(DARKGRAY);
+        rml_core::draw::draw_root(&mut engine);
+        next_frame().await
+    }
+}
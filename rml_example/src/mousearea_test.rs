@@ -258,7 +258,7 @@ async fn main() {
     loop {
         let _events = engine.process_events();
         clear_background(BLACK);
-        rml_core::draw::draw_childs(&mut engine, "root", (0., 0.));
+        rml_core::draw::draw_childs(&mut engine, "root");
         next_frame().await;
     }
 }
\ No newline at end of file
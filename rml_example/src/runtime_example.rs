@@ -0,0 +1,45 @@
+// Example demonstrating the runtime loader: the UI comes from runtime.rml, the logic from Rust
+
+use rml_core::prelude::*;
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "RML Runtime Loader Example".to_owned(),
+        window_width: 600,
+        window_height: 400,
+        window_resizable: true,
+        fullscreen: false,
+        platform: miniquad::conf::Platform {
+            linux_backend: miniquad::conf::LinuxBackend::WaylandOnly,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
+async fn main() {
+    let mut engine = match load_rml_file("runtime.rml") {
+        Ok(engine) => engine,
+        Err(error) => panic!("Failed to load runtime.rml: {}", error),
+    };
+
+    // handlers can't be written in the .rml file, they are attached by node id
+    let click = engine.add_callback(|engine| {
+        engine.emit("counter_btn", "clicked");
+    });
+    engine.add_event_handler(EventType::Click, "counter_area", click);
+
+    engine.connect("counter_btn", "clicked", |engine| {
+        let count = engine.get_number_property_of_node("title", "count", 0.0) + 1.0;
+        engine.set_property_of_node("title", "text", AbstractValue::String(format!("Clicked {} times", count)));
+        engine.set_property_of_node("title", "count", AbstractValue::Number(count));
+    });
+
+    loop {
+        engine.process_events();
+        clear_background(BLACK);
+        rml_core::draw::draw_root(&mut engine);
+        next_frame().await
+    }
+}
@@ -0,0 +1,129 @@
+// Example demonstrating nodes created and destroyed at runtime: a to-do list
+
+use rml_core::prelude::*;
+use rml_macros::{rml, rml_component};
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "RML To-do Example".to_owned(),
+        window_width: 400,
+        window_height: 500,
+        window_resizable: true,
+        fullscreen: false,
+        platform: miniquad::conf::Platform {
+            linux_backend: miniquad::conf::LinuxBackend::WaylandOnly,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
+async fn main() {
+    // each instance has its own todo_item, the callbacks address the instance they belong to
+    let todo_item = rml_component!(
+        Rectangle {
+            id: todo_item
+            width: 360
+            height: 36
+            string title: ""
+            color color: { Color::new(0.3, 0.3, 0.4, 1.0) }
+
+            Text {
+                anchors: left | vertical_center
+                margins: 10
+                text: { $.todo_item.title }
+                color color: { WHITE }
+                number font_size: 18
+            }
+
+            MouseArea {
+                anchors: fill
+                on_click: {
+                    // done: the item removes itself
+                    if let Some(item) = engine.get_node_id("todo_item") {
+                        engine.destroy(item);
+                    }
+                }
+            }
+        }
+    );
+
+    let mut engine = rml!(
+        Node {
+            id: root
+            anchors: fill
+            number count: 0
+
+            Rectangle {
+                x: 20
+                y: 20
+                width: 250
+                height: 40
+                color color: { Color::new(0.2, 0.2, 0.25, 1.0) }
+
+                TextInput {
+                    id: title_field
+                    anchors: fill
+                    left_margin: 10
+                    right_margin: 10
+                    placeholder: "New task"
+                    max_length: 40
+                }
+            }
+
+            Rectangle {
+                id: add_button
+                x: 280
+                y: 20
+                width: 100
+                height: 40
+                // lighter when Tab gives it the focus, Space or Enter clicks it
+                color color: { if $.add_area.active_focus { Color::new(0.5, 0.95, 0.75, 1.0) } else { Color::new(0.3, 0.8, 0.6, 1.0) } }
+
+                Text {
+                    anchors: center
+                    text: "Add"
+                    color color: { WHITE }
+                    number font_size: 18
+                }
+
+                MouseArea {
+                    id: add_area
+                    anchors: fill
+                    focusable: true
+                }
+            }
+
+            Column {
+                id: todo_list
+                x: 20
+                y: 80
+                spacing: 6
+            }
+        }
+    );
+
+    // the handler owns the component, it is attached from Rust
+    let add = engine.add_callback(move |engine| {
+        let count = engine.get_number_property_of_node("root", "count", 0.0) + 1.0;
+        engine.set_property_of_node("root", "count", AbstractValue::Number(count));
+        let typed = engine.get_string_property_of_node("title_field", "text", String::new());
+        let title = if typed.is_empty() { format!("Task {}", count) } else { typed };
+        engine.set_property_of_node("title_field", "text", AbstractValue::String(String::new()));
+        if let Some(list) = engine.get_node_id("todo_list") {
+            engine.instantiate(&todo_item, list, &[("title", AbstractValue::String(title))]);
+        }
+    });
+    engine.add_event_handler(EventType::Click, "add_area", add);
+    // Enter in the field adds the task too
+    engine.bind_node_property_to_callback("title_field", "accepted", add);
+    engine.set_focused_node("title_field");
+
+    loop {
+        engine.process_events();
+        clear_background(DARKGRAY);
+        rml_core::draw::draw_root(&mut engine);
+        next_frame().await
+    }
+}
@@ -1,6 +1,12 @@
 
-use std::{collections::HashMap, process::{Command, Stdio}};
-
+use std::collections::{HashMap, HashSet};
+
+use proc_macro2::{Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned};
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Expr, Pat, Token};
 use rml_core::AbstractValue;
 
 pub fn format_code_for_binding_extraction(code: &str) -> String {
@@ -18,123 +24,352 @@ pub fn format_code_for_binding_extraction(code: &str) -> String {
     code.to_string()
 }
 
-pub fn format_code(code: &str) -> String {
-    let mut rustfmt = Command::new("rustfmt")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("Failed to run rustfmt");
+/// Message of an invalid compound assignment on a property, if any
+fn compound_assignment_error(abstract_value: &AbstractValue, operator: &str, node_id: &str, property: &str) -> Option<String> {
+    match abstract_value {
+        AbstractValue::String(_) if operator != "+" => {
+            Some(format!("Invalid operator '{}' for string property '{}.{}'. Only '+=' is allowed for strings.", operator, node_id, property))
+        },
+        AbstractValue::Bool(_) => {
+            Some(format!("Compound assignments are not supported for boolean properties '{}.{}'.", node_id, property))
+        },
+        AbstractValue::Color(_) => {
+            Some(format!("Compound assignments are not supported for color properties '{}.{}'.", node_id, property))
+        },
+        _ => None,
+    }
+}
+
+/// Check every `$.node.prop` of the tokens against the properties mapping, so that the errors point at the reference itself.
+/// `rename` is the id replacement applied to the tokens of a component file before its transformation.
+pub fn check_dollar_references(tokens: TokenStream, properties_mapping: &HashMap<String, AbstractValue>, rename: Option<(&str, &str)>) -> syn::Result<()> {
+    DollarTransform { properties_mapping, rename }.transform(tokens).map(|_| ())
+}
+
+/// Replace the `$.node.prop` sugar with the engine accessors:
+/// - `$.node.prop = value;` becomes `set_*!(engine, node, prop, value);`
+/// - `$.node.prop op= value;` becomes `set_*!(engine, node, prop, get_*!(engine, node, prop) op (value));`
+/// - any other `$.node.prop` becomes `get_*!(engine, node, prop)`
+///
+/// The token trees are walked, so string literals are left alone and nested blocks are handled at any depth.
+pub fn transform_dollar_syntax(tokens: TokenStream, properties_mapping: &HashMap<String, AbstractValue>) -> syn::Result<TokenStream> {
+    DollarTransform { properties_mapping, rename: None }.transform(tokens)
+}
+
+struct DollarTransform<'a> {
+    properties_mapping: &'a HashMap<String, AbstractValue>,
+    rename: Option<(&'a str, &'a str)>,
+}
+
+impl DollarTransform<'_> {
+    fn transform(&self, tokens: TokenStream) -> syn::Result<TokenStream> {
+        let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+        let mut result = TokenStream::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let (node_id, property) = match &tokens[i..] {
+                [TokenTree::Punct(dollar), TokenTree::Punct(dot), TokenTree::Ident(node_id), TokenTree::Punct(second_dot), TokenTree::Ident(property), ..]
+                    if dollar.as_char() == '$' && dot.as_char() == '.' && second_dot.as_char() == '.' => (node_id.clone(), property.clone()),
+                [TokenTree::Group(group), ..] => {
+                    let mut transformed = Group::new(group.delimiter(), self.transform(group.stream())?);
+                    transformed.set_span(group.span());
+                    result.extend([TokenTree::Group(transformed)]);
+                    i += 1;
+                    continue;
+                }
+                [token, ..] => {
+                    result.extend([token.clone()]);
+                    i += 1;
+                    continue;
+                }
+                [] => unreachable!(),
+            };
+            let reference: TokenStream = tokens[i..i + 5].iter().cloned().collect();
+            let abstract_value = self.property_type(reference, &node_id, &property)?;
+            i += 5;
+
+            // `=` alone is an assignment, `op=` a compound one; `==`, `<=` and the others are reads
+            let operator = match &tokens[i..] {
+                [TokenTree::Punct(equal), ..] if equal.as_char() == '=' && equal.spacing() == Spacing::Alone => Some(None),
+                [TokenTree::Punct(operator), TokenTree::Punct(equal), ..]
+                    if operator.spacing() == Spacing::Joint && equal.as_char() == '=' && matches!(operator.as_char(), '+' | '-' | '*' | '/') => {
+                    // no longer joined to the `=`
+                    let mut alone = Punct::new(operator.as_char(), Spacing::Alone);
+                    alone.set_span(operator.span());
+                    Some(Some(alone))
+                }
+                _ => None,
+            };
+            let Some(operator) = operator else {
+                let getter = accessor("get", abstract_value, node_id.span());
+                result.extend(quote_spanned! {node_id.span()=> #getter(engine, #node_id, #property) });
+                continue;
+            };
 
-    {
-        use std::io::Write;
-        let stdin = rustfmt.stdin.as_mut().expect("Failed to open stdin");
-        stdin.write_all(code.as_bytes()).expect("Failed to write code");
+            if let Some(operator) = &operator {
+                if let Some(message) = compound_assignment_error(abstract_value, &operator.to_string(), &node_id.to_string(), &property.to_string()) {
+                    let assignment: TokenStream = tokens[i - 5..i + 2].iter().cloned().collect();
+                    return Err(syn::Error::new_spanned(assignment, message));
+                }
+                i += 1;
+            }
+            i += 1;
+
+            // the assigned value is the expression up to the end of the statement, or of the match arm: parsed as
+            // one, so the commas of a turbofish or of closure parameters don't end it
+            let mut rest: Vec<TokenTree> = self.transform(tokens[i..].iter().cloned().collect())?.into_iter().collect();
+            let after = (|input: ParseStream| { input.parse::<Expr>()?; input.parse::<TokenStream>() }).parse2(rest.iter().cloned().collect())?;
+            let value: TokenStream = rest.drain(..rest.len() - after.into_iter().count()).collect();
+            i = tokens.len();
+
+            let setter = accessor("set", abstract_value, node_id.span());
+            let getter = accessor("get", abstract_value, node_id.span());
+            result.extend(match (operator, abstract_value) {
+                (None, _) => quote_spanned! {node_id.span()=> #setter(engine, #node_id, #property, #value) },
+                (Some(_), AbstractValue::String(_)) => quote_spanned! {node_id.span()=>
+                    #setter(engine, #node_id, #property, format!("{}{}", #getter(engine, #node_id, #property), #value))
+                },
+                (Some(operator), _) => quote_spanned! {node_id.span()=>
+                    #setter(engine, #node_id, #property, #getter(engine, #node_id, #property) #operator (#value))
+                },
+            });
+            result.extend(rest);
+        }
+        Ok(result)
     }
 
-    let output = rustfmt.wait_with_output().expect("Failed to read output");
-    String::from_utf8_lossy(&output.stdout).into_owned()
+    fn property_type(&self, reference: TokenStream, node_id: &Ident, property: &Ident) -> syn::Result<&AbstractValue> {
+        let node_id = node_id.to_string();
+        let mapped_id = match self.rename {
+            Some((from, to)) => node_id.replace(from, to),
+            None => node_id.clone(),
+        };
+        match self.properties_mapping.get(&format!("{}.{}", mapped_id, property)) {
+            Some(value @ (AbstractValue::Number(_) | AbstractValue::String(_) | AbstractValue::Bool(_) | AbstractValue::Color(_))) => Ok(value),
+            _ => Err(syn::Error::new_spanned(reference, format!("Can't find property '{}.{}'.", node_id, property))),
+        }
+    }
 }
 
-pub fn transform_dollar_syntax(code: &str, properties_mapping: &HashMap<String, AbstractValue>) -> String {
-    use regex::Regex;
-    
-    // Only transform if there are actually $ expressions
-    if !code.contains("$.") {
-        return code.to_string();
+/// `get_number!`, `set_color!`, ... for the type of a property
+fn accessor(prefix: &str, abstract_value: &AbstractValue, span: Span) -> TokenStream {
+    let type_name = match abstract_value {
+        AbstractValue::String(_) => "string",
+        AbstractValue::Bool(_) => "bool",
+        AbstractValue::Color(_) => "color",
+        _ => "number",
+    };
+    let name = Ident::new(&format!("{}_{}", prefix, type_name), span);
+    quote_spanned! {span=> #name! }
+}
+
+/// Pass the engine as first argument to the calls of the functions declared in the tree.
+///
+/// At the top level of the generated code `engine` is the engine itself (`by_reference` false), except
+/// in a component where it is the engine given to the build closure. In the callbacks (`move |engine| ...`)
+/// and in the functions it is always a `&mut RmlEngine`.
+pub fn inject_engine(tokens: TokenStream, functions: &HashSet<String>, by_reference: bool) -> syn::Result<TokenStream> {
+    let mut stmts = syn::parse::Parser::parse2(syn::Block::parse_within, tokens)?;
+    let mut injector = EngineInjector { functions, by_reference, error: None };
+    for stmt in &mut stmts {
+        injector.visit_stmt_mut(stmt);
     }
-    
-    let mut result = code.to_string();
-    
-    // Handle compound assignments first: $.node.prop += value; ([+\-*/])=
-    let compound_assign_pattern = Regex::new(r"\$\.([a-zA-Z_][a-zA-Z0-9_]*)\.([a-zA-Z_][a-zA-Z0-9_]*)\s*([+\-*/])=\s*([^;]+)\s*;").unwrap();
-    result = compound_assign_pattern.replace_all(&result, |caps: &regex::Captures| {
-        let node_id = &caps[1];
-        let property = &caps[2];
-        let operator = &caps[3];
-        let value = &caps[4].trim();
-
-        let abstract_value = properties_mapping.get(&format!("{}.{}", node_id, property));
-
-        match Some(abstract_value) {
-            Some(Some(AbstractValue::String(_))) => {
-                if operator != "+" {
-                    panic!("Invalid operator '{}' for string property '{}.{}'. Only '+=' is allowed for strings.", operator, node_id, property);
-                }
-                format!("set_string!(engine, {}, {}, format!(\"{{}}{{}}\", get_string!(engine, {}, {}), {}));", 
-                    node_id, property, node_id, property, value)
-            },
-            Some(Some(AbstractValue::Bool(_))) => {
-                panic!("Compound assignments are not supported for boolean properties '{}.{}'.", node_id, property);
-            },
-            Some(Some(AbstractValue::Color(_))) => {
-                panic!("Compound assignments are not supported for color properties '{}.{}'.", node_id, property);
-            },
-            Some(Some(AbstractValue::Number(_))) => {
-                format!("set_number!(engine, {}, {}, get_number!(engine, {}, {}) {} {});", 
-                    node_id, property, node_id, property, operator, value)
+    match injector.error {
+        Some(error) => Err(error),
+        None => Ok(quote! { #(#stmts)* }),
+    }
+}
+
+/// Names of the functions defined in generated code
+pub fn defined_functions(tokens: TokenStream) -> syn::Result<HashSet<String>> {
+    let stmts = syn::parse::Parser::parse2(syn::Block::parse_within, tokens)?;
+    Ok(stmts.iter().filter_map(|stmt| match stmt {
+        syn::Stmt::Item(syn::Item::Fn(function)) => Some(function.sig.ident.to_string()),
+        _ => None,
+    }).collect())
+}
+
+struct EngineInjector<'a> {
+    functions: &'a HashSet<String>,
+    by_reference: bool,
+    /// the first macro whose body needs the engine but can't be visited
+    error: Option<syn::Error>,
+}
+
+impl EngineInjector<'_> {
+    fn with_reference(&mut self, f: impl FnOnce(&mut Self)) {
+        let by_reference = std::mem::replace(&mut self.by_reference, true);
+        f(self);
+        self.by_reference = by_reference;
+    }
+
+    /// The tokens use the engine, a `$` reference or a function declared in the tree
+    fn needs_engine(&self, tokens: TokenStream) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => ident == "engine" || self.functions.contains(&ident.to_string()),
+            TokenTree::Punct(punct) => punct.as_char() == '$',
+            TokenTree::Group(group) => self.needs_engine(group.stream()),
+            TokenTree::Literal(_) => false,
+        })
+    }
+
+    /// Visit a part of a macro body if it is an expression
+    fn visit_macro_part(&mut self, part: TokenStream, mac: &syn::Macro) -> TokenStream {
+        match syn::parse2::<Expr>(part.clone()) {
+            Ok(mut expr) => {
+                self.visit_expr_mut(&mut expr);
+                quote! { #expr }
             }
-            _ => {
-                panic!("Can't find property '{}.{}'.", node_id, property);
+            Err(_) => {
+                if self.error.is_none() && self.needs_engine(part.clone()) {
+                    self.error = Some(syn::Error::new_spanned(mac, "Can't pass the engine in this macro, its arguments are not all expressions. Move the code using the engine out of it."));
+                }
+                part
             }
         }
-    }).to_string();
-    
-    // Handle simple assignments: $.node.prop = value;
-    // Match = but not ==, !=, <=, >=, +=, -=, *=, /=
-    let assign_pattern = Regex::new(r"\$\.([a-zA-Z_][a-zA-Z0-9_]*)\.([a-zA-Z_][a-zA-Z0-9_]*)\s*=\s*([^=;][^;]*)\s*;").unwrap();
-    result = assign_pattern.replace_all(&result, |caps: &regex::Captures| {
-        let node_id = &caps[1];
-        let property = &caps[2];
-        let value = &caps[3].trim();
-
-        let abstract_value = properties_mapping.get(&format!("{}.{}", node_id, property));
-        match Some(abstract_value) {
-            Some(Some(AbstractValue::String(_))) => {
-                format!("set_string!(engine, {}, {}, {});", node_id, property, value)
-            },
-            Some(Some(AbstractValue::Bool(_))) => {
-                format!("set_bool!(engine, {}, {}, {});", node_id, property, value)
-            },
-            Some(Some(AbstractValue::Color(_))) => {
-                format!("set_color!(engine, {}, {}, {});", node_id, property, value)
-            },
-            Some(Some(AbstractValue::Number(_))) => {
-                format!("set_number!(engine, {}, {}, {});", node_id, property, value)
+    }
+}
+
+fn is_engine(expr: &Expr) -> bool {
+    match expr {
+        Expr::Path(path) => path.path.is_ident("engine"),
+        Expr::Reference(reference) => is_engine(&reference.expr),
+        _ => false,
+    }
+}
+
+impl VisitMut for EngineInjector<'_> {
+    fn visit_expr_call_mut(&mut self, call: &mut syn::ExprCall) {
+        visit_mut::visit_expr_call_mut(self, call);
+
+        let Expr::Path(function) = &*call.func else { return };
+        let is_declared = function.path.get_ident().is_some_and(|ident| self.functions.contains(&ident.to_string()));
+        if !is_declared || call.args.first().is_some_and(is_engine) {
+            return;
+        }
+        let engine: Expr = if self.by_reference {
+            syn::parse_quote!(engine)
+        } else {
+            syn::parse_quote!(&mut engine)
+        };
+        call.args.insert(0, engine);
+    }
+
+    fn visit_expr_closure_mut(&mut self, closure: &mut syn::ExprClosure) {
+        let takes_engine = closure.inputs.iter().any(|input| match input {
+            Pat::Ident(pat) => pat.ident == "engine",
+            Pat::Type(pat) => matches!(&*pat.pat, Pat::Ident(pat) if pat.ident == "engine"),
+            _ => false,
+        });
+        if takes_engine {
+            self.with_reference(|injector| visit_mut::visit_expr_closure_mut(injector, closure));
+        } else {
+            visit_mut::visit_expr_closure_mut(self, closure);
+        }
+    }
+
+    fn visit_item_fn_mut(&mut self, function: &mut syn::ItemFn) {
+        self.with_reference(|injector| visit_mut::visit_item_fn_mut(injector, function));
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        // the arguments of `format!`, `set_number!`, ... are expressions too
+        if let Ok(mut args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for arg in &mut args {
+                self.visit_expr_mut(arg);
             }
-            _ => {
-                panic!("Can't find property '{}.{}'.", node_id, property);
+            mac.tokens = quote! { #args };
+            return;
+        }
+        // otherwise the parts between the commas and the semicolons, as in `vec![x; n]` or `assert!(x, "{}", y)`
+        let mut tokens = TokenStream::new();
+        let mut part = TokenStream::new();
+        for token in mac.tokens.clone() {
+            match token {
+                TokenTree::Punct(punct) if matches!(punct.as_char(), ',' | ';') => {
+                    tokens.extend(self.visit_macro_part(std::mem::take(&mut part), mac));
+                    tokens.extend([TokenTree::Punct(punct)]);
+                }
+                token => part.extend([token]),
             }
         }
-    }).to_string();
+        tokens.extend(self.visit_macro_part(part, mac));
+        mac.tokens = tokens;
+    }
+}
+
+pub fn find_related_property_for_binding(id: String, property: String, block_string: String) -> Vec<(String, String)> {
+    // ex: k_string = "x", block_string =
+    // "{
+    // let outer_rect_width = get_number!(engine, outer_rect, width);
+    // let inner_rect_width = get_number!(engine, inner_rect, width);
+    // let inner_rect_width = engine.get_number_property_of_node(inner_rect, "width", 0.0);
+    // outer_rect_width / 2.0 - inner_rect_width / 2.0
+    // }"
+    // will return [(outer_rect, width), (inner_rect, width)]
+    let block_string = format_code_for_binding_extraction(block_string.as_str());
+    let mut related_properties = Vec::new();
     
-    // Handle regular read operations: $.node.prop (in expressions)
-    // Be more careful to only match standalone expressions, not inside strings
-    let dollar_pattern = Regex::new(r"\$\.([a-zA-Z_][a-zA-Z0-9_]*)\.([a-zA-Z_][a-zA-Z0-9_]*)\b").unwrap();
-    result = dollar_pattern.replace_all(&result, |caps: &regex::Captures| {
-        let node_id = &caps[1];
-        let property = &caps[2];
+    // if in block we find get_number!, get_string!, get_bool!, get_color!
+    // get_computed_x!, get_computed_y!, get_computed_width!, get_computed_height!
+    // get_number_property_of_node, get_string_property_of_node, get_bool_property_of_node, get_color_property_of_node
+    // or get_property_of_node
+    // we will add it to related_properties
+    for line in block_string.lines() {
+        let trimmed_line = line.trim();
         
-        let abstract_value = properties_mapping.get(&format!("{}.{}", node_id, property));
-        match Some(abstract_value) {
-            Some(Some(AbstractValue::String(_))) => {
-                format!("get_string!(engine, {}, {})", node_id, property)
-            },
-            Some(Some(AbstractValue::Bool(_))) => {
-                format!("get_bool!(engine, {}, {})", node_id, property)
-            },
-            Some(Some(AbstractValue::Color(_))) => {
-                format!("get_color!(engine, {}, {})", node_id, property)
-            },
-            Some(Some(AbstractValue::Number(_))) => {
-                format!("get_number!(engine, {}, {})", node_id, property)
+        if trimmed_line.contains("get_value!") ||trimmed_line.contains("get_number!") || trimmed_line.contains("get_string!") || 
+           trimmed_line.contains("get_bool!") || trimmed_line.contains("get_color!") ||
+           trimmed_line.contains("get_computed_x!") || trimmed_line.contains("get_computed_y!") || 
+           trimmed_line.contains("get_computed_width!") || trimmed_line.contains("get_computed_height!") {
+            
+            // Parse macro calls like get_number!(engine, node_name, property_name)
+            if let Some(start) = trimmed_line.find('(') {
+                if let Some(end) = trimmed_line.find(')') {
+                    let params = &trimmed_line[start + 1..end];
+                    let parts: Vec<&str> = params.split(',').map(|s| s.trim()).collect();
+                    
+                    if parts.len() >= 3 {
+                        let node_name = parts[1].trim();
+                        let property_name = parts[2].trim().trim_matches('"');
+                        if node_name == id && property_name == property {
+                            continue;
+                        }
+                        related_properties.push((node_name.to_string(), property_name.to_string()));
+                    }
+                }
             }
-            _ => {
-                panic!("Can't find property '{}.{}'.", node_id, property);
+        }
+        else if trimmed_line.contains("get_number_property_of_node") || 
+                trimmed_line.contains("get_string_property_of_node") ||
+                trimmed_line.contains("get_bool_property_of_node") || 
+                trimmed_line.contains("get_color_property_of_node") || 
+                trimmed_line.contains("get_property_of_node") {
+            
+            // Parse method calls like engine.get_number_property_of_node(node_name, "property_name", default)
+            if let Some(start) = trimmed_line.find('(') {
+                if let Some(end) = trimmed_line.rfind(')') {
+                    let params = &trimmed_line[start + 1..end];
+                    let parts: Vec<&str> = params.split(',').map(|s| s.trim()).collect();
+                    
+                    if parts.len() >= 2 {
+                        let node_name = parts[0].trim();
+                        let property_name = parts[1].trim().trim_matches('"');
+                        if node_name == id && property_name == property {
+                            continue;
+                        }
+                        related_properties.push((node_name.to_string(), property_name.to_string()));
+                    }
+                }
             }
         }
-    }).to_string();
+    }
     
-    result
+    // Remove duplicates while preserving order
+    let mut seen = std::collections::HashSet::new();
+    related_properties.retain(|item| seen.insert(item.clone()));
+    
+    related_properties
 }
 
 #[cfg(test)]
@@ -154,35 +389,47 @@ mod tests {
         m
     }
 
+    fn tokens_of(code: &str) -> TokenStream {
+        code.parse().unwrap()
+    }
+
+    fn tokens(code: &str) -> String {
+        tokens_of(code).to_string()
+    }
+
+    fn transform(code: &str) -> String {
+        transform_dollar_syntax(tokens_of(code), &mapping()).unwrap().to_string()
+    }
+
     // ---------------------------------------------------
     // 1. SIMPLE ASSIGNMENTS
     // ---------------------------------------------------
     #[test]
     fn test_simple_assignment_number() {
         let code = "$.node.num = 5;";
-        let result = transform_dollar_syntax(code, &mapping());
-        assert_eq!(result, "set_number!(engine, node, num, 5);");
+        let result = transform(code);
+        assert_eq!(result, tokens("set_number!(engine, node, num, 5);"));
     }
 
     #[test]
     fn test_simple_assignment_string() {
         let code = "$.node.str = \"hello\";";
-        let result = transform_dollar_syntax(code, &mapping());
-        assert_eq!(result, "set_string!(engine, node, str, \"hello\");");
+        let result = transform(code);
+        assert_eq!(result, tokens("set_string!(engine, node, str, \"hello\");"));
     }
 
     #[test]
     fn test_simple_assignment_bool() {
         let code = "$.node.bool = true;";
-        let result = transform_dollar_syntax(code, &mapping());
-        assert_eq!(result, "set_bool!(engine, node, bool, true);");
+        let result = transform(code);
+        assert_eq!(result, tokens("set_bool!(engine, node, bool, true);"));
     }
 
     #[test]
     fn test_simple_assignment_color() {
         let code = "$.node.color = red;";
-        let result = transform_dollar_syntax(code, &mapping());
-        assert_eq!(result, "set_color!(engine, node, color, red);");
+        let result = transform(code);
+        assert_eq!(result, tokens("set_color!(engine, node, color, red);"));
     }
 
     // ---------------------------------------------------
@@ -191,15 +438,15 @@ mod tests {
     #[test]
     fn test_comparison_not_transformed() {
         let code = "if ($.node.num == 3) {}";
-        let result = transform_dollar_syntax(code, &mapping());
-        assert_eq!(result, "if (get_number!(engine, node, num) == 3) {}");
+        let result = transform(code);
+        assert_eq!(result, tokens("if (get_number!(engine, node, num) == 3) {}"));
     }
 
     #[test]
     fn test_comparison_with_strings_not_transformed() {
         let code = "if ($.node.str == \"ok\") {}";
-        let result = transform_dollar_syntax(code, &mapping());
-        assert_eq!(result, "if (get_string!(engine, node, str) == \"ok\") {}");
+        let result = transform(code);
+        assert_eq!(result, tokens("if (get_string!(engine, node, str) == \"ok\") {}"));
     }
 
     // ---------------------------------------------------
@@ -208,33 +455,43 @@ mod tests {
     #[test]
     fn test_compound_plus_number() {
         let code = "$.node.num += 2;";
-        let result = transform_dollar_syntax(code, &mapping());
+        let result = transform(code);
         assert_eq!(
             result,
-            "set_number!(engine, node, num, get_number!(engine, node, num) + 2);"
+            tokens("set_number!(engine, node, num, get_number!(engine, node, num) + (2));")
         );
     }
 
     #[test]
     fn test_compound_plus_string() {
         let code = "$.node.str += \" world\";";
-        let result = transform_dollar_syntax(code, &mapping());
+        let result = transform(code);
         assert_eq!(
             result,
-            "set_string!(engine, node, str, format!(\"{}{}\", get_string!(engine, node, str), \" world\"));"
+            tokens("set_string!(engine, node, str, format!(\"{}{}\", get_string!(engine, node, str), \" world\"));")
+        );
+    }
+
+    #[test]
+    fn test_compound_other_operators_number() {
+        assert_eq!(
+            transform("$.node.num -= 1.0; $.node.num *= 2.0 + 1.0; $.node.num /= $.node.num;"),
+            tokens("set_number!(engine, node, num, get_number!(engine, node, num) - (1.0)); \
+                set_number!(engine, node, num, get_number!(engine, node, num) * (2.0 + 1.0)); \
+                set_number!(engine, node, num, get_number!(engine, node, num) / (get_number!(engine, node, num)));")
         );
     }
 
     #[test]
-    #[should_panic(expected = "Invalid operator '-' for string property")]
     fn test_compound_wrong_operator_string() {
-        transform_dollar_syntax("$.node.str -= \"bad\";", &mapping());
+        let error = transform_dollar_syntax(tokens_of("$.node.str -= \"bad\";"), &mapping()).unwrap_err();
+        assert!(error.to_string().contains("Invalid operator '-' for string property"));
     }
 
     #[test]
-    #[should_panic(expected = "Compound assignments are not supported for boolean properties")]
     fn test_compound_bool_invalid() {
-        transform_dollar_syntax("$.node.bool += true;", &mapping());
+        let error = transform_dollar_syntax(tokens_of("$.node.bool += true;"), &mapping()).unwrap_err();
+        assert!(error.to_string().contains("Compound assignments are not supported for boolean properties"));
     }
 
     // ---------------------------------------------------
@@ -243,30 +500,30 @@ mod tests {
     #[test]
     fn test_read_number() {
         let code = "x = $.node.num + 1;";
-        let result = transform_dollar_syntax(code, &mapping());
+        let result = transform(code);
         assert_eq!(
             result,
-            "x = get_number!(engine, node, num) + 1;"
+            tokens("x = get_number!(engine, node, num) + 1;")
         );
     }
 
     #[test]
     fn test_read_string() {
         let code = "print($.node.str);";
-        let result = transform_dollar_syntax(code, &mapping());
+        let result = transform(code);
         assert_eq!(
             result,
-            "print(get_string!(engine, node, str));"
+            tokens("print(get_string!(engine, node, str));")
         );
     }
 
     #[test]
     fn test_read_multiple() {
         let code = "$.node.num + $.node.num * $.node.num";
-        let result = transform_dollar_syntax(code, &mapping());
+        let result = transform(code);
         assert_eq!(
             result,
-            "get_number!(engine, node, num) + get_number!(engine, node, num) * get_number!(engine, node, num)"
+            tokens("get_number!(engine, node, num) + get_number!(engine, node, num) * get_number!(engine, node, num)")
         );
     }
 
@@ -274,214 +531,138 @@ mod tests {
     // 5. UNKNOWN PROPERTY
     // ---------------------------------------------------
     #[test]
-    #[should_panic(expected = "Can't find property")]
     fn test_unknown_property() {
-        transform_dollar_syntax("$.x.y = 10;", &mapping());
+        let error = transform_dollar_syntax(tokens_of("$.x.y = 10;"), &mapping()).unwrap_err();
+        assert!(error.to_string().contains("Can't find property 'x.y'"));
     }
 
     // ---------------------------------------------------
-    // 5. UNKNOWN PROPERTY
+    // 6. CHECKS ON TOKENS
     // ---------------------------------------------------
     #[test]
-    fn test_real_01_property() {
-
-        let code = "fn compute_font_size() { if $.node.num == 0.0 { $.node.str = \"Click Me\".to_string(); } }";
-        let result = transform_dollar_syntax(code, &mapping());
-        println!("Result: {}", result);
-        assert_eq!(
-            result,
-            "fn compute_font_size() { if get_number!(engine, node, num) == 0.0 { set_string!(engine, node, str, \"Click Me\".to_string()); } }"
-        );
+    fn test_check_unknown_property_span() {
+        let tokens: TokenStream = "fn f() {\n    $.node.num = 1;\n    $.node.nope = 2;\n}".parse().unwrap();
+        let error = check_dollar_references(tokens, &mapping(), None).unwrap_err();
+        assert_eq!(error.to_string(), "Can't find property 'node.nope'.");
+        assert_eq!(error.span().start().line, 3);
+        assert_eq!(error.span().start().column, 4);
     }
-}
-
-pub fn inject_engine_text_based(
-    input: &str,
-    engine_str: &str,
-    definition: bool,
-    mutable: bool,
-    functions: &Vec<String>,
-) -> String {
-    // prepare what we should inject OUTSIDE callback blocks
-    let injected_normal = if definition {
-        format!("&mut {engine_str}")
-    } else if mutable {
-        format!("&mut {engine_str}")
-    } else {
-        format!("{engine_str}")
-    };
-
-    // inside a callback block we always inject "engine"
-    let injected_callback = "engine".to_string();
-
-    let mut output = String::new();
-
-    let mut in_callback = false;
-    let mut brace_depth: i32 = 0;
-
-    for line in input.lines() {
-        let mut modified_line = line.to_string();
-
-        // detect start of callback
-        if line.contains("engine.add_callback")
-            && line.contains("move | engine |")
-        {
-            in_callback = true;
-            // we expect an opening brace, but we count braces
-            // anyway to remain robust.
-            brace_depth += line.matches('{').count() as i32;
-            brace_depth -= line.matches('}').count() as i32;
-            output.push_str(&modified_line);
-            output.push('\n');
-            continue;
-        }
 
-        // If inside callback, keep track of braces to know when we exit
-        if in_callback {
-            brace_depth += line.matches('{').count() as i32;
-            brace_depth -= line.matches('}').count() as i32;
-            if brace_depth <= 0 {
-                in_callback = false;
-            }
-        }
+    #[test]
+    fn test_check_compound_assignment() {
+        let tokens: TokenStream = "$.node.num -= 1; $.node.color += red;".parse().unwrap();
+        let error = check_dollar_references(tokens, &mapping(), None).unwrap_err();
+        assert!(error.to_string().contains("Compound assignments are not supported for color properties"));
+    }
 
-        for func in functions {
-            let def_pattern = format!("fn {func}(");
+    #[test]
+    fn test_check_renamed_node() {
+        let tokens: TokenStream = "$.root.num = 1;".parse().unwrap();
+        assert!(check_dollar_references(tokens.clone(), &mapping(), None).is_err());
+        assert!(check_dollar_references(tokens, &mapping(), Some(("root", "node"))).is_ok());
+    }
 
-            if line.contains(&def_pattern) {
-                // Function definition – skip
-                continue;
-            }
+    // ---------------------------------------------------
+    // 7. TOKEN STRUCTURE
+    // ---------------------------------------------------
+    #[test]
+    fn test_dollar_in_string_literal() {
+        assert_eq!(
+            transform("$.node.str = \"costs $.node.num\";"),
+            tokens("set_string!(engine, node, str, \"costs $.node.num\");")
+        );
+    }
 
-            // Function call injection
-            let call_pattern = format!("{func}(");
+    #[test]
+    fn test_assignment_with_closure() {
+        assert_eq!(
+            transform("$.node.num = values.iter().map(|v| { let w = v * 2.0; w }).sum(); $.node.bool = true;"),
+            tokens("set_number!(engine, node, num, values.iter().map(|v| { let w = v * 2.0; w }).sum()); set_bool!(engine, node, bool, true);")
+        );
+    }
 
-            if modified_line.contains(&call_pattern) {
-                let replacement = if in_callback {
-                    format!("{func}({injected_callback}")
-                } else {
-                    format!("{func}({injected_normal}")
-                };
+    #[test]
+    fn test_assignment_with_commas_in_the_value() {
+        assert_eq!(
+            transform("$.node.num = HashMap::<u8, u8>::new().len() as f32; $.node.bool = true;"),
+            tokens("set_number!(engine, node, num, HashMap::<u8, u8>::new().len() as f32); set_bool!(engine, node, bool, true);")
+        );
+        assert_eq!(
+            transform("match c { 0 => $.node.num = values.iter().fold(0.0, |a, b| a + b), _ => $.node.num = $.node.num }"),
+            tokens("match c { 0 => set_number!(engine, node, num, values.iter().fold(0.0, |a, b| a + b)), _ => set_number!(engine, node, num, get_number!(engine, node, num)) }")
+        );
+        assert_eq!(
+            transform("$.node.num = (|a, b| a * b)(2.0, 3.0);"),
+            tokens("set_number!(engine, node, num, (|a, b| a * b)(2.0, 3.0));")
+        );
+    }
 
-                modified_line = modified_line.replace(&call_pattern, &replacement);
-            }
-        }
+    #[test]
+    fn test_nested_blocks() {
+        assert_eq!(
+            transform("if a { if b { $.node.num = 1.0 } else { match c { 0 => $.node.bool = true, _ => {} } } }"),
+            tokens("if a { if b { set_number!(engine, node, num, 1.0) } else { match c { 0 => set_bool!(engine, node, bool, true), _ => {} } } }")
+        );
+    }
 
-        output.push_str(&modified_line);
-        output.push('\n');
+    #[test]
+    fn test_transform_keeps_spans() {
+        let result = transform_dollar_syntax(tokens_of("{\n    $.node.num = 1;\n}"), &mapping()).unwrap();
+        let Some(TokenTree::Group(block)) = result.into_iter().next() else { panic!("expected a block") };
+        let setter = block.stream().into_iter().next().unwrap();
+        assert_eq!(setter.span().start().line, 2);
     }
 
-    output
-}
+    // ---------------------------------------------------
+    // 8. ENGINE INJECTION
+    // ---------------------------------------------------
+    fn inject(code: &str) -> String {
+        let functions = defined_functions(tokens_of(code)).unwrap();
+        inject_engine(tokens_of(code), &functions, false).unwrap().to_string()
+    }
 
-pub fn inject_engine_in_block(mut block: syn::Block, initializer: bool) -> syn::Block {
-    use syn::{Expr, Stmt};
-
-    block.stmts = block
-        .stmts
-        .into_iter()
-        .map(|stmt| match stmt {
-            Stmt::Expr(expr, semi_opt) => {
-                let expr = match expr {
-                    Expr::Call(mut call) => {
-                        let has_engine = call.args.iter().any(|arg| {
-                            matches!(arg, Expr::Path(p) if p.path.is_ident("engine"))
-                        });
-
-                        if !has_engine {
-                            if initializer {
-                                let engine_expr: Expr = syn::parse_quote!(&mut engine);
-                                call.args.insert(0, engine_expr);
-                            } else {
-                                let engine_expr: Expr = syn::parse_quote!(engine);
-                                call.args.insert(0, engine_expr);
-                            }
-                        }
+    #[test]
+    fn test_inject_engine() {
+        assert_eq!(
+            inject("fn update(step: f32) { helper(); drop(step); } \
+                fn helper() {} \
+                update(1.0); \
+                engine.add_callback(move |engine| { update(2.0); println!(\"{}\", helper()); });"),
+            tokens("fn update(step: f32) { helper(engine); drop(step); } \
+                fn helper() {} \
+                update(&mut engine, 1.0); \
+                engine.add_callback(move |engine| { update(engine, 2.0); println!(\"{}\", helper(engine)); });")
+        );
+    }
 
-                        Expr::Call(call)
-                    }
-                    other => other,
-                };
-                Stmt::Expr(expr, semi_opt)
-            }
-            other => other,
-        })
-        .collect();
+    #[test]
+    fn test_inject_engine_in_macros_with_other_arguments() {
+        assert_eq!(
+            inject("fn helper() -> f32 { 1.0 } let values = vec![helper(); 2]; assert!(helper() > 0.0, \"{}\", helper());"),
+            tokens("fn helper() -> f32 { 1.0 } let values = vec![helper(&mut engine); 2]; \
+                assert!(helper(&mut engine) > 0.0, \"{}\", helper(&mut engine));")
+        );
+        let functions = HashSet::from(["helper".to_string()]);
+        let error = inject_engine(tokens_of("let ok = matches!(value, Some(x) if helper(x));"), &functions, false).unwrap_err();
+        assert!(error.to_string().starts_with("Can't pass the engine in this macro"));
+    }
 
-    block
-}
+    #[test]
+    fn test_inject_engine_only_once() {
+        assert_eq!(inject("fn update() {} update(&mut engine);"), tokens("fn update() {} update(&mut engine);"));
+    }
 
+    // ---------------------------------------------------
+    // 9. REAL CODE
+    // ---------------------------------------------------
+    #[test]
+    fn test_real_01_property() {
 
-pub fn find_related_property_for_binding(id: String, property: String, block_string: String) -> Vec<(String, String)> {
-    // ex: k_string = "x", block_string =
-    // "{
-    // let outer_rect_width = get_number!(engine, outer_rect, width);
-    // let inner_rect_width = get_number!(engine, inner_rect, width);
-    // let inner_rect_width = engine.get_number_property_of_node(inner_rect, "width", 0.0);
-    // outer_rect_width / 2.0 - inner_rect_width / 2.0
-    // }"
-    // will return [(outer_rect, width), (inner_rect, width)]
-    let block_string = format_code_for_binding_extraction(block_string.as_str());
-    let mut related_properties = Vec::new();
-    
-    // if in block we find get_number!, get_string!, get_bool!, get_color!
-    // get_computed_x!, get_computed_y!, get_computed_width!, get_computed_height!
-    // get_number_property_of_node, get_string_property_of_node, get_bool_property_of_node, get_color_property_of_node
-    // or get_property_of_node
-    // we will add it to related_properties
-    for line in block_string.lines() {
-        let trimmed_line = line.trim();
-        
-        if trimmed_line.contains("get_value!") ||trimmed_line.contains("get_number!") || trimmed_line.contains("get_string!") || 
-           trimmed_line.contains("get_bool!") || trimmed_line.contains("get_color!") ||
-           trimmed_line.contains("get_computed_x!") || trimmed_line.contains("get_computed_y!") || 
-           trimmed_line.contains("get_computed_width!") || trimmed_line.contains("get_computed_height!") {
-            
-            // Parse macro calls like get_number!(engine, node_name, property_name)
-            if let Some(start) = trimmed_line.find('(') {
-                if let Some(end) = trimmed_line.find(')') {
-                    let params = &trimmed_line[start + 1..end];
-                    let parts: Vec<&str> = params.split(',').map(|s| s.trim()).collect();
-                    
-                    if parts.len() >= 3 {
-                        let node_name = parts[1].trim();
-                        let property_name = parts[2].trim().trim_matches('"');
-                        if node_name == id && property_name == property {
-                            continue;
-                        }
-                        related_properties.push((node_name.to_string(), property_name.to_string()));
-                    }
-                }
-            }
-        }
-        else if trimmed_line.contains("get_number_property_of_node") || 
-                trimmed_line.contains("get_string_property_of_node") ||
-                trimmed_line.contains("get_bool_property_of_node") || 
-                trimmed_line.contains("get_color_property_of_node") || 
-                trimmed_line.contains("get_property_of_node") {
-            
-            // Parse method calls like engine.get_number_property_of_node(node_name, "property_name", default)
-            if let Some(start) = trimmed_line.find('(') {
-                if let Some(end) = trimmed_line.rfind(')') {
-                    let params = &trimmed_line[start + 1..end];
-                    let parts: Vec<&str> = params.split(',').map(|s| s.trim()).collect();
-                    
-                    if parts.len() >= 2 {
-                        let node_name = parts[0].trim();
-                        let property_name = parts[1].trim().trim_matches('"');
-                        if node_name == id && property_name == property {
-                            continue;
-                        }
-                        related_properties.push((node_name.to_string(), property_name.to_string()));
-                    }
-                }
-            }
-        }
+        let code = "fn compute_font_size() { if $.node.num == 0.0 { $.node.str = \"Click Me\".to_string(); } }";
+        let result = transform(code);
+        assert_eq!(
+            result,
+            tokens("fn compute_font_size() { if get_number!(engine, node, num) == 0.0 { set_string!(engine, node, str, \"Click Me\".to_string()); } }")
+        );
     }
-    
-    // Remove duplicates while preserving order
-    let mut seen = std::collections::HashSet::new();
-    related_properties.retain(|item| seen.insert(item.clone()));
-    
-    related_properties
 }
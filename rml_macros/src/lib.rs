@@ -1,91 +1,149 @@
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use quote::{quote};
+use std::collections::HashMap;
+use std::str::FromStr;
 use syn::parse::{ParseStream};
 
 mod structs;
 use structs::*;
 mod macros;
+use macros::{generate_ui_handles, id_spans, in_file};
 mod format;
 use format::*;
 
 #[proc_macro]
 pub fn rml(input: TokenStream) -> TokenStream {
-    // First parse
-    let mut res = RmlParser::empty();
-    match syn::parse::Parser::parse(|input: ParseStream| {
-        res = RmlParser::parse_with_path(input, "".to_string(), true).unwrap();
-        Ok(RmlParser::empty())
-    }, input.clone()) {
-        Ok(r) => r,
-        Err(_e) => { RmlParser::empty() }
-    };
+    match expand_rml(input.into(), Output::Engine) {
+        Ok(result) => result.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
 
-    // let res = syn::parse::Parser::parse(|input: ParseStream| {
-    //     RmlParser::parse_with_path(input, "".to_string(), true)
-    // }, input.clone()).unwrap_or(RmlParser::empty());
+/// Same syntax as `rml!` after a struct declaration, in item position: `rml_ui! { pub struct CounterUi; Node { ... } }`
+/// declares `CounterUi`, holding a typed handle on each property whose type is known for every node with an id,
+/// and `CounterUi::build()` returns `(engine, ui)`. `ui.counter_btn.counter(&engine)` is a `f32`,
+/// `ui.counter_btn.set_counter(&mut engine, 2.0)` sets it. A misspelled node or property doesn't compile.
+#[proc_macro]
+pub fn rml_ui(input: TokenStream) -> TokenStream {
+    match expand_rml(input.into(), Output::Ui) {
+        Ok(result) => result.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
 
-    //let input_clone = input.clone();
+/// Same syntax as `rml!`, but returns a `Component` that builds the tree in an existing engine, as many
+/// times as needed: `engine.instantiate(&component, parent, &[("text", "Ok".into())])`. The ids of the
+/// tree are local to each instance, its `$.node.property` references address the nodes of the instance.
+#[proc_macro]
+pub fn rml_component(input: TokenStream) -> TokenStream {
+    match expand_rml(input.into(), Output::Component) {
+        Ok(result) => result.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
 
-    // let res = syn::parse::Parser::parse(|input: ParseStream| {
-    //     let result = RmlParser::parse_with_path(input, "".to_string(), true)?;
-    //     eprintln!("Parsing succeeded! Empty: {}", input.is_empty());
-    //     Ok(result)
-    // }, input).unwrap_or_else(|e| {
-    //     eprintln!("Parse failed: {}", e);
-    //     RmlParser::empty()
-    // });
+/// What the macros return
+#[derive(Clone, Copy, PartialEq)]
+enum Output {
+    Engine,
+    Ui,
+    Component,
+}
 
-    println!("Res : {:#?}", res);
+fn expand_rml(input: proc_macro2::TokenStream, output: Output) -> syn::Result<proc_macro2::TokenStream> {
+    // `rml_ui!` starts with the declaration of its struct
+    let (ui, input) = if output == Output::Ui {
+        let (vis, name, input) = syn::parse::Parser::parse2(|input: ParseStream| {
+            let vis: syn::Visibility = input.parse()?;
+            input.parse::<syn::Token![struct]>()?;
+            let name: syn::Ident = input.parse()?;
+            input.parse::<syn::Token![;]>()?;
+            Ok((vis, name, input.parse::<proc_macro2::TokenStream>()?))
+        }, input)?;
+        (Some((vis, name)), input)
+    } else {
+        (None, input)
+    };
+
+    // First parse
+    let res = syn::parse::Parser::parse2(|input: ParseStream| {
+        RmlParser::parse_with_path(input, "".to_string(), true)
+    }, input.clone())?;
 
     // now we need equivalent process to parsed_node.generate_with_components(&components);
     // but only to develop children components, and be sure to use a deterministic way to rename items (a global counter should do the tricks)
     // after that we could be able to map a list of property name with theirs types
     let (mut parsed_node, components) = (res.root_node, res.components);
-    let properties_mapping = parsed_node.pre_generate_with_components_and_counter(&components, &mut 0);
+    let mut spans = HashMap::new();
+    id_spans(&parsed_node, &mut spans);
+    let mut file_checks = Vec::new();
+    let properties_mapping = parsed_node.pre_generate_with_components_and_counter(&components, &mut 0, &mut file_checks)?;
 
-    // println!("Res : {:#?}", properties_mapping);
     // we have the struct of the application, and can infer property type, and use it in transform_dollar_syntax
-
-    // transform the input to replace $ syntax before parsing
-    let input_string = input.to_string();
-    let input_string = transform_dollar_syntax(&input_string, &properties_mapping);
-
-    //println!("Transformed input: {}", input_string);
-    
-    // Parse the transformed input
-    let transformed_input: TokenStream = input_string.parse().unwrap();
-    let res = syn::parse::Parser::parse(|transformed_input: ParseStream| {
+    // the tokens of the component files lose their location once inserted, so their $ references are checked on their own
+    for check in &file_checks {
+        in_file(&check.path, || {
+            let content = std::fs::read_to_string(&check.path)
+                .map_err(|error| syn::Error::new(Span::call_site(), error.to_string()))?;
+            let tokens = proc_macro2::TokenStream::from_str(&content)
+                .map_err(|error| syn::Error::new(error.span(), error.to_string()))?;
+            check_dollar_references(tokens, &properties_mapping, Some((&check.original_id, &check.instance_id)))
+        }).map_err(|message| syn::Error::new(check.span.unwrap_or_else(Span::call_site), message))?;
+    }
+
+    // transform the $ syntax before parsing
+    let transformed_input = transform_dollar_syntax(input, &properties_mapping)?;
+    let res = syn::parse::Parser::parse2(|transformed_input: ParseStream| {
         RmlParser::parse_with_path(transformed_input, "".to_string(), false)
-    }, transformed_input.clone()).unwrap();
+    }, transformed_input)?;
 
     let (mut parsed_node, components) = (res.root_node, res.components);
     
-    let generated = parsed_node.generate_with_components_and_counter(&components, &mut 0, &properties_mapping);
+    let generated = parsed_node.generate_with_components_and_counter(&components, &mut 0, &properties_mapping)?;
+    let root_id = generated.0;
     let generated_node = generated.1;
     let generated_functions = generated.2;
     let generated_initializer = generated.3;
 
-    let functions_name: Vec<String> = parsed_node
-        .functions
-        .iter()
-        .map(|f| f.sig.ident.to_string())
-        .collect();
-
-    let generated_initializer = generated_initializer.to_string();
-    //let generated_initializer = format_code(&generated_initializer);
-    let generated_initializer = inject_engine_text_based(&generated_initializer, "engine", false, true, &functions_name);
-    let generated_initializer = generated_initializer.parse::<proc_macro2::TokenStream>().unwrap();
-
-    let generated_functions = generated_functions.to_string();
-    let generated_functions = format_code(&generated_functions);
-    let generated_functions = inject_engine_text_based(&generated_functions, "engine", true, true, &functions_name);
-    let generated_functions = generated_functions.parse::<proc_macro2::TokenStream>().unwrap();
-
-    let generated_node = generated_node.to_string();
-    let generated_node = inject_engine_text_based(&generated_node, "engine", false, false, &functions_name);
-    let generated_node = generated_node.parse::<proc_macro2::TokenStream>().unwrap();
-
-    let result = quote! {
+    // the functions take the engine as first argument
+    let functions = defined_functions(generated_functions.clone())?;
+    let by_reference = output == Output::Component;
+    let generated_node = inject_engine(generated_node, &functions, by_reference)?;
+    let generated_functions = inject_engine(generated_functions, &functions, by_reference)?;
+    let generated_initializer = inject_engine(generated_initializer, &functions, by_reference)?;
+
+    if output == Output::Component {
+        return Ok(quote! {
+            Component::new(move |engine: &mut RmlEngine| {
+                #generated_node;
+                #generated_functions
+                #generated_initializer
+                engine.get_node_id(#root_id)
+            })
+        });
+    }
+
+    if let Some((vis, name)) = ui {
+        let (handle_types, handles) = generate_ui_handles(&name, &vis, &properties_mapping, &spans)?;
+        return Ok(quote! {
+            #handle_types
+
+            impl #name {
+                /// Build the tree in a new engine, with the handles on its properties
+                #vis fn build() -> (RmlEngine, Self) {
+                    let mut engine = RmlEngine::new();
+                    #generated_node;
+                    #generated_functions
+                    #generated_initializer
+                    let ui = #handles;
+                    (engine, ui)
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
         {
             let mut engine = RmlEngine::new();
             #generated_node;
@@ -93,12 +151,5 @@ pub fn rml(input: TokenStream) -> TokenStream {
             #generated_initializer
             engine
         }
-    };
-
-    // let result = quote! {
-    //     RmlEngine::new()
-    // };
-
-
-    TokenStream::from(result)
+    })
 }
@@ -1,11 +1,13 @@
 
 use proc_macro2::Span;
+use std::cell::Cell;
+use std::str::FromStr;
 use quote::{format_ident, quote};
 use rml_core::prelude::{warn, RED};
-use syn::parse::{Parse, ParseBuffer, ParseStream};
+use syn::parse::{discouraged::Speculative, Parse, ParseBuffer, ParseStream};
 use syn::{Ident, Lit, Token, Expr, ExprPath, Member, LitStr};
-use rml_core::{AbstractValue, ItemTypeEnum};
-use std::collections::HashMap;
+use rml_core::{AbstractValue, AnimationSpec, ItemTypeEnum, Transition};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use regex::Regex;
 use std::path::Path;
@@ -37,7 +39,7 @@ pub fn load_components_from_path(path: &str) -> Result<Vec<ComponentDefinition>,
 
             components.push(ComponentDefinition {
                 name: component_name,
-                path: file_path.as_path().to_str().unwrap().to_string(),
+                path: file_path.to_string_lossy().to_string(),
             });
         }
     }
@@ -45,6 +47,50 @@ pub fn load_components_from_path(path: &str) -> Result<Vec<ComponentDefinition>,
     Ok(components)
 }
 
+thread_local! {
+    static FILE_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Run `f` on the tokens of an imported .rml file, and prefix its error with the location in the file.
+///
+/// The compiler gives no line information for tokens parsed from a string, so the file is read with
+/// proc_macro2's own implementation meanwhile. None of these tokens may leave `f`: the error
+/// is returned as a message, to be reported on a token of the macro input.
+pub fn in_file<T>(path: &str, f: impl FnOnce() -> syn::Result<T>) -> Result<T, String> {
+    let depth = FILE_DEPTH.with(|depth| depth.replace(depth.get() + 1));
+    if depth == 0 {
+        proc_macro2::fallback::force();
+    }
+    let result = f();
+    FILE_DEPTH.with(|file_depth| file_depth.set(depth));
+    if depth == 0 {
+        proc_macro2::fallback::unforce();
+    }
+
+    result.map_err(|error| {
+        let start = error.span().start();
+        if start.line == 0 {
+            // the error is about a token of the instance, not of the file
+            error.to_string()
+        } else {
+            format!("{}:{}:{}: {}", path, start.line, start.column + 1, error)
+        }
+    })
+}
+
+/// True while the tokens of an imported file are handled
+pub fn is_in_file() -> bool {
+    FILE_DEPTH.with(|depth| depth.get() > 0)
+}
+
+/// Parse the content of an imported .rml file
+fn parse_file(path: &str, content: &str, pre_parse: bool) -> syn::Result<RmlParser> {
+    let tokens = proc_macro2::TokenStream::from_str(content)
+        .map_err(|error| syn::Error::new(error.span(), format!("Invalid tokens: {}", error)))?;
+    syn::parse::Parser::parse2(|input: ParseStream| {
+        RmlParser::parse_with_path(input, path.to_string(), pre_parse)
+    }, tokens)
+}
 
 // Function to parse a property key; it can be a simple identifier or a composed one (base.field)
 pub fn parse_property_key(input: ParseStream) -> syn::Result<PropertyKey> {
@@ -102,18 +148,27 @@ pub fn property_parse(content: &ParseBuffer, pre_parse: bool) -> Result<Value, s
             
             value = Value::Ident(Ident::new(&composed_value, Span::call_site()));
             //println!("the content after : {}, composed value {}", content.to_string(), composed_value);
+        } else if content.peek2(Token![.]) {
+            // handle references to an edge of another node: header.bottom, parent.right
+            let target: Ident = content.parse()?;
+            content.parse::<Token![.]>()?;
+            let edge: Ident = content.parse()?;
+            value = Value::Lit(Lit::Str(LitStr::new(&format!("{}.{}", target, edge), target.span())));
         } else {
             value = Value::Ident(content.parse()?);
         }
     } else if content.peek(syn::token::Brace) {
         // Just try to parse the block directly and ignore any $ syntax errors for now
-        match content.parse::<syn::Block>() {
+        let fork = content.fork();
+        match fork.parse::<syn::Block>() {
             Ok(block) => {
+                content.advance_to(&fork);
                 value = Value::Block(block);
             }
             Err(_) => {
                 if !pre_parse { warn!("Failed to parse block directly, creating dummy block"); }
-                // If parsing fails due to $ syntax, create a dummy block
+                // If parsing fails due to $ syntax, skip the block and create a dummy value
+                content.parse::<proc_macro2::TokenTree>()?;
                 value = Value::Ident(Ident::new("test", Span::call_site()));
             }
         }
@@ -123,11 +178,67 @@ pub fn property_parse(content: &ParseBuffer, pre_parse: bool) -> Result<Value, s
     Ok(value)
 }
 
-impl RmlParser {
-    pub fn empty() -> RmlParser {
-        RmlParser { components: HashMap::new(), root_node: RmlNode { _ident: "".to_string(), properties: Vec::new(), children: Vec::new(), functions: Vec::new() } }
+/// Content of a `states { }` section: `name { property: value PropertyChanges { target: other property: value } }`
+pub fn parse_states(input: ParseStream, pre_parse: bool) -> syn::Result<Vec<StateDecl>> {
+    let mut states = Vec::new();
+    while !input.is_empty() {
+        let name: Ident = input.parse()?;
+        let body;
+        syn::braced!(body in input);
+        let mut changes = Vec::new();
+        while !body.is_empty() {
+            if body.peek(Ident) && body.peek2(syn::token::Brace) {
+                let node = RmlNode::parse_with_flag(&body, pre_parse)?;
+                if node._ident != "PropertyChanges" {
+                    return Err(syn::Error::new(node.span, "Expected a property or PropertyChanges in a state"));
+                }
+                let target = node.properties.iter()
+                    .find(|(_, k, _)| k.to_string() == "target")
+                    .map(|(_, _, v)| v.to_string())
+                    .ok_or_else(|| syn::Error::new(node.span, "PropertyChanges needs a `target`"))?;
+                changes.extend(node.properties.into_iter()
+                    .filter(|(_, k, _)| k.to_string() != "target")
+                    .map(|(_, k, v)| (Some(target.clone()), k, v)));
+            } else {
+                let key = parse_property_key(&body)?;
+                body.parse::<Token![:]>()?;
+                let value = property_parse(&body, pre_parse)?;
+                body.parse::<Token![,]>().ok();
+                changes.push((None, key, value));
+            }
+        }
+        states.push(StateDecl { name, changes });
+        input.parse::<Token![,]>().ok();
+    }
+    Ok(states)
+}
+
+/// Content of a `transitions { }` section: `Transition { from: "a" to: "b" NumberAnimation { duration: 100 } }`
+pub fn parse_transitions(input: ParseStream, pre_parse: bool) -> syn::Result<Vec<Transition>> {
+    let mut transitions = Vec::new();
+    while !input.is_empty() {
+        let node = RmlNode::parse_with_flag(input, pre_parse)?;
+        if node._ident != "Transition" {
+            return Err(syn::Error::new(node.span, "Expected a Transition"));
+        }
+        let [animation] = node.children.as_slice() else {
+            return Err(syn::Error::new(node.span, "A Transition holds a single animation"));
+        };
+        let mut transition = Transition::new(animation.animation_spec("Transition")?);
+        for (_, k, v) in &node.properties {
+            match (k.to_string().as_str(), value_to_abstract_value(v)) {
+                ("from", AbstractValue::String(from)) => transition = transition.from_state(&from),
+                ("to", AbstractValue::String(to)) => transition = transition.to_state(&to),
+                _ => return Err(syn::Error::new(k.span(), format!("Unexpected property `{}` in a Transition", k.to_string()))),
+            }
+        }
+        transitions.push(transition);
+        input.parse::<Token![,]>().ok();
     }
+    Ok(transitions)
+}
 
+impl RmlParser {
     pub fn parse_with_path(input: ParseStream, parent_path: String, pre_parse: bool) -> syn::Result<Self> {
         let mut components = HashMap::new();
         // Parse imports first
@@ -148,7 +259,9 @@ impl RmlParser {
                         Path::new(&parent_path).parent().unwrap_or(Path::new("")).join(&import.path).to_string_lossy().to_string()
                     };
 
-                    if let Ok(loaded_components) = load_components_from_path(&resolved_path) {
+                    let loaded_components = load_components_from_path(&resolved_path)
+                        .map_err(|error| syn::Error::new(import.span, error.to_string()))?;
+                    {
                         for component in loaded_components {
                             let component_name = if let Some(alias) = &import.alias {
                                 format!("{}::{}", alias, component.name)
@@ -191,6 +304,7 @@ impl Parse for ImportStatement {
         
         let path_str: LitStr = input.parse()?;
         let path = path_str.value(); //"components".to_string(); 
+        let span = path_str.span();
         
         let alias = if input.peek(Token![as]) {
             input.parse::<Token![as]>()?;
@@ -200,7 +314,7 @@ impl Parse for ImportStatement {
             None
         };
         
-        Ok(ImportStatement { path, alias })
+        Ok(ImportStatement { path, alias, span })
     }
 }
 
@@ -262,6 +376,7 @@ impl RmlNode {
     pub fn parse_with_flag(input: ParseStream, pre_parse: bool) -> syn::Result<Self> {
         // node name can be a sigle Ident or Ident . Ident
         let _ident: Ident = input.parse()?;
+        let span = _ident.span();
         let mut _ident = _ident.to_string();    
         if input.peek(Token![::]) {
             input.parse::<Token![::]>()?;
@@ -274,6 +389,9 @@ impl RmlNode {
 
         let mut properties = Vec::new();
         let mut children = Vec::new();
+        let mut behaviors = Vec::new();
+        let mut states = Vec::new();
+        let mut transitions = Vec::new();
         let mut functions = Vec::new();
 
         while !content.is_empty() {
@@ -281,10 +399,22 @@ impl RmlNode {
 
             if content.peek(Token![fn]) {
                 //let item: syn::Item = content.parse()?;
-                let item: syn::Item = match content.parse::<syn::Item>() {
-                    Ok(block) => block,
+                let fork = content.fork();
+                let item: syn::Item = match fork.parse::<syn::Item>() {
+                    Ok(block) => {
+                        content.advance_to(&fork);
+                        block
+                    }
                     Err(_) => {
                         if !pre_parse { warn!("Failed to parse Fn block directly, creating dummy block"); }
+                        // skip the function up to its body
+                        loop {
+                            if let proc_macro2::TokenTree::Group(group) = content.parse::<proc_macro2::TokenTree>()? {
+                                if group.delimiter() == proc_macro2::Delimiter::Brace {
+                                    break;
+                                }
+                            }
+                        }
 
                         syn::Item::Fn(syn::ItemFn {
                             attrs: Vec::new(),
@@ -328,6 +458,34 @@ impl RmlNode {
                 if content.peek(Ident) {
                     let fork = content.fork();
                     if let Ok(keyword) = fork.parse::<Ident>() {
+                        if keyword == "Behavior" && fork.fork().parse::<Ident>().is_ok_and(|on| on == "on") {
+                            // parse behavior declaration: Behavior on property { Animation { ... } }
+                            content.parse::<Ident>()?;
+                            content.parse::<Ident>()?;
+                            let property: Ident = content.parse()?;
+                            let body;
+                            syn::braced!(body in content);
+                            let animation = RmlNode::parse_with_flag(&body, pre_parse)?;
+                            if !body.is_empty() {
+                                return Err(body.error("A Behavior holds a single animation"));
+                            }
+                            behaviors.push((property, animation.animation_spec("Behavior")?));
+                            continue;
+                        }
+
+                        if (keyword == "states" || keyword == "transitions") && fork.peek(syn::token::Brace) {
+                            // parse states { name { ... } } and transitions { Transition { ... } } sections
+                            content.parse::<Ident>()?;
+                            let section;
+                            syn::braced!(section in content);
+                            if keyword == "states" {
+                                states.extend(parse_states(&section, pre_parse)?);
+                            } else {
+                                transitions.extend(parse_transitions(&section, pre_parse)?);
+                            }
+                            continue;
+                        }
+
                         if keyword == "signal" {
                             // parse signal declaration: signal identifier
                             content.parse::<Ident>()?; // consume "signal" string
@@ -397,8 +555,12 @@ impl RmlNode {
 
         Ok(Self {
             _ident,
+            span,
             properties,
             children,
+            behaviors,
+            states,
+            transitions,
             functions,
         })
     }
@@ -407,25 +569,221 @@ impl RmlNode {
 
 impl RmlNode {
 
-    pub fn generate_with_components_and_counter(&mut self, components: &HashMap<String, ComponentDefinition>, id_counter: &mut u32, properties_mapping: &HashMap<String, AbstractValue>) -> GenResult {
+    /// Type of a built-in node
+    fn check_node_type(&self) -> syn::Result<ItemTypeEnum> {
         let node_type_str = self._ident.to_string();
-
-        // Check if this is a custom component
-        if let Some(component_def) = components.get(&node_type_str) {
-            // For custom components, we expand them by generating the component's node
-            // and applying the properties passed to the component
-            let cmp = self.generate_custom_component_with_counter(component_def, id_counter, properties_mapping);
-            return cmp;
-        }
-
         let node_type = match node_type_str.as_str() {
             "Node" => ItemTypeEnum::Node,
             "Rectangle" => ItemTypeEnum::Rectangle,
             "Text" => ItemTypeEnum::Text,
             "MouseArea" => ItemTypeEnum::MouseArea,
             "Texture" => ItemTypeEnum::Texture,
-            _ => panic!("Unknown node type: {}", node_type_str),
+            "Row" => ItemTypeEnum::Row,
+            "Column" => ItemTypeEnum::Column,
+            "Grid" => ItemTypeEnum::Grid,
+            "Repeater" => ItemTypeEnum::Repeater,
+            "ListView" => ItemTypeEnum::ListView,
+            "NumberAnimation" => ItemTypeEnum::NumberAnimation,
+            "ColorAnimation" => ItemTypeEnum::ColorAnimation,
+            "Timer" => ItemTypeEnum::Timer,
+            "TextInput" => ItemTypeEnum::TextInput,
+            "TextArea" => ItemTypeEnum::TextArea,
+            "DropArea" => ItemTypeEnum::DropArea,
+            _ => return Err(syn::Error::new(self.span, format!("Unknown node type: {}", node_type_str))),
         };
+
+        Ok(node_type)
+    }
+
+    /// Animation held by a `Behavior on <property>` declaration or a Transition (`holder`),
+    /// its values are known at compile time
+    fn animation_spec(&self, holder: &str) -> syn::Result<AnimationSpec> {
+        if !matches!(self._ident.as_str(), "NumberAnimation" | "ColorAnimation") || !self.children.is_empty() {
+            return Err(syn::Error::new(self.span, format!("A {} holds a NumberAnimation or a ColorAnimation", holder)));
+        }
+        let mut values = Vec::new();
+        for (_t, k, v) in self.properties.iter() {
+            if let Value::Block(_) = v {
+                return Err(syn::Error::new(k.span(), format!("The animation of a {} only takes literal values", holder)));
+            }
+            values.push((k.to_string(), value_to_abstract_value(v)));
+        }
+        AnimationSpec::from_properties(values.iter().map(|(name, value)| (name.as_str(), value)))
+            .map_err(|error| syn::Error::new(self.span, error))
+    }
+
+    /// An animation node gets a `running` property, and a `target` and a `property` naming what it animates
+    fn prepare_animation(&mut self) {
+        if self._ident != "NumberAnimation" && self._ident != "ColorAnimation" {
+            return;
+        }
+        let defaults: [(&str, PropertyType, Lit); 3] = [
+            ("running", PropertyType::Bool, syn::parse_quote!(false)),
+            ("target", PropertyType::String, syn::parse_quote!("")),
+            ("property", PropertyType::String, syn::parse_quote!("")),
+        ];
+        for (name, property_type, value) in defaults {
+            if !self.properties.iter().any(|(_, k, _)| k.to_string() == name) {
+                self.properties.push((property_type, PropertyKey::Simple(Ident::new(name, self.span)), Value::Lit(value)));
+            }
+        }
+    }
+
+    /// A Timer, a TextInput, a TextArea, a MouseArea and a DropArea get their properties, and their signals
+    /// (`triggered`, `accepted`, `entered`...), so their handlers (`on_triggered`...) are bound like the handlers of
+    /// declared signals
+    fn prepare_item_properties(&mut self) {
+        let (defaults, signals): (Vec<(&str, PropertyType, Lit)>, &[&str]) = match self._ident.as_str() {
+            "Timer" => (vec![
+                ("interval", PropertyType::Number, syn::parse_quote!(1000)),
+                ("running", PropertyType::Bool, syn::parse_quote!(false)),
+                ("repeat", PropertyType::Bool, syn::parse_quote!(false)),
+                ("triggered_on_start", PropertyType::Bool, syn::parse_quote!(false)),
+            ], &["triggered"]),
+            "TextInput" => (vec![
+                ("text", PropertyType::String, syn::parse_quote!("")),
+                ("placeholder", PropertyType::String, syn::parse_quote!("")),
+                ("echo_mode", PropertyType::String, syn::parse_quote!("normal")),
+                ("max_length", PropertyType::Number, syn::parse_quote!(0)),
+                ("read_only", PropertyType::Bool, syn::parse_quote!(false)),
+                ("cursor_position", PropertyType::Number, syn::parse_quote!(0)),
+                ("selection_start", PropertyType::Number, syn::parse_quote!(0)),
+                ("selection_end", PropertyType::Number, syn::parse_quote!(0)),
+                ("focusable", PropertyType::Bool, syn::parse_quote!(true)),
+            ], &["accepted"]),
+            "TextArea" => (vec![
+                ("text", PropertyType::String, syn::parse_quote!("")),
+                ("placeholder", PropertyType::String, syn::parse_quote!("")),
+                ("wrap", PropertyType::Bool, syn::parse_quote!(true)),
+                ("max_length", PropertyType::Number, syn::parse_quote!(0)),
+                ("read_only", PropertyType::Bool, syn::parse_quote!(false)),
+                ("cursor_position", PropertyType::Number, syn::parse_quote!(0)),
+                ("selection_start", PropertyType::Number, syn::parse_quote!(0)),
+                ("selection_end", PropertyType::Number, syn::parse_quote!(0)),
+                ("focusable", PropertyType::Bool, syn::parse_quote!(true)),
+            ], &[]),
+            "MouseArea" => (vec![
+                ("pressed", PropertyType::Bool, syn::parse_quote!(false)),
+                ("contains_mouse", PropertyType::Bool, syn::parse_quote!(false)),
+            ], &[]),
+            "DropArea" => (vec![
+                ("keys", PropertyType::String, syn::parse_quote!("")),
+                ("contains_drag", PropertyType::Bool, syn::parse_quote!(false)),
+                ("source", PropertyType::String, syn::parse_quote!("")),
+                ("drag_x", PropertyType::Number, syn::parse_quote!(0)),
+                ("drag_y", PropertyType::Number, syn::parse_quote!(0)),
+            ], &["entered", "exited", "dropped"]),
+            _ => return,
+        };
+        for (name, property_type, value) in defaults {
+            if !self.properties.iter().any(|(_, k, _)| k.to_string() == name) {
+                self.properties.push((property_type, PropertyKey::Simple(Ident::new(name, self.span)), Value::Lit(value)));
+            }
+        }
+        // declared first, the handlers are bound to them when the properties are created
+        for signal in signals.iter().rev() {
+            if !self.properties.iter().any(|(_, k, _)| k.to_string() == *signal) {
+                self.properties.insert(0, (PropertyType::Signal, PropertyKey::Signal(Ident::new(signal, self.span)), Value::Ident(Ident::new("signal", self.span))));
+            }
+        }
+    }
+
+    /// A MouseArea dragging an item (declaring `drag_target`) gets a `drag_active` property
+    fn prepare_drag(&mut self) {
+        let declares = |name: &str| self.properties.iter().any(|(_, k, _)| k.to_string() == name);
+        if !declares("drag_target") || declares("drag_active") {
+            return;
+        }
+        self.properties.push((PropertyType::Bool, PropertyKey::Simple(Ident::new("drag_active", self.span)), Value::Lit(syn::parse_quote!(false))));
+    }
+
+    /// A focusable node (one declaring `focusable`, a TextInput or a TextArea) gets an `active_focus` and a
+    /// `focus_order` property
+    fn prepare_focus(&mut self) {
+        if !self.properties.iter().any(|(_, k, _)| k.to_string() == "focusable") {
+            return;
+        }
+        let defaults: [(&str, PropertyType, Lit); 2] = [
+            ("active_focus", PropertyType::Bool, syn::parse_quote!(false)),
+            ("focus_order", PropertyType::Number, syn::parse_quote!(0)),
+        ];
+        for (name, property_type, value) in defaults {
+            if !self.properties.iter().any(|(_, k, _)| k.to_string() == name) {
+                self.properties.push((property_type, PropertyKey::Simple(Ident::new(name, self.span)), Value::Lit(value)));
+            }
+        }
+    }
+
+    /// A node with states gets a `state` property, the name of the active state
+    fn prepare_states(&mut self) {
+        if (self.states.is_empty() && self.transitions.is_empty()) || self.properties.iter().any(|(_, k, _)| k.to_string() == "state") {
+            return;
+        }
+        self.properties.push((PropertyType::String, PropertyKey::Simple(Ident::new("state", self.span)), Value::Lit(syn::parse_quote!(""))));
+    }
+
+    /// The child of a Repeater or a ListView is its delegate, it gets an `index` and a `model_data` property set for
+    /// each entry of the model (and `is_current` in a ListView). `model_data` is a string, unless the delegate
+    /// declares it with another type.
+    fn prepare_repeater_delegate(&mut self) -> syn::Result<()> {
+        let is_list_view = self._ident == "ListView";
+        if self._ident != "Repeater" && !is_list_view {
+            return Ok(());
+        }
+        if self.children.len() != 1 {
+            return Err(syn::Error::new(self.span, format!("A {} must have a single child, its delegate", self._ident)));
+        }
+        let span = self.span;
+        let delegate = &mut self.children[0];
+        let mut defaults: Vec<(&str, PropertyType, Lit)> = vec![
+            ("index", PropertyType::Number, syn::parse_quote!(0)),
+            ("model_data", PropertyType::String, syn::parse_quote!("")),
+        ];
+        if is_list_view {
+            defaults.push(("is_current", PropertyType::Bool, syn::parse_quote!(false)));
+        }
+        for (name, property_type, value) in defaults {
+            if !delegate.properties.iter().any(|(_, k, _)| k.to_string() == name) {
+                delegate.properties.push((property_type, PropertyKey::Simple(Ident::new(name, span)), Value::Lit(value)));
+            }
+        }
+
+        // the properties of the ListView itself, so they can be used in the bindings and callbacks
+        if is_list_view {
+            let list_defaults: [(&str, PropertyType, Lit); 6] = [
+                ("content_y", PropertyType::Number, syn::parse_quote!(0)),
+                ("content_height", PropertyType::Number, syn::parse_quote!(0)),
+                ("current_index", PropertyType::Number, syn::parse_quote!(0)),
+                ("spacing", PropertyType::Number, syn::parse_quote!(0)),
+                ("flick_deceleration", PropertyType::Number, syn::parse_quote!(1500)),
+                ("interactive", PropertyType::Bool, syn::parse_quote!(true)),
+            ];
+            for (name, property_type, value) in list_defaults {
+                if !self.properties.iter().any(|(_, k, _)| k.to_string() == name) {
+                    self.properties.push((property_type, PropertyKey::Simple(Ident::new(name, span)), Value::Lit(value)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn generate_with_components_and_counter(&mut self, components: &HashMap<String, ComponentDefinition>, id_counter: &mut u32, properties_mapping: &HashMap<String, AbstractValue>) -> syn::Result<GenResult> {
+        let node_type_str = self._ident.to_string();
+
+        // Check if this is a custom component
+        if let Some(component_def) = components.get(&node_type_str) {
+            // For custom components, we expand them by generating the component's node
+            // and applying the properties passed to the component
+            return self.generate_custom_component_with_counter(component_def, id_counter, properties_mapping);
+        }
+
+        let node_type = self.check_node_type()?;
+        self.prepare_repeater_delegate()?;
+        self.prepare_animation();
+        self.prepare_item_properties();
+        self.prepare_focus();
+        self.prepare_drag();
+        self.prepare_states();
         
         // search for the id property and generate a uuid if not found
         let id = self
@@ -448,12 +806,34 @@ impl RmlNode {
 
         let temp_node = format_ident!("temp_node_{}", id);
 
-        let child_results: Vec<GenResult> = self
+        let mut child_results: Vec<GenResult> = self
             .children
             //.iter()
             .iter_mut()
             .map(|child| child.generate_with_components_and_counter(components, id_counter, properties_mapping))
-            .collect();
+            .collect::<syn::Result<_>>()?;
+
+        // the delegate of a Repeater or a ListView is not added to the tree, it is instantiated for the entries of the model
+        let mut delegate_functions = quote! {};
+        let delegate_code = if matches!(node_type, ItemTypeEnum::Repeater | ItemTypeEnum::ListView) {
+            let (delegate_id, delegate_node, functions, delegate_initializer) = child_results.remove(0);
+            let delegate_temp_node = format_ident!("temp_node_{}", delegate_id);
+            delegate_functions = functions;
+            let set_delegate = if node_type == ItemTypeEnum::Repeater {
+                format_ident!("set_repeater_delegate")
+            } else {
+                format_ident!("set_list_view_delegate")
+            };
+            quote! {
+                engine.#set_delegate(#temp_node, Component::new(move |engine: &mut RmlEngine| {
+                    #delegate_node;
+                    #delegate_initializer
+                    Some(#delegate_temp_node)
+                }));
+            }
+        } else {
+            quote! {}
+        };
 
         let child_code: Vec<proc_macro2::TokenStream> = child_results
             .iter()
@@ -537,9 +917,69 @@ impl RmlNode {
                 }
             }).collect();
 
+        // the behaviors are added once the initial values are set, they are not animated
+        let behaviors: Vec<proc_macro2::TokenStream> = self
+            .behaviors
+            .iter()
+            .map(|(property, spec)| quote! {
+                engine.add_behavior(#temp_node, stringify!(#property), #spec);
+            })
+            .collect();
+
+        let states: Vec<proc_macro2::TokenStream> = self
+            .states
+            .iter()
+            .map(|state| {
+                let name = state.name.to_string();
+                let changes = state.changes.iter().map(|(target, k, v)| {
+                    let property = k.to_string();
+                    let target_tokens = match target {
+                        Some(target) => quote! { Some(#target) },
+                        None => quote! { None },
+                    };
+                    match v {
+                        Value::Block(block) => {
+                            let block_string = format!("{}", quote! { #block });
+                            let target_id = target.clone().unwrap_or_else(|| id.clone());
+                            let (nodes, properties): (Vec<String>, Vec<String>) = find_related_property_for_binding(target_id, property.clone(), block_string)
+                                .into_iter()
+                                .unzip();
+                            quote! {
+                                .with_binding(#target_tokens, #property, &[#((#nodes, #properties)),*], move |engine: &mut RmlEngine| {
+                                    let value: AbstractValue = #block .into();
+                                    value
+                                })
+                            }
+                        }
+                        _ => {
+                            let value = value_to_abstract_value(v);
+                            quote! { .with_value(#target_tokens, #property, #value) }
+                        }
+                    }
+                });
+                quote! {
+                    engine.add_state(#temp_node, rml_core::State::new(#name) #(#changes)*);
+                }
+            })
+            .collect();
+
+        let transitions: Vec<proc_macro2::TokenStream> = self
+            .transitions
+            .iter()
+            .map(|transition| {
+                let Transition { from, to, spec } = transition;
+                quote! {
+                    engine.add_transition(#temp_node, rml_core::Transition::new(#spec).from_state(#from).to_state(#to));
+                }
+            })
+            .collect();
+
         let initializer_code = quote! {
             #(#initializer)*
             #(#initializer_of_childs)*
+            #(#behaviors)*
+            #(#states)*
+            #(#transitions)*
         };
 
         let functions: Vec<proc_macro2::TokenStream> = self
@@ -549,7 +989,7 @@ impl RmlNode {
                 let f_name = f.sig.ident.clone();
                 let f_inputs = f.sig.inputs.clone();
                 let f_output = f.sig.output.clone();
-                let f_body = inject_engine_in_block((*f.block).clone(), false);
+                let f_body = f.block.clone();
 
                 let res = match f_output {
                     syn::ReturnType::Default => {
@@ -568,6 +1008,7 @@ impl RmlNode {
 
         let functions_code = quote! {
             #(#functions)*
+            #delegate_functions
         };
 
         // Generate the properties code
@@ -616,23 +1057,18 @@ impl RmlNode {
                             quote! {}
                         }
                     } else {
-                        // System event handlers
-                        
-                        // Check if this is a mouse event and if we're in a MouseArea
-                        let is_mouse_event = matches!(event_name, 
-                            "mouse_down" | "mouse_up" | "mouse_move" | "mouse_wheel" | 
-                            "mouse_enter" | "mouse_leave" | "click"
-                        );
-                        
-                        if is_mouse_event && node_type != ItemTypeEnum::MouseArea {
-                            // Mouse events are only allowed on MouseArea nodes
-                            panic!("Mouse events can only be used in MouseArea nodes");
-                        }
-                        
+                        // System event handlers, `on_<event>_capture` ones run before the handlers of the descendants
+                        let (event_name, capture) = match event_name.strip_suffix("_capture") {
+                            Some(event_name) => (event_name, true),
+                            None => (event_name, false),
+                        };
                         let event_type = match event_name {
                             "key_down" => quote! { EventType::KeyDown },
                             "key_up" => quote! { EventType::KeyUp },
                             "key_pressed" => quote! { EventType::KeyPressed },
+                            "char_input" => quote! { EventType::CharInput },
+                            "focus_in" => quote! { EventType::FocusIn },
+                            "focus_out" => quote! { EventType::FocusOut },
                             "mouse_down" => quote! { EventType::MouseDown },
                             "mouse_up" => quote! { EventType::MouseUp },
                             "mouse_move" => quote! { EventType::MouseMove },
@@ -640,6 +1076,9 @@ impl RmlNode {
                             "mouse_enter" => quote! { EventType::MouseEnter },
                             "mouse_leave" => quote! { EventType::MouseLeave },
                             "click" => quote! { EventType::Click },
+                            "double_click" => quote! { EventType::DoubleClick },
+                            "press_and_hold" => quote! { EventType::PressAndHold },
+                            "canceled" => quote! { EventType::Canceled },
                             "window_resize" => quote! { EventType::WindowResize },
                             "window_focus" => quote! { EventType::WindowFocus },
                             "window_lost_focus" => quote! { EventType::WindowLostFocus },
@@ -647,9 +1086,10 @@ impl RmlNode {
                         };
                         
                         if let Value::Block(block) = v {
+                            let add_handler = if capture { quote! { add_capture_event_handler } } else { quote! { add_event_handler } };
                             quote! {
                                 let cb_id = engine.add_callback( |engine| #block );
-                                engine.add_event_handler( #event_type, #id, cb_id );
+                                engine.#add_handler( #event_type, #id, cb_id );
                             }
                         } else {
                             quote! {}
@@ -708,14 +1148,17 @@ impl RmlNode {
                 #child_code;
                 engine.add_child(#temp_node, #child_temp_nodes);
             )*
+
+            #delegate_code
         };
 
-        (id, node_code, functions_code, initializer_code)
+        Ok((id, node_code, functions_code, initializer_code))
     }
 
-    fn generate_custom_component_with_counter(&self, component_def: &ComponentDefinition, id_counter: &mut u32, properties_mapping: &HashMap<String, AbstractValue>) -> GenResult {
+    fn generate_custom_component_with_counter(&self, component_def: &ComponentDefinition, id_counter: &mut u32, properties_mapping: &HashMap<String, AbstractValue>) -> syn::Result<GenResult> {
         // Read and parse the component file
-        let file_content = fs::read_to_string(&component_def.path).unwrap();
+        let file_content = fs::read_to_string(&component_def.path)
+            .map_err(|error| syn::Error::new(self.span, format!("Can't read component '{}': {}", component_def.path, error)))?;
 
         // we need to perform the id diversification in advance
         // to be able to find it in the properties_mapping
@@ -748,12 +1191,20 @@ impl RmlNode {
             file_content
         };
 
-        let file_content = transform_dollar_syntax(&file_content, properties_mapping);
-        let tokens: proc_macro::TokenStream = file_content.parse().unwrap();
-
-        let res= syn::parse::Parser::parse(|input: ParseStream| {
-          RmlParser::parse_with_path(input, component_def.path.clone(), false)
-        }, tokens.clone()).unwrap();
+        let tokens = proc_macro2::TokenStream::from_str(&file_content)
+            .map_err(|error| syn::Error::new(self.span, format!("{}: {}", component_def.path, error)))?;
+        let tokens = transform_dollar_syntax(tokens, properties_mapping)
+            .map_err(|error| syn::Error::new(self.span, format!("{}: {}", component_def.path, error)))?;
+
+        let res = syn::parse::Parser::parse2(|input: ParseStream| {
+            RmlParser::parse_with_path(input, component_def.path.clone(), false)
+        }, tokens).map_err(|error| {
+            // parse the file again on its own to locate the error
+            let message = in_file(&component_def.path, || parse_file(&component_def.path, &file_content, true).map(|_| ()))
+                .err()
+                .unwrap_or_else(|| format!("{}: {}", component_def.path, error));
+            syn::Error::new(self.span, message)
+        })?;
 
         let (mut component_node, components) = (res.root_node, res.components);
 
@@ -768,11 +1219,14 @@ impl RmlNode {
             }
         }
         
-        // Add children from this instance to the component
+        // Add children and behaviors from this instance to the component
         component_node.children.extend(self.children.clone());
+        component_node.behaviors.extend(self.behaviors.clone());
+        component_node.states.extend(self.states.clone());
+        component_node.transitions.extend(self.transitions.clone());
         
         // Generate the component with the applied properties
-        let component_gen_res = component_node.generate_with_components_and_counter(&components, id_counter, properties_mapping);
+        let component_gen_res = component_node.generate_with_components_and_counter(&components, id_counter, properties_mapping)?;
         let new_id = component_gen_res.0.clone();
 
         // replace the original id present in the component (in callbacks) with the new id
@@ -801,10 +1255,21 @@ impl RmlNode {
             initializer_code = new_initializer_str.parse().unwrap_or(initializer_code);
         }
 
-        (new_id, component_code, functions_code, initializer_code)
+        // record where the instance comes from, for the hot reload (added after the id replacement, the path must stay intact)
+        let component_path = fs::canonicalize(&component_def.path)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| component_def.path.clone());
+        let overridden: Vec<String> = self.properties.iter().map(|(_, k, _)| k.to_string()).collect();
+        let instance_children = self.children.len();
+        let component_code = quote! {
+            #component_code
+            engine.watch_component(#new_id, #component_path, &[#(#overridden),*], #instance_children);
+        };
+
+        Ok((new_id, component_code, functions_code, initializer_code))
     }
 
-    pub fn pre_generate_with_components_and_counter(&mut self, components: &HashMap<String, ComponentDefinition>, id_counter: &mut u32) -> HashMap<String, AbstractValue> {
+    pub fn pre_generate_with_components_and_counter(&mut self, components: &HashMap<String, ComponentDefinition>, id_counter: &mut u32, checks: &mut Vec<FileCheck>) -> syn::Result<HashMap<String, AbstractValue>> {
         
         let node_type_str = self._ident.to_string();
 
@@ -812,9 +1277,15 @@ impl RmlNode {
         if let Some(component_def) = components.get(&node_type_str) {
             // For custom components, we expand them by generating the component's node
             // and applying the properties passed to the component
-            let cmp = self.pre_generate_custom_component_with_counter(component_def, id_counter);
-            return cmp;
+            return self.pre_generate_custom_component_with_counter(component_def, id_counter, checks);
         }
+        self.check_node_type()?;
+        self.prepare_repeater_delegate()?;
+        self.prepare_animation();
+        self.prepare_item_properties();
+        self.prepare_focus();
+        self.prepare_drag();
+        self.prepare_states();
         
         // search for the id property and generate a uuid if not found
         let id: String = self
@@ -838,7 +1309,7 @@ impl RmlNode {
         // for each child, we need to merge the hashmap into a single one
         let mut merged_child_results: HashMap<String, AbstractValue> = HashMap::new();
         for i in 0..self.children.len() {
-            merged_child_results.extend(self.children[i].pre_generate_with_components_and_counter(components, id_counter));
+            merged_child_results.extend(self.children[i].pre_generate_with_components_and_counter(components, id_counter, checks)?);
         }
 
         let mut properties: HashMap<String, AbstractValue> = self
@@ -895,25 +1366,48 @@ impl RmlNode {
             properties.insert(format!("{}.{}", id, "computed_height"), AbstractValue::Number(0.0));
         }
 
-        properties
+        Ok(properties)
     }
     
-    fn pre_generate_custom_component_with_counter(&self, component_def: &ComponentDefinition, id_counter: &mut u32) -> HashMap<String, AbstractValue> {
+    fn pre_generate_custom_component_with_counter(&self, component_def: &ComponentDefinition, id_counter: &mut u32, checks: &mut Vec<FileCheck>) -> syn::Result<HashMap<String, AbstractValue>> {
         // Read and parse the component file
-        let file_content = fs::read_to_string(&component_def.path).unwrap();
-        let tokens: proc_macro::TokenStream = file_content.parse().unwrap();
-
-        let mut res = RmlParser::empty();
-        match syn::parse::Parser::parse(|input: ParseStream| {
-            res = RmlParser::parse_with_path(input, component_def.path.clone(), true).unwrap();
-            Ok(RmlParser::empty())
-        }, tokens.clone()) {
-            Ok(r) => r,
-            Err(_) => {
-                RmlParser::empty()
+        let file_content = fs::read_to_string(&component_def.path)
+            .map_err(|error| syn::Error::new(self.span, format!("Can't read component '{}': {}", component_def.path, error)))?;
+
+        // same id diversification as in the generation
+        let re = Regex::new(r#"id:\s*(\w+)"#).unwrap();
+        let original_id = re.captures(&file_content)
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str().to_string())
+            .unwrap_or_default();
+        let instance_id = self.properties.iter().find_map(|(_t, k, v)| {
+            if k.to_string() == "id" { Some(v.to_string()) } else { None }
+        }).unwrap_or_else(|| format!("generated_id_{}", id_counter));
+
+        let first_check = checks.len();
+        let path = component_def.path.clone();
+        let result = in_file(&path, || {
+            let res = parse_file(&path, &file_content, true)?;
+            self.pre_generate_component_node(res, component_def, id_counter, checks)
+        }).map_err(|message| syn::Error::new(self.span, message))?;
+
+        checks.push(FileCheck {
+            path: component_def.path.clone(),
+            original_id,
+            instance_id,
+            span: None,
+        });
+        if !is_in_file() {
+            // the checks of nested files are reported on the outermost instance
+            for check in &mut checks[first_check..] {
+                check.span = Some(self.span);
             }
-        };
+        }
 
+        Ok(result)
+    }
+
+    fn pre_generate_component_node(&self, res: RmlParser, component_def: &ComponentDefinition, id_counter: &mut u32, checks: &mut Vec<FileCheck>) -> syn::Result<HashMap<String, AbstractValue>> {
         let (mut component_node, components) = (res.root_node, res.components);
 
         // remove id property if exist
@@ -934,10 +1428,378 @@ impl RmlNode {
             }
         }
         
-        // Add children from this instance to the component
+        // Add children and behaviors from this instance to the component
         component_node.children.extend(self.children.clone());
+        component_node.behaviors.extend(self.behaviors.clone());
+        component_node.states.extend(self.states.clone());
+        component_node.transitions.extend(self.transitions.clone());
         
         // Generate the component with the applied properties
-        component_node.pre_generate_with_components_and_counter(&components, id_counter)
+        component_node.pre_generate_with_components_and_counter(&components, id_counter, checks)
+    }
+}
+
+/// The spans of the explicit ids of the tree, to report the errors of the generated handles on them
+pub fn id_spans(node: &RmlNode, spans: &mut HashMap<String, Span>) {
+    for (_, key, value) in &node.properties {
+        if let (true, Value::Ident(id)) = (key.to_string() == "id", value) {
+            spans.entry(id.to_string()).or_insert_with(|| id.span());
+        }
+    }
+    for child in &node.children {
+        id_spans(child, spans);
+    }
+}
+
+/// Types and construction of the typed handles of `rml_ui!`: the fields of the struct `ui`, one for each node
+/// with an explicit id, and a `<Ui><Node>Handle` type for each of them, holding a `PropertyHandle` with a getter
+/// and a setter for each property of known type
+pub fn generate_ui_handles(ui: &Ident, vis: &syn::Visibility, properties_mapping: &HashMap<String, AbstractValue>, id_spans: &HashMap<String, Span>)
+    -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let mut nodes: BTreeMap<&str, Vec<(&str, proc_macro2::TokenStream)>> = BTreeMap::new();
+    for (key, value) in properties_mapping {
+        let Some((node, property)) = key.split_once('.') else { continue };
+        let value_type = match value {
+            AbstractValue::Number(_) => quote! { f32 },
+            AbstractValue::String(_) => quote! { String },
+            AbstractValue::Bool(_) => quote! { bool },
+            AbstractValue::Color(_) => quote! { Color },
+            _ => continue,
+        };
+        // nodes without id can't be named, neither can composed properties
+        if node.starts_with("generated_id_") || syn::parse_str::<Ident>(node).is_err() || syn::parse_str::<Ident>(property).is_err() {
+            continue;
+        }
+        nodes.entry(node).or_default().push((property, value_type));
     }
-}
\ No newline at end of file
+
+    let mut handle_types = Vec::new();
+    let mut fields = Vec::new();
+    let mut type_names: HashMap<String, &str> = HashMap::new();
+    for (node, mut properties) in nodes {
+        properties.sort_by(|a, b| a.0.cmp(b.0));
+        let node_ident = format_ident!("{}", node);
+        let type_name: String = node.split('_')
+            .map(|part| {
+                let mut chars = part.chars();
+                chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+            })
+            .collect();
+        let handle_type = format_ident!("{}{}Handle", ui, type_name);
+        // `my_btn` and `myBtn`, or `a_b` and `a__b`, would give the same type
+        if let Some(other) = type_names.insert(handle_type.to_string(), node) {
+            let span = id_spans.get(node).copied().unwrap_or_else(|| ui.span());
+            return Err(syn::Error::new(span, format!("The ids '{}' and '{}' give the same handle type '{}', rename one of them.", other, node, handle_type)));
+        }
+
+        let accessors = properties.iter().map(|(property, value_type)| {
+            let getter = format_ident!("{}", property);
+            let setter = format_ident!("set_{}", property);
+            // a property named `set_x` would collide with the setter of `x`
+            let setter = (!properties.iter().any(|(other, _)| setter == other)).then(|| quote! {
+                pub fn #setter(&self, engine: &mut RmlEngine, value: #value_type) -> bool {
+                    self.#getter.set(engine, value)
+                }
+            });
+            quote! {
+                pub fn #getter(&self, engine: &RmlEngine) -> #value_type {
+                    self.#getter.get(engine)
+                }
+                #setter
+            }
+        });
+        let property_idents: Vec<Ident> = properties.iter().map(|(property, _)| format_ident!("{}", property)).collect();
+        let property_types = properties.iter().map(|(_, value_type)| value_type);
+        let property_names = properties.iter().map(|(property, _)| *property);
+
+        handle_types.push(quote! {
+            #[derive(Debug, Clone, Copy)]
+            #vis struct #handle_type {
+                #( pub #property_idents: PropertyHandle<#property_types>, )*
+            }
+
+            impl #handle_type {
+                #( #accessors )*
+            }
+        });
+        fields.push((node_ident.clone(), handle_type.clone(), quote! {
+            #handle_type {
+                #( #property_idents: PropertyHandle::new(&engine, #node, #property_names)
+                    .expect(concat!("missing property ", #node, ".", #property_names)), )*
+            }
+        }));
+    }
+
+    let field_idents: Vec<&Ident> = fields.iter().map(|(ident, _, _)| ident).collect();
+    let field_types = fields.iter().map(|(_, handle_type, _)| handle_type);
+    let field_values = fields.iter().map(|(_, _, value)| value);
+    let handle_types = quote! {
+        #( #handle_types )*
+
+        #[derive(Debug, Clone, Copy)]
+        #vis struct #ui {
+            #( pub #field_idents: #field_types, )*
+        }
+    };
+    let handles = quote! {
+        #ui {
+            #( #field_idents: #field_values, )*
+        }
+    };
+    Ok((handle_types, handles))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> syn::Result<RmlParser> {
+        let tokens = proc_macro2::TokenStream::from_str(source).unwrap();
+        syn::parse::Parser::parse2(|input: ParseStream| {
+            RmlParser::parse_with_path(input, "".to_string(), true)
+        }, tokens)
+    }
+
+    fn pre_generate(source: &str) -> syn::Result<HashMap<String, AbstractValue>> {
+        let res = parse(source)?;
+        let (mut node, components) = (res.root_node, res.components);
+        node.pre_generate_with_components_and_counter(&components, &mut 0, &mut Vec::new())
+    }
+
+    #[test]
+    fn test_unknown_node_type_span() {
+        let error = pre_generate("Node {\n    Rectangel { width: 10 }\n}").unwrap_err();
+        assert_eq!(error.to_string(), "Unknown node type: Rectangel");
+        assert_eq!(error.span().start().line, 2);
+        assert_eq!(error.span().start().column, 4);
+    }
+
+    #[test]
+    fn test_event_handlers_on_any_item() {
+        let source = "Node {\n    Rectangle {\n        id: panel\n        on_click: { }\n        on_key_pressed_capture: { }\n    }\n}";
+        let mapping = pre_generate(source).unwrap();
+        let res = parse(source).unwrap();
+        let (mut node, components) = (res.root_node, res.components);
+        let (_, code, _, _) = node.generate_with_components_and_counter(&components, &mut 0, &mapping).unwrap();
+        let code = code.to_string();
+        assert!(code.contains("add_event_handler (EventType :: Click , \"panel\""));
+        assert!(code.contains("add_capture_event_handler (EventType :: KeyPressed , \"panel\""));
+    }
+
+    #[test]
+    fn test_pointer_grab() {
+        let source = "Node {\n    MouseArea { id: knob on_canceled: { } }\n}";
+        let mapping = pre_generate(source).unwrap();
+        assert_eq!(mapping.get("knob.pressed"), Some(&AbstractValue::Bool(false)));
+        assert_eq!(mapping.get("knob.contains_mouse"), Some(&AbstractValue::Bool(false)));
+
+        let res = parse(source).unwrap();
+        let (mut node, components) = (res.root_node, res.components);
+        let (_, code, _, _) = node.generate_with_components_and_counter(&components, &mut 0, &mapping).unwrap();
+        assert!(code.to_string().contains("add_event_handler (EventType :: Canceled , \"knob\""));
+    }
+
+    #[test]
+    fn test_click_handlers() {
+        let source = "Node {\n    MouseArea { id: file on_double_click: { } on_press_and_hold: { } }\n}";
+        let mapping = pre_generate(source).unwrap();
+        let res = parse(source).unwrap();
+        let (mut node, components) = (res.root_node, res.components);
+        let (_, code, _, _) = node.generate_with_components_and_counter(&components, &mut 0, &mapping).unwrap();
+        let code = code.to_string();
+        assert!(code.contains("add_event_handler (EventType :: DoubleClick , \"file\""));
+        assert!(code.contains("add_event_handler (EventType :: PressAndHold , \"file\""));
+    }
+
+    #[test]
+    fn test_repeater_delegate() {
+        let mapping = pre_generate("Node {\n    Repeater {\n        model: 3\n        Text { id: row number model_data: 0 }\n    }\n}").unwrap();
+        assert_eq!(mapping.get("row.index"), Some(&AbstractValue::Number(0.0)));
+        assert_eq!(mapping.get("row.model_data"), Some(&AbstractValue::Number(0.0)));
+
+        let error = pre_generate("Node {\n    Repeater {\n        Text { }\n        Text { }\n    }\n}").unwrap_err();
+        assert_eq!(error.to_string(), "A Repeater must have a single child, its delegate");
+        assert_eq!(error.span().start().line, 2);
+    }
+
+    #[test]
+    fn test_list_view_delegate() {
+        let mapping = pre_generate("Node {\n    ListView {\n        model: 3\n        Text { id: row height: 20 }\n    }\n}").unwrap();
+        assert_eq!(mapping.get("row.model_data"), Some(&AbstractValue::String(String::new())));
+        assert_eq!(mapping.get("row.is_current"), Some(&AbstractValue::Bool(false)));
+        assert!(mapping.contains_key("generated_id_1.current_index"));
+
+        let error = pre_generate("Node {\n    ListView {\n        model: 3\n    }\n}").unwrap_err();
+        assert_eq!(error.to_string(), "A ListView must have a single child, its delegate");
+    }
+
+    #[test]
+    fn test_behaviors_and_animations() {
+        let res = parse("Rectangle {\n    color color: { WHITE }\n    Behavior on color { ColorAnimation { duration: 200 easing: out_quad } }\n    NumberAnimation { id: slide property: \"x\" to: 10 loops: infinite }\n}").unwrap();
+        let node = &res.root_node;
+        assert_eq!(node.children.len(), 1);
+        assert_eq!(node.behaviors.len(), 1);
+        assert_eq!(node.behaviors[0].0.to_string(), "color");
+        assert_eq!(node.behaviors[0].1, AnimationSpec::new(200.0).with_easing(rml_core::Easing::OutQuad));
+
+        let mapping = pre_generate("Rectangle {\n    NumberAnimation { id: slide property: \"x\" to: 10 }\n}").unwrap();
+        assert_eq!(mapping.get("slide.running"), Some(&AbstractValue::Bool(false)));
+
+        let error = parse("Rectangle {\n    Behavior on color { ColorAnimation { easing: wobbly } }\n}").unwrap_err();
+        assert_eq!(error.to_string(), "Unknown easing: wobbly");
+        assert_eq!(error.span().start().line, 2);
+        let error = parse("Rectangle {\n    Behavior on x { Rectangle { } }\n}").unwrap_err();
+        assert_eq!(error.to_string(), "A Behavior holds a NumberAnimation or a ColorAnimation");
+    }
+
+    #[test]
+    fn test_timer() {
+        let source = "Node {\n    Timer { id: toast interval: 3000 running: true on_triggered: { } }\n}";
+        let mapping = pre_generate(source).unwrap();
+        assert!(mapping.contains_key("toast.interval"));
+        assert_eq!(mapping.get("toast.repeat"), Some(&AbstractValue::Bool(false)));
+        assert_eq!(mapping.get("toast.triggered_on_start"), Some(&AbstractValue::Bool(false)));
+
+        // the handler is bound to the `triggered` signal
+        let res = parse(source).unwrap();
+        let (mut node, components) = (res.root_node, res.components);
+        let (_, code, _, initializer) = node.generate_with_components_and_counter(&components, &mut 0, &mapping).unwrap();
+        let code = quote! { #code #initializer };
+        assert!(code.to_string().contains("bind_node_property_to_callback (\"toast\" , \"triggered\""));
+    }
+
+    #[test]
+    fn test_text_input() {
+        let source = "Node {\n    TextInput { id: name_field placeholder: \"Name\" on_accepted: { } }\n}";
+        let mapping = pre_generate(source).unwrap();
+        assert_eq!(mapping.get("name_field.text"), Some(&AbstractValue::String(String::new())));
+        assert_eq!(mapping.get("name_field.cursor_position"), Some(&AbstractValue::Number(0.0)));
+
+        let res = parse(source).unwrap();
+        let (mut node, components) = (res.root_node, res.components);
+        let (_, code, _, initializer) = node.generate_with_components_and_counter(&components, &mut 0, &mapping).unwrap();
+        let code = quote! { #code #initializer };
+        // the signal exists when the handler is bound
+        let code = code.to_string();
+        let binding = code.find("bind_node_property_to_callback (\"name_field\" , \"accepted\"").unwrap();
+        assert!(code.find("stringify ! (accepted)").unwrap() < binding);
+    }
+
+    #[test]
+    fn test_text_area() {
+        let source = "Node {\n    TextArea { id: notes wrap: false }\n}";
+        let mapping = pre_generate(source).unwrap();
+        assert!(mapping.contains_key("notes.wrap"));
+        assert_eq!(mapping.get("notes.read_only"), Some(&AbstractValue::Bool(false)));
+        assert_eq!(mapping.get("notes.text"), Some(&AbstractValue::String(String::new())));
+        assert!(!mapping.contains_key("notes.accepted"));
+    }
+
+    #[test]
+    fn test_focus() {
+        let source = "Node {\n    MouseArea { id: ok_button focusable: true on_focus_in: { } }\n    TextInput { id: field }\n}";
+        let mapping = pre_generate(source).unwrap();
+        assert_eq!(mapping.get("ok_button.active_focus"), Some(&AbstractValue::Bool(false)));
+        assert_eq!(mapping.get("ok_button.focus_order"), Some(&AbstractValue::Number(0.0)));
+        assert_eq!(mapping.get("field.active_focus"), Some(&AbstractValue::Bool(false)));
+
+        let res = parse(source).unwrap();
+        let (mut node, components) = (res.root_node, res.components);
+        let (_, code, _, _) = node.generate_with_components_and_counter(&components, &mut 0, &mapping).unwrap();
+        assert!(code.to_string().contains("EventType :: FocusIn"));
+    }
+
+    #[test]
+    fn test_drag_and_drop() {
+        let source = "Node {\n    Rectangle { id: card MouseArea { id: handle drag_target: \"card\" } }\n    DropArea { id: bin keys: \"card\" on_dropped: { } }\n}";
+        let mapping = pre_generate(source).unwrap();
+        assert_eq!(mapping.get("handle.drag_active"), Some(&AbstractValue::Bool(false)));
+        assert_eq!(mapping.get("bin.contains_drag"), Some(&AbstractValue::Bool(false)));
+        assert_eq!(mapping.get("bin.drag_x"), Some(&AbstractValue::Number(0.0)));
+
+        let res = parse(source).unwrap();
+        let (mut node, components) = (res.root_node, res.components);
+        let (_, code, _, _) = node.generate_with_components_and_counter(&components, &mut 0, &mapping).unwrap();
+        let code = code.to_string();
+        assert!(code.contains("ItemTypeEnum :: DropArea"));
+        for signal in ["entered", "exited", "dropped"] {
+            assert!(code.contains(&format!("stringify ! ({signal})")), "{signal}");
+        }
+        let binding = code.find("bind_node_property_to_callback (\"bin\" , \"dropped\"").unwrap();
+        assert!(code.find("stringify ! (dropped)").unwrap() < binding);
+    }
+
+    #[test]
+    fn test_states_and_transitions() {
+        let source = "Rectangle {\n    id: panel\n    states {\n        wide { width: 100 PropertyChanges { target: label text: { \"wide\" } } }\n        hidden { visible: false }\n    }\n    transitions {\n        Transition { to: \"wide\" NumberAnimation { duration: 100 } }\n    }\n    Text { id: label }\n}";
+        let res = parse(source).unwrap();
+        let node = &res.root_node;
+        assert_eq!(node.children.len(), 1);
+        assert_eq!(node.states.len(), 2);
+        assert_eq!(node.states[0].name.to_string(), "wide");
+        assert_eq!(node.states[0].changes.len(), 2);
+        assert_eq!(node.states[0].changes[1].0.as_deref(), Some("label"));
+        assert_eq!(node.transitions, vec![Transition::new(AnimationSpec::new(100.0)).to_state("wide")]);
+
+        let mapping = pre_generate(source).unwrap();
+        assert_eq!(mapping.get("panel.state"), Some(&AbstractValue::String(String::new())));
+
+        let error = parse("Rectangle {\n    transitions {\n        Transition { }\n    }\n}").unwrap_err();
+        assert_eq!(error.to_string(), "A Transition holds a single animation");
+        assert_eq!(error.span().start().line, 3);
+        let error = parse("Rectangle {\n    states {\n        wide { Changes { target: label } }\n    }\n}").unwrap_err();
+        assert_eq!(error.to_string(), "Expected a property or PropertyChanges in a state");
+    }
+
+    #[test]
+    fn test_error_in_imported_file() {
+        let dir = std::env::temp_dir().join(format!("rml_macros_errors_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let component = dir.join("Button.rml");
+        fs::write(&component, "Rectangle {\n    id: button\n    Txet { }\n}\n").unwrap();
+
+        let source = format!("import \"{}\"\nNode {{\n    Button {{ }}\n}}", dir.to_string_lossy());
+        let error = pre_generate(&source).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(error.to_string(), format!("{}:3:5: Unknown node type: Txet", component.to_string_lossy()));
+        // reported on the instance in the macro input
+        assert_eq!(error.span().start().line, 3);
+    }
+
+    #[test]
+    fn test_ui_handles() {
+        let mapping = pre_generate("Node {\n    id: counter_btn\n    number counter: 0\n    signal clicked\n    Text { string text: \"\" }\n}").unwrap();
+        let (handle_types, handles) = generate_ui_handles(&format_ident!("CounterUi"), &syn::Visibility::Inherited, &mapping, &HashMap::new()).unwrap();
+        let file: syn::File = syn::parse2(handle_types).unwrap();
+
+        let fields = |name: &str| -> Vec<String> {
+            file.items.iter().find_map(|item| match item {
+                syn::Item::Struct(item) if item.ident == name => Some(item.fields.iter().map(|f| f.ident.as_ref().unwrap().to_string()).collect()),
+                _ => None,
+            }).unwrap()
+        };
+        // the Text has no id, the signal no type
+        assert_eq!(fields("CounterUi"), ["counter_btn"]);
+        assert!(fields("CounterUiCounterBtnHandle").contains(&"counter".to_string()));
+        assert!(!fields("CounterUiCounterBtnHandle").contains(&"clicked".to_string()));
+        assert!(handles.to_string().contains("PropertyHandle :: new (& engine , \"counter_btn\" , \"counter\")"));
+    }
+
+    #[test]
+    fn test_ui_handle_name_collision() {
+        let source = "Node {\n    Rectangle { id: my_btn number n: 0 }\n    Rectangle { id: myBtn number n: 0 }\n}";
+        let mut spans = HashMap::new();
+        id_spans(&parse(source).unwrap().root_node, &mut spans);
+        let error = generate_ui_handles(&format_ident!("Ui"), &syn::Visibility::Inherited, &pre_generate(source).unwrap(), &spans).unwrap_err();
+        assert_eq!(error.to_string(), "The ids 'myBtn' and 'my_btn' give the same handle type 'UiMyBtnHandle', rename one of them.");
+        assert_eq!(error.span().start().line, 2);
+    }
+
+    #[test]
+    fn test_missing_import_dir() {
+        let error = parse("import \"/nonexistent/rml/components\"\nNode { }").err().unwrap();
+        assert!(error.to_string().contains("not found"));
+        assert_eq!(error.span().start().line, 1);
+    }
+}
@@ -1,7 +1,8 @@
 
+use proc_macro2::Span;
 use quote::{format_ident};
 use syn::{Ident, Lit};
-use rml_core::{AbstractValue};
+use rml_core::{AbstractValue, AnimationSpec, Transition};
 use std::{collections::HashMap, fmt::Debug};
 
 #[derive(Debug, Clone)]
@@ -31,6 +32,7 @@ pub enum PropertyKey {
 pub struct ImportStatement {
     pub path: String,
     pub alias: Option<String>,
+    pub span: Span,
 }
 
 #[derive(Clone, Debug)]
@@ -55,6 +57,13 @@ impl PropertyKey {
     pub fn is_signal(&self) -> bool {
         matches!(self, PropertyKey::Signal(_))
     }
+
+    pub fn span(&self) -> Span {
+        match self {
+            PropertyKey::Simple(ident) | PropertyKey::Signal(ident) => ident.span(),
+            PropertyKey::Composed { base, .. } => base.span(),
+        }
+    }
 }
 
 
@@ -129,17 +138,34 @@ impl ToString for Value {
 #[derive(Clone)]
 pub struct RmlNode {
     pub _ident: String,
+    /// Span of the node type, errors about the node point there
+    pub span: Span,
     pub properties: Vec<(PropertyType, PropertyKey, Value)>,
     pub children: Vec<RmlNode>,
+    /// `Behavior on <property> { ... }` declarations: the animated property and its animation
+    pub behaviors: Vec<(Ident, AnimationSpec)>,
+    /// `states { ... }` and `transitions { ... }` sections
+    pub states: Vec<StateDecl>,
+    pub transitions: Vec<Transition>,
     pub functions: Vec<syn::ItemFn>
 }
 
+/// A state of a `states` section: its name and the properties it overrides, of another node when a target is given
+#[derive(Clone, Debug)]
+pub struct StateDecl {
+    pub name: Ident,
+    pub changes: Vec<(Option<String>, PropertyKey, Value)>,
+}
+
 impl Debug for RmlNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("RmlNode")
             .field("_ident", &self._ident)
             .field("properties", &self.properties)
             .field("children", &self.children)
+            .field("behaviors", &self.behaviors)
+            .field("states", &self.states)
+            .field("transitions", &self.transitions)
             .field("functions", &self.functions.iter().map(|func| &func.sig.ident).collect::<Vec<&Ident>>())
             .finish()
     }
@@ -153,4 +179,15 @@ pub struct RmlParser {
     pub root_node: RmlNode,
 }
 
+/// `$` references of an imported file, checked once the type of every property is known
+#[derive(Debug, Clone)]
+pub struct FileCheck {
+    pub path: String,
+    /// The id of the component root in the file is replaced by the id of the instance
+    pub original_id: String,
+    pub instance_id: String,
+    /// Instance in the macro input the errors are reported on, set by the outermost instance
+    pub span: Option<Span>,
+}
+
 pub type GenResult = (String, proc_macro2::TokenStream, proc_macro2::TokenStream, proc_macro2::TokenStream);
\ No newline at end of file